@@ -11,7 +11,7 @@ fn main() -> Result<()> {
     keys.load_prod_keys(File::open("prod.keys")?)?;
 
     let program = fs::read("program.nca")?;
-    let plaintext = decrypt_header(&program, &keys.header_key.unwrap());
+    let plaintext = decrypt_header(&program, &keys.header_key.unwrap())?;
 
     let mut cursor = Cursor::new(&plaintext);
     let nca = Nca::parse(&mut cursor)?;
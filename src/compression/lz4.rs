@@ -6,11 +6,16 @@
 //! followed by the raw LZ4 block data. This matches the layout produced and
 //! consumed by [`lz4_flex::decompress_size_prepended`]`.
 //!
+//! Standard LZ4 frames (magic `04 22 4D 18`) are handled by
+//! [`decompress_lz4_frame`].
+//!
 //! For the formats more commonly encountered in this library (SARC `.zs`
 //! archives, NCZ section blocks) see [`crate::compression::zstd`].
 
 #![cfg(feature = "compression")]
 
+use std::io::Read;
+
 use crate::{Error, Result};
 
 /// Decompress an LZ4-compressed buffer.
@@ -22,3 +27,17 @@ use crate::{Error, Result};
 pub fn decompress_lz4(data: &[u8]) -> Result<Vec<u8>> {
     lz4_flex::decompress_size_prepended(data).map_err(|_| Error::Lz4)
 }
+
+/// Magic bytes at the start of an LZ4 frame.
+pub const LZ4_FRAME_MAGIC: [u8; 4] = [0x04, 0x22, 0x4D, 0x18];
+
+/// Decompress a complete LZ4 frame.
+///
+/// Returns [`Error::Lz4`] on any decompression failure.
+pub fn decompress_lz4_frame(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    lz4_flex::frame::FrameDecoder::new(data)
+        .read_to_end(&mut out)
+        .map_err(|_| Error::Lz4)?;
+    Ok(out)
+}
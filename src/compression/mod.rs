@@ -13,8 +13,10 @@
 //!
 //! | Module | Algorithm | Typical use in hakkit |
 //! |--------|-----------|-----------------------|
-//! | [`lz4`]  | LZ4 block | Older Nintendo tooling |
-//! | [`zstd`] | Zstandard | SARC `.zs` archives; NCZ section blocks |
+//! | [`lz4`]  | LZ4 block / frame | Older Nintendo tooling |
+//! | [`yaz0`] | Yaz0 (LZ77) | SARC `.szs` archives; older first-party assets |
+//! | [`zlib`] | zlib (DEFLATE) | Assorted game data |
+//! | [`zstd`] | Zstandard | SARC `.zs` archives; NCZ section blocks; writing both |
 //!
 //! ## Choosing the right function
 //!
@@ -26,9 +28,77 @@
 //!   [`zstd::decompress_zstd_with_size`] to avoid reallocations.
 //! * **LZ4** - use [`lz4::decompress_lz4`] for the size-prepended block
 //!   format used by older Nintendo tools.
+//! * **Unknown or mixed content** - [`detect`] sniffs the leading magic and
+//!   [`decompress_auto`] dispatches to the matching decoder.
+//! * **Writing `.zs` / NCZ blocks** - use [`zstd::compress_zstd`] for a
+//!   whole buffer, or [`zstd::ZstdWriter`] to stream into any `Write`.
 
 #[cfg(feature = "compression")]
 pub mod lz4;
 
+#[cfg(feature = "compression")]
+pub mod yaz0;
+
+#[cfg(feature = "compression")]
+pub mod zlib;
+
 #[cfg(feature = "compression")]
 pub mod zstd;
+
+#[cfg(feature = "compression")]
+use crate::Result;
+
+/// Zstandard frame magic (`0xFD2FB528` little-endian).
+#[cfg(feature = "compression")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Compression format recognised by [`detect`].
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Codec {
+    Zstd,
+    /// LZ4 frame format. The size-prepended block format has no magic and
+    /// is never detected.
+    Lz4,
+    Yaz0,
+    Zlib,
+}
+
+/// Identify the compression format of `data` from its leading bytes.
+///
+/// Returns [`None`] if no known magic matches, which usually means the data
+/// is not compressed. zlib is checked last since its two-byte header is the
+/// weakest signature.
+#[cfg(feature = "compression")]
+pub fn detect(data: &[u8]) -> Option<Codec> {
+    if data.starts_with(&ZSTD_MAGIC) {
+        Some(Codec::Zstd)
+    } else if data.starts_with(&lz4::LZ4_FRAME_MAGIC) {
+        Some(Codec::Lz4)
+    } else if data.starts_with(yaz0::YAZ0_MAGIC) {
+        Some(Codec::Yaz0)
+    } else if zlib::is_zlib(data) {
+        Some(Codec::Zlib)
+    } else {
+        None
+    }
+}
+
+/// Decompress `data` with the codec reported by [`detect`].
+///
+/// Returns `Ok(None)` if no codec is detected, leaving the caller to treat
+/// the data as uncompressed.
+#[cfg(feature = "compression")]
+pub fn decompress_auto(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let Some(codec) = detect(data) else {
+        return Ok(None);
+    };
+    match codec {
+        Codec::Zstd => zstd::decompress_zstd(data),
+        Codec::Lz4 => lz4::decompress_lz4_frame(data),
+        Codec::Yaz0 => yaz0::decompress_yaz0(data),
+        Codec::Zlib => zlib::decompress_zlib(data),
+    }
+    .map(Some)
+}
@@ -0,0 +1,81 @@
+//! Yaz0 decompression (requires the `compression` feature).
+//!
+//! Yaz0 is Nintendo's LZ77 variant, used for `.szs` SARC archives and many
+//! older first-party assets. Decompress with [`decompress_yaz0`], then parse
+//! the result like any uncompressed file.
+//!
+//! ## Layout
+//! ```text
+//! [0x00] Magic            "Yaz0"
+//! [0x04] DecompressedSize (u32 BE)
+//! [0x08] Alignment        (u32 BE, 0 on older files)
+//! [0x0C] Reserved         (4 bytes)
+//! [0x10] Data
+//! ```
+//!
+//! ## Data
+//! The data is a sequence of groups. Each group starts with a header byte
+//! whose bits, MSB first, describe up to eight chunks: `1` copies one literal
+//! byte, `0` is a back-reference of two or three bytes:
+//! ```text
+//! NR RR          → length N + 2 (N = 1..=15), distance 0xRRR + 1
+//! 0R RR NN       → length 0xNN + 0x12,         distance 0xRRR + 1
+//! ```
+
+#![cfg(feature = "compression")]
+
+use crate::{Error, Result};
+
+/// Magic bytes at the start of a Yaz0 stream.
+pub const YAZ0_MAGIC: &[u8; 4] = b"Yaz0";
+
+/// Size of the Yaz0 header.
+pub const HEADER_SIZE: usize = 0x10;
+
+/// Decompressed size recorded in a Yaz0 header.
+///
+/// Returns [`Error::BadMagic`] if `data` does not start with a Yaz0 header.
+pub fn decompressed_size(data: &[u8]) -> Result<u32> {
+    if data.len() < HEADER_SIZE || &data[..4] != YAZ0_MAGIC {
+        return Err(Error::BadMagic);
+    }
+    Ok(u32::from_be_bytes(data[4..8].try_into().unwrap()))
+}
+
+/// Decompress a complete Yaz0 stream.
+///
+/// Returns [`Error::BadMagic`] for a missing header, [`Error::UnexpectedEof`]
+/// if the data ends before the recorded size is reached, and
+/// [`Error::InvalidRange`] for a back-reference before the start of output.
+pub fn decompress_yaz0(data: &[u8]) -> Result<Vec<u8>> {
+    let size = decompressed_size(data)? as usize;
+    let mut out = Vec::with_capacity(size);
+    let mut src = data[HEADER_SIZE..].iter().copied();
+    let mut next = || src.next().ok_or(Error::UnexpectedEof);
+
+    while out.len() < size {
+        let header = next()?;
+        for bit in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+            if header & (1 << bit) != 0 {
+                out.push(next()?);
+                continue;
+            }
+            let (b1, b2) = (next()?, next()?);
+            let distance = (((b1 as usize & 0x0F) << 8) | b2 as usize) + 1;
+            let length = match b1 >> 4 {
+                0 => next()? as usize + 0x12,
+                n => n as usize + 2,
+            };
+            let start = out.len().checked_sub(distance).ok_or(Error::InvalidRange)?;
+            // The source may overlap the bytes being written, so copy one
+            // byte at a time.
+            for i in 0..length.min(size - out.len()) {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    Ok(out)
+}
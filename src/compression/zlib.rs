@@ -0,0 +1,27 @@
+//! zlib decompression (requires the `compression` feature).
+//!
+//! zlib-wrapped DEFLATE streams turn up in assorted game data and homebrew
+//! formats. Only streams with the two-byte zlib header are accepted; raw
+//! DEFLATE data has no header to recognise it by.
+
+#![cfg(feature = "compression")]
+
+use crate::{Error, Result};
+
+/// `true` if `data` starts with a valid zlib header: the DEFLATE method, a
+/// window of at most 32 KiB, and a correct header check value.
+pub fn is_zlib(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => {
+            cmf & 0x0F == 8 && cmf >> 4 <= 7 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0
+        }
+        _ => false,
+    }
+}
+
+/// Decompress a complete zlib stream.
+///
+/// Returns [`Error::Zlib`] on any decompression failure.
+pub fn decompress_zlib(data: &[u8]) -> Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(data).map_err(|_| Error::Zlib)
+}
@@ -1,4 +1,5 @@
-//! Zstandard decompression (requires the `compression` feature).
+//! Zstandard compression and decompression (requires the `compression`
+//! feature).
 //!
 //! Zstd is Nintendo's preferred compression algorithm for modern Switch
 //! content. It appears in two contexts within hakkit:
@@ -8,15 +9,24 @@
 //!   with [`decompress_zstd`], then parse the resulting bytes with
 //!   [`crate::formats::sarc::Sarc::parse`].
 //!
-//! * **NCZ blocks** - each compressed block inside a `.ncz` file is an
-//!   independent Zstd stream prefixed by its compressed byte length. Use
-//!   [`decompress_zstd_with_size`] when the decompressed size is known in
-//!   advance (it is recorded in the NCZ section descriptor) to avoid
-//!   reallocations on large NCA sections.
+//! * **NCZ data** - a solid `.ncz` holds its NCA body as one Zstd stream,
+//!   which [`ZstdDecoder`] decompresses piece by piece as the caller feeds
+//!   it. A block-mode `.ncz` holds independent Zstd streams whose sizes are
+//!   listed in its block table; use [`decompress_zstd_with_size`], since
+//!   their decompressed size is known in advance.
+//!
+//! For producing `.zs` and `.ncz` output, [`compress_zstd`] compresses a
+//! buffer in one call and [`ZstdWriter`] streams data through an encoder.
+//! Levels follow the zstd library: 1-22, with 0 selecting the default (3).
+//!
+//! With the `parallel` feature, [`decompress_zstd_blocks`] spreads
+//! independent NCZ blocks across a rayon thread pool and
+//! [`ZstdWriter::set_workers`] enables zstd's multithreaded encoder.
+//! Zstd itself cannot decompress a single frame on more than one thread.
 
 #![cfg(feature = "compression")]
 
-use std::io;
+use std::io::{self, Write};
 
 use crate::{Error, Result};
 
@@ -42,3 +52,146 @@ pub fn decompress_zstd_with_size(data: &[u8], decompressed_size: usize) -> Resul
     io::copy(&mut decoder, &mut out)?;
     Ok(out)
 }
+
+/// Decompressed size recorded in the header of the Zstandard frame at the
+/// start of `data`, if the encoder recorded one.
+///
+/// Only the frame header is inspected, so `data` may be just the first
+/// [`FRAME_HEADER_SIZE_MAX`] bytes of the frame.
+pub fn frame_content_size(data: &[u8]) -> Option<u64> {
+    zstd::zstd_safe::get_frame_content_size(data).ok().flatten()
+}
+
+/// Largest possible size of a Zstandard frame header.
+pub const FRAME_HEADER_SIZE_MAX: usize = 18;
+
+/// Decompress independent Zstandard blocks, such as the blocks of an NCZ
+/// section, returning them in order.
+///
+/// With the `parallel` feature the blocks are decompressed concurrently;
+/// otherwise one after another. Returns the first error encountered.
+pub fn decompress_zstd_blocks<B: AsRef<[u8]> + Sync>(blocks: &[B]) -> Result<Vec<Vec<u8>>> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        blocks
+            .par_iter()
+            .map(|b| decompress_zstd(b.as_ref()))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        blocks.iter().map(|b| decompress_zstd(b.as_ref())).collect()
+    }
+}
+
+/// Incremental Zstandard decoder fed with input by the caller.
+///
+/// For streams read through a reader the caller keeps, such as the data of
+/// a solid NCZ. Concatenated frames are decoded as one stream.
+pub struct ZstdDecoder {
+    decoder: zstd::stream::raw::Decoder<'static>,
+}
+
+impl ZstdDecoder {
+    /// Create a decoder at the start of a stream.
+    ///
+    /// Returns [`Error::Zstd`] if the decoder cannot be initialised.
+    pub fn new() -> Result<Self> {
+        let decoder = zstd::stream::raw::Decoder::new().map_err(|_| Error::Zstd)?;
+        Ok(Self { decoder })
+    }
+
+    /// Decompress from `input` into `output`, returning the number of bytes
+    /// consumed and produced.
+    ///
+    /// Data buffered by the decoder is produced even if `input` is empty.
+    /// Returns [`Error::Zstd`] if the input is corrupt.
+    pub fn decompress(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize)> {
+        use zstd::stream::raw::Operation;
+        let status = self
+            .decoder
+            .run_on_buffers(input, output)
+            .map_err(|_| Error::Zstd)?;
+        Ok((status.bytes_read, status.bytes_written))
+    }
+
+    /// Discard the decoder's state to decode a stream from its start.
+    pub fn reset(&mut self) -> Result<()> {
+        use zstd::stream::raw::Operation;
+        self.decoder.reinit().map_err(|_| Error::Zstd)
+    }
+}
+
+/// Default compression level used by the zstd library.
+pub const DEFAULT_LEVEL: i32 = 0;
+
+/// Compress `data` into a single Zstandard frame at `level`.
+///
+/// Returns [`Error::Zstd`] if `level` is rejected or compression fails.
+pub fn compress_zstd(data: &[u8], level: i32) -> Result<Vec<u8>> {
+    zstd::bulk::compress(data, level).map_err(|_| Error::Zstd)
+}
+
+/// Streaming Zstandard encoder writing one frame to `W`.
+///
+/// Call [`finish`](Self::finish) when done; dropping the writer without
+/// finishing leaves the frame truncated.
+pub struct ZstdWriter<W: Write> {
+    encoder: zstd::Encoder<'static, W>,
+}
+
+impl<W: Write> ZstdWriter<W> {
+    /// Create an encoder at `level` writing to `inner`.
+    ///
+    /// Returns [`Error::Zstd`] if `level` is rejected.
+    pub fn new(inner: W, level: i32) -> Result<Self> {
+        let encoder = zstd::Encoder::new(inner, level).map_err(|_| Error::Zstd)?;
+        Ok(Self { encoder })
+    }
+
+    /// Record the total uncompressed size in the frame header.
+    ///
+    /// Must be called before any data is written; the frame fails to finish
+    /// if a different number of bytes is written.
+    pub fn set_pledged_size(&mut self, size: u64) -> Result<()> {
+        self.encoder
+            .set_pledged_src_size(Some(size))
+            .map_err(|_| Error::Zstd)
+    }
+
+    /// Write a content checksum at the end of the frame.
+    pub fn include_checksum(&mut self, include: bool) -> Result<()> {
+        self.encoder
+            .include_checksum(include)
+            .map_err(|_| Error::Zstd)
+    }
+
+    /// Compress on `workers` background threads (0 disables multithreading).
+    ///
+    /// Must be called before any data is written.
+    #[cfg(feature = "parallel")]
+    pub fn set_workers(&mut self, workers: u32) -> Result<()> {
+        self.encoder.multithread(workers).map_err(|_| Error::Zstd)
+    }
+
+    /// Flush the remaining data, end the frame, and return the inner writer.
+    pub fn finish(self) -> Result<W> {
+        Ok(self.encoder.finish()?)
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.encoder.get_ref()
+    }
+}
+
+impl<W: Write> Write for ZstdWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
@@ -12,7 +12,9 @@
 //!
 //! | Module | Purpose |
 //! |--------|---------|
-//! | [`nca`] | AES-128-XTS header decryption, AES-128-CTR section decryption, AES-128-ECB key-area unwrapping |
+//! | [`nca`] | Reusable [`nca::Aes128`] cipher, AES-128-XTS header encryption and decryption, XTS streams, AES-128-CTR section encryption and decryption, AES-128-ECB key-area unwrapping, AES-128-CBC |
+//! | [`rsa`] | RSA-2048-PSS signature verification (NCA headers, ACIDs) |
+//! | [`sha256`] | SHA-256 digests (HFS0 entries, FsHeader hashes, CNMT content records) |
 //!
 //! ## Key hierarchy (brief)
 //!
@@ -28,3 +30,5 @@
 //! ```
 
 pub mod nca;
+pub mod rsa;
+pub mod sha256;
@@ -14,18 +14,38 @@
 //! For NCA2, each FsHeader is independently encrypted as sector 0 rather
 //! than using the sector that corresponds to its position.
 //!
+//! [`Aes128Xts`] holds both expanded keys, so a header key can be expanded
+//! once and reused for every NCA with [`decrypt_header_with_cipher`].
+//! [`AesXtsReader`] applies the same scheme with any sector size to content
+//! that is XTS-encrypted as a whole (NAX0, save images).
+//!
 //! ## AES-128-CTR - NCA section decryption
 //!
 //! Each NCA section uses AES-128-CTR. The 128-bit counter is built from the
 //! `Generation` and `SecureValue` fields in the FsHeader combined with the
 //! byte offset being decrypted, as described in the switchbrew wiki.
+//! [`decrypt_section_ctr`] works on a buffer in memory given a counter,
+//! [`decrypt_section_ctr_at`] given the buffer's offset in the NCA;
+//! [`AesCtrReader`] streams a region of any size.
 //!
 //! ## Pure-Rust implementation note
 //!
+//! Every mode here is built on [`Aes128`], an expanded key that callers can
+//! also hold on to and use for their own block cipher modes.
+//!
 //! To keep the dependency footprint small, AES is implemented here with a
 //! compact lookup-table approach. This is not constant-time and should not
 //! be used for security-sensitive applications, but it is correct and
 //! sufficient for offline file-format parsing.
+//!
+//! Enabling the `aes-accel` feature swaps the block cipher for the `aes`
+//! crate, which uses AES-NI / ARMv8 crypto instructions when available. The
+//! public functions and readers keep the same signatures.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::{Error, Result};
 
 // The AES S-box is a 256-entry substitution table applied byte-by-byte during SubBytes.
 // It is constructed by: (1) taking the multiplicative inverse of each byte in GF(2^8) - mapping 0 to 0,
@@ -33,6 +53,7 @@
 // The affine step is what makes the S-box resistant to interpolation attacks in GF(2^8).
 // Without it, AES could be described as a simple rational function and broken algebraically.
 // https://en.wikipedia.org/wiki/Rijndael_S-box
+#[cfg(not(feature = "aes-accel"))]
 const SBOX: [u8; 256] = [
     0x63, 0x7C, 0x77, 0x7B, 0xF2, 0x6B, 0x6F, 0xC5, 0x30, 0x01, 0x67, 0x2B, 0xFE, 0xD7, 0xAB, 0x76,
     0xCA, 0x82, 0xC9, 0x7D, 0xFA, 0x59, 0x47, 0xF0, 0xAD, 0xD4, 0xA2, 0xAF, 0x9C, 0xA4, 0x72, 0xC0,
@@ -59,6 +80,7 @@ const SBOX: [u8; 256] = [
 // This function is used by MixColumns and InvMixColumns to compute linear combinations of state bytes.
 // https://en.wikipedia.org/wiki/Finite_field_arithmetic#Rijndael's_(AES)_finite_field
 #[inline]
+#[cfg(not(feature = "aes-accel"))]
 fn gmul(mut a: u8, mut b: u8) -> u8 {
     let mut p = 0u8; // product accumulator, starts at additive identity (zero in GF(2^8))
     for _ in 0..8 {
@@ -81,11 +103,111 @@ fn gmul(mut a: u8, mut b: u8) -> u8 {
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#Description_of_the_cipher
 type Block = [u8; 16];
 
+// Expanded key material for one AES-128 key: the 176-byte software schedule, or the
+// `aes` crate's cipher (AES-NI / ARMv8 crypto extensions, detected at runtime) with `aes-accel`.
+#[cfg(not(feature = "aes-accel"))]
+type RoundKeys = [u8; 176];
+#[cfg(feature = "aes-accel")]
+type RoundKeys = aes::Aes128;
+
+#[cfg(feature = "aes-accel")]
+fn key_expand(key: &[u8; 16]) -> RoundKeys {
+    use aes::cipher::KeyInit;
+    aes::Aes128::new(key.into())
+}
+
+#[cfg(feature = "aes-accel")]
+fn aes128_encrypt_block(block: &Block, round_keys: &RoundKeys) -> Block {
+    use aes::cipher::BlockEncrypt;
+    let mut b = (*block).into();
+    round_keys.encrypt_block(&mut b);
+    b.into()
+}
+
+#[cfg(feature = "aes-accel")]
+fn aes128_decrypt_block(block: &Block, round_keys: &RoundKeys) -> Block {
+    use aes::cipher::BlockDecrypt;
+    let mut b = (*block).into();
+    round_keys.decrypt_block(&mut b);
+    b.into()
+}
+
+/// An expanded AES-128 key, reusable across any number of blocks.
+///
+/// Key expansion happens once in [`Aes128::new`]; the XTS, CTR, ECB, and CBC
+/// helpers in this module are built on it, and it can be used directly to
+/// implement other modes. Backed by the software implementation below, or by
+/// the `aes` crate with the `aes-accel` feature.
+#[derive(Clone)]
+pub struct Aes128 {
+    round_keys: RoundKeys,
+}
+
+impl Aes128 {
+    /// Expand `key` into its round keys.
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self {
+            round_keys: key_expand(key),
+        }
+    }
+
+    /// Encrypt a single 16-byte block.
+    pub fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        aes128_encrypt_block(block, &self.round_keys)
+    }
+
+    /// Decrypt a single 16-byte block.
+    pub fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        aes128_decrypt_block(block, &self.round_keys)
+    }
+}
+
+// Round keys are secret material; keep them out of debug output.
+impl fmt::Debug for Aes128 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Aes128").finish_non_exhaustive()
+    }
+}
+
+/// Expanded keys for Nintendo's AES-128-XTS variant, reusable across any
+/// number of sectors.
+///
+/// Built from a 32-byte XTS key: the data key followed by the tweak key.
+/// Sector numbers go into the tweak big-endian. Sectors may be any length;
+/// trailing bytes that do not fill a 16-byte block are left untouched, as
+/// Nintendo never uses ciphertext stealing.
+#[derive(Debug, Clone)]
+pub struct Aes128Xts {
+    data: Aes128,
+    tweak: Aes128,
+}
+
+impl Aes128Xts {
+    /// Expand both halves of `key`.
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            data: Aes128::new(key[..16].try_into().unwrap()),
+            tweak: Aes128::new(key[16..].try_into().unwrap()),
+        }
+    }
+
+    /// Decrypt one sector in place.
+    pub fn decrypt_sector(&self, data: &mut [u8], sector: u64) {
+        xts_decrypt_sector_rk(data, &self.data, &self.tweak, sector);
+    }
+
+    /// Encrypt one sector in place.
+    pub fn encrypt_sector(&self, data: &mut [u8], sector: u64) {
+        xts_encrypt_sector_rk(data, &self.data, &self.tweak, sector);
+    }
+}
+
 // SubBytes: replace each byte of the state with the value at that index in the S-box.
 // This is the only non-linear step in AES. Without non-linearity, the entire cipher would be
 // a linear function of the key and plaintext, making it trivially breakable by linear algebra.
 // The S-box's non-linearity specifically resists linear cryptanalysis and differential cryptanalysis.
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#The_SubBytes_step
+#[cfg(not(feature = "aes-accel"))]
 fn sub_bytes(s: &mut Block) {
     for b in s.iter_mut() {
         *b = SBOX[*b as usize];
@@ -98,6 +220,7 @@ fn sub_bytes(s: &mut Block) {
 // This step ensures that after MixColumns, every byte of each column came from a different original column,
 // which is how AES achieves full diffusion across the state in just two rounds.
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#The_ShiftRows_step
+#[cfg(not(feature = "aes-accel"))]
 fn shift_rows(s: &mut Block) {
     // Row 1 (bytes at col-major indices 1, 5, 9, 13): left-rotate by 1 position
     let t = s[1];
@@ -123,6 +246,7 @@ fn shift_rows(s: &mut Block) {
 // which is the formal definition of optimal diffusion. Combined with ShiftRows, any 1-byte change
 // in the input will fully spread across the entire state after 2 rounds (the "avalanche effect").
 // https://en.wikipedia.org/wiki/Rijndael_MixColumns
+#[cfg(not(feature = "aes-accel"))]
 fn mix_columns(s: &mut Block) {
     for i in 0..4 {
         let b = i * 4; // byte offset of the start of column i in the column-major block
@@ -141,6 +265,7 @@ fn mix_columns(s: &mut Block) {
 // This is the only step that incorporates secret key material; all other steps are public transformations.
 // XOR is used because it is its own inverse - the same operation works for both encryption and decryption.
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#The_AddRoundKey_step
+#[cfg(not(feature = "aes-accel"))]
 fn add_round_key(s: &mut Block, rk: &[u8]) {
     for (b, k) in s.iter_mut().zip(rk.iter()) {
         *b ^= k;
@@ -153,7 +278,8 @@ fn add_round_key(s: &mut Block, rk: &[u8]) {
 // The purpose of RCON is to break the symmetry between rounds - without it, round keys would have a regular
 // structure that could be exploited in related-key attacks.
 // https://en.wikipedia.org/wiki/AES_key_schedule
-fn key_expand(key: &[u8; 16]) -> [u8; 176] {
+#[cfg(not(feature = "aes-accel"))]
+fn key_expand(key: &[u8; 16]) -> RoundKeys {
     let mut w = [0u8; 176];
     w[..16].copy_from_slice(key); // round key 0 is just the original key itself
     let rcon: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1B, 0x36]; // x^0 through x^9 in GF(2^8)
@@ -189,7 +315,8 @@ fn key_expand(key: &[u8; 16]) -> [u8; 176] {
 // Omitting MixColumns in the final round makes the inverse cipher structurally symmetric,
 // allowing a hardware implementation to share SubBytes/ShiftRows logic between encrypt and decrypt.
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#High-level_description_of_the_algorithm
-fn aes128_encrypt_block(block: &Block, round_keys: &[u8; 176]) -> Block {
+#[cfg(not(feature = "aes-accel"))]
+fn aes128_encrypt_block(block: &Block, round_keys: &RoundKeys) -> Block {
     let mut s = *block;
     add_round_key(&mut s, &round_keys[..16]); // initial key whitening before round 1 - prevents known-plaintext attacks on round 1 alone
     for round in 1..10 {
@@ -238,43 +365,64 @@ fn xts_mult_tweak(t: &mut Block) {
     }
 }
 
-// Decrypt a single 0x200-byte (512-byte) XTS sector in-place.
+// Decrypt one XTS sector of any size in-place with pre-expanded round keys.
 // XTS decryption is: for each 16-byte block, pre-XOR with tweak T, AES-decrypt, post-XOR with same T.
 // The double XOR with T (called "whitening") hides plaintext patterns without depending on other blocks.
-// key1 is the block cipher key; key2 is only ever used to produce the initial encrypted tweak value.
-// Keeping key1 and key2 separate prevents the whitening tweak from revealing information about key1.
+// rk1 is the block cipher key; rk2 is only ever used to produce the initial encrypted tweak value.
+// Keeping the two keys separate prevents the whitening tweak from revealing information about rk1.
+// Trailing bytes that do not fill a 16-byte block are left untouched (Nintendo never uses ciphertext stealing).
 // https://en.wikipedia.org/wiki/Disk_encryption_theory#XTS
-fn xts_decrypt_sector(data: &mut [u8; 0x200], key1: &[u8; 16], key2: &[u8; 16], sector: u64) {
-    let rk1 = key_expand(key1); // round keys for AES decryption of the actual data blocks
-    let rk2 = key_expand(key2); // round keys for AES encryption of the tweak (only done once per sector)
-
+fn xts_decrypt_sector_rk(data: &mut [u8], rk1: &Aes128, rk2: &Aes128, sector: u64) {
     // T = E_k2(sector_number): encrypt the sector number with key2 to produce the initial tweak value.
     // Encrypting the sector number makes the tweak secret (requires key2 to predict), which is necessary
     // for XTS's security proof - a predictable tweak would let an attacker detect when sectors are identical.
-    let mut t = aes128_encrypt_block(&make_xts_tweak(sector), &rk2);
+    let mut t = rk2.encrypt_block(&make_xts_tweak(sector));
 
-    for block_start in (0..0x200usize).step_by(16) {
+    for chunk in data.chunks_exact_mut(16) {
         let mut block = [0u8; 16];
-        block.copy_from_slice(&data[block_start..block_start + 16]);
+        block.copy_from_slice(chunk);
 
         for i in 0..16 {
             block[i] ^= t[i];
         } // pre-whitening: XOR ciphertext with tweak T before AES decryption
-        block = aes128_decrypt_block(&block, &rk1); // AES decrypt the whitened block
+        block = rk1.decrypt_block(&block); // AES decrypt the whitened block
         for i in 0..16 {
             block[i] ^= t[i];
         } // post-whitening: XOR decrypted result with the same T to recover plaintext
 
-        data[block_start..block_start + 16].copy_from_slice(&block);
+        chunk.copy_from_slice(&block);
         xts_mult_tweak(&mut t); // advance T by multiplying by x in GF(2^128) for the next 16-byte block
     }
 }
 
+// Encrypt one XTS sector in-place: the mirror image of `xts_decrypt_sector_rk`.
+// The tweak sequence is identical; only the direction of the block cipher changes.
+fn xts_encrypt_sector_rk(data: &mut [u8], rk1: &Aes128, rk2: &Aes128, sector: u64) {
+    let mut t = rk2.encrypt_block(&make_xts_tweak(sector));
+
+    for chunk in data.chunks_exact_mut(16) {
+        let mut block = [0u8; 16];
+        block.copy_from_slice(chunk);
+
+        for i in 0..16 {
+            block[i] ^= t[i];
+        } // pre-whitening: XOR plaintext with tweak T before AES encryption
+        block = rk1.encrypt_block(&block);
+        for i in 0..16 {
+            block[i] ^= t[i];
+        } // post-whitening: XOR the ciphertext with the same T
+
+        chunk.copy_from_slice(&block);
+        xts_mult_tweak(&mut t);
+    }
+}
+
 // The inverse S-box is the exact inverse lookup table of SBOX.
 // Applying INV_SBOX after SBOX (or vice versa) returns the original byte, since the S-box is a bijection.
 // It is precomputed as a flat table because computing the GF(2^8) inverse + inverse affine transform
 // on the fly during decryption would be significantly slower than a single table lookup.
 // https://en.wikipedia.org/wiki/Rijndael_S-box#Inverse_S-box
+#[cfg(not(feature = "aes-accel"))]
 const INV_SBOX: [u8; 256] = [
     0x52, 0x09, 0x6A, 0xD5, 0x30, 0x36, 0xA5, 0x38, 0xBF, 0x40, 0xA3, 0x9E, 0x81, 0xF3, 0xD7, 0xFB,
     0x7C, 0xE3, 0x39, 0x82, 0x9B, 0x2F, 0xFF, 0x87, 0x34, 0x8E, 0x43, 0x44, 0xC4, 0xDE, 0xE9, 0xCB,
@@ -295,6 +443,7 @@ const INV_SBOX: [u8; 256] = [
 ];
 
 // InvSubBytes: undo SubBytes by applying the inverse S-box to each byte of the state.
+#[cfg(not(feature = "aes-accel"))]
 fn inv_sub_bytes(s: &mut Block) {
     for b in s.iter_mut() {
         *b = INV_SBOX[*b as usize];
@@ -305,6 +454,7 @@ fn inv_sub_bytes(s: &mut Block) {
 // Row 0: no shift. Row 1: right-rotate by 1. Row 2: right-rotate by 2. Row 3: right-rotate by 3.
 // Right-rotation by n is the inverse of left-rotation by n for a 4-element row.
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#The_ShiftRows_step
+#[cfg(not(feature = "aes-accel"))]
 fn inv_shift_rows(s: &mut Block) {
     // Row 1 (indices 1, 5, 9, 13): right-rotate by 1 (reverse of left-rotate by 1)
     let t = s[13];
@@ -327,6 +477,7 @@ fn inv_shift_rows(s: &mut Block) {
 // The inverse polynomial is a(x)^-1 mod x^4+1 = {0B}x^3 + {0D}x^2 + {09}x + {0E}.
 // These coefficients are defined such that multiplying by both matrices in sequence gives the identity.
 // https://en.wikipedia.org/wiki/Rijndael_MixColumns#InvMixColumns
+#[cfg(not(feature = "aes-accel"))]
 fn inv_mix_columns(s: &mut Block) {
     for i in 0..4 {
         let b = i * 4;
@@ -346,7 +497,8 @@ fn inv_mix_columns(s: &mut Block) {
 // mirroring how encryption's final round omits MixColumns.
 // Note: InvShiftRows and InvSubBytes commute with each other, so their relative order doesn't matter.
 // https://en.wikipedia.org/wiki/Advanced_Encryption_Standard#Description_of_the_cipher
-fn aes128_decrypt_block(block: &Block, round_keys: &[u8; 176]) -> Block {
+#[cfg(not(feature = "aes-accel"))]
+fn aes128_decrypt_block(block: &Block, round_keys: &RoundKeys) -> Block {
     let mut s = *block;
     add_round_key(&mut s, &round_keys[160..]); // undo the final AddRoundKey from encryption (round key 10)
     for round in (1..10).rev() {
@@ -367,36 +519,31 @@ fn aes128_decrypt_block(block: &Block, round_keys: &[u8; 176]) -> Block {
 /// The first 16 bytes are used as the AES cipher key and the second 16 bytes
 /// as the tweak key, matching Nintendo's convention.
 ///
-/// Returns the 0xC00-byte plaintext header region.
+/// Returns the 0xC00-byte plaintext header region, or
+/// [`Error::UnexpectedEof`] if `encrypted` is shorter than that.
 ///
 /// For NCA3, sectors are numbered 0-5 contiguously.
 /// For NCA2, the two NCA header sectors (0-1) are decrypted normally, but
 /// each FsHeader sector is decrypted independently as sector 0.
 ///
 /// The NCA version is detected automatically from the decrypted header.
-pub fn decrypt_header(encrypted: &[u8], header_key: &[u8; 32]) -> [u8; 0xC00] {
-    assert!(
-        encrypted.len() >= 0xC00,
-        "NCA header region must be at least 0xC00 bytes"
-    );
-
-    // Split the 32-byte header_key into two independent 16-byte AES keys per the XTS specification.
-    // k1 is the data encryption key (used to decrypt the actual content of each sector).
-    // k2 is the tweak encryption key (used only to encrypt the sector number into the XTS tweak value).
-    // They must be independent - reusing the same key for both halves would weaken XTS's security guarantees.
-    let k1: [u8; 16] = header_key[..16].try_into().unwrap();
-    let k2: [u8; 16] = header_key[16..].try_into().unwrap();
+/// To decrypt many headers, expand the key once with [`Aes128Xts::new`] and
+/// call [`decrypt_header_with_cipher`].
+pub fn decrypt_header(encrypted: &[u8], header_key: &[u8; 32]) -> Result<[u8; 0xC00]> {
+    decrypt_header_with_cipher(encrypted, &Aes128Xts::new(header_key))
+}
 
+/// Like [`decrypt_header`], with the header key already expanded.
+pub fn decrypt_header_with_cipher(encrypted: &[u8], cipher: &Aes128Xts) -> Result<[u8; 0xC00]> {
+    let encrypted = encrypted.get(..0xC00).ok_or(Error::UnexpectedEof)?;
     let mut out = [0u8; 0xC00];
+    out.copy_from_slice(encrypted);
 
     // Decrypt the first two sectors (sectors 0 and 1), which hold the main NCA header structure.
     // Both NCA2 and NCA3 number these two sectors the same way, so no version check is needed yet.
     // The NCA header contains the magic, crypto type, key generation, and section table.
-    for sector in 0..2usize {
-        let off = sector * 0x200;
-        let mut block: [u8; 0x200] = encrypted[off..off + 0x200].try_into().unwrap();
-        xts_decrypt_sector(&mut block, &k1, &k2, sector as u64);
-        out[off..off + 0x200].copy_from_slice(&block);
+    for (sector, block) in out[..0x400].chunks_exact_mut(0x200).enumerate() {
+        cipher.decrypt_sector(block, sector as u64);
     }
 
     // Detect NCA version by reading the 4-byte magic from the decrypted output.
@@ -408,15 +555,38 @@ pub fn decrypt_header(encrypted: &[u8], header_key: &[u8; 32]) -> [u8; 0xC00] {
     // Each FsHeader describes one filesystem partition entry: crypto type, hash type, key generation, etc.
     // NCA3: FsHeaders use contiguous sector numbers 2, 3, 4, 5 (continuing from the NCA header sectors).
     // NCA2: each FsHeader is independently encrypted as sector 0, regardless of its position in the header.
-    for fs in 0..4usize {
+    for (fs, block) in out[0x400..].chunks_exact_mut(0x200).enumerate() {
         let sector = if is_nca2 { 0 } else { (fs + 2) as u64 }; // NCA2 always decrypts each FsHeader with tweak for sector 0
-        let off = 0x400 + fs * 0x200;
-        let mut block: [u8; 0x200] = encrypted[off..off + 0x200].try_into().unwrap();
-        xts_decrypt_sector(&mut block, &k1, &k2, sector);
-        out[off..off + 0x200].copy_from_slice(&block);
+        cipher.decrypt_sector(block, sector);
     }
 
-    out
+    Ok(out)
+}
+
+/// Encrypt a 0xC00-byte plaintext NCA header with AES-128-XTS.
+///
+/// The inverse of [`decrypt_header`]: the sector numbering (including the
+/// NCA2 convention of encrypting every FsHeader as sector 0) is chosen from
+/// the plaintext magic at offset 0x200. Use this to write back a header
+/// after modifying its fields. Returns [`Error::UnexpectedEof`] if
+/// `plaintext` is shorter than 0xC00 bytes.
+pub fn encrypt_header(plaintext: &[u8], header_key: &[u8; 32]) -> Result<[u8; 0xC00]> {
+    encrypt_header_with_cipher(plaintext, &Aes128Xts::new(header_key))
+}
+
+/// Like [`encrypt_header`], with the header key already expanded.
+pub fn encrypt_header_with_cipher(plaintext: &[u8], cipher: &Aes128Xts) -> Result<[u8; 0xC00]> {
+    let plaintext = plaintext.get(..0xC00).ok_or(Error::UnexpectedEof)?;
+    let is_nca2 = &plaintext[0x200..0x204] == b"NCA2";
+
+    let mut out = [0u8; 0xC00];
+    out.copy_from_slice(plaintext);
+    for (i, sector) in out.chunks_exact_mut(0x200).enumerate() {
+        // Sectors 0-1 are the NCA header; 2-5 are the FsHeaders, numbered 0 on NCA2.
+        let number = if is_nca2 && i >= 2 { 0 } else { i as u64 };
+        cipher.encrypt_sector(sector, number);
+    }
+    Ok(out)
 }
 
 /// Decrypt NCA section data in-place using AES-128-CTR.
@@ -433,26 +603,55 @@ pub fn decrypt_header(encrypted: &[u8], header_key: &[u8; 32]) -> [u8; 0xC00] {
 /// * `data` - The section data to decrypt (modified in-place).
 /// * `key` - 16-byte section key (decrypted from the NCA key area using the KAEK).
 /// * `counter` - 16-byte initial counter value, built from the `FsHeader` fields:
-///     - bytes `[0..8]` = `SecureValue` (big-endian `u64`) - unique per section, prevents counter reuse across sections
-///     - bytes `[8..16]` = offset within section / 0x10 (big-endian `u64`) - advances per 16-byte block
+///     - bytes `[0..8]` = `SecureValue` and `Generation` (big-endian `u64`) - unique per section, prevents counter reuse across sections
+///     - bytes `[8..16]` = NCA offset / 0x10 (big-endian `u64`) - advances per 16-byte block
+///
+///   [`build_ctr`] assembles this layout.
 pub fn decrypt_section_ctr(data: &mut [u8], key: &[u8; 16], counter: &[u8; 16]) {
-    let rk = key_expand(key);
+    apply_ctr_keystream(data, &Aes128::new(key), counter);
+}
+
+/// Decrypt NCA section data in-place using AES-128-CTR, given where it
+/// lies in the NCA rather than a prepared counter.
+///
+/// `byte_offset` is the absolute offset of `data[0]` within the NCA and
+/// need not be 16-byte aligned: the keystream is taken from the middle of
+/// the first block as needed. `secure_value` is the upper half of the
+/// counter, as for [`build_ctr`] (see also
+/// [`FsHeader::ctr_upper`](crate::formats::nca::FsHeader::ctr_upper)).
+pub fn decrypt_section_ctr_at(
+    data: &mut [u8],
+    key: &[u8; 16],
+    secure_value: u64,
+    byte_offset: u64,
+) {
+    let counter = build_ctr(secure_value, byte_offset);
+    let skip = (byte_offset % 0x10) as usize;
+    apply_ctr_keystream_from(data, &Aes128::new(key), &counter, skip);
+}
+
+// XOR `data` with the AES-CTR keystream starting at `counter`. Shared by the
+// one-shot and streaming decryptors so the round keys can be expanded once.
+fn apply_ctr_keystream(data: &mut [u8], rk: &Aes128, counter: &[u8; 16]) {
+    apply_ctr_keystream_from(data, rk, counter, 0);
+}
+
+// Like `apply_ctr_keystream`, but `data` starts `skip` bytes into the block
+// for `counter`, so the first `skip` keystream bytes are discarded.
+fn apply_ctr_keystream_from(data: &mut [u8], rk: &Aes128, counter: &[u8; 16], skip: usize) {
     let mut ctr = *counter;
     let mut keystream = [0u8; 16]; // one AES-encrypted counter block = 16 bytes of keystream
     let mut ks_pos = 16; // index into keystream; initialized to 16 so the first byte triggers generation
+    if skip != 0 {
+        keystream = rk.encrypt_block(&ctr); // the partial first block still uses the whole counter block
+        increment_ctr(&mut ctr);
+        ks_pos = skip;
+    }
 
     for byte in data.iter_mut() {
         if ks_pos == 16 {
-            keystream = aes128_encrypt_block(&ctr, &rk); // encrypt the counter block to produce 16 fresh keystream bytes
-            // Increment the counter as a 128-bit big-endian unsigned integer.
-            // Big-endian increment matches Nintendo's CTR layout (high bytes at low addresses).
-            // wrapping_add is used because counter overflow is expected and intentional.
-            for i in (0..16).rev() {
-                ctr[i] = ctr[i].wrapping_add(1);
-                if ctr[i] != 0 {
-                    break;
-                } // no carry into the next byte, so stop propagating
-            }
+            keystream = rk.encrypt_block(&ctr); // encrypt the counter block to produce 16 fresh keystream bytes
+            increment_ctr(&mut ctr);
             ks_pos = 0;
         }
         *byte ^= keystream[ks_pos]; // XOR one byte of data with one byte of keystream (same op for encrypt and decrypt)
@@ -460,6 +659,293 @@ pub fn decrypt_section_ctr(data: &mut [u8], key: &[u8; 16], counter: &[u8; 16])
     }
 }
 
+// Increment the counter as a 128-bit big-endian unsigned integer.
+// Big-endian increment matches Nintendo's CTR layout (high bytes at low addresses).
+// wrapping_add is used because counter overflow is expected and intentional.
+fn increment_ctr(ctr: &mut Block) {
+    for i in (0..16).rev() {
+        ctr[i] = ctr[i].wrapping_add(1);
+        if ctr[i] != 0 {
+            break;
+        } // no carry into the next byte, so stop propagating
+    }
+}
+
+/// Streaming AES-128-CTR decryptor over an encrypted region of `R`.
+///
+/// Unlike [`decrypt_section_ctr`], nothing is buffered beyond the caller's
+/// read: each read regenerates the counter from the current position, so
+/// multi-gigabyte sections can be read and seeked at random.
+///
+/// Positions are relative to the start of the region. The counter for a
+/// position `p` is the initial counter plus `p / 0x10`, treated as a 128-bit
+/// big-endian integer.
+pub struct AesCtrReader<R> {
+    inner: R,
+    /// Stream offset of the first byte of the region in `inner`.
+    start: u64,
+    size: u64,
+    pos: u64,
+    cipher: Aes128,
+    /// Counter of the first 16-byte block of the region.
+    counter: [u8; 16],
+}
+
+impl<R: Read + Seek> AesCtrReader<R> {
+    /// Wrap `size` bytes of `inner` starting at stream offset `start`.
+    ///
+    /// `counter` is the counter of the block at `start`. For an NCA section
+    /// this is [`FsHeader::build_ctr_base`](crate::formats::nca::FsHeader::build_ctr_base)
+    /// with bytes `[8..16]` set to the section's NCA offset / 0x10.
+    pub fn new(inner: R, key: &[u8; 16], counter: [u8; 16], start: u64, size: u64) -> Self {
+        Self {
+            inner,
+            start,
+            size,
+            pos: 0,
+            cipher: Aes128::new(key),
+            counter,
+        }
+    }
+}
+
+impl<R> AesCtrReader<R> {
+    /// Size of the region in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Replace bytes `[4..8]` of the counter, as the per-range generation of
+    /// AES-CTR-Ex (BKTR patch) sections does.
+    pub fn set_generation(&mut self, generation: u32) {
+        self.counter[4..8].copy_from_slice(&generation.to_be_bytes());
+    }
+
+    /// Consume the reader, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn counter_at(&self, block: u64) -> [u8; 16] {
+        u128::from_be_bytes(self.counter)
+            .wrapping_add(block as u128)
+            .to_be_bytes()
+    }
+}
+
+impl<R: Read + Seek> Read for AesCtrReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let skip = (self.pos % 0x10) as usize;
+        let aligned = self.pos - skip as u64;
+        let ctr = self.counter_at(aligned >> 4);
+
+        // An unaligned position only completes its current block, so every
+        // later read starts on a block boundary and decrypts in place.
+        let len = if skip != 0 {
+            let mut block = [0u8; 16];
+            let avail = (self.size - aligned).min(16) as usize;
+            self.inner.seek(SeekFrom::Start(self.start + aligned))?;
+            self.inner.read_exact(&mut block[..avail])?;
+            apply_ctr_keystream(&mut block[..avail], &self.cipher, &ctr);
+
+            let len = buf.len().min(avail - skip);
+            buf[..len].copy_from_slice(&block[skip..skip + len]);
+            len
+        } else {
+            let len = (buf.len() as u64).min(remaining) as usize;
+            self.inner.seek(SeekFrom::Start(self.start + self.pos))?;
+            self.inner.read_exact(&mut buf[..len])?;
+            apply_ctr_keystream(&mut buf[..len], &self.cipher, &ctr);
+            len
+        };
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R> Seek for AesCtrReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.size.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        self.pos = new.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before region start")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Streaming AES-128-XTS decryptor over an encrypted region of `R`.
+///
+/// Used for content that is XTS-encrypted as a whole, such as NAX0 files
+/// (0x4000-byte sectors) and save images. Sectors are decrypted on demand
+/// and the most recent one is cached, so sequential reads decrypt each
+/// sector once. Tweaks use Nintendo's big-endian sector numbering, as in
+/// [`decrypt_header`].
+///
+/// Positions are relative to the start of the region, which must begin on a
+/// sector boundary.
+pub struct AesXtsReader<R> {
+    inner: R,
+    /// Stream offset of the first byte of the region in `inner`.
+    start: u64,
+    size: u64,
+    pos: u64,
+    sector_size: usize,
+    /// Sector number of the first sector of the region.
+    first_sector: u64,
+    cipher: Aes128Xts,
+    /// Index (relative to the region) and plaintext of the cached sector.
+    cached: Option<(u64, Vec<u8>)>,
+}
+
+impl<R: Read + Seek> AesXtsReader<R> {
+    /// Wrap `size` bytes of `inner` starting at stream offset `start`.
+    ///
+    /// `key` is the 32-byte XTS key (data key followed by tweak key) and
+    /// `first_sector` the sector number used for the tweak of the first
+    /// sector.
+    ///
+    /// # Panics
+    /// Panics if `sector_size` is zero or not a multiple of 16.
+    pub fn new(
+        inner: R,
+        key: &[u8; 32],
+        start: u64,
+        size: u64,
+        sector_size: usize,
+        first_sector: u64,
+    ) -> Self {
+        Self::with_cipher(
+            inner,
+            Aes128Xts::new(key),
+            start,
+            size,
+            sector_size,
+            first_sector,
+        )
+    }
+
+    /// Like [`new`](Self::new), with the XTS key already expanded.
+    ///
+    /// # Panics
+    /// Panics if `sector_size` is zero or not a multiple of 16.
+    pub fn with_cipher(
+        inner: R,
+        cipher: Aes128Xts,
+        start: u64,
+        size: u64,
+        sector_size: usize,
+        first_sector: u64,
+    ) -> Self {
+        assert!(
+            sector_size != 0 && sector_size.is_multiple_of(16),
+            "XTS sector size must be a non-zero multiple of 16"
+        );
+        Self {
+            inner,
+            start,
+            size,
+            pos: 0,
+            sector_size,
+            first_sector,
+            cipher,
+            cached: None,
+        }
+    }
+
+    fn load_sector(&mut self, index: u64) -> io::Result<&[u8]> {
+        if self.cached.as_ref().is_none_or(|(i, _)| *i != index) {
+            let offset = index * self.sector_size as u64;
+            let len = (self.size - offset).min(self.sector_size as u64) as usize;
+            let mut data = self.cached.take().map(|(_, v)| v).unwrap_or_default();
+            data.resize(len, 0);
+            self.inner.seek(SeekFrom::Start(self.start + offset))?;
+            self.inner.read_exact(&mut data)?;
+            self.cipher
+                .decrypt_sector(&mut data, self.first_sector + index);
+            self.cached = Some((index, data));
+        }
+        Ok(&self.cached.as_ref().unwrap().1)
+    }
+}
+
+impl<R> AesXtsReader<R> {
+    /// Size of the region in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Consume the reader, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for AesXtsReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let index = self.pos / self.sector_size as u64;
+        let skip = (self.pos % self.sector_size as u64) as usize;
+        let sector = self.load_sector(index)?;
+
+        let len = buf.len().min(sector.len() - skip);
+        buf[..len].copy_from_slice(&sector[skip..skip + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R> Seek for AesXtsReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.size.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        self.pos = new.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before region start")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Encrypt NCA section data in-place using AES-128-CTR.
+///
+/// CTR encryption and decryption are the same keystream XOR; this exists so
+/// writers read as what they do. See [`decrypt_section_ctr`] for the
+/// counter layout, and [`build_ctr`] to construct it.
+pub fn encrypt_section_ctr(data: &mut [u8], key: &[u8; 16], counter: &[u8; 16]) {
+    apply_ctr_keystream(data, &Aes128::new(key), counter);
+}
+
+/// Build the AES-CTR counter for the 16-byte block containing `byte_offset`.
+///
+/// `secure_value` is the upper half of the counter: the FsHeader's
+/// `SecureValue` in the high 32 bits and `Generation` in the low 32 bits
+/// (the little-endian `u64` at FsHeader offset 0x140). `byte_offset` is the
+/// absolute offset within the NCA; the low 4 bits are ignored.
+///
+/// ```text
+/// [0x00..0x08]  secure_value      (u64 BE)
+/// [0x08..0x10]  byte_offset / 0x10 (u64 BE)
+/// ```
+pub fn build_ctr(secure_value: u64, byte_offset: u64) -> [u8; 16] {
+    let mut ctr = [0u8; 16];
+    ctr[..8].copy_from_slice(&secure_value.to_be_bytes());
+    ctr[8..].copy_from_slice(&(byte_offset >> 4).to_be_bytes());
+    ctr
+}
+
 /// Decrypt a single 16-byte block with AES-128-ECB (used for NCA key area decryption).
 ///
 /// ECB (Electronic Codebook) mode applies the block cipher directly with no IV, no chaining,
@@ -470,6 +956,59 @@ pub fn decrypt_section_ctr(data: &mut [u8], key: &[u8; 16], counter: &[u8; 16])
 ///
 /// <https://en.wikipedia.org/wiki/Block_cipher_mode_of_operation#Electronic_codebook_(ECB)>
 pub fn decrypt_block_ecb(block: &[u8; 16], key: &[u8; 16]) -> [u8; 16] {
-    let rk = key_expand(key);
-    aes128_decrypt_block(block, &rk)
+    let rk = Aes128::new(key);
+    rk.decrypt_block(block)
+}
+
+/// Encrypt `data` in-place with AES-128-CBC.
+///
+/// CBC (Cipher Block Chaining) XORs each plaintext block with the previous ciphertext block
+/// (or the IV for the first block) before encrypting it, so identical blocks encrypt differently.
+/// Encryption is inherently sequential, but decryption of any block needs only the ciphertext
+/// block before it. No padding is applied; callers encrypt fixed-size, block-aligned structures.
+///
+/// <https://en.wikipedia.org/wiki/Block_cipher_mode_of_operation#Cipher_block_chaining_(CBC)>
+///
+/// # Panics
+/// Panics if `data.len()` is not a multiple of 16.
+pub fn encrypt_cbc(data: &mut [u8], key: &[u8; 16], iv: &[u8; 16]) {
+    assert!(
+        data.len().is_multiple_of(16),
+        "AES-CBC data must be a multiple of 16 bytes"
+    );
+    let rk = Aes128::new(key);
+    let mut prev = *iv;
+    for chunk in data.chunks_exact_mut(16) {
+        let mut block: Block = chunk.try_into().unwrap();
+        for i in 0..16 {
+            block[i] ^= prev[i];
+        } // chain: mix in the previous ciphertext block before encrypting
+        prev = rk.encrypt_block(&block);
+        chunk.copy_from_slice(&prev);
+    }
+}
+
+/// Decrypt `data` in-place with AES-128-CBC.
+///
+/// Used for the XCI `CardHeaderEncryptedData` (0x70 bytes, keyed with the
+/// XCI header key and the byte-reversed IV stored in the CardHeader).
+///
+/// # Panics
+/// Panics if `data.len()` is not a multiple of 16.
+pub fn decrypt_cbc(data: &mut [u8], key: &[u8; 16], iv: &[u8; 16]) {
+    assert!(
+        data.len().is_multiple_of(16),
+        "AES-CBC data must be a multiple of 16 bytes"
+    );
+    let rk = Aes128::new(key);
+    let mut prev = *iv;
+    for chunk in data.chunks_exact_mut(16) {
+        let ciphertext: Block = chunk.try_into().unwrap();
+        let mut block = rk.decrypt_block(&ciphertext);
+        for i in 0..16 {
+            block[i] ^= prev[i];
+        } // unchain: XOR with the previous ciphertext block to recover the plaintext
+        chunk.copy_from_slice(&block);
+        prev = ciphertext;
+    }
 }
@@ -0,0 +1,225 @@
+//! RSA-2048 signature verification.
+//!
+//! Nintendo signs NCA headers, ACIDs, and tickets with RSA-2048 using the
+//! public exponent 65537. NCA headers and ACIDs use RSA-PSS with SHA-256 and
+//! a 32-byte salt, which is what [`verify_pss_sha256`] checks.
+//!
+//! Modular exponentiation is implemented with Montgomery multiplication over
+//! 32-bit limbs. Only public-key operations are provided, so the lack of
+//! constant-time guarantees does not expose any secret.
+
+use super::sha256::{DIGEST_SIZE, sha256};
+
+/// Size of an RSA-2048 modulus or signature in bytes.
+pub const RSA_2048_SIZE: usize = 0x100;
+
+const LIMBS: usize = RSA_2048_SIZE / 4;
+
+/// A 2048-bit unsigned integer, least-significant limb first.
+type Num = [u32; LIMBS];
+
+/// Verify an RSA-2048 PSS signature (SHA-256, MGF1-SHA256, 32-byte salt,
+/// public exponent 65537) over `message`.
+///
+/// Returns `false` for any malformed or non-matching signature.
+pub fn verify_pss_sha256(
+    message: &[u8],
+    signature: &[u8; RSA_2048_SIZE],
+    modulus: &[u8; RSA_2048_SIZE],
+) -> bool {
+    let Some(em) = public_op(signature, modulus) else {
+        return false;
+    };
+
+    let mod_bits = modulus_bits(modulus);
+    if mod_bits < 8 * (2 * DIGEST_SIZE + 2) {
+        return false;
+    }
+    let em_bits = mod_bits - 1;
+    let em_len = em_bits.div_ceil(8);
+    // When emBits is a multiple of 8 the encoded message is one byte shorter
+    // than the modulus, and that leading byte must be zero.
+    let (lead, em) = em.split_at(RSA_2048_SIZE - em_len);
+    if lead.iter().any(|&b| b != 0) {
+        return false;
+    }
+
+    if em[em_len - 1] != 0xBC {
+        return false;
+    }
+    let db_len = em_len - DIGEST_SIZE - 1;
+    let (masked_db, rest) = em.split_at(db_len);
+    let h = &rest[..DIGEST_SIZE];
+
+    let top_mask = 0xFFu8 >> (8 * em_len - em_bits);
+    if masked_db[0] & !top_mask != 0 {
+        return false;
+    }
+
+    let mut db = mgf1_sha256(h, db_len);
+    for (d, m) in db.iter_mut().zip(masked_db) {
+        *d ^= m;
+    }
+    db[0] &= top_mask;
+
+    // DB = PS (zeros) || 0x01 || salt
+    let salt_len = DIGEST_SIZE;
+    let ps_len = db_len - salt_len - 1;
+    if db[..ps_len].iter().any(|&b| b != 0) || db[ps_len] != 0x01 {
+        return false;
+    }
+    let salt = &db[ps_len + 1..];
+
+    let mut m_prime = Vec::with_capacity(8 + DIGEST_SIZE + salt_len);
+    m_prime.extend_from_slice(&[0u8; 8]);
+    m_prime.extend_from_slice(&sha256(message));
+    m_prime.extend_from_slice(salt);
+    sha256(&m_prime) == h
+}
+
+/// Compute `signature ^ 65537 mod modulus` as big-endian bytes.
+///
+/// Returns `None` if the modulus is even or the signature is not smaller
+/// than the modulus.
+fn public_op(
+    signature: &[u8; RSA_2048_SIZE],
+    modulus: &[u8; RSA_2048_SIZE],
+) -> Option<[u8; RSA_2048_SIZE]> {
+    let n = from_be(modulus);
+    let s = from_be(signature);
+    if n[0] & 1 == 0 || geq(&s, &n) {
+        return None;
+    }
+
+    let n0inv = neg_inv_u32(n[0]);
+    let r2 = r_squared(&n);
+    let mut one = [0u32; LIMBS];
+    one[0] = 1;
+
+    // e = 65537 = 2^16 + 1: sixteen squarings and one multiplication.
+    let sm = mont_mul(&s, &r2, &n, n0inv);
+    let mut y = sm;
+    for _ in 0..16 {
+        y = mont_mul(&y, &y, &n, n0inv);
+    }
+    y = mont_mul(&y, &sm, &n, n0inv);
+    Some(to_be(&mont_mul(&y, &one, &n, n0inv)))
+}
+
+/// Mask generation function MGF1 with SHA-256.
+fn mgf1_sha256(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + DIGEST_SIZE);
+    let mut input = Vec::with_capacity(seed.len() + 4);
+    let mut counter = 0u32;
+    while out.len() < len {
+        input.clear();
+        input.extend_from_slice(seed);
+        input.extend_from_slice(&counter.to_be_bytes());
+        out.extend_from_slice(&sha256(&input));
+        counter += 1;
+    }
+    out.truncate(len);
+    out
+}
+
+fn modulus_bits(modulus: &[u8; RSA_2048_SIZE]) -> usize {
+    match modulus.iter().position(|&b| b != 0) {
+        Some(i) => (RSA_2048_SIZE - i) * 8 - modulus[i].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn from_be(bytes: &[u8; RSA_2048_SIZE]) -> Num {
+    let mut n = [0u32; LIMBS];
+    for (limb, chunk) in n.iter_mut().zip(bytes.rchunks_exact(4)) {
+        *limb = u32::from_be_bytes(chunk.try_into().unwrap());
+    }
+    n
+}
+
+fn to_be(n: &Num) -> [u8; RSA_2048_SIZE] {
+    let mut out = [0u8; RSA_2048_SIZE];
+    for (chunk, limb) in out.rchunks_exact_mut(4).zip(n) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+    out
+}
+
+fn geq(a: &Num, b: &Num) -> bool {
+    for i in (0..LIMBS).rev() {
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+    true
+}
+
+fn sub_assign(a: &mut Num, b: &Num) {
+    let mut borrow = 0u64;
+    for (x, &y) in a.iter_mut().zip(b) {
+        let d = (*x as u64).wrapping_sub(y as u64).wrapping_sub(borrow);
+        *x = d as u32;
+        borrow = (d >> 63) & 1;
+    }
+}
+
+// -n0^-1 mod 2^32 via Newton iteration (each step doubles the correct bits).
+fn neg_inv_u32(n0: u32) -> u32 {
+    let mut inv = 1u32;
+    for _ in 0..5 {
+        inv = inv.wrapping_mul(2u32.wrapping_sub(n0.wrapping_mul(inv)));
+    }
+    inv.wrapping_neg()
+}
+
+// R^2 mod n for R = 2^2048, by doubling 1 a total of 4096 times.
+fn r_squared(n: &Num) -> Num {
+    let mut x = [0u32; LIMBS];
+    x[0] = 1;
+    for _ in 0..2 * 32 * LIMBS {
+        let mut carry = 0u32;
+        for limb in x.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 || geq(&x, n) {
+            sub_assign(&mut x, n);
+        }
+    }
+    x
+}
+
+// Montgomery product a * b * R^-1 mod n (coarsely integrated operand scanning).
+fn mont_mul(a: &Num, b: &Num, n: &Num, n0inv: u32) -> Num {
+    let mut t = [0u32; LIMBS + 2];
+    for &bi in b {
+        let mut c = 0u64;
+        for j in 0..LIMBS {
+            let x = t[j] as u64 + a[j] as u64 * bi as u64 + c;
+            t[j] = x as u32;
+            c = x >> 32;
+        }
+        let x = t[LIMBS] as u64 + c;
+        t[LIMBS] = x as u32;
+        t[LIMBS + 1] = (x >> 32) as u32;
+
+        let m = t[0].wrapping_mul(n0inv);
+        let mut c = (t[0] as u64 + m as u64 * n[0] as u64) >> 32;
+        for j in 1..LIMBS {
+            let x = t[j] as u64 + m as u64 * n[j] as u64 + c;
+            t[j - 1] = x as u32;
+            c = x >> 32;
+        }
+        let x = t[LIMBS] as u64 + c;
+        t[LIMBS - 1] = x as u32;
+        t[LIMBS] = t[LIMBS + 1] + (x >> 32) as u32;
+    }
+
+    let mut out = [0u32; LIMBS];
+    out.copy_from_slice(&t[..LIMBS]);
+    if t[LIMBS] != 0 || geq(&out, n) {
+        sub_assign(&mut out, n);
+    }
+    out
+}
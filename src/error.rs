@@ -8,8 +8,33 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 /// All errors the library can produce.
 ///
-/// Error messages are kept intentionally terse; callers that need richer
-/// context should wrap `Error` in their own type.
+/// Errors raised while parsing a container's header or tables are wrapped
+/// in [`Error::Context`], which says where they happened:
+/// ```text
+/// SARC: SFAT entry 512 at 0x2140: hash: unexpected end of file
+/// ```
+/// Use [`root`](Self::root) to match on the underlying error.
+///
+/// [`code`](Self::code) and [`category`](Self::category) identify an error
+/// without parsing its message, for FFI and logging. Codes never change
+/// meaning once assigned:
+///
+/// | Code | Variant                | Category      |
+/// |------|------------------------|---------------|
+/// | 1    | `BadMagic`             | `Format`      |
+/// | 2    | `UnsupportedVersion`   | `Format`      |
+/// | 3    | `UnexpectedEof`        | `Format`      |
+/// | 4    | `UnterminatedName`     | `Format`      |
+/// | 5    | `InvalidRange`         | `Format`      |
+/// | 6    | `Parse`                | `Format`      |
+/// | 7    | `NotFound`             | `NotFound`    |
+/// | 8    | `MissingKey`           | `Key`         |
+/// | 9    | `Io`                   | `Io`          |
+/// | 10   | `Lz4`                  | `Compression` |
+/// | 11   | `Zstd`                 | `Compression` |
+/// | 12   | `Zlib`                 | `Compression` |
+///
+/// `Context` reports the code of the error it wraps.
 #[derive(Debug)]
 pub enum Error {
     /// A magic/signature field did not match the expected value.
@@ -25,14 +50,145 @@ pub enum Error {
     InvalidRange,
     /// A structural constraint was violated (message describes which one).
     Parse(&'static str),
+    /// No entry (file, partition, or texture) has the requested name.
+    NotFound { name: String },
+    /// A key required for decryption is absent from the
+    /// [`KeySet`](crate::keys::KeySet). Holds the key's `prod.keys` name.
+    MissingKey(String),
     /// An underlying I/O operation failed.
     Io(io::Error),
     /// LZ4 decompression failed.
     #[cfg(feature = "compression")]
     Lz4,
-    /// Zstandard decompression failed.
+    /// Zstandard compression or decompression failed.
     #[cfg(feature = "compression")]
     Zstd,
+    /// zlib decompression failed.
+    #[cfg(feature = "compression")]
+    Zlib,
+    /// `source` occurred while reading `item` of a `format` structure at
+    /// absolute stream `offset`.
+    Context {
+        /// Format being parsed, e.g. `"SARC"`.
+        format: &'static str,
+        /// Structure being read, e.g. `"SFAT entry 512"`.
+        item: String,
+        /// Absolute stream offset of `item`.
+        offset: u64,
+        /// Field of `item` being read or checked, if known.
+        field: Option<&'static str>,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// The innermost error, with any [`Error::Context`] layers removed.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Absolute stream offset of the innermost structure the error was
+    /// raised in, if known.
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Error::Context { offset, source, .. } => source.offset().or(Some(*offset)),
+            _ => None,
+        }
+    }
+
+    /// Stable numeric code of the variant; see the table on [`Error`].
+    ///
+    /// Codes are nonzero, so 0 can mean success across an FFI boundary.
+    pub fn code(&self) -> u32 {
+        match self {
+            Error::BadMagic => 1,
+            Error::UnsupportedVersion(_) => 2,
+            Error::UnexpectedEof => 3,
+            Error::UnterminatedName => 4,
+            Error::InvalidRange => 5,
+            Error::Parse(_) => 6,
+            Error::NotFound { .. } => 7,
+            Error::MissingKey(_) => 8,
+            Error::Io(_) => 9,
+            #[cfg(feature = "compression")]
+            Error::Lz4 => 10,
+            #[cfg(feature = "compression")]
+            Error::Zstd => 11,
+            #[cfg(feature = "compression")]
+            Error::Zlib => 12,
+            Error::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Broad class of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self.root() {
+            Error::NotFound { .. } => ErrorCategory::NotFound,
+            Error::MissingKey(_) => ErrorCategory::Key,
+            Error::Io(_) => ErrorCategory::Io,
+            #[cfg(feature = "compression")]
+            Error::Lz4 | Error::Zstd | Error::Zlib => ErrorCategory::Compression,
+            _ => ErrorCategory::Format,
+        }
+    }
+
+    /// A copy of the error, for errors that are stored and reported more
+    /// than once. I/O errors keep their kind and message.
+    pub(crate) fn duplicate(&self) -> Error {
+        match self {
+            Error::BadMagic => Error::BadMagic,
+            Error::UnsupportedVersion(v) => Error::UnsupportedVersion(*v),
+            Error::UnexpectedEof => Error::UnexpectedEof,
+            Error::UnterminatedName => Error::UnterminatedName,
+            Error::InvalidRange => Error::InvalidRange,
+            Error::Parse(msg) => Error::Parse(msg),
+            Error::NotFound { name } => Error::NotFound { name: name.clone() },
+            Error::MissingKey(name) => Error::MissingKey(name.clone()),
+            Error::Io(e) => Error::Io(io::Error::new(e.kind(), e.to_string())),
+            #[cfg(feature = "compression")]
+            Error::Lz4 => Error::Lz4,
+            #[cfg(feature = "compression")]
+            Error::Zstd => Error::Zstd,
+            #[cfg(feature = "compression")]
+            Error::Zlib => Error::Zlib,
+            Error::Context {
+                format,
+                item,
+                offset,
+                field,
+                source,
+            } => Error::Context {
+                format,
+                item: item.clone(),
+                offset: *offset,
+                field: *field,
+                source: Box::new(source.duplicate()),
+            },
+        }
+    }
+}
+
+/// Broad class of an [`Error`], from [`Error::category`].
+///
+/// The numeric values are stable, like [`Error::code`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u32)]
+pub enum ErrorCategory {
+    /// The data is malformed, truncated, or in an unsupported variant of
+    /// its format.
+    Format = 1,
+    /// A named entry does not exist.
+    NotFound = 2,
+    /// A required key is missing from the key set.
+    Key = 3,
+    /// The underlying reader or writer failed.
+    Io = 4,
+    /// A compression codec failed.
+    Compression = 5,
 }
 
 impl fmt::Display for Error {
@@ -44,21 +200,38 @@ impl fmt::Display for Error {
             Error::UnterminatedName => write!(f, "unterminated string"),
             Error::InvalidRange => write!(f, "invalid offset or size"),
             Error::Parse(s) => write!(f, "parse error: {s}"),
+            Error::NotFound { name } => write!(f, "no entry named {name:?}"),
+            Error::MissingKey(name) => write!(f, "missing key: {name}"),
             Error::Io(e) => write!(f, "I/O error: {e}"),
             #[cfg(feature = "compression")]
             Error::Lz4 => write!(f, "lz4 decompression failed"),
             #[cfg(feature = "compression")]
-            Error::Zstd => write!(f, "zstd decompression failed"),
+            Error::Zstd => write!(f, "zstd compression or decompression failed"),
+            #[cfg(feature = "compression")]
+            Error::Zlib => write!(f, "zlib decompression failed"),
+            Error::Context {
+                format,
+                item,
+                offset,
+                field,
+                source,
+            } => {
+                write!(f, "{format}: {item} at {offset:#x}: ")?;
+                if let Some(field) = field {
+                    write!(f, "{field}: ")?;
+                }
+                write!(f, "{source}")
+            }
         }
     }
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        if let Error::Io(e) = self {
-            Some(e)
-        } else {
-            None
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
         }
     }
 }
@@ -68,3 +241,70 @@ impl From<io::Error> for Error {
         Error::Io(e)
     }
 }
+
+/// Attach [`Error::Context`] to a parse result.
+pub(crate) trait Context<T> {
+    /// Wrap an error with the format, the absolute `offset` of the
+    /// structure being read, and its name, built only on failure.
+    fn at(self, format: &'static str, offset: u64, item: impl FnOnce() -> String) -> Result<T>;
+
+    /// Like [`at`](Self::at), also naming the field being read.
+    fn at_field(
+        self,
+        format: &'static str,
+        offset: u64,
+        item: impl FnOnce() -> String,
+        field: &'static str,
+    ) -> Result<T>;
+}
+
+impl<T> Context<T> for Result<T> {
+    fn at(self, format: &'static str, offset: u64, item: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|e| Error::Context {
+            format,
+            item: item(),
+            offset,
+            field: None,
+            source: Box::new(e),
+        })
+    }
+
+    fn at_field(
+        self,
+        format: &'static str,
+        offset: u64,
+        item: impl FnOnce() -> String,
+        field: &'static str,
+    ) -> Result<T> {
+        self.map_err(|e| Error::Context {
+            format,
+            item: item(),
+            offset,
+            field: Some(field),
+            source: Box::new(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_keeps_context_and_source() {
+        let e: Result<()> = Err(Error::Io(io::ErrorKind::PermissionDenied.into()));
+        let e = e
+            .at_field("NCA", 0x200, || "header".into(), "key")
+            .unwrap_err();
+        let copy = e.duplicate();
+        assert_eq!(copy.to_string(), e.to_string());
+        assert_eq!(copy.code(), 9);
+        assert_eq!(copy.offset(), Some(0x200));
+        assert!(
+            matches!(copy.root(), Error::Io(io) if io.kind() == io::ErrorKind::PermissionDenied)
+        );
+
+        let e = Error::MissingKey("titlekek_00".into());
+        assert!(matches!(e.duplicate(), Error::MissingKey(name) if name == "titlekek_00"));
+    }
+}
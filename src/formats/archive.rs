@@ -0,0 +1,69 @@
+//! Common interface over the archive readers.
+//!
+//! [`ArchiveRead`] is implemented by [`Pfs0Reader`], [`Hfs0Reader`],
+//! [`SarcReader`], and [`RomFsReader`], so code that lists or copies out
+//! entries can be written once for all of them:
+//! ```text
+//! fn dump<A: ArchiveRead>(archive: &mut A) -> hakkit::Result<()> {
+//!     for entry in archive.entries().cloned().collect::<Vec<_>>() {
+//!         let mut r = archive.open_entry(&entry)?;
+//!         io::copy(&mut r, &mut io::sink())?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Entry names are those the format stores: file names for PFS0 and HFS0,
+//! paths without a leading slash for SARC, and absolute paths (`/a/b.bin`)
+//! for RomFS. SARC entries without a name have none.
+//!
+//! [`Pfs0Reader`]: super::pfs0::Pfs0Reader
+//! [`Hfs0Reader`]: super::hfs0::Hfs0Reader
+//! [`SarcReader`]: super::sarc::SarcReader
+//! [`RomFsReader`]: super::romfs::RomFsReader
+
+use std::io::Read;
+
+use crate::{Error, Result};
+
+/// Metadata of one archive entry.
+pub trait ArchiveEntry {
+    /// Entry name or path, if the archive stores one.
+    fn name(&self) -> Option<&str>;
+
+    /// Size of the entry's data in bytes.
+    fn size(&self) -> u64;
+}
+
+/// An archive whose entries can be listed and read.
+pub trait ArchiveRead {
+    /// Entry metadata type.
+    type Entry: ArchiveEntry + Clone;
+
+    /// Stream over one entry's data, borrowing the archive.
+    type EntryReader<'a>: Read
+    where
+        Self: 'a;
+
+    /// Iterate over all entries in archive order.
+    fn entries(&self) -> impl Iterator<Item = &Self::Entry>;
+
+    /// Find an entry by name. Returns [`None`] if not found.
+    fn get_entry(&self, name: &str) -> Option<&Self::Entry>;
+
+    /// Find an entry by name. Returns [`Error::NotFound`] if not found.
+    fn try_get_entry(&self, name: &str) -> Result<&Self::Entry> {
+        self.get_entry(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    /// Open an entry for streaming access.
+    fn open_entry(&mut self, entry: &Self::Entry) -> Result<Self::EntryReader<'_>>;
+
+    /// Read a whole entry into memory.
+    fn read_entry_to_vec(&mut self, entry: &Self::Entry) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        self.open_entry(entry)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
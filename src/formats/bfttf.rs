@@ -20,12 +20,13 @@
 //! | Switch   | `15 9A 7D 6F 16 6F D0 0C 67 E7 39 98 0B EB F6 62` |
 //! | Windows  | `97 3B 5C 6C 26 F3 FA B5 A2 D5 8E B5 5A 4D D5 51` |
 
-use std::io::Read;
+use std::io::{ErrorKind, Read, Write};
 
 use crate::{Error, Result};
 
 /// Platform for which a BFTTF/BFOTF font is intended.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FontPlatform {
     WiiU,
     Switch,
@@ -52,11 +53,40 @@ impl FontPlatform {
     }
 }
 
+/// Kind of font inside a BFTTF/BFOTF, from its decrypted magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FontKind {
+    /// TrueType (`\x00\x01\x00\x00\x00`).
+    TrueType,
+    /// OpenType with CFF outlines (`OTTO`).
+    OpenType,
+    /// TrueType Collection (`ttcf`).
+    Collection,
+}
+
+impl FontKind {
+    /// Detect the font kind from the start of a decrypted font.
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&[0x00, 0x01, 0x00, 0x00, 0x00]) {
+            Some(Self::TrueType)
+        } else if head.starts_with(b"OTTO") {
+            Some(Self::OpenType)
+        } else if head.starts_with(b"ttcf") {
+            Some(Self::Collection)
+        } else {
+            None
+        }
+    }
+}
+
 /// Parsed BFTTF/BFOTF file (holds the raw encrypted bytes).
 #[derive(Debug)]
 pub struct Bfttf {
     /// Platform detected from the decrypted magic bytes.
     pub platform: FontPlatform,
+    /// Kind of the decrypted font.
+    pub kind: FontKind,
     data: Vec<u8>,
 }
 
@@ -69,22 +99,42 @@ impl Bfttf {
         let mut data = Vec::new();
         r.read_to_end(&mut data)?;
 
-        for &platform in &[
-            FontPlatform::Switch,
-            FontPlatform::WiiU,
-            FontPlatform::Windows,
-        ] {
-            if is_valid_font_after_xor(&data, platform.xor_key()) {
-                return Ok(Self { platform, data });
-            }
-        }
-        Err(Error::BadMagic)
+        let (platform, kind) = detect(&data).ok_or(Error::BadMagic)?;
+        Ok(Self {
+            platform,
+            kind,
+            data,
+        })
     }
 
     /// Decrypt to raw TTF/OTF bytes.
     pub fn decrypt(&self) -> Vec<u8> {
         xor_with_key(&self.data, self.platform.xor_key())
     }
+
+    /// Size of the decrypted font in bytes (the same as the encrypted size).
+    pub fn decrypted_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Consume the font, returning the encrypted bytes without copying.
+    pub fn into_encrypted_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Detect the platform and font kind from the first bytes of a BFTTF/BFOTF.
+///
+/// Tries each XOR key and checks the resulting font magic; `head` needs at
+/// least 5 bytes. Returns [`None`] if no platform matches.
+pub fn detect(head: &[u8]) -> Option<(FontPlatform, FontKind)> {
+    [
+        FontPlatform::Switch,
+        FontPlatform::WiiU,
+        FontPlatform::Windows,
+    ]
+    .into_iter()
+    .find_map(|platform| Some((platform, font_kind_after_xor(head, platform.xor_key())?)))
 }
 
 /// Decrypt a BFTTF/BFOTF byte slice for the given platform.
@@ -101,20 +151,66 @@ pub fn encrypt(data: &[u8], platform: FontPlatform) -> Vec<u8> {
     xor_with_key(data, platform.xor_key())
 }
 
+/// Decrypt a BFTTF/BFOTF stream from `r` into `w` for the given platform.
+///
+/// Streams through a fixed-size buffer, so the font is never held in memory.
+/// Returns the number of bytes written.
+pub fn decrypt_to<R: Read, W: Write>(r: &mut R, w: &mut W, platform: FontPlatform) -> Result<u64> {
+    xor_stream(r, w, platform.xor_key())
+}
+
+/// Encrypt a raw TTF/OTF stream from `r` into `w` for the given platform.
+///
+/// Streams through a fixed-size buffer, so the font is never held in memory.
+/// Returns the number of bytes written.
+pub fn encrypt_to<R: Read, W: Write>(r: &mut R, w: &mut W, platform: FontPlatform) -> Result<u64> {
+    xor_stream(r, w, platform.xor_key())
+}
+
+fn xor_stream<R: Read, W: Write>(r: &mut R, w: &mut W, key: &[u8; 16]) -> Result<u64> {
+    let mut buf = [0u8; 0x10000];
+    let mut total = 0u64;
+    loop {
+        let n = match r.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        // Reads may be short, so start the key at the absolute position.
+        xor_in_place(&mut buf[..n], key, (total % 16) as usize);
+        w.write_all(&buf[..n])?;
+        total += n as u64;
+    }
+}
+
 fn xor_with_key(data: &[u8], key: &[u8; 16]) -> Vec<u8> {
-    data.iter()
-        .enumerate()
-        .map(|(i, &b)| b ^ key[i % 16])
-        .collect()
+    let mut out = data.to_vec();
+    xor_in_place(&mut out, key, 0);
+    out
 }
 
-fn is_valid_font_after_xor(data: &[u8], key: &[u8; 16]) -> bool {
+/// XOR `buf` with `key` repeated, starting at key byte `phase`.
+///
+/// Works on whole 16-byte words: the key is rotated to `phase` once, so
+/// every full chunk of `buf` lines up with it.
+fn xor_in_place(buf: &mut [u8], key: &[u8; 16], phase: usize) {
+    let rotated: [u8; 16] = std::array::from_fn(|i| key[(phase + i) % 16]);
+    let word = u128::from_ne_bytes(rotated);
+    let mut chunks = buf.chunks_exact_mut(16);
+    for chunk in &mut chunks {
+        let chunk: &mut [u8; 16] = chunk.try_into().expect("chunk is 16 bytes");
+        *chunk = (u128::from_ne_bytes(*chunk) ^ word).to_ne_bytes();
+    }
+    for (b, k) in chunks.into_remainder().iter_mut().zip(rotated) {
+        *b ^= k;
+    }
+}
+
+fn font_kind_after_xor(data: &[u8], key: &[u8; 16]) -> Option<FontKind> {
     if data.len() < 5 {
-        return false;
+        return None;
     }
     let head: [u8; 5] = std::array::from_fn(|i| data[i] ^ key[i % 16]);
-
-    head.starts_with(&[0x00, 0x01, 0x00, 0x00, 0x00])  // TrueType
-        || head.starts_with(b"OTTO")                   // OpenType
-        || head.starts_with(b"ttcf") // TTC
+    FontKind::from_magic(&head)
 }
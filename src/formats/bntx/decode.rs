@@ -0,0 +1,547 @@
+//! BCn texture decoding to RGBA8 (requires the `texture-decode` feature).
+//!
+//! Decodes linear block data - deswizzle GPU data with
+//! [`swizzle::deswizzle`](super::swizzle::deswizzle) first - into a tightly
+//! packed RGBA8 buffer of `width * height * 4` bytes.
+//!
+//! | Format | Block | Output |
+//! |--------|-------|--------|
+//! | BC1 | 8 bytes | RGB + 1-bit alpha |
+//! | BC3 | 16 bytes | RGB + interpolated alpha |
+//! | BC4 | 8 bytes | Red replicated to RGB, alpha 255 |
+//! | BC5 | 16 bytes | Red and green, blue 0, alpha 255 |
+//! | BC7 | 16 bytes | RGBA, all eight modes |
+//!
+//! Signed (`Snorm`) BC4/BC5 values are offset by 128 so that 0 maps to 128.
+
+use super::format::{ChannelType, TextureFormat};
+use crate::{Error, Result};
+
+type Block = [[u8; 4]; 16];
+
+/// Decode `data` in `format` to RGBA8.
+///
+/// `channel_type` selects signed decoding for BC4/BC5. Returns
+/// [`Error::Parse`] for formats other than BC1/BC3/BC4/BC5/BC7.
+pub fn decode_rgba8(
+    format: TextureFormat,
+    channel_type: ChannelType,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>> {
+    let signed = channel_type == ChannelType::Snorm;
+    match format {
+        TextureFormat::Bc1 => decode_bc1(data, width, height),
+        TextureFormat::Bc3 => decode_bc3(data, width, height),
+        TextureFormat::Bc4 => decode_bc4(data, width, height, signed),
+        TextureFormat::Bc5 => decode_bc5(data, width, height, signed),
+        TextureFormat::Bc7 => decode_bc7(data, width, height),
+        _ => Err(Error::Parse("unsupported texture format")),
+    }
+}
+
+/// Decode BC1 (DXT1) data to RGBA8.
+pub fn decode_bc1(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 8, |b| bc1_block(b, true))
+}
+
+/// Decode BC3 (DXT5) data to RGBA8.
+pub fn decode_bc3(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 16, |b| {
+        let alpha = bc4_block(&b[..8], false);
+        let mut px = bc1_block(&b[8..], false);
+        for (p, a) in px.iter_mut().zip(alpha) {
+            p[3] = a;
+        }
+        px
+    })
+}
+
+/// Decode BC4 (RGTC1) data to RGBA8.
+pub fn decode_bc4(data: &[u8], width: u32, height: u32, signed: bool) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 8, |b| {
+        bc4_block(b, signed).map(|r| [r, r, r, 255])
+    })
+}
+
+/// Decode BC5 (RGTC2) data to RGBA8.
+pub fn decode_bc5(data: &[u8], width: u32, height: u32, signed: bool) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 16, |b| {
+        let red = bc4_block(&b[..8], signed);
+        let green = bc4_block(&b[8..], signed);
+        std::array::from_fn(|i| [red[i], green[i], 0, 255])
+    })
+}
+
+/// Decode BC7 (BPTC) data to RGBA8.
+pub fn decode_bc7(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    decode_blocks(data, width, height, 16, bc7_block)
+}
+
+/// Run `decode` over every 4×4 block and copy the in-bounds texels out.
+fn decode_blocks(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    block_size: usize,
+    decode: impl Fn(&[u8]) -> Block,
+) -> Result<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    let blocks_x = width.div_ceil(4);
+    let blocks_y = height.div_ceil(4);
+    if data.len() < blocks_x * blocks_y * block_size {
+        return Err(Error::UnexpectedEof);
+    }
+
+    let mut out = vec![0u8; width * height * 4];
+    for (i, block) in data
+        .chunks_exact(block_size)
+        .take(blocks_x * blocks_y)
+        .enumerate()
+    {
+        let (bx, by) = (i % blocks_x * 4, i / blocks_x * 4);
+        let px = decode(block);
+        for y in 0..4.min(height - by) {
+            for x in 0..4.min(width - bx) {
+                let dst = ((by + y) * width + bx + x) * 4;
+                out[dst..dst + 4].copy_from_slice(&px[y * 4 + x]);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn rgb565(c: u16) -> [u8; 4] {
+    let r = (c >> 11 & 0x1F) as u8;
+    let g = (c >> 5 & 0x3F) as u8;
+    let b = (c & 0x1F) as u8;
+    [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2, 255]
+}
+
+/// Decode an 8-byte BC1 colour block. BC2/BC3 colour blocks always use
+/// four-colour mode (`allow_alpha = false`).
+fn bc1_block(b: &[u8], allow_alpha: bool) -> Block {
+    let c0 = u16::from_le_bytes([b[0], b[1]]);
+    let c1 = u16::from_le_bytes([b[2], b[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let mix = |w0: u16, w1: u16, d: u16| -> [u8; 4] {
+        std::array::from_fn(|i| {
+            if i == 3 {
+                255
+            } else {
+                ((e0[i] as u16 * w0 + e1[i] as u16 * w1) / d) as u8
+            }
+        })
+    };
+    let palette = if c0 > c1 || !allow_alpha {
+        [e0, e1, mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [e0, e1, mix(1, 1, 2), [0; 4]]
+    };
+    let indices = u32::from_le_bytes([b[4], b[5], b[6], b[7]]);
+    std::array::from_fn(|i| palette[(indices >> (i * 2) & 3) as usize])
+}
+
+/// Decode an 8-byte BC4 block to one 8-bit channel.
+fn bc4_block(b: &[u8], signed: bool) -> [u8; 16] {
+    let palette: [u8; 8] = if signed {
+        let a0 = (b[0] as i8).max(-127) as i32;
+        let a1 = (b[1] as i8).max(-127) as i32;
+        let p: [i32; 8] = if a0 > a1 {
+            std::array::from_fn(|i| match i {
+                0 => a0,
+                1 => a1,
+                _ => ((8 - i as i32) * a0 + (i as i32 - 1) * a1) / 7,
+            })
+        } else {
+            std::array::from_fn(|i| match i {
+                0 => a0,
+                1 => a1,
+                6 => -127,
+                7 => 127,
+                _ => ((6 - i as i32) * a0 + (i as i32 - 1) * a1) / 5,
+            })
+        };
+        p.map(|v| (v + 128) as u8)
+    } else {
+        let (a0, a1) = (b[0] as u32, b[1] as u32);
+        if a0 > a1 {
+            std::array::from_fn(|i| match i {
+                0 => a0 as u8,
+                1 => a1 as u8,
+                _ => (((8 - i as u32) * a0 + (i as u32 - 1) * a1) / 7) as u8,
+            })
+        } else {
+            std::array::from_fn(|i| match i {
+                0 => a0 as u8,
+                1 => a1 as u8,
+                6 => 0,
+                7 => 255,
+                _ => (((6 - i as u32) * a0 + (i as u32 - 1) * a1) / 5) as u8,
+            })
+        }
+    };
+    let mut bits = [0u8; 8];
+    bits[..6].copy_from_slice(&b[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    std::array::from_fn(|i| palette[(indices >> (i * 3) & 7) as usize])
+}
+
+/// Per-mode BC7 parameters.
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index2_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 4,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 4,
+        alpha_bits: 0,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 3,
+        index2_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 6,
+        alpha_bits: 0,
+        endpoint_pbits: false,
+        shared_pbits: true,
+        index_bits: 3,
+        index2_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 3,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 0,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        index2_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 0,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 2,
+        index2_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 1,
+        color_bits: 5,
+        alpha_bits: 6,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        index2_bits: 3,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 2,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 8,
+        endpoint_pbits: false,
+        shared_pbits: false,
+        index_bits: 2,
+        index2_bits: 2,
+    },
+    Bc7Mode {
+        subsets: 1,
+        partition_bits: 0,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 7,
+        alpha_bits: 7,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 4,
+        index2_bits: 0,
+    },
+    Bc7Mode {
+        subsets: 2,
+        partition_bits: 6,
+        rotation_bits: 0,
+        index_selection_bits: 0,
+        color_bits: 5,
+        alpha_bits: 5,
+        endpoint_pbits: true,
+        shared_pbits: false,
+        index_bits: 2,
+        index2_bits: 0,
+    },
+];
+
+/// Two-subset partitions; bit `i` is the subset of texel `i`.
+const PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800,
+    0xFFE8, 0xFF00, 0xFFF0, 0xF000, 0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C, 0xAAAA, 0xF0F0, 0x5A5A, 0x33CC,
+    0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718,
+    0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Three-subset partitions; bits `2i..2i+2` are the subset of texel `i`.
+const PARTITIONS_3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Anchor texel of subset 1 in two-subset partitions.
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor texels of subsets 1 and 2 in three-subset partitions.
+const ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15],
+    [3, 8],
+    [15, 8],
+    [15, 3],
+    [8, 15],
+    [3, 15],
+    [15, 3],
+    [15, 8],
+    [8, 15],
+    [8, 15],
+    [6, 15],
+    [6, 15],
+    [6, 15],
+    [5, 15],
+    [3, 15],
+    [3, 8],
+    [3, 15],
+    [3, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [3, 8],
+    [6, 15],
+    [10, 8],
+    [5, 3],
+    [8, 15],
+    [8, 6],
+    [6, 10],
+    [8, 15],
+    [5, 15],
+    [15, 10],
+    [15, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [5, 10],
+    [6, 10],
+    [10, 8],
+    [8, 9],
+    [15, 10],
+    [15, 6],
+    [3, 15],
+    [15, 8],
+    [5, 15],
+    [15, 3],
+    [15, 6],
+    [15, 6],
+    [15, 8],
+    [3, 15],
+    [15, 3],
+    [5, 15],
+    [5, 15],
+    [5, 15],
+    [8, 15],
+    [5, 15],
+    [10, 15],
+    [5, 15],
+    [10, 15],
+    [8, 15],
+    [13, 15],
+    [15, 3],
+    [12, 15],
+    [3, 15],
+    [3, 8],
+];
+
+const WEIGHTS_2: [u16; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u16; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u16; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// LSB-first reader over a 128-bit block.
+struct Bits(u128);
+
+impl Bits {
+    fn take(&mut self, n: u32) -> u8 {
+        let v = (self.0 & ((1 << n) - 1)) as u8;
+        self.0 >>= n;
+        v
+    }
+}
+
+fn interpolate(e0: u8, e1: u8, index: u8, bits: u32) -> u8 {
+    let w = match bits {
+        2 => WEIGHTS_2[index as usize],
+        3 => WEIGHTS_3[index as usize],
+        _ => WEIGHTS_4[index as usize],
+    };
+    (((64 - w) * e0 as u16 + w * e1 as u16 + 32) >> 6) as u8
+}
+
+/// Expand an `n`-bit endpoint value to 8 bits.
+fn expand(v: u8, n: u32) -> u8 {
+    if n >= 8 {
+        return v;
+    }
+    let v = v << (8 - n);
+    v | v >> n
+}
+
+fn bc7_block(b: &[u8]) -> Block {
+    let mut bits = Bits(u128::from_le_bytes(b.try_into().unwrap()));
+    let Some(m) = (0..8).find(|_| bits.take(1) == 1) else {
+        // Reserved mode 8: transparent black.
+        return [[0; 4]; 16];
+    };
+    let mode = &BC7_MODES[m];
+
+    let partition = bits.take(mode.partition_bits) as usize;
+    let rotation = bits.take(mode.rotation_bits);
+    let index_selection = bits.take(mode.index_selection_bits);
+
+    // endpoints[subset * 2 + end][channel]
+    let mut endpoints = [[0u8; 4]; 6];
+    let count = mode.subsets * 2;
+    for c in 0..3 {
+        for e in &mut endpoints[..count] {
+            e[c] = bits.take(mode.color_bits);
+        }
+    }
+    for e in &mut endpoints[..count] {
+        e[3] = bits.take(mode.alpha_bits);
+    }
+
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let pbits: Vec<u8> = if mode.endpoint_pbits {
+            (0..count).map(|_| bits.take(1)).collect()
+        } else {
+            (0..mode.subsets)
+                .flat_map(|_| {
+                    let p = bits.take(1);
+                    [p, p]
+                })
+                .collect()
+        };
+        for (e, p) in endpoints[..count].iter_mut().zip(pbits) {
+            for v in e.iter_mut() {
+                *v = *v << 1 | p;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    for e in &mut endpoints[..count] {
+        for v in &mut e[..3] {
+            *v = expand(*v, color_bits);
+        }
+        e[3] = if alpha_bits > 0 {
+            expand(e[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let subset_of = |i: usize| -> usize {
+        match mode.subsets {
+            2 => (PARTITIONS_2[partition] >> i & 1) as usize,
+            3 => (PARTITIONS_3[partition] >> (i * 2) & 3) as usize,
+            _ => 0,
+        }
+    };
+    let is_anchor = |i: usize| -> bool {
+        i == 0
+            || match mode.subsets {
+                2 => i == ANCHORS_2[partition] as usize,
+                3 => ANCHORS_3[partition].contains(&(i as u8)),
+                _ => false,
+            }
+    };
+
+    let mut indices = [0u8; 16];
+    for (i, idx) in indices.iter_mut().enumerate() {
+        *idx = bits.take(mode.index_bits - is_anchor(i) as u32);
+    }
+    let mut indices2 = [0u8; 16];
+    if mode.index2_bits > 0 {
+        for (i, idx) in indices2.iter_mut().enumerate() {
+            *idx = bits.take(mode.index2_bits - (i == 0) as u32);
+        }
+    }
+
+    std::array::from_fn(|i| {
+        let s = subset_of(i);
+        let (e0, e1) = (endpoints[s * 2], endpoints[s * 2 + 1]);
+        let (color, alpha) = if mode.index2_bits == 0 {
+            ((indices[i], mode.index_bits), (indices[i], mode.index_bits))
+        } else if index_selection == 0 {
+            (
+                (indices[i], mode.index_bits),
+                (indices2[i], mode.index2_bits),
+            )
+        } else {
+            (
+                (indices2[i], mode.index2_bits),
+                (indices[i], mode.index_bits),
+            )
+        };
+        let mut px: [u8; 4] = std::array::from_fn(|c| {
+            let (index, n) = if c == 3 { alpha } else { color };
+            interpolate(e0[c], e1[c], index, n)
+        });
+        match rotation {
+            1 => px.swap(0, 3),
+            2 => px.swap(1, 3),
+            3 => px.swap(2, 3),
+            _ => {}
+        }
+        px
+    })
+}
@@ -0,0 +1,453 @@
+//! Export BNTX textures to DDS, KTX2, and `.astc` files.
+//!
+//! The exporters take a [`TextureInfo`] and its raw GPU data (the
+//! `data_length` bytes at `data_block_offset + data_offset_rel`), deswizzle
+//! every mip level, and write a file other tools can open.
+//!
+//! ## DDS
+//! ```text
+//! [0x00] Magic "DDS "                           (4 bytes)
+//! [0x04] DDS_HEADER                             (0x7C bytes)
+//! [0x80] DDS_HEADER_DXT10                       (0x14 bytes)
+//! [0x94] Per slice (cube face or array layer): mip levels, largest
+//!        first, linear
+//! ```
+//! The pixel format is always the `DX10` FourCC so that the exact DXGI
+//! format (including sRGB) is recorded.
+//!
+//! ## KTX2
+//! ```text
+//! [0x00] Identifier "«KTX 20»\r\n\x1A\n"      (12 bytes)
+//! [0x0C] vkFormat, typeSize, width, height, depth, layerCount,
+//!        faceCount, levelCount, supercompression  (9 × u32 LE)
+//! [0x30] DFD offset/length, KVD offset/length  (4 × u32 LE)
+//! [0x40] SGD offset/length                     (2 × u64 LE)
+//! [0x50] Level index: offset, length, uncompressed length per level
+//!                                              (levelCount × 3 × u64 LE)
+//! [...]  Data format descriptor (one basic block)
+//! [...]  Mip levels, smallest first, each aligned to lcm(block size, 4);
+//!        each level holds every array layer and cube face in turn
+//! ```
+//! KTX2 is the only exporter that handles ASTC. Formats without a
+//! descriptor here (ETC, PVRTC, packed 16-bit) are rejected.
+//!
+//! ## `.astc`
+//! ```text
+//! [0x00] Magic 0x5CA1AB13                      (u32 LE)
+//! [0x04] BlockX, BlockY, BlockZ                (3 × u8)
+//! [0x07] Width, Height, Depth                  (3 × u24 LE)
+//! [0x10] Blocks of mip 0
+//! ```
+
+use std::io::Write;
+
+use super::TextureInfo;
+use super::format::{ChannelType, TextureFormat};
+use crate::{Error, Result};
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x40_0000;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFE00;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+const KTX2_IDENTIFIER: [u8; 12] = *b"\xABKTX 20\xBB\r\n\x1A\n";
+const ASTC_MAGIC: u32 = 0x5CA1_AB13;
+
+// Data format descriptor values (Khronos Data Format Specification 1.3).
+const KHR_DF_MODEL_RGBSDA: u8 = 1;
+const KHR_DF_MODEL_BC1A: u8 = 128;
+const KHR_DF_MODEL_BC2: u8 = 129;
+const KHR_DF_MODEL_BC3: u8 = 130;
+const KHR_DF_MODEL_BC4: u8 = 131;
+const KHR_DF_MODEL_BC5: u8 = 132;
+const KHR_DF_MODEL_BC6H: u8 = 133;
+const KHR_DF_MODEL_BPTC: u8 = 134;
+const KHR_DF_MODEL_ASTC: u8 = 162;
+const KHR_DF_PRIMARIES_BT709: u8 = 1;
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+const KHR_DF_CHANNEL_RED: u8 = 0;
+const KHR_DF_CHANNEL_GREEN: u8 = 1;
+const KHR_DF_CHANNEL_BLUE: u8 = 2;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+const KHR_DF_SAMPLE_LINEAR: u8 = 0x10;
+const KHR_DF_SAMPLE_SIGNED: u8 = 0x40;
+const KHR_DF_SAMPLE_FLOAT: u8 = 0x80;
+
+/// DXGI format equivalent to `format` with `channel_type`, if one exists.
+pub fn dxgi_format(format: TextureFormat, channel_type: ChannelType) -> Option<u32> {
+    use ChannelType::*;
+    use TextureFormat::*;
+    Some(match (format, channel_type) {
+        (R8G8B8A8, Unorm) => 28,
+        (R8G8B8A8, UnormSrgb) => 29,
+        (R8G8B8A8, Uint) => 30,
+        (R8G8B8A8, Snorm) => 31,
+        (R8G8B8A8, Sint) => 32,
+        (B8G8R8A8, Unorm) => 87,
+        (B8G8R8A8, UnormSrgb) => 91,
+        (R8, Unorm) => 61,
+        (R8, Uint) => 62,
+        (R8, Snorm) => 63,
+        (R8, Sint) => 64,
+        (R8G8, Unorm) => 49,
+        (R8G8, Uint) => 50,
+        (R8G8, Snorm) => 51,
+        (R8G8, Sint) => 52,
+        (R16, Float) => 54,
+        (R16, Unorm) => 56,
+        (R16G16, Float) => 34,
+        (R16G16, Unorm) => 35,
+        (R16G16B16A16, Float) => 10,
+        (R16G16B16A16, Unorm) => 11,
+        (R32, Float) => 41,
+        (R32, Uint) => 42,
+        (R32G32, Float) => 16,
+        (R32G32B32, Float) => 6,
+        (R32G32B32A32, Float) => 2,
+        (R10G10B10A2, Unorm) => 24,
+        (R11G11B10, Float) => 26,
+        (R9G9B9E5, Float) => 67,
+        (Bc1, UnormSrgb) => 72,
+        (Bc1, _) => 71,
+        (Bc2, UnormSrgb) => 75,
+        (Bc2, _) => 74,
+        (Bc3, UnormSrgb) => 78,
+        (Bc3, _) => 77,
+        (Bc4, Snorm) => 81,
+        (Bc4, _) => 80,
+        (Bc5, Snorm) => 84,
+        (Bc5, _) => 83,
+        (Bc6, Float) => 96,
+        (Bc6, _) => 95,
+        (Bc7, UnormSrgb) => 99,
+        (Bc7, _) => 98,
+        _ => return None,
+    })
+}
+
+/// Write `tex` as a DDS file.
+///
+/// `data` is the texture's raw GPU data; every slice and mip level is
+/// deswizzled and written. Returns [`Error::Parse`] if the format has no
+/// DXGI equivalent (ASTC, ETC, PVRTC, and some packed formats).
+pub fn to_dds<W: Write>(tex: &TextureInfo, data: &[u8], w: &mut W) -> Result<()> {
+    let format = tex.texture_format();
+    let dxgi = dxgi_format(format, tex.channel_type())
+        .ok_or(Error::Parse("texture format has no DXGI equivalent"))?;
+    let mip_count = (tex.mipmap_count as u32).max(1);
+    let slices = tex.slice_count();
+    let mips = (0..slices)
+        .flat_map(|slice| (0..mip_count).map(move |level| (slice, level)))
+        .map(|(slice, level)| tex.deswizzle_slice(data, slice, level))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT;
+    let pitch_or_linear_size = if format.is_compressed() {
+        flags |= DDSD_LINEARSIZE;
+        mips[0].len() as u32
+    } else {
+        flags |= DDSD_PITCH;
+        tex.width * format.bytes_per_block().unwrap_or(0)
+    };
+    let mut caps = DDSCAPS_TEXTURE;
+    if mip_count > 1 {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    let (caps2, misc, array_size) = if tex.is_cube_map() {
+        caps |= DDSCAPS_COMPLEX;
+        (
+            DDSCAPS2_CUBEMAP_ALL_FACES,
+            D3D10_RESOURCE_MISC_TEXTURECUBE,
+            slices / 6,
+        )
+    } else {
+        (0, 0, slices)
+    };
+
+    let mut header = Vec::with_capacity(0x94);
+    header.extend_from_slice(b"DDS ");
+    for v in [
+        0x7C,
+        flags,
+        tex.height,
+        tex.width,
+        pitch_or_linear_size,
+        0,
+        mip_count,
+    ] {
+        header.extend_from_slice(&v.to_le_bytes());
+    }
+    header.extend_from_slice(&[0; 11 * 4]);
+    // DDS_PIXELFORMAT
+    header.extend_from_slice(&0x20u32.to_le_bytes());
+    header.extend_from_slice(&DDPF_FOURCC.to_le_bytes());
+    header.extend_from_slice(b"DX10");
+    header.extend_from_slice(&[0; 5 * 4]);
+    header.extend_from_slice(&caps.to_le_bytes());
+    header.extend_from_slice(&caps2.to_le_bytes());
+    header.extend_from_slice(&[0; 3 * 4]);
+    // DDS_HEADER_DXT10
+    for v in [
+        dxgi,
+        D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+        misc,
+        array_size,
+        0,
+    ] {
+        header.extend_from_slice(&v.to_le_bytes());
+    }
+
+    w.write_all(&header)?;
+    for mip in &mips {
+        w.write_all(mip)?;
+    }
+    Ok(())
+}
+
+/// Vulkan format equivalent to `format` with `channel_type`, for the
+/// formats [`to_ktx2`] can describe.
+pub fn vk_format(format: TextureFormat, channel_type: ChannelType) -> Option<u32> {
+    use ChannelType::*;
+    use TextureFormat::*;
+    let srgb = channel_type == UnormSrgb;
+    Some(match (format, channel_type) {
+        (R8G8B8A8, Unorm) => 37,
+        (R8G8B8A8, UnormSrgb) => 43,
+        (B8G8R8A8, Unorm) => 44,
+        (B8G8R8A8, UnormSrgb) => 50,
+        (Bc1, _) => 133 + srgb as u32,
+        (Bc2, _) => 135 + srgb as u32,
+        (Bc3, _) => 137 + srgb as u32,
+        (Bc4, Snorm) => 140,
+        (Bc4, _) => 139,
+        (Bc5, Snorm) => 142,
+        (Bc5, _) => 141,
+        (Bc6, Float) => 144,
+        (Bc6, _) => 143,
+        (Bc7, _) => 145 + srgb as u32,
+        (Astc4x4, _) => 157 + srgb as u32,
+        (Astc5x4, _) => 159 + srgb as u32,
+        (Astc5x5, _) => 161 + srgb as u32,
+        (Astc6x5, _) => 163 + srgb as u32,
+        (Astc6x6, _) => 165 + srgb as u32,
+        (Astc8x5, _) => 167 + srgb as u32,
+        (Astc8x6, _) => 169 + srgb as u32,
+        (Astc8x8, _) => 171 + srgb as u32,
+        (Astc10x5, _) => 173 + srgb as u32,
+        (Astc10x6, _) => 175 + srgb as u32,
+        (Astc10x8, _) => 177 + srgb as u32,
+        (Astc10x10, _) => 179 + srgb as u32,
+        (Astc12x10, _) => 181 + srgb as u32,
+        (Astc12x12, _) => 183 + srgb as u32,
+        _ => return None,
+    })
+}
+
+/// One DFD sample: `(bit offset, bit length, channel | qualifiers, lower, upper)`.
+type Sample = (u16, u8, u8, u32, u32);
+
+/// Build the basic data format descriptor for `format`.
+fn data_format_descriptor(format: TextureFormat, channel_type: ChannelType) -> Vec<u8> {
+    use TextureFormat::*;
+    let srgb = channel_type == ChannelType::UnormSrgb;
+    let (lower, upper, qualifiers) = match channel_type {
+        ChannelType::Snorm => (0x8000_0000, 0x7FFF_FFFF, KHR_DF_SAMPLE_SIGNED),
+        ChannelType::Float => (
+            (-1.0f32).to_bits(),
+            1.0f32.to_bits(),
+            KHR_DF_SAMPLE_SIGNED | KHR_DF_SAMPLE_FLOAT,
+        ),
+        ChannelType::Ufloat => (0, 1.0f32.to_bits(), KHR_DF_SAMPLE_FLOAT),
+        _ => (0, u32::MAX, 0),
+    };
+    let whole =
+        |channel: u8, bits: u8| -> Sample { (0, bits - 1, channel | qualifiers, lower, upper) };
+    let (model, samples): (u8, Vec<Sample>) = match format {
+        R8G8B8A8 | B8G8R8A8 => {
+            let order = if format == R8G8B8A8 {
+                [
+                    KHR_DF_CHANNEL_RED,
+                    KHR_DF_CHANNEL_GREEN,
+                    KHR_DF_CHANNEL_BLUE,
+                ]
+            } else {
+                [
+                    KHR_DF_CHANNEL_BLUE,
+                    KHR_DF_CHANNEL_GREEN,
+                    KHR_DF_CHANNEL_RED,
+                ]
+            };
+            let alpha = KHR_DF_CHANNEL_ALPHA | if srgb { KHR_DF_SAMPLE_LINEAR } else { 0 };
+            let samples = order
+                .into_iter()
+                .chain([alpha])
+                .enumerate()
+                .map(|(i, c)| (i as u16 * 8, 7, c, 0, 255))
+                .collect();
+            (KHR_DF_MODEL_RGBSDA, samples)
+        }
+        Bc1 => (KHR_DF_MODEL_BC1A, vec![whole(1, 64)]),
+        Bc2 | Bc3 => (
+            if format == Bc2 {
+                KHR_DF_MODEL_BC2
+            } else {
+                KHR_DF_MODEL_BC3
+            },
+            vec![whole(KHR_DF_CHANNEL_ALPHA, 64), (64, 63, 0, lower, upper)],
+        ),
+        Bc4 => (KHR_DF_MODEL_BC4, vec![whole(0, 64)]),
+        Bc5 => (
+            KHR_DF_MODEL_BC5,
+            vec![whole(0, 64), (64, 63, 1 | qualifiers, lower, upper)],
+        ),
+        Bc6 => (KHR_DF_MODEL_BC6H, vec![whole(0, 128)]),
+        Bc7 => (KHR_DF_MODEL_BPTC, vec![whole(0, 128)]),
+        _ => (KHR_DF_MODEL_ASTC, vec![whole(0, 128)]),
+    };
+
+    let (bw, bh) = format.block_dim();
+    let block_size = 24 + 16 * samples.len() as u16;
+    let mut dfd = Vec::with_capacity(4 + block_size as usize);
+    dfd.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+    dfd.extend_from_slice(&0u32.to_le_bytes()); // vendor 0 (Khronos), type 0 (basic)
+    dfd.extend_from_slice(&2u16.to_le_bytes());
+    dfd.extend_from_slice(&block_size.to_le_bytes());
+    dfd.push(model);
+    dfd.push(KHR_DF_PRIMARIES_BT709);
+    dfd.push(if srgb {
+        KHR_DF_TRANSFER_SRGB
+    } else {
+        KHR_DF_TRANSFER_LINEAR
+    });
+    dfd.push(0); // straight alpha
+    dfd.extend_from_slice(&[bw as u8 - 1, bh as u8 - 1, 0, 0]);
+    let mut bytes_plane = [0u8; 8];
+    bytes_plane[0] = format.bytes_per_block().unwrap_or(0) as u8;
+    dfd.extend_from_slice(&bytes_plane);
+    for (offset, bits, channel, lower, upper) in samples {
+        dfd.extend_from_slice(&offset.to_le_bytes());
+        dfd.push(bits);
+        dfd.push(channel);
+        dfd.extend_from_slice(&[0; 4]);
+        dfd.extend_from_slice(&lower.to_le_bytes());
+        dfd.extend_from_slice(&upper.to_le_bytes());
+    }
+    dfd
+}
+
+/// Write `tex` as a KTX2 file.
+///
+/// `data` is the texture's raw GPU data; every slice and mip level is
+/// deswizzled and written. Returns [`Error::Parse`] if the format has no
+/// Vulkan equivalent here (see [`vk_format`]).
+pub fn to_ktx2<W: Write>(tex: &TextureInfo, data: &[u8], w: &mut W) -> Result<()> {
+    let format = tex.texture_format();
+    let channel_type = tex.channel_type();
+    let vk = vk_format(format, channel_type)
+        .ok_or(Error::Parse("texture format has no KTX2 equivalent"))?;
+    let level_count = (tex.mipmap_count as u32).max(1);
+    let slices = tex.slice_count();
+    let faces = if tex.is_cube_map() { 6 } else { 1 };
+    let layers = match slices / faces {
+        1 => 0,
+        n => n,
+    };
+    // KTX2 stores each level as all of its layers and faces.
+    let mips = (0..level_count)
+        .map(|level| {
+            (0..slices)
+                .map(|slice| tex.deswizzle_slice(data, slice, level))
+                .collect::<Result<Vec<_>>>()
+                .map(|slices| slices.concat())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let dfd = data_format_descriptor(format, channel_type);
+
+    let dfd_offset = 0x50 + 24 * level_count as usize;
+    let block_size = format.bytes_per_block().unwrap_or(1) as usize;
+    let align = block_size * 4 / gcd(block_size, 4);
+
+    // Levels are stored smallest first.
+    let mut offsets = vec![0u64; mips.len()];
+    let mut pos = dfd_offset + dfd.len();
+    for (level, mip) in mips.iter().enumerate().rev() {
+        pos = pos.next_multiple_of(align);
+        offsets[level] = pos as u64;
+        pos += mip.len();
+    }
+
+    let mut header = Vec::with_capacity(dfd_offset);
+    header.extend_from_slice(&KTX2_IDENTIFIER);
+    for v in [
+        vk,
+        1,
+        tex.width,
+        tex.height,
+        0,
+        layers,
+        faces,
+        level_count,
+        0,
+    ] {
+        header.extend_from_slice(&v.to_le_bytes());
+    }
+    for v in [dfd_offset as u32, dfd.len() as u32, 0, 0] {
+        header.extend_from_slice(&v.to_le_bytes());
+    }
+    header.extend_from_slice(&[0; 16]);
+    for (mip, offset) in mips.iter().zip(&offsets) {
+        let len = mip.len() as u64;
+        for v in [*offset, len, len] {
+            header.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    header.extend_from_slice(&dfd);
+    w.write_all(&header)?;
+
+    let mut pos = header.len();
+    for (level, mip) in mips.iter().enumerate().rev() {
+        let pad = offsets[level] as usize - pos;
+        w.write_all(&vec![0; pad])?;
+        w.write_all(mip)?;
+        pos += pad + mip.len();
+    }
+    Ok(())
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Write mip 0 of an ASTC-format `tex` as a `.astc` file.
+///
+/// Returns [`Error::Parse`] if the texture is not ASTC.
+pub fn to_astc<W: Write>(tex: &TextureInfo, data: &[u8], w: &mut W) -> Result<()> {
+    let format = tex.texture_format();
+    if !format.is_astc() {
+        return Err(Error::Parse("texture is not ASTC"));
+    }
+    let blocks = tex.deswizzle_mip(data, 0)?;
+    let (bw, bh) = format.block_dim();
+
+    let mut header = Vec::with_capacity(0x10);
+    header.extend_from_slice(&ASTC_MAGIC.to_le_bytes());
+    header.extend_from_slice(&[bw as u8, bh as u8, 1]);
+    for dim in [tex.width, tex.height, 1] {
+        header.extend_from_slice(&dim.to_le_bytes()[..3]);
+    }
+    w.write_all(&header)?;
+    w.write_all(&blocks)?;
+    Ok(())
+}
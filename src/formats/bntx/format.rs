@@ -0,0 +1,260 @@
+//! BNTX texture formats.
+//!
+//! The BRTI `Format` field packs two codes:
+//! ```text
+//! [bits 8-15] Surface format  ([`TextureFormat`], e.g. 0x1A = BC1)
+//! [bits 0-7]  Channel type    ([`ChannelType`], e.g. 0x06 = UNORM sRGB)
+//! ```
+//! so `0x1A06` is BC1 with sRGB encoding.
+
+/// Surface format: the layout of one block of texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextureFormat {
+    R4G4,
+    R8,
+    R4G4B4A4,
+    A4B4G4R4,
+    R5G5B5A1,
+    A1B5G5R5,
+    R5G6B5,
+    B5G6R5,
+    R8G8,
+    R16,
+    R8G8B8A8,
+    B8G8R8A8,
+    R9G9B9E5,
+    R10G10B10A2,
+    R11G11B10,
+    B10G11R11,
+    R10G11B11,
+    R16G16,
+    R24G8,
+    R32,
+    R16G16B16A16,
+    R32G8X24,
+    R32G32,
+    R32G32B32,
+    R32G32B32A32,
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc6,
+    Bc7,
+    EacR11,
+    EacR11G11,
+    Etc1,
+    Etc2,
+    Etc2Mask,
+    Etc2Alpha,
+    Pvrtc1_2Bpp,
+    Pvrtc1_4Bpp,
+    Pvrtc1Alpha2Bpp,
+    Pvrtc1Alpha4Bpp,
+    Pvrtc2Alpha2Bpp,
+    Pvrtc2Alpha4Bpp,
+    Astc4x4,
+    Astc5x4,
+    Astc5x5,
+    Astc6x5,
+    Astc6x6,
+    Astc8x5,
+    Astc8x6,
+    Astc8x8,
+    Astc10x5,
+    Astc10x6,
+    Astc10x8,
+    Astc10x10,
+    Astc12x10,
+    Astc12x12,
+    B5G5R5A1,
+    Unknown(u8),
+}
+
+impl From<u8> for TextureFormat {
+    fn from(v: u8) -> Self {
+        use TextureFormat::*;
+        match v {
+            0x01 => R4G4,
+            0x02 => R8,
+            0x03 => R4G4B4A4,
+            0x04 => A4B4G4R4,
+            0x05 => R5G5B5A1,
+            0x06 => A1B5G5R5,
+            0x07 => R5G6B5,
+            0x08 => B5G6R5,
+            0x09 => R8G8,
+            0x0A => R16,
+            0x0B => R8G8B8A8,
+            0x0C => B8G8R8A8,
+            0x0D => R9G9B9E5,
+            0x0E => R10G10B10A2,
+            0x0F => R11G11B10,
+            0x10 => B10G11R11,
+            0x11 => R10G11B11,
+            0x12 => R16G16,
+            0x13 => R24G8,
+            0x14 => R32,
+            0x15 => R16G16B16A16,
+            0x16 => R32G8X24,
+            0x17 => R32G32,
+            0x18 => R32G32B32,
+            0x19 => R32G32B32A32,
+            0x1A => Bc1,
+            0x1B => Bc2,
+            0x1C => Bc3,
+            0x1D => Bc4,
+            0x1E => Bc5,
+            0x1F => Bc6,
+            0x20 => Bc7,
+            0x21 => EacR11,
+            0x22 => EacR11G11,
+            0x23 => Etc1,
+            0x24 => Etc2,
+            0x25 => Etc2Mask,
+            0x26 => Etc2Alpha,
+            0x27 => Pvrtc1_2Bpp,
+            0x28 => Pvrtc1_4Bpp,
+            0x29 => Pvrtc1Alpha2Bpp,
+            0x2A => Pvrtc1Alpha4Bpp,
+            0x2B => Pvrtc2Alpha2Bpp,
+            0x2C => Pvrtc2Alpha4Bpp,
+            0x2D => Astc4x4,
+            0x2E => Astc5x4,
+            0x2F => Astc5x5,
+            0x30 => Astc6x5,
+            0x31 => Astc6x6,
+            0x32 => Astc8x5,
+            0x33 => Astc8x6,
+            0x34 => Astc8x8,
+            0x35 => Astc10x5,
+            0x36 => Astc10x6,
+            0x37 => Astc10x8,
+            0x38 => Astc10x10,
+            0x39 => Astc12x10,
+            0x3A => Astc12x12,
+            0x3B => B5G5R5A1,
+            x => Unknown(x),
+        }
+    }
+}
+
+impl TextureFormat {
+    /// Width and height of one block in texels (`(1, 1)` for uncompressed
+    /// formats).
+    pub fn block_dim(self) -> (u32, u32) {
+        use TextureFormat::*;
+        match self {
+            Bc1 | Bc2 | Bc3 | Bc4 | Bc5 | Bc6 | Bc7 => (4, 4),
+            EacR11 | EacR11G11 | Etc1 | Etc2 | Etc2Mask | Etc2Alpha => (4, 4),
+            Pvrtc1_4Bpp | Pvrtc1Alpha4Bpp | Pvrtc2Alpha4Bpp => (4, 4),
+            Pvrtc1_2Bpp | Pvrtc1Alpha2Bpp | Pvrtc2Alpha2Bpp => (8, 4),
+            Astc4x4 => (4, 4),
+            Astc5x4 => (5, 4),
+            Astc5x5 => (5, 5),
+            Astc6x5 => (6, 5),
+            Astc6x6 => (6, 6),
+            Astc8x5 => (8, 5),
+            Astc8x6 => (8, 6),
+            Astc8x8 => (8, 8),
+            Astc10x5 => (10, 5),
+            Astc10x6 => (10, 6),
+            Astc10x8 => (10, 8),
+            Astc10x10 => (10, 10),
+            Astc12x10 => (12, 10),
+            Astc12x12 => (12, 12),
+            _ => (1, 1),
+        }
+    }
+
+    /// Size of one block (or texel, for uncompressed formats) in bytes.
+    ///
+    /// Returns [`None`] for unknown formats.
+    pub fn bytes_per_block(self) -> Option<u32> {
+        use TextureFormat::*;
+        Some(match self {
+            R4G4 | R8 => 1,
+            R4G4B4A4 | A4B4G4R4 | R5G5B5A1 | A1B5G5R5 | R5G6B5 | B5G6R5 | R8G8 | R16 | B5G5R5A1 => {
+                2
+            }
+            R8G8B8A8 | B8G8R8A8 | R9G9B9E5 | R10G10B10A2 | R11G11B10 | B10G11R11 | R10G11B11
+            | R16G16 | R24G8 | R32 => 4,
+            R16G16B16A16 | R32G8X24 | R32G32 => 8,
+            R32G32B32 => 12,
+            R32G32B32A32 => 16,
+            Bc1 | Bc4 | EacR11 | Etc1 | Etc2 | Etc2Mask => 8,
+            Bc2 | Bc3 | Bc5 | Bc6 | Bc7 | EacR11G11 | Etc2Alpha => 16,
+            Pvrtc1_2Bpp | Pvrtc1_4Bpp | Pvrtc1Alpha2Bpp | Pvrtc1Alpha4Bpp | Pvrtc2Alpha2Bpp
+            | Pvrtc2Alpha4Bpp => 8,
+            Astc4x4 | Astc5x4 | Astc5x5 | Astc6x5 | Astc6x6 | Astc8x5 | Astc8x6 | Astc8x8
+            | Astc10x5 | Astc10x6 | Astc10x8 | Astc10x10 | Astc12x10 | Astc12x12 => 16,
+            Unknown(_) => return None,
+        })
+    }
+
+    /// `true` for block-compressed formats (BCn, ETC/EAC, PVRTC, ASTC).
+    pub fn is_compressed(self) -> bool {
+        self.block_dim() != (1, 1)
+    }
+
+    /// `true` for the ASTC formats.
+    pub fn is_astc(self) -> bool {
+        use TextureFormat::*;
+        matches!(
+            self,
+            Astc4x4
+                | Astc5x4
+                | Astc5x5
+                | Astc6x5
+                | Astc6x6
+                | Astc8x5
+                | Astc8x6
+                | Astc8x8
+                | Astc10x5
+                | Astc10x6
+                | Astc10x8
+                | Astc10x10
+                | Astc12x10
+                | Astc12x12
+        )
+    }
+}
+
+/// How the channels of a [`TextureFormat`] are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelType {
+    Unorm,
+    Snorm,
+    Uint,
+    Sint,
+    Float,
+    /// UNORM with sRGB transfer function.
+    UnormSrgb,
+    DepthStencil,
+    UintToFloat,
+    SintToFloat,
+    /// Unsigned float (BC6H UF16).
+    Ufloat,
+    Unknown(u8),
+}
+
+impl From<u8> for ChannelType {
+    fn from(v: u8) -> Self {
+        match v {
+            0x01 => Self::Unorm,
+            0x02 => Self::Snorm,
+            0x03 => Self::Uint,
+            0x04 => Self::Sint,
+            0x05 => Self::Float,
+            0x06 => Self::UnormSrgb,
+            0x07 => Self::DepthStencil,
+            0x08 => Self::UintToFloat,
+            0x09 => Self::SintToFloat,
+            0x0A => Self::Ufloat,
+            x => Self::Unknown(x),
+        }
+    }
+}
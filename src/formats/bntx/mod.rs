@@ -0,0 +1,851 @@
+//! BNTX (Binary NX Texture) - Nintendo Switch texture container.
+//!
+//! Contains one or more GPU textures with a relocation table for pointer
+//! fixup. This parser resolves name pointers by seeking directly; it does
+//! **not** process the relocation table (the layout is predictable enough
+//! that absolute offsets work correctly for read-only parsing).
+//!
+//! ## Layout
+//! ```text
+//! [0x00] BNTX header  (0x20 bytes)
+//! [0x20] NX section   (0x28 bytes)
+//! [InfoPtrsOffset]
+//!        Array of u64 pointers to BRTI blocks (TextureCount entries)
+//! [...]  BRTI blocks  (one per texture, each 0x90 bytes)
+//! [...]  String pool, data blocks, relocation table
+//! ```
+//!
+//! ## Endianness
+//! Determined by the BOM bytes: `FF FE` = Little Endian (Switch), `FE FF` =
+//! Big Endian. Every multi-byte field below, including those in `_STR` and
+//! `_DIC`, follows it; [`Bntx::le`] records which was found.
+//!
+//! ## BNTX Header (0x20 bytes)
+//! ```text
+//! [0x00] Magic "BNTX"                       (4 bytes)
+//! [0x04] DataLength (0, unused)             (u32)
+//! [0x08] Padding / version                  (8 bytes)
+//! [0x10] BOM (FE FF = BE, FF FE = LE)       (2 bytes)
+//! [0x12] FormatRevision (0x0400)            (u16)
+//! [0x14] NameOffset (rel-ptr)               (u32)
+//! [0x18] StringPoolOffset (rel)             (u16)
+//! [0x1A] RelocTableOffset (rel)             (u16)
+//! [0x1C] FileSize                           (u32)
+//! ```
+//!
+//! ## NX Section (at 0x20)
+//! ```text
+//! [0x00] Magic "NX  "                        (4 bytes)
+//! [0x04] TextureCount                        (u32)
+//! [0x08] InfoPtrsOffset (abs ptr)            (u64)
+//! [0x10] DataBlkOffset  (abs ptr)            (u64)
+//! [0x18] DictOffset     (abs ptr)            (u64)
+//! [0x20] StrDictOffset                       (u32)
+//! ```
+//!
+//! ## BRTI (Texture Info, per texture, 0x90 bytes)
+//! ```text
+//! [0x00] Magic "BRTI"                       (4 bytes)
+//! [0x04] Length (always 0x90)               (u32)
+//! [0x08] DataLength                         (u64)
+//! [0x10] Flags                              (u8)
+//! [0x11] Dimensions (1=1D,2=2D,3=3D,6=Cube) (u8)
+//! [0x12] TileMode                           (u16)
+//! [0x14] SwizzleValue                       (u16)
+//! [0x16] MipmapCount                        (u16)
+//! [0x18] MultiSampleCount                   (u16)
+//! [0x1A] Reserved                           (u16)
+//! [0x1C] Format                             (u32)
+//! [0x20] AccessFlags                        (u32)
+//! [0x24] Width                              (u32)
+//! [0x28] Height                             (u32)
+//! [0x2C] Depth                              (u32)
+//! [0x30] ArrayCount                         (u32)
+//! [0x34] BlockHeightLog2                    (u32)
+//! [0x38] Reserved (0x14 bytes)
+//! [0x4C] DataOffset (rel to DataBlkOffset)  (u32)
+//! [0x50] NameOffset (abs ptr)               (u64)
+//! [0x58] ParentOffset (abs ptr)             (u64)
+//! [0x60] PtrsOffset   (abs ptr)             (u64)
+//! [0x68] UserDataOffset (abs ptr)           (u64)
+//! [0x70] TexturePtr, TextureViewPtr, DescriptorSlot (runtime, 3 × u64)
+//! [0x88] UserDataDictOffset (abs ptr)       (u64)
+//! ```
+//!
+//! Format codes are decoded by [`format`](mod@format); GPU data is
+//! converted to and from linear layout by [`swizzle`]. With the
+//! `texture-decode` feature, `decode` turns BCn data into RGBA8 pixels.
+//! [`export`] writes a texture out as a DDS, KTX2, or `.astc` file.
+//!
+//! ## Name encoding
+//! Names are length-prefixed: a `u16` byte count followed by that many
+//! UTF-8 bytes (no null terminator).
+//!
+//! ## String pool (`_STR`, at StringPoolOffset)
+//! ```text
+//! [0x00] Magic "_STR"                       (4 bytes)
+//! [0x04] NextBlockOffset                    (u32)
+//! [0x08] BlockSize                          (u32)
+//! [0x0C] Reserved                           (u32)
+//! [0x10] StringCount                        (u32)
+//! [0x14] Strings: u16 length, bytes, NUL, padded to 2 bytes
+//!        (preceded by an uncounted empty string)
+//! ```
+//!
+//! ## User data (at UserDataOffset, 0x40 bytes each)
+//! ```text
+//! [0x00] NameOffset (abs ptr)               (u64)
+//! [0x08] DataOffset (abs ptr)               (u64)
+//! [0x10] Count                              (u32)
+//! [0x14] Type (0=i32, 1=f32, 2=string, 3=wide string, 4=byte) (u8)
+//! [0x15] Reserved (0x2B bytes)
+//! ```
+//! The entry count is that of the `_DIC` at UserDataDictOffset. String
+//! values are an array of name pointers.
+//!
+//! ## Texture dictionary (`_DIC`, at DictOffset)
+//! ```text
+//! [0x00] Magic "_DIC"                       (4 bytes)
+//! [0x04] EntryCount                         (u32)
+//! [0x08] Nodes ((EntryCount + 1) × 0x10 bytes; node 0 is the root)
+//! ```
+//! Each node is a Patricia-trie node; node `i + 1` names texture `i`:
+//! ```text
+//! [0x00] ReferenceBit                       (i32 LE, -1 for the root)
+//! [0x04] LeftIndex                          (u16)
+//! [0x06] RightIndex                         (u16)
+//! [0x08] KeyOffset (abs ptr to a pool name) (u64)
+//! ```
+
+#[cfg(feature = "texture-decode")]
+pub mod decode;
+pub mod export;
+pub mod format;
+pub mod swizzle;
+
+use std::io::{Read, Seek, SeekFrom, Take};
+use std::ops::Index;
+
+use self::format::{ChannelType, TextureFormat};
+
+use crate::utils::{bytesa, bytesv, end_u16, end_u32, end_u64, magic, subslice, table, u8};
+use crate::{Error, Result};
+
+/// Metadata for a single texture stored in a BNTX file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureInfo {
+    /// Texture name (resolved from the string pool).
+    pub name: String,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Depth (for 3D textures) or face count (for cube maps).
+    pub depth: u32,
+    /// Number of array slices.
+    pub array_count: u32,
+    /// Dimensions code (1 = 1D, 2 = 2D, 3 = 3D, 6 = cube map).
+    pub dimensions: u8,
+    /// Number of mip levels.
+    pub mipmap_count: u16,
+    /// Tile mode: 0 = block-linear (see [`swizzle`]), 1 = pitch-linear.
+    pub tile_mode: u16,
+    /// log2 of the block height in GOBs used by the block-linear layout.
+    pub block_height_log2: u32,
+    /// Raw format identifier; decode it with
+    /// [`texture_format`](Self::texture_format) and
+    /// [`channel_type`](Self::channel_type).
+    pub format: u32,
+    /// Byte offset of GPU data relative to the BNTX data block start
+    /// (`DataBlkOffset` in the NX section). Add `data_block_offset` from
+    /// [`Bntx`] to get an absolute file offset.
+    pub data_offset_rel: u32,
+    /// Total size of GPU data in bytes.
+    pub data_length: u64,
+    /// Per-texture user data, in file order.
+    pub user_data: Vec<UserData>,
+}
+
+/// One named user data entry attached to a texture.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserData {
+    pub name: String,
+    pub value: UserDataValue,
+}
+
+/// Values of a [`UserData`] entry.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UserDataValue {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    String(Vec<String>),
+    /// Wide strings, stored the same way as [`String`](Self::String).
+    WString(Vec<String>),
+    Bytes(Vec<u8>),
+    /// Unrecognised type code; the values are not read.
+    Unknown(u8),
+}
+
+impl TextureInfo {
+    /// Surface format (high byte of [`format`](Self::format)).
+    pub fn texture_format(&self) -> TextureFormat {
+        TextureFormat::from((self.format >> 8) as u8)
+    }
+
+    /// Channel type (low byte of [`format`](Self::format)).
+    pub fn channel_type(&self) -> ChannelType {
+        ChannelType::from(self.format as u8)
+    }
+
+    /// `true` if the texture is stored sRGB-encoded.
+    pub fn is_srgb(&self) -> bool {
+        self.channel_type() == ChannelType::UnormSrgb
+    }
+
+    /// Width and height in pixels of mip `level`.
+    pub fn mip_dimensions(&self, level: u32) -> (u32, u32) {
+        (
+            (self.width >> level.min(31)).max(1),
+            (self.height >> level.min(31)).max(1),
+        )
+    }
+
+    /// Width and height in blocks of mip `level`.
+    fn mip_blocks(&self, level: u32) -> (usize, usize) {
+        let (bw, bh) = self.texture_format().block_dim();
+        let (w, h) = self.mip_dimensions(level);
+        (w.div_ceil(bw) as usize, h.div_ceil(bh) as usize)
+    }
+
+    /// Block height (log2, in GOBs) used by mip `level`.
+    ///
+    /// The GPU shrinks the block height for small mips so that a block is
+    /// never more than twice as tall as the level itself; pass this to
+    /// [`swizzle::deswizzle`] when reading a level other than 0. The result
+    /// is at most [`swizzle::MAX_BLOCK_HEIGHT_LOG2`].
+    pub fn mip_block_height_log2(&self, level: u32) -> u32 {
+        let (_, h) = self.mip_blocks(level);
+        let mut log2 = self.block_height_log2.min(swizzle::MAX_BLOCK_HEIGHT_LOG2);
+        while log2 > 0 && h <= swizzle::GOB_HEIGHT << (log2 - 1) {
+            log2 -= 1;
+        }
+        log2
+    }
+
+    /// Size in bytes of mip `level` of one array slice, including GOB
+    /// padding.
+    ///
+    /// Returns [`None`] if the level is out of range, the format is
+    /// unknown, or the size overflows.
+    pub fn mip_size(&self, level: u32) -> Option<u64> {
+        if level >= self.mipmap_count as u32 {
+            return None;
+        }
+        let bpp = self.texture_format().bytes_per_block()? as usize;
+        let (w, h) = self.mip_blocks(level);
+        let size = if self.tile_mode == 1 {
+            w.checked_mul(bpp)?
+                .checked_next_multiple_of(32)?
+                .checked_mul(h)?
+        } else {
+            swizzle::surface_size(w, h, bpp, self.mip_block_height_log2(level)).ok()?
+        };
+        Some(size as u64)
+    }
+
+    /// Byte offset of mip `level` from the start of its slice (see
+    /// [`slice_offset`](Self::slice_offset)); for slice 0 this is the start
+    /// of the texture's data.
+    ///
+    /// Levels are stored back to back, largest first. Returns [`None`]
+    /// under the same conditions as [`mip_size`](Self::mip_size).
+    pub fn mip_offset(&self, level: u32) -> Option<u64> {
+        self.mip_size(level)?;
+        (0..level).try_fold(0u64, |offset, l| offset.checked_add(self.mip_size(l)?))
+    }
+
+    /// `true` if the texture is a cube map (`Dimensions == 6`).
+    pub fn is_cube_map(&self) -> bool {
+        self.dimensions == 6
+    }
+
+    /// Number of slices: cube faces or array layers.
+    ///
+    /// Cube map faces are stored as consecutive slices in the order +X,
+    /// -X, +Y, -Y, +Z, -Z; a cube map array repeats that for each cube.
+    pub fn slice_count(&self) -> u32 {
+        if self.is_cube_map() {
+            self.array_count.max(6)
+        } else {
+            self.array_count.max(1)
+        }
+    }
+
+    /// Distance in bytes between consecutive slices: the whole mip chain,
+    /// padded to a block of GOBs for block-linear textures.
+    ///
+    /// Returns [`None`] if the format is unknown or the size overflows.
+    pub fn slice_size(&self) -> Option<u64> {
+        let size = (0..(self.mipmap_count as u32).max(1))
+            .try_fold(0u64, |size, l| size.checked_add(self.mip_size(l)?))?;
+        if self.tile_mode == 1 {
+            return Some(size);
+        }
+        let align = (swizzle::GOB_SIZE << self.mip_block_height_log2(0)) as u64;
+        size.checked_next_multiple_of(align)
+    }
+
+    /// Byte offset of `slice` from the start of the texture's data.
+    ///
+    /// Returns [`None`] if the slice is out of range, the format is
+    /// unknown, or the offset overflows.
+    pub fn slice_offset(&self, slice: u32) -> Option<u64> {
+        if slice >= self.slice_count() {
+            return None;
+        }
+        (slice as u64).checked_mul(self.slice_size()?)
+    }
+
+    /// Extract mip `level` of slice 0 from the texture's GPU `data`; see
+    /// [`deswizzle_slice`](Self::deswizzle_slice).
+    pub fn deswizzle_mip(&self, data: &[u8], level: u32) -> Result<Vec<u8>> {
+        self.deswizzle_slice(data, 0, level)
+    }
+
+    /// Extract mip `level` of `slice` from the texture's GPU `data` as
+    /// tightly packed linear blocks, undoing the tiling.
+    ///
+    /// `data` starts at the texture's data offset. Returns
+    /// [`Error::InvalidRange`] if the level lies outside `data`.
+    pub fn deswizzle_slice(&self, data: &[u8], slice: u32, level: u32) -> Result<Vec<u8>> {
+        let (Some(slice_offset), Some(mip_offset), Some(size), Some(bpp)) = (
+            self.slice_offset(slice),
+            self.mip_offset(level),
+            self.mip_size(level),
+            self.texture_format().bytes_per_block(),
+        ) else {
+            return Err(Error::Parse("unknown texture format, slice, or mip level"));
+        };
+        let offset = slice_offset
+            .checked_add(mip_offset)
+            .ok_or(Error::InvalidRange)?;
+        let mip = subslice(data, offset, size)?;
+        let (w, h) = self.mip_blocks(level);
+        let bpp = bpp as usize;
+        if self.tile_mode == 1 {
+            let pitch = (w * bpp).next_multiple_of(32);
+            Ok(mip
+                .chunks(pitch)
+                .take(h)
+                .flat_map(|row| &row[..w * bpp])
+                .copied()
+                .collect())
+        } else {
+            swizzle::deswizzle(w, h, bpp, self.mip_block_height_log2(level), mip)
+        }
+    }
+}
+
+/// Parsed BNTX texture container.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bntx {
+    /// Number of textures.
+    pub texture_count: u32,
+    /// Metadata for each texture. GPU data is not loaded into memory; use
+    /// [`BntxReader`] to read it.
+    pub textures: Vec<TextureInfo>,
+    /// Whether the file uses little-endian encoding.
+    pub le: bool,
+    /// Absolute offset of the GPU data block within the file
+    /// (NX section `DataBlkOffset`).
+    pub data_block_offset: u64,
+    /// Texture name dictionary, if the file has one.
+    pub dict: Option<ResDict>,
+    /// String pool, if the file has one.
+    pub string_pool: Option<StringPool>,
+    /// `(name, texture index)` sorted by name, for binary search.
+    name_index: Vec<(String, usize)>,
+}
+
+/// One node of a [`ResDict`] Patricia trie.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DictNode {
+    /// Index of the key bit this node tests (-1 for the root).
+    pub reference: i32,
+    pub left: u16,
+    pub right: u16,
+    /// Key stored at this node (empty for the root).
+    pub key: String,
+}
+
+/// Name dictionary (`_DIC`) mapping names to entry indices.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResDict {
+    /// All nodes, including the root at index 0. Node `i + 1` holds the
+    /// name of entry `i`.
+    pub nodes: Vec<DictNode>,
+}
+
+impl ResDict {
+    /// Parse a dictionary from `r`.
+    ///
+    /// The reader must be positioned at the `_DIC` magic; `le` is the
+    /// file's byte order ([`Bntx::le`]).
+    pub fn parse<R: Read + Seek>(r: &mut R, le: bool) -> Result<Self> {
+        magic(r, b"_DIC")?;
+        let count = end_u32(r, le)?;
+        // Counts come from the file, so let the vectors grow as entries are
+        // read instead of preallocating.
+        let mut raw = Vec::new();
+        for _ in 0..=count {
+            let reference = end_u32(r, le)? as i32;
+            let left = end_u16(r, le)?;
+            let right = end_u16(r, le)?;
+            let key_ptr = end_u64(r, le)?;
+            raw.push((reference, left, right, key_ptr));
+        }
+
+        let mut nodes = Vec::with_capacity(raw.len());
+        for (reference, left, right, key_ptr) in raw {
+            let key = match key_ptr {
+                0 => String::new(),
+                ptr => read_bntx_name(r, ptr, le)?,
+            };
+            nodes.push(DictNode {
+                reference,
+                left,
+                right,
+                key,
+            });
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Entry names in entry order (the root is skipped).
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().skip(1).map(|n| n.key.as_str())
+    }
+}
+
+/// String pool (`_STR`) holding every name in the file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringPool {
+    /// Strings in pool order, without the leading empty string.
+    pub strings: Vec<String>,
+}
+
+impl StringPool {
+    /// Parse a string pool from `r`.
+    ///
+    /// The reader must be positioned at the `_STR` magic; `le` is the
+    /// file's byte order ([`Bntx::le`]).
+    pub fn parse<R: Read + Seek>(r: &mut R, le: bool) -> Result<Self> {
+        magic(r, b"_STR")?;
+        let _next_block = end_u32(r, le)?;
+        let _block_size = end_u32(r, le)?;
+        let _reserved = end_u32(r, le)?;
+        let count = end_u32(r, le)?;
+
+        let mut strings = Vec::new();
+        let mut leading_empty = true;
+        while strings.len() < count as usize {
+            let len = end_u16(r, le)? as usize;
+            // Length, bytes, and NUL, padded to an even size.
+            let mut buf = bytesv(r, (len + 1).next_multiple_of(2))?;
+            buf.truncate(len);
+            if std::mem::take(&mut leading_empty) && len == 0 {
+                continue;
+            }
+            strings.push(String::from_utf8_lossy(&buf).into_owned());
+        }
+        Ok(Self { strings })
+    }
+}
+
+impl Bntx {
+    /// Parse a BNTX file from `r`.
+    ///
+    /// The reader must be positioned at the `BNTX` magic.
+    pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        // BNTX header (0x20 bytes)
+        magic(r, b"BNTX")?;
+
+        let _data_length = bytesa::<4>(r)?; // always 0
+        let _version = bytesa::<8>(r)?;
+
+        let le = match bytesa::<2>(r)? {
+            [0xFF, 0xFE] => true,
+            [0xFE, 0xFF] => false,
+            _ => return Err(Error::Parse("invalid BNTX BOM")),
+        };
+
+        let _format_revision = end_u16(r, le)?;
+        let _name_offset = end_u32(r, le)?;
+        let string_pool_off = end_u16(r, le)?;
+        let _reloc_table_off = end_u16(r, le)?;
+        let _file_size = end_u32(r, le)?;
+
+        // NX section (0x28 bytes)
+        magic(r, b"NX  ")?;
+        let texture_count = end_u32(r, le)?;
+        let info_ptrs_offset = end_u64(r, le)?;
+        let data_block_offset = end_u64(r, le)?;
+        let dict_offset = end_u64(r, le)?;
+        let _str_dict_offset = end_u32(r, le)?;
+
+        // BRTI pointer array
+        r.seek(SeekFrom::Start(info_ptrs_offset))?;
+        let mut brti_offsets = Vec::new();
+        for _ in 0..texture_count {
+            brti_offsets.push(end_u64(r, le)?);
+        }
+
+        // Parse each BRTI
+        let mut textures = Vec::with_capacity(brti_offsets.len());
+        for brti_abs in brti_offsets {
+            r.seek(SeekFrom::Start(brti_abs))?;
+            textures.push(parse_brti(r, le)?);
+        }
+
+        let string_pool = match string_pool_off {
+            0 => None,
+            off => {
+                r.seek(SeekFrom::Start(off as u64))?;
+                Some(StringPool::parse(r, le)?)
+            }
+        };
+        let dict = match dict_offset {
+            0 => None,
+            off => {
+                r.seek(SeekFrom::Start(off))?;
+                Some(ResDict::parse(r, le)?)
+            }
+        };
+
+        // The dictionary is authoritative for lookups; fall back to the
+        // names in the BRTI blocks when there is none.
+        let mut name_index: Vec<(String, usize)> = match &dict {
+            Some(dict) => dict
+                .keys()
+                .map(str::to_owned)
+                .enumerate()
+                .map(|(i, k)| (k, i))
+                .collect(),
+            None => textures
+                .iter()
+                .enumerate()
+                .map(|(i, t)| (t.name.clone(), i))
+                .collect(),
+        };
+        name_index.sort();
+
+        Ok(Bntx {
+            texture_count,
+            textures,
+            le,
+            data_block_offset,
+            dict,
+            string_pool,
+            name_index,
+        })
+    }
+
+    /// Index of the texture named `name`, found by binary search.
+    pub fn texture_index(&self, name: &str) -> Option<usize> {
+        let i = self
+            .name_index
+            .binary_search_by(|(n, _)| n.as_str().cmp(name))
+            .ok()?;
+        let index = self.name_index[i].1;
+        (index < self.textures.len()).then_some(index)
+    }
+
+    /// Find a texture by name.
+    pub fn get_texture(&self, name: &str) -> Option<&TextureInfo> {
+        self.texture_index(name).map(|i| &self.textures[i])
+    }
+}
+
+/// Streaming reader wrapper around a [`Bntx`] container.
+///
+/// Owns the underlying reader and provides zero-copy bounded access to
+/// texture data via [`Take<&mut R>`].
+pub struct BntxReader<R> {
+    inner: R,
+    /// Parsed metadata.
+    pub bntx: Bntx,
+}
+
+impl<R: Read + Seek> BntxReader<R> {
+    /// Parse a BNTX and wrap the provided reader.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let bntx = Bntx::parse(&mut reader)?;
+        Ok(Self {
+            inner: reader,
+            bntx,
+        })
+    }
+
+    /// Find a texture by name. Returns [`None`] if not found.
+    pub fn get_texture(&self, name: &str) -> Option<&TextureInfo> {
+        self.bntx.get_texture(name)
+    }
+
+    /// Find a texture by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&TextureInfo> {
+        self.get_texture(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    /// Open the GPU data of the texture named `name`, as
+    /// [`read_texture`](Self::read_texture) does.
+    ///
+    /// Returns [`Error::NotFound`] if there is no such texture.
+    pub fn open(&mut self, name: &str) -> Result<Take<&mut R>> {
+        let tex = self.try_get(name)?.clone();
+        self.read_texture(&tex)
+    }
+
+    /// Open a texture's GPU data (every slice and mip level, still
+    /// swizzled) for streaming access.
+    ///
+    /// Seeks to the data's start and returns a [`Take`] limited to
+    /// [`data_length`](TextureInfo::data_length) bytes.
+    pub fn read_texture(&mut self, tex: &TextureInfo) -> Result<Take<&mut R>> {
+        self.read_range(tex, 0, tex.data_length)
+    }
+
+    /// Open mip `level` of slice 0 of a texture; see
+    /// [`read_slice`](Self::read_slice).
+    pub fn read_mip(&mut self, tex: &TextureInfo, level: u32) -> Result<Take<&mut R>> {
+        self.read_slice(tex, 0, level)
+    }
+
+    /// Open mip `level` of `slice` of a texture for streaming access.
+    ///
+    /// The data is still swizzled; pass it to [`swizzle::deswizzle`] with
+    /// [`TextureInfo::mip_block_height_log2`].
+    pub fn read_slice(
+        &mut self,
+        tex: &TextureInfo,
+        slice: u32,
+        level: u32,
+    ) -> Result<Take<&mut R>> {
+        let (Some(slice_offset), Some(mip_offset), Some(size)) = (
+            tex.slice_offset(slice),
+            tex.mip_offset(level),
+            tex.mip_size(level),
+        ) else {
+            return Err(Error::Parse("unknown texture format, slice, or mip level"));
+        };
+        self.read_range(tex, slice_offset + mip_offset, size)
+    }
+
+    fn read_range(&mut self, tex: &TextureInfo, offset: u64, size: u64) -> Result<Take<&mut R>> {
+        let start = self.bntx.data_block_offset + tex.data_offset_rel as u64 + offset;
+        self.inner.seek(SeekFrom::Start(start))?;
+        Ok(self.inner.by_ref().take(size))
+    }
+
+    /// Iterate over all textures.
+    pub fn textures(&self) -> impl Iterator<Item = &TextureInfo> {
+        self.bntx.textures.iter()
+    }
+
+    /// Consume the reader, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Index<&str> for BntxReader<R> {
+    type Output = TextureInfo;
+
+    /// Index by texture name.
+    ///
+    /// # Panics
+    /// Panics if the texture name does not exist in the container; use
+    /// [`try_get`](BntxReader::try_get) to handle that case.
+    fn index(&self, index: &str) -> &Self::Output {
+        self.try_get(index).unwrap_or_else(|e| panic!("BNTX: {e}"))
+    }
+}
+
+fn parse_brti<R: Read + Seek>(r: &mut R, le: bool) -> Result<TextureInfo> {
+    magic(r, b"BRTI")?;
+    let _length = end_u32(r, le)?; // always 0x90
+    let data_length = end_u64(r, le)?;
+    let _flags = u8(r)?;
+    let dimensions = u8(r)?;
+    let tile_mode = end_u16(r, le)?;
+    let _swizzle = end_u16(r, le)?;
+    let mipmap_count = end_u16(r, le)?;
+    let _ms_count = end_u16(r, le)?;
+    let _reserved0 = end_u16(r, le)?;
+    let format = end_u32(r, le)?;
+    let _access_flags = end_u32(r, le)?;
+    let width = end_u32(r, le)?;
+    let height = end_u32(r, le)?;
+    let depth = end_u32(r, le)?;
+    let array_count = end_u32(r, le)?;
+    let block_height_log2 = end_u32(r, le)?;
+    if block_height_log2 > swizzle::MAX_BLOCK_HEIGHT_LOG2 {
+        return Err(Error::Parse("BRTI block height out of range"));
+    }
+    // 0x14 reserved bytes at BRTI+0x38
+    r.seek(SeekFrom::Current(0x14))?;
+    let data_offset_rel = end_u32(r, le)?;
+    let name_abs = end_u64(r, le)?;
+    let _parent = end_u64(r, le)?;
+    let _ptrs = end_u64(r, le)?;
+    let user_data_offset = end_u64(r, le)?;
+    let _runtime = bytesa::<0x18>(r)?;
+    let user_data_dict = end_u64(r, le)?;
+
+    let name = read_bntx_name(r, name_abs, le)?;
+    let user_data = match (user_data_offset, user_data_dict) {
+        (0, _) | (_, 0) => Vec::new(),
+        (offset, dict) => {
+            r.seek(SeekFrom::Start(dict))?;
+            let count = ResDict::parse(r, le)?.nodes.len() - 1;
+            (0..count as u64)
+                .map(|i| parse_user_data(r, offset + i * 0x40, le))
+                .collect::<Result<_>>()?
+        }
+    };
+
+    Ok(TextureInfo {
+        name,
+        width,
+        height,
+        depth,
+        array_count,
+        dimensions,
+        mipmap_count,
+        tile_mode,
+        block_height_log2,
+        format,
+        data_offset_rel,
+        data_length,
+        user_data,
+    })
+}
+
+fn parse_user_data<R: Read + Seek>(r: &mut R, offset: u64, le: bool) -> Result<UserData> {
+    r.seek(SeekFrom::Start(offset))?;
+    let name_abs = end_u64(r, le)?;
+    let data_abs = end_u64(r, le)?;
+    let count = end_u32(r, le)? as usize;
+    let kind = u8(r)?;
+
+    let name = read_bntx_name(r, name_abs, le)?;
+    r.seek(SeekFrom::Start(data_abs))?;
+    let value = match kind {
+        0 => UserDataValue::Int(
+            (0..count)
+                .map(|_| end_u32(r, le).map(|v| v as i32))
+                .collect::<Result<_>>()?,
+        ),
+        1 => UserDataValue::Float(
+            (0..count)
+                .map(|_| end_u32(r, le).map(f32::from_bits))
+                .collect::<Result<_>>()?,
+        ),
+        2 | 3 => {
+            let ptrs = (0..count)
+                .map(|_| end_u64(r, le))
+                .collect::<Result<Vec<_>>>()?;
+            let strings = ptrs
+                .into_iter()
+                .map(|ptr| read_bntx_name(r, ptr, le))
+                .collect::<Result<_>>()?;
+            if kind == 2 {
+                UserDataValue::String(strings)
+            } else {
+                UserDataValue::WString(strings)
+            }
+        }
+        4 => UserDataValue::Bytes(table(r, count as u64)?),
+        x => UserDataValue::Unknown(x),
+    };
+    Ok(UserData { name, value })
+}
+
+/// Read a length-prefixed string from the string pool.
+///
+/// The pointer `ptr` is the absolute byte offset of the `u16` length field.
+/// Names have no null terminator.
+fn read_bntx_name<R: Read + Seek>(r: &mut R, ptr: u64, le: bool) -> Result<String> {
+    r.seek(SeekFrom::Start(ptr))?;
+    let len = end_u16(r, le)?;
+    let buf = table(r, len as u64)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// An R8 texture, one byte per pixel.
+    fn texture(width: u32, height: u32, tile_mode: u16, block_height_log2: u32) -> TextureInfo {
+        TextureInfo {
+            name: "tex".into(),
+            width,
+            height,
+            depth: 1,
+            array_count: 1,
+            dimensions: 2,
+            mipmap_count: 1,
+            tile_mode,
+            block_height_log2,
+            format: 0x0201,
+            data_offset_rel: 0,
+            data_length: 0,
+            user_data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn user_data_huge_byte_count() {
+        // Entry at 0: name at 0x20 (empty), bytes at 0x22.
+        let mut data = 0x20u64.to_le_bytes().to_vec();
+        data.extend(0x22u64.to_le_bytes());
+        data.extend(u32::MAX.to_le_bytes());
+        data.push(4);
+        data.resize(0x22, 0);
+        data.extend([1, 2, 3]);
+        assert!(parse_user_data(&mut Cursor::new(data), 0, true).is_err());
+    }
+
+    #[test]
+    fn mip_block_height_is_clamped() {
+        assert_eq!(texture(256, 256, 0, u32::MAX).mip_block_height_log2(0), 5);
+        assert_eq!(texture(256, 8, 0, u32::MAX).mip_block_height_log2(0), 0);
+    }
+
+    #[test]
+    fn mip_sizes() {
+        let tex = texture(100, 20, 0, 1);
+        assert_eq!(tex.mip_size(0), Some(128 * 32));
+        assert_eq!(tex.slice_size(), Some(128 * 32));
+        let tex = texture(100, 20, 1, 0);
+        assert_eq!(tex.mip_size(0), Some(128 * 20));
+    }
+
+    #[test]
+    fn oversized_texture() {
+        let mut tex = texture(u32::MAX, u32::MAX, 0, 5);
+        tex.array_count = u32::MAX;
+        assert!(tex.slice_offset(u32::MAX - 1).is_none());
+        assert!(tex.deswizzle_slice(&[0; 0x200], 1, 0).is_err());
+    }
+}
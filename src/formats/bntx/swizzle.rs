@@ -0,0 +1,174 @@
+//! Tegra X1 block-linear texture layout.
+//!
+//! Switch GPU textures are stored "block-linear": the surface is divided
+//! into GOBs (groups of bytes) of 64 bytes × 8 rows = 512 bytes, and GOBs are
+//! stacked vertically into blocks of `1 << BlockHeightLog2` GOBs. Blocks are
+//! laid out left to right, then top to bottom:
+//!
+//! ```text
+//! surface ─┬─ block row 0: [block 0][block 1] ... (each 64 B × 8·H rows)
+//!          └─ block row 1: ...
+//! block    ─── GOB 0 above GOB 1 above ... GOB H-1
+//! GOB      ─── 512 bytes in a fixed 16-byte-sector interleave
+//! ```
+//!
+//! All functions here work in units of format blocks: for compressed
+//! formats `width` and `height` are in 4×4 (etc.) blocks and `bpp` is the
+//! block size in bytes; see [`TextureFormat::block_dim`] and
+//! [`TextureFormat::bytes_per_block`].
+//!
+//! [`TextureFormat::block_dim`]: super::format::TextureFormat::block_dim
+//! [`TextureFormat::bytes_per_block`]: super::format::TextureFormat::bytes_per_block
+
+use crate::{Error, Result};
+
+/// Width of a GOB in bytes.
+pub const GOB_WIDTH: usize = 64;
+/// Height of a GOB in rows.
+pub const GOB_HEIGHT: usize = 8;
+/// Size of a GOB in bytes.
+pub const GOB_SIZE: usize = GOB_WIDTH * GOB_HEIGHT;
+/// Largest block height the GPU supports (32 GOBs), as log2.
+pub const MAX_BLOCK_HEIGHT_LOG2: u32 = 5;
+
+/// Size in bytes of a block-linear surface of `width` × `height` blocks,
+/// including the padding to whole GOB blocks.
+///
+/// Returns [`Error::InvalidRange`] if `block_height_log2` exceeds
+/// [`MAX_BLOCK_HEIGHT_LOG2`] or the size does not fit in a `usize`.
+pub fn surface_size(
+    width: usize,
+    height: usize,
+    bpp: usize,
+    block_height_log2: u32,
+) -> Result<usize> {
+    if block_height_log2 > MAX_BLOCK_HEIGHT_LOG2 {
+        return Err(Error::InvalidRange);
+    }
+    let rows = GOB_HEIGHT << block_height_log2;
+    width
+        .checked_mul(bpp)
+        .and_then(|pitch| pitch.checked_next_multiple_of(GOB_WIDTH))
+        .zip(height.checked_next_multiple_of(rows))
+        .and_then(|(pitch, height)| pitch.checked_mul(height))
+        .ok_or(Error::InvalidRange)
+}
+
+/// Byte offset of block `(x, y)` within a block-linear surface.
+///
+/// `(x, y)` must lie within a surface whose [`surface_size`] succeeds;
+/// `block_height_log2` is capped at [`MAX_BLOCK_HEIGHT_LOG2`].
+pub fn block_linear_offset(
+    x: usize,
+    y: usize,
+    width: usize,
+    bpp: usize,
+    block_height_log2: u32,
+) -> usize {
+    let block_height = 1 << block_height_log2.min(MAX_BLOCK_HEIGHT_LOG2);
+    let width_in_gobs = (width * bpp).div_ceil(GOB_WIDTH);
+    let x = x * bpp;
+
+    let gob = (y / (GOB_HEIGHT * block_height)) * GOB_SIZE * block_height * width_in_gobs
+        + (x / GOB_WIDTH) * GOB_SIZE * block_height
+        + (y % (GOB_HEIGHT * block_height) / GOB_HEIGHT) * GOB_SIZE;
+    gob + (x % 64) / 32 * 256 + (y % 8) / 2 * 64 + (x % 32) / 16 * 32 + (y % 2) * 16 + x % 16
+}
+
+/// Convert block-linear `data` to a tightly packed linear image.
+///
+/// Returns [`Error::UnexpectedEof`] if `data` is smaller than
+/// [`surface_size`], or [`Error::InvalidRange`] if the surface is too
+/// large to address.
+pub fn deswizzle(
+    width: usize,
+    height: usize,
+    bpp: usize,
+    block_height_log2: u32,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    if data.len() < surface_size(width, height, bpp, block_height_log2)? {
+        return Err(Error::UnexpectedEof);
+    }
+    let mut out = vec![0u8; linear_size(width, height, bpp)?];
+    for y in 0..height {
+        for x in 0..width {
+            let src = block_linear_offset(x, y, width, bpp, block_height_log2);
+            let dst = (y * width + x) * bpp;
+            out[dst..dst + bpp].copy_from_slice(&data[src..src + bpp]);
+        }
+    }
+    Ok(out)
+}
+
+/// Convert a tightly packed linear image to block-linear layout.
+///
+/// The result is [`surface_size`] bytes, with padding zeroed. Returns
+/// [`Error::UnexpectedEof`] if `data` is smaller than
+/// `width * height * bpp`, or [`Error::InvalidRange`] as [`deswizzle`]
+/// does.
+pub fn swizzle(
+    width: usize,
+    height: usize,
+    bpp: usize,
+    block_height_log2: u32,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let size = surface_size(width, height, bpp, block_height_log2)?;
+    if data.len() < linear_size(width, height, bpp)? {
+        return Err(Error::UnexpectedEof);
+    }
+    let mut out = vec![0u8; size];
+    for y in 0..height {
+        for x in 0..width {
+            let dst = block_linear_offset(x, y, width, bpp, block_height_log2);
+            let src = (y * width + x) * bpp;
+            out[dst..dst + bpp].copy_from_slice(&data[src..src + bpp]);
+        }
+    }
+    Ok(out)
+}
+
+/// `width * height * bpp`, or [`Error::InvalidRange`] on overflow.
+fn linear_size(width: usize, height: usize, bpp: usize) -> Result<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(bpp))
+        .ok_or(Error::InvalidRange)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let (width, height, bpp) = (20, 30, 4);
+        let linear: Vec<u8> = (0..width * height * bpp).map(|i| i as u8).collect();
+        for log2 in 0..=MAX_BLOCK_HEIGHT_LOG2 {
+            let tiled = swizzle(width, height, bpp, log2, &linear).unwrap();
+            assert_eq!(tiled.len(), surface_size(width, height, bpp, log2).unwrap());
+            assert_eq!(deswizzle(width, height, bpp, log2, &tiled).unwrap(), linear);
+        }
+    }
+
+    #[test]
+    fn block_height_out_of_range() {
+        assert!(matches!(
+            surface_size(1, 1, 4, MAX_BLOCK_HEIGHT_LOG2 + 1),
+            Err(Error::InvalidRange)
+        ));
+        assert!(deswizzle(1, 1, 4, u32::MAX, &[0; GOB_SIZE]).is_err());
+    }
+
+    #[test]
+    fn oversized_surface() {
+        let huge = u32::MAX as usize;
+        assert!(matches!(
+            surface_size(usize::MAX, 1, 4, 0),
+            Err(Error::InvalidRange)
+        ));
+        assert!(deswizzle(huge, huge, 16, 4, &[0; GOB_SIZE]).is_err());
+        assert!(deswizzle(usize::MAX, usize::MAX, 16, 4, &[0; GOB_SIZE]).is_err());
+    }
+}
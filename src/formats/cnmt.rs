@@ -0,0 +1,453 @@
+//! CNMT (PackagedContentMeta) - content metadata for a title.
+//!
+//! Every title ships a Meta NCA whose single PFS0 section holds one
+//! `<Type>_<TitleId>.cnmt` file. It lists the NCAs that make up the title
+//! (with their sizes and SHA-256 hashes) and, for system updates, the
+//! titles included in the update.
+//!
+//! ## Layout
+//! ```text
+//! [0x00] TitleId                             (u64 LE)
+//! [0x08] Version                             (u32 LE)
+//! [0x0C] Type                                (u8, see ContentMetaType)
+//! [0x0D] Platform                            (u8)
+//! [0x0E] ExtendedHeaderSize                  (u16 LE)
+//! [0x10] ContentCount                        (u16 LE)
+//! [0x12] ContentMetaCount                    (u16 LE)
+//! [0x14] Attributes                          (u8)
+//! [0x15] Reserved                            (3 bytes)
+//! [0x18] RequiredDownloadSystemVersion       (u32 LE)
+//! [0x1C] Reserved                            (4 bytes)
+//! [0x20] ExtendedHeader                      (ExtendedHeaderSize bytes)
+//! [...]  Content records                     (ContentCount × 0x38 bytes)
+//! [...]  Content meta records                (ContentMetaCount × 0x10 bytes)
+//! [...]  Digest                              (0x20 bytes)
+//! ```
+//!
+//! ## Content record (0x38 bytes)
+//! ```text
+//! [0x00] SHA-256 of the NCA                  (32 bytes)
+//! [0x20] ContentId (first 16 bytes of hash)  (16 bytes)
+//! [0x30] Size                                (u48 LE)
+//! [0x36] ContentType                         (u8)
+//! [0x37] IdOffset                            (u8)
+//! ```
+//!
+//! ## Content meta record (0x10 bytes)
+//! ```text
+//! [0x00] TitleId                             (u64 LE)
+//! [0x08] Version                             (u32 LE)
+//! [0x0C] Type                                (u8)
+//! [0x0D] Attributes                          (u8)
+//! [0x0E] Reserved                            (2 bytes)
+//! ```
+//!
+//! ## Versions
+//! Title versions of system titles (and the SystemUpdate title in
+//! particular) pack a firmware version as
+//! `major << 26 | minor << 20 | micro << 16 | relstep`; see
+//! [`system_version_string`].
+//!
+//! ## Verifying an NSP
+//! [`verify_nsp`] reads the CNMT from every `*.cnmt.nca` in an NSP and
+//! checks each referenced `<ContentId>.nca` against the record's size and
+//! SHA-256, like `nsz --verify`. The meta NCAs themselves are not listed in
+//! their CNMT and are not checked. [`verify_nsp_at`] does the same through
+//! a [`ReadAt`] source, hashing the NCAs concurrently with the `parallel`
+//! feature.
+
+use std::io::{self, Cursor, Read, Seek};
+
+use super::nca::NcaReader;
+use super::pfs0::{Pfs0File, Pfs0Reader};
+use crate::crypto::sha256::Sha256;
+use crate::error::Context;
+use crate::keys::KeySet;
+use crate::read_at::ReadAt;
+use crate::utils::{bytesa, bytesv, le_u16, le_u32, le_u64, u8};
+use crate::{Error, Result};
+
+/// Title ID of the SystemUpdate meta title.
+pub const SYSTEM_UPDATE_TITLE_ID: u64 = 0x0100_0000_0000_0816;
+
+/// Kind of title a CNMT describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContentMetaType {
+    SystemProgram,
+    SystemData,
+    SystemUpdate,
+    BootImagePackage,
+    BootImagePackageSafe,
+    Application,
+    Patch,
+    AddOnContent,
+    Delta,
+    DataPatch,
+    Unknown(u8),
+}
+
+impl From<u8> for ContentMetaType {
+    fn from(v: u8) -> Self {
+        match v {
+            0x01 => Self::SystemProgram,
+            0x02 => Self::SystemData,
+            0x03 => Self::SystemUpdate,
+            0x04 => Self::BootImagePackage,
+            0x05 => Self::BootImagePackageSafe,
+            0x80 => Self::Application,
+            0x81 => Self::Patch,
+            0x82 => Self::AddOnContent,
+            0x83 => Self::Delta,
+            0x84 => Self::DataPatch,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+/// Role of one NCA within a title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContentRecordType {
+    Meta,
+    Program,
+    Data,
+    Control,
+    HtmlDocument,
+    LegalInformation,
+    DeltaFragment,
+    Unknown(u8),
+}
+
+impl From<u8> for ContentRecordType {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => Self::Meta,
+            1 => Self::Program,
+            2 => Self::Data,
+            3 => Self::Control,
+            4 => Self::HtmlDocument,
+            5 => Self::LegalInformation,
+            6 => Self::DeltaFragment,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+/// One NCA referenced by a CNMT.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentRecord {
+    /// SHA-256 of the whole NCA.
+    pub hash: [u8; 32],
+    /// Content ID; the NCA is stored as `<content_id as hex>.nca`.
+    pub content_id: [u8; 16],
+    /// NCA size in bytes.
+    pub size: u64,
+    pub content_type: ContentRecordType,
+    pub id_offset: u8,
+}
+
+impl ContentRecord {
+    /// Lowercase hex content ID, the NCA's file name without extension.
+    pub fn content_id_hex(&self) -> String {
+        self.content_id.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// One title referenced by a CNMT (e.g. the titles in a system update).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetaRecord {
+    pub title_id: u64,
+    pub version: u32,
+    pub meta_type: ContentMetaType,
+    pub attributes: u8,
+}
+
+/// Parsed CNMT.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cnmt {
+    pub title_id: u64,
+    pub version: u32,
+    pub meta_type: ContentMetaType,
+    pub attributes: u8,
+    pub required_download_system_version: u32,
+    /// Raw type-specific extended header.
+    pub extended_header: Vec<u8>,
+    pub contents: Vec<ContentRecord>,
+    pub content_meta: Vec<MetaRecord>,
+    pub digest: [u8; 32],
+}
+
+impl Cnmt {
+    /// Parse a CNMT from `r`.
+    ///
+    /// The reader must be positioned at the start of the `.cnmt` file.
+    pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
+        let header = || "header".to_string();
+        let title_id = le_u64(r).at_field("CNMT", base, header, "TitleId")?;
+        let version = le_u32(r).at_field("CNMT", base, header, "Version")?;
+        let meta_type = ContentMetaType::from(u8(r).at_field("CNMT", base, header, "Type")?);
+        let _platform = u8(r).at_field("CNMT", base, header, "Platform")?;
+        let extended_header_size =
+            le_u16(r).at_field("CNMT", base, header, "ExtendedHeaderSize")?;
+        let content_count = le_u16(r).at_field("CNMT", base, header, "ContentCount")?;
+        let content_meta_count = le_u16(r).at_field("CNMT", base, header, "ContentMetaCount")?;
+        let attributes = u8(r).at_field("CNMT", base, header, "Attributes")?;
+        let _reserved = bytesa::<3>(r).at_field("CNMT", base, header, "Reserved")?;
+        let required_download_system_version =
+            le_u32(r).at_field("CNMT", base, header, "RequiredDownloadSystemVersion")?;
+        let _reserved = bytesa::<4>(r).at_field("CNMT", base, header, "Reserved")?;
+        let extended_header =
+            bytesv(r, extended_header_size as usize)
+                .at("CNMT", base + 0x20, || "extended header".into())?;
+
+        let contents_base = base + 0x20 + extended_header_size as u64;
+        let mut contents = Vec::with_capacity(content_count as usize);
+        for i in 0..content_count {
+            let at = contents_base + i as u64 * 0x38;
+            let record = || format!("content record {i}");
+            let hash = bytesa::<32>(r).at_field("CNMT", at, record, "Hash")?;
+            let content_id = bytesa::<16>(r).at_field("CNMT", at, record, "ContentId")?;
+            let size = bytesa::<6>(r).at_field("CNMT", at, record, "Size")?;
+            let content_type =
+                ContentRecordType::from(u8(r).at_field("CNMT", at, record, "ContentType")?);
+            let id_offset = u8(r).at_field("CNMT", at, record, "IdOffset")?;
+            let mut size8 = [0u8; 8];
+            size8[..6].copy_from_slice(&size);
+            contents.push(ContentRecord {
+                hash,
+                content_id,
+                size: u64::from_le_bytes(size8),
+                content_type,
+                id_offset,
+            });
+        }
+
+        let meta_base = contents_base + content_count as u64 * 0x38;
+        let mut content_meta = Vec::with_capacity(content_meta_count as usize);
+        for i in 0..content_meta_count {
+            let at = meta_base + i as u64 * 0x10;
+            let record = || format!("meta record {i}");
+            let title_id = le_u64(r).at_field("CNMT", at, record, "TitleId")?;
+            let version = le_u32(r).at_field("CNMT", at, record, "Version")?;
+            let meta_type = ContentMetaType::from(u8(r).at_field("CNMT", at, record, "Type")?);
+            let attributes = u8(r).at_field("CNMT", at, record, "Attributes")?;
+            let _reserved = bytesa::<2>(r).at_field("CNMT", at, record, "Reserved")?;
+            content_meta.push(MetaRecord {
+                title_id,
+                version,
+                meta_type,
+                attributes,
+            });
+        }
+
+        let digest_at = meta_base + content_meta_count as u64 * 0x10;
+        let digest = bytesa::<32>(r).at("CNMT", digest_at, || "digest".into())?;
+
+        Ok(Self {
+            title_id,
+            version,
+            meta_type,
+            attributes,
+            required_download_system_version,
+            extended_header,
+            contents,
+            content_meta,
+            digest,
+        })
+    }
+
+    /// Read the CNMT from a Meta NCA.
+    ///
+    /// `r` must be positioned at the start of the NCA; `keys` must hold the
+    /// header key and the key area keys. Returns [`Error::Parse`] if the
+    /// NCA's PFS0 section has no `.cnmt` file.
+    pub fn from_meta_nca<R: Read + Seek>(r: R, keys: &KeySet) -> Result<Self> {
+        let mut nca = NcaReader::new(r, keys)?;
+        let mut meta = nca.open_pfs0(0)?;
+        let entry = meta
+            .files()
+            .find(|f| f.name.ends_with(".cnmt"))
+            .cloned()
+            .ok_or(Error::Parse("meta NCA has no CNMT"))?;
+        let data = meta.read_file_to_vec(&entry)?;
+        Self::parse(&mut Cursor::new(data))
+    }
+}
+
+/// Format a system title version as `major.minor.micro`, e.g. `16.0.3`.
+///
+/// Versions from before 3.0.0 used a plain counter and do not decode
+/// meaningfully.
+pub fn system_version_string(version: u32) -> String {
+    format!(
+        "{}.{}.{}",
+        version >> 26,
+        (version >> 20) & 0x3F,
+        (version >> 16) & 0xF
+    )
+}
+
+/// Outcome of checking one content record against an NSP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContentStatus {
+    /// The NCA is present with the expected size and hash.
+    Ok,
+    /// No `.nca` or `.ncz` entry for the content ID.
+    Missing,
+    /// The NCA is present but its size differs; holds the actual size.
+    WrongSize(u64),
+    /// The NCA has the expected size but its SHA-256 differs.
+    WrongHash,
+    /// Only a compressed `.ncz` entry is present; it is not checked.
+    Compressed,
+}
+
+/// Results of [`verify_nsp`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NspReport {
+    /// Every content record of every CNMT, with its status.
+    pub contents: Vec<(ContentRecord, ContentStatus)>,
+    /// `.nca`/`.ncz` entries not referenced by any CNMT (meta NCAs
+    /// excluded).
+    pub extra: Vec<String>,
+}
+
+impl NspReport {
+    /// `true` if no content is missing or corrupt and there are no extra
+    /// entries. [`ContentStatus::Compressed`] entries count as ok.
+    pub fn is_ok(&self) -> bool {
+        self.extra.is_empty()
+            && self
+                .contents
+                .iter()
+                .all(|(_, s)| matches!(s, ContentStatus::Ok | ContentStatus::Compressed))
+    }
+}
+
+/// Check the NCAs of an NSP against its CNMTs.
+///
+/// `keys` must hold the header key and the key area keys for the meta
+/// NCAs. Each content record is hashed in full, so this reads every
+/// referenced NCA. Returns [`Error::Parse`] if the NSP has no
+/// `*.cnmt.nca`.
+pub fn verify_nsp<R: Read + Seek>(nsp: &mut Pfs0Reader<R>, keys: &KeySet) -> Result<NspReport> {
+    let mut records = Vec::new();
+    for file in meta_files(nsp)? {
+        let cnmt = Cnmt::from_meta_nca(nsp.read_file(&file)?.into_inner(), keys)?;
+        records.extend(cnmt.contents);
+    }
+
+    let mut contents = Vec::with_capacity(records.len());
+    for record in records {
+        let status = match content_file(nsp, &record) {
+            Ok(file) => {
+                let mut hasher = Sha256::new();
+                io::copy(&mut nsp.read_file(&file)?, &mut hasher)?;
+                hash_status(hasher, &record)
+            }
+            Err(status) => status,
+        };
+        contents.push((record, status));
+    }
+    Ok(report(nsp, contents))
+}
+
+/// Like [`verify_nsp`], but reads through a shared reference.
+///
+/// With the `parallel` feature the content NCAs are hashed concurrently
+/// on the rayon thread pool; otherwise one after another.
+pub fn verify_nsp_at<R: ReadAt + Sync>(nsp: &Pfs0Reader<R>, keys: &KeySet) -> Result<NspReport> {
+    let mut records = Vec::new();
+    for file in meta_files(nsp)? {
+        let cnmt = Cnmt::from_meta_nca(nsp.read_file_at(&file), keys)?;
+        records.extend(cnmt.contents);
+    }
+
+    let check = |record: ContentRecord| -> Result<(ContentRecord, ContentStatus)> {
+        let status = match content_file(nsp, &record) {
+            Ok(file) => {
+                let mut hasher = Sha256::new();
+                io::copy(&mut nsp.read_file_at(&file), &mut hasher)?;
+                hash_status(hasher, &record)
+            }
+            Err(status) => status,
+        };
+        Ok((record, status))
+    };
+    #[cfg(feature = "parallel")]
+    let contents = {
+        use rayon::prelude::*;
+        records.into_par_iter().map(check).collect::<Result<_>>()?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let contents = records.into_iter().map(check).collect::<Result<_>>()?;
+    Ok(report(nsp, contents))
+}
+
+/// The `*.cnmt.nca` entries of an NSP; [`Error::Parse`] if there are none.
+fn meta_files<R>(nsp: &Pfs0Reader<R>) -> Result<Vec<Pfs0File>> {
+    let metas: Vec<Pfs0File> = nsp
+        .pfs0
+        .files
+        .iter()
+        .filter(|f| f.name.ends_with(".cnmt.nca"))
+        .cloned()
+        .collect();
+    if metas.is_empty() {
+        return Err(Error::Parse("NSP has no meta NCA"));
+    }
+    Ok(metas)
+}
+
+/// The NCA entry to hash for `record`, or the status to report if it
+/// cannot be hashed.
+fn content_file<R>(
+    nsp: &Pfs0Reader<R>,
+    record: &ContentRecord,
+) -> std::result::Result<Pfs0File, ContentStatus> {
+    let id = record.content_id_hex();
+    let get = |name: String| nsp.pfs0.files.iter().find(|f| f.name == name);
+    match get(format!("{id}.nca")) {
+        Some(file) if file.size != record.size => Err(ContentStatus::WrongSize(file.size)),
+        Some(file) => Ok(file.clone()),
+        None if get(format!("{id}.ncz")).is_some() => Err(ContentStatus::Compressed),
+        None => Err(ContentStatus::Missing),
+    }
+}
+
+fn hash_status(hasher: Sha256, record: &ContentRecord) -> ContentStatus {
+    if hasher.finalize() == record.hash {
+        ContentStatus::Ok
+    } else {
+        ContentStatus::WrongHash
+    }
+}
+
+/// Build the report, listing NCA/NCZ entries no record refers to as extra.
+fn report<R>(nsp: &Pfs0Reader<R>, contents: Vec<(ContentRecord, ContentStatus)>) -> NspReport {
+    let extra = nsp
+        .pfs0
+        .files
+        .iter()
+        .filter(|f| {
+            let Some(stem) = f
+                .name
+                .strip_suffix(".nca")
+                .or_else(|| f.name.strip_suffix(".ncz"))
+            else {
+                return false;
+            };
+            !f.name.ends_with(".cnmt.nca")
+                && !contents.iter().any(|(r, _)| r.content_id_hex() == stem)
+        })
+        .map(|f| f.name.clone())
+        .collect();
+    NspReport { contents, extra }
+}
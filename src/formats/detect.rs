@@ -0,0 +1,142 @@
+//! Format detection from leading bytes.
+//!
+//! ## Signatures
+//! ```text
+//! [0x000] "PFS0" / "HFS0" / "SARC" / "BNTX"
+//! [0x100] "HEAD"                  XCI (at 0x1100 with a CardKeyArea)
+//! [0x200] "NCA3" ... "NCA0"       decrypted NCA
+//! [0x200] (after header decrypt)  encrypted NCA; needs the header key
+//! [0x4000] "NCZSECTN"             NCZ
+//! [0x000] font magic after XOR    BFTTF / BFOTF
+//! ```
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::bfttf::{self, Bfttf, FontPlatform};
+use super::bntx::BntxReader;
+use super::hfs0::Hfs0Reader;
+use super::nca::NcaReader;
+use super::ncz::{NczHeader, UNCOMPRESSABLE_HEADER_SIZE};
+use super::pfs0::Pfs0Reader;
+use super::sarc::SarcReader;
+use super::xci::{KEY_AREA_SIZE, Xci};
+use crate::crypto::nca::decrypt_header;
+use crate::keys::KeySet;
+use crate::{Error, Result};
+
+/// Bytes read by [`detect`]: enough to reach the NCZ magic after the
+/// NCA's first [`UNCOMPRESSABLE_HEADER_SIZE`] bytes.
+const SNIFF_SIZE: usize = UNCOMPRESSABLE_HEADER_SIZE + 8;
+
+/// Container format identified by [`detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatKind {
+    Pfs0,
+    Hfs0,
+    Xci,
+    /// NCA; `encrypted` is `false` for a decrypted dump.
+    Nca {
+        encrypted: bool,
+    },
+    Ncz,
+    Sarc,
+    Bntx,
+    /// BFTTF/BFOTF font obfuscated for the given platform.
+    Bfttf(FontPlatform),
+    Unknown,
+}
+
+/// Identify the format of the data at the current position of `r`.
+///
+/// Reads at most 0x1200 bytes and seeks back, so `r` can be passed straight
+/// to the matching parser. Encrypted NCAs cannot be recognized without the
+/// header key and are reported as [`FormatKind::Unknown`]; use
+/// [`detect_with_keys`] for them.
+pub fn detect<R: Read + Seek>(r: &mut R) -> Result<FormatKind> {
+    sniff(r, None)
+}
+
+/// Like [`detect`], but also recognizes encrypted NCAs by decrypting their
+/// header with the header key in `keys`.
+pub fn detect_with_keys<R: Read + Seek>(r: &mut R, keys: &KeySet) -> Result<FormatKind> {
+    sniff(r, keys.header_key.as_ref())
+}
+
+fn sniff<R: Read + Seek>(r: &mut R, header_key: Option<&[u8; 32]>) -> Result<FormatKind> {
+    let start = r.stream_position()?;
+    let mut head = Vec::with_capacity(SNIFF_SIZE);
+    r.take(SNIFF_SIZE as u64).read_to_end(&mut head)?;
+    r.seek(SeekFrom::Start(start))?;
+
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    let is_nca = |header: &[u8]| {
+        matches!(
+            header.get(0x200..0x204),
+            Some(b"NCA3" | b"NCA2" | b"NCA1" | b"NCA0")
+        )
+    };
+    let xci_magic = [0x100, KEY_AREA_SIZE as usize + 0x100];
+
+    Ok(match head.get(..4) {
+        Some(b"PFS0") => FormatKind::Pfs0,
+        Some(b"HFS0") => FormatKind::Hfs0,
+        Some(b"SARC") => FormatKind::Sarc,
+        Some(b"BNTX") => FormatKind::Bntx,
+        _ if xci_magic.iter().any(|&o| at(o, b"HEAD")) => FormatKind::Xci,
+        _ if is_nca(&head) => FormatKind::Nca { encrypted: false },
+        _ if at(UNCOMPRESSABLE_HEADER_SIZE, b"NCZSECTN") => FormatKind::Ncz,
+        _ => {
+            if let Some((platform, _)) = bfttf::detect(&head) {
+                FormatKind::Bfttf(platform)
+            } else if let Some(key) = header_key
+                && decrypt_header(&head, key).is_ok_and(|header| is_nca(&header))
+            {
+                FormatKind::Nca { encrypted: true }
+            } else {
+                FormatKind::Unknown
+            }
+        }
+    })
+}
+
+/// A container opened by [`open_any`], tagged with its format.
+pub enum AnyFormat<R> {
+    Pfs0(Pfs0Reader<R>),
+    Hfs0(Hfs0Reader<R>),
+    /// XCI metadata and the reader it was parsed from.
+    Xci(Xci, R),
+    Nca(Box<NcaReader<R>>),
+    /// NCZ section header and the reader, positioned at the start of the
+    /// NCZ. Wrap it in an [`NczReader`](super::ncz::NczReader) to get the
+    /// NCA back (requires the `compression` feature).
+    Ncz(NczHeader, R),
+    Sarc(SarcReader<R>),
+    Bntx(BntxReader<R>),
+    Bfttf(Bfttf),
+}
+
+/// Detect the format of `reader` and parse it with the matching reader.
+///
+/// `keys` is used to recognize and open NCAs; it needs the header key for
+/// encrypted ones. Returns [`Error::BadMagic`] if the format is not
+/// recognized.
+pub fn open_any<R: Read + Seek>(mut reader: R, keys: &KeySet) -> Result<AnyFormat<R>> {
+    Ok(match detect_with_keys(&mut reader, keys)? {
+        FormatKind::Pfs0 => AnyFormat::Pfs0(Pfs0Reader::new(reader)?),
+        FormatKind::Hfs0 => AnyFormat::Hfs0(Hfs0Reader::new(reader)?),
+        FormatKind::Xci => AnyFormat::Xci(Xci::parse(&mut reader)?, reader),
+        FormatKind::Nca { .. } => AnyFormat::Nca(Box::new(NcaReader::new(reader, keys)?)),
+        FormatKind::Ncz => {
+            let start = reader.stream_position()?;
+            reader.seek(SeekFrom::Start(start + UNCOMPRESSABLE_HEADER_SIZE as u64))?;
+            let header = NczHeader::parse(&mut reader)?;
+            reader.seek(SeekFrom::Start(start))?;
+            AnyFormat::Ncz(header, reader)
+        }
+        FormatKind::Sarc => AnyFormat::Sarc(SarcReader::new(reader)?),
+        FormatKind::Bntx => AnyFormat::Bntx(BntxReader::new(reader)?),
+        FormatKind::Bfttf(_) => AnyFormat::Bfttf(Bfttf::parse(&mut reader)?),
+        FormatKind::Unknown => return Err(Error::BadMagic),
+    })
+}
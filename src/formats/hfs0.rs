@@ -33,17 +33,44 @@
 //! * `logo`   - [4.0.0+] supersedes normal partition content.
 //! * `update` - system update NCAs.
 //! * `secure` - all game NCAs (encrypted).
+//!
+//! Each sub-partition is itself an HFS0. [`Hfs0Reader::open_partition`]
+//! opens one as a nested reader, and [`Hfs0Reader::walk`] lists the files
+//! of all of them.
+//!
+//! ## Hashed regions
+//! Game cards hash the first 0x200 bytes (the NCA header) of each secure
+//! partition NCA, and the header of each root sub-partition. [`Hfs0Writer`]
+//! takes the region size as a [`HashedRegion`] policy, and
+//! [`Hfs0Reader::verify_report`] checks every entry against its hash.
+//!
+//! ## In-memory archives
+//! [`Hfs0Slice`] parses an HFS0 already in memory (e.g. a mmapped XCI),
+//! borrowing names and file data from it instead of reading.
+//! [`Hfs0Listing`] reads only the entries and keeps the string table,
+//! borrowing names from it instead of allocating one per entry.
 
-use std::io::{Read, Seek, SeekFrom, Take};
-use std::ops::Index;
+use std::borrow::Cow;
+use std::io::{self, Read, Seek, SeekFrom, Take, Write};
+use std::ops::{Index, Range};
+use std::path::PathBuf;
 
-use crate::Result;
-use crate::utils::{bytesa, bytesv, le_u32, le_u64, magic, null_string};
+use super::archive::{ArchiveEntry, ArchiveRead};
+use crate::crypto::sha256::{HASH_BATCH_SIZE, Sha256, sha256_many};
+use crate::error::Context;
+use crate::progress::{NoProgress, Progress, ProgressReader};
+use crate::read_at::{RangeReader, ReadAt};
+use crate::utils::{
+    EntrySource, SubReader, bytesa, le_u32, le_u64, magic, null_range, null_str, string_table,
+    subslice, table,
+};
+use crate::{Error, Result};
 
 /// Parsed HFS0 container (metadata only).
 ///
 /// File data is accessed via [`Hfs0Reader`].
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hfs0 {
     /// All file entries in declaration order.
     pub files: Vec<Hfs0File>,
@@ -53,6 +80,7 @@ pub struct Hfs0 {
 
 /// Metadata for a single file inside an HFS0.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hfs0File {
     /// File name decoded from the string table.
     pub name: String,
@@ -72,31 +100,113 @@ impl Hfs0 {
     /// The reader must be positioned at the `HFS0` magic.
     /// File contents are not read; use [`Hfs0Reader`] for data access.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
-        let base = r.stream_position()?;
-        magic(r, b"HFS0")?;
+        Hfs0Listing::parse(r).map(Self::from)
+    }
+}
 
-        let file_count = le_u32(r)?;
-        let string_table_size = le_u32(r)?;
-        let _reserved = le_u32(r)?;
+impl From<Hfs0Listing> for Hfs0 {
+    fn from(listing: Hfs0Listing) -> Self {
+        Self {
+            files: listing.files().map(Hfs0ListingFile::into_owned).collect(),
+            data_offset: listing.data_offset,
+        }
+    }
+}
 
-        let mut entries = Vec::with_capacity(file_count as usize);
-        for _ in 0..file_count {
-            let offset = le_u64(r)?;
-            let size = le_u64(r)?;
-            let name_offset = le_u32(r)?;
-            let hashed_region_size = le_u32(r)?;
-            let _reserved1 = le_u32(r)?;
-            let _reserved2 = le_u32(r)?;
-            let sha256 = bytesa::<32>(r)?;
-            entries.push((offset, size, name_offset, hashed_region_size, sha256));
+/// HFS0 entry list that keeps the raw string table.
+///
+/// Unlike [`Hfs0`], names are not allocated per entry: [`files`] borrows
+/// each one from the table. Convert into an [`Hfs0`] with [`From`] to get
+/// owned entries.
+///
+/// [`files`]: Hfs0Listing::files
+#[derive(Debug, Clone)]
+pub struct Hfs0Listing {
+    entries: Vec<ListedFile>,
+    string_table: Vec<u8>,
+    data_offset: u64,
+}
+
+/// Entry of an [`Hfs0Listing`]; `name` is a range of the string table.
+#[derive(Debug, Clone)]
+struct ListedFile {
+    name: Range<usize>,
+    offset: u64,
+    size: u64,
+    hashed_region_size: u32,
+    sha256: [u8; 32],
+}
+
+/// A single file of an [`Hfs0Listing`].
+#[derive(Debug, Clone)]
+pub struct Hfs0ListingFile<'a> {
+    /// File name, borrowed from the string table unless it is not valid
+    /// UTF-8.
+    pub name: Cow<'a, str>,
+    /// Offset relative to the HFS0 data section.
+    pub offset: u64,
+    /// File size in bytes.
+    pub size: u64,
+    /// Number of leading bytes covered by `sha256`.
+    pub hashed_region_size: u32,
+    /// SHA-256 hash of the first `hashed_region_size` bytes.
+    pub sha256: [u8; 32],
+}
+
+impl Hfs0ListingFile<'_> {
+    /// Copy into an owned [`Hfs0File`].
+    pub fn into_owned(self) -> Hfs0File {
+        Hfs0File {
+            name: self.name.into_owned(),
+            offset: self.offset,
+            size: self.size,
+            hashed_region_size: self.hashed_region_size,
+            sha256: self.sha256,
         }
+    }
+}
 
-        let string_table = bytesv(r, string_table_size as usize)?;
+impl Hfs0Listing {
+    /// Parse the entries of an HFS0 container from `r`.
+    ///
+    /// The reader must be positioned at the `HFS0` magic. Fails like
+    /// [`Hfs0::parse`].
+    pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
+        let header = || "header".to_string();
+        magic(r, b"HFS0").at_field("HFS0", base, header, "magic")?;
 
-        let mut files = Vec::with_capacity(file_count as usize);
-        for (offset, size, name_offset, hashed_region_size, sha256) in entries {
-            let name = null_string(&string_table, name_offset as usize)?;
-            files.push(Hfs0File {
+        // Read each table in one go and decode it from memory; a read per
+        // field is a syscall each on an unbuffered `File`.
+        let head = bytesa::<0xC>(r).at("HFS0", base, header)?;
+        let h = &mut &head[..];
+        let file_count = le_u32(h)?;
+        let string_table_size = le_u32(h)?;
+
+        let entry_table =
+            table(r, file_count as u64 * 0x40).at("HFS0", base + 0x10, || "entry table".into())?;
+        let string_table = table(r, string_table_size as u64).at(
+            "HFS0",
+            base + 0x10 + file_count as u64 * 0x40,
+            || "string table".into(),
+        )?;
+
+        let mut entries = Vec::with_capacity(file_count as usize);
+        for (i, entry) in entry_table.chunks_exact(0x40).enumerate() {
+            let e = &mut &entry[..];
+            let offset = le_u64(e)?;
+            let size = le_u64(e)?;
+            let name_offset = le_u32(e)?;
+            let hashed_region_size = le_u32(e)?;
+            let _reserved = le_u64(e)?;
+            let sha256 = bytesa::<32>(e)?;
+            let name = null_range(&string_table, name_offset as usize).at_field(
+                "HFS0",
+                base + 0x10 + i as u64 * 0x40,
+                || format!("entry {i}"),
+                "NameOffset",
+            )?;
+            entries.push(ListedFile {
                 name,
                 offset,
                 size,
@@ -109,7 +219,43 @@ impl Hfs0 {
         let entry_table_size = file_count as u64 * 0x40;
         let data_offset = base + 0x10 + entry_table_size + string_table_size as u64;
 
-        Ok(Self { files, data_offset })
+        Ok(Self {
+            entries,
+            string_table,
+            data_offset,
+        })
+    }
+
+    /// Number of files.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `true` if the archive has no files.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over all file entries, borrowing their names.
+    pub fn files(&self) -> impl Iterator<Item = Hfs0ListingFile<'_>> {
+        self.entries.iter().map(|e| Hfs0ListingFile {
+            name: String::from_utf8_lossy(&self.string_table[e.name.clone()]),
+            offset: e.offset,
+            size: e.size,
+            hashed_region_size: e.hashed_region_size,
+            sha256: e.sha256,
+        })
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<Hfs0ListingFile<'_>> {
+        self.files().find(|f| f.name == name)
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<Hfs0ListingFile<'_>> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
     }
 }
 
@@ -135,6 +281,12 @@ impl<R: Read + Seek> Hfs0Reader<R> {
         self.files().find(|f| f.name == name)
     }
 
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&Hfs0File> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
     /// Open a file for streaming access.
     ///
     /// Seeks to the file's start and returns a [`Take`] limited to its byte
@@ -145,6 +297,14 @@ impl<R: Read + Seek> Hfs0Reader<R> {
         Ok(self.inner.by_ref().take(file.size))
     }
 
+    /// Open the file named `name` for streaming access.
+    ///
+    /// Returns [`Error::NotFound`] if there is no such file.
+    pub fn open(&mut self, name: &str) -> Result<Take<&mut R>> {
+        let file = self.try_get(name)?.clone();
+        self.read_file(&file)
+    }
+
     /// Iterate over all file entries.
     pub fn files(&self) -> impl Iterator<Item = &Hfs0File> {
         self.hfs0.files.iter()
@@ -154,6 +314,241 @@ impl<R: Read + Seek> Hfs0Reader<R> {
     pub fn into_inner(self) -> R {
         self.inner
     }
+
+    /// Consume the reader, returning a stream over just `file`.
+    ///
+    /// Seeks within the returned [`SubReader`] are relative to the file, so
+    /// it can be passed to another parser without borrowing this reader.
+    pub fn into_file(self, file: &Hfs0File) -> SubReader<R> {
+        SubReader::new(self.inner, self.hfs0.data_offset + file.offset, file.size)
+    }
+
+    /// Consume the reader, opening the entry named `name` as a nested HFS0
+    /// that owns the stream.
+    ///
+    /// Unlike [`open_partition`](Self::open_partition), the nested reader's
+    /// offsets are relative to the partition. Returns [`Error::NotFound`]
+    /// if there is no such entry.
+    pub fn into_partition(self, name: &str) -> Result<Hfs0Reader<SubReader<R>>> {
+        let file = self.try_get(name)?.clone();
+        Hfs0Reader::new(self.into_file(&file))
+    }
+
+    /// Check every entry's hashed region against its stored SHA-256.
+    ///
+    /// Only the hashed region of each entry is read. I/O errors are
+    /// returned; a stream that ends inside a hashed region is reported as
+    /// [`HashStatus::Truncated`].
+    pub fn verify_report(&mut self) -> Result<Hfs0Report> {
+        self.verify_report_with_progress(&mut NoProgress)
+    }
+
+    /// Like [`verify_report`](Self::verify_report), reporting the bytes
+    /// hashed against the total size of the hashed regions.
+    pub fn verify_report_with_progress(
+        &mut self,
+        progress: &mut dyn Progress,
+    ) -> Result<Hfs0Report> {
+        let total = self.files().map(|f| f.hashed_region_size as u64).sum();
+        let mut done = 0;
+        let mut entries = Vec::with_capacity(self.hfs0.files.len());
+        // Small regions are read into a batch and hashed together; larger
+        // ones are streamed through a single hasher.
+        let mut batch = Vec::new();
+        let mut batch_bytes = 0;
+        for file in self.hfs0.files.clone() {
+            let hashed = file.hashed_region_size as u64;
+            self.inner
+                .seek(SeekFrom::Start(self.hfs0.data_offset + file.offset))?;
+            let mut r = ProgressReader::new(
+                self.inner.by_ref().take(hashed),
+                &mut *progress,
+                &file.name,
+                &mut done,
+                total,
+            );
+            let status = if hashed > HASH_BATCH_SIZE {
+                let mut hasher = Sha256::new();
+                let read = io::copy(&mut r, &mut hasher)?;
+                if read < hashed {
+                    HashStatus::Truncated
+                } else if hasher.finalize() == file.sha256 {
+                    HashStatus::Ok
+                } else {
+                    HashStatus::Mismatch
+                }
+            } else {
+                let mut region = Vec::with_capacity(hashed as usize);
+                r.read_to_end(&mut region)?;
+                if (region.len() as u64) < hashed {
+                    HashStatus::Truncated
+                } else {
+                    // Resolved when the batch is hashed.
+                    batch_bytes += hashed;
+                    batch.push((entries.len(), file.sha256, region));
+                    HashStatus::Ok
+                }
+            };
+            entries.push(EntryCheck {
+                name: file.name,
+                status,
+                hashed_bytes: hashed,
+                unhashed_bytes: file.size.saturating_sub(hashed),
+            });
+            if batch_bytes >= HASH_BATCH_SIZE {
+                check_batch(&mut entries, &mut batch);
+                batch_bytes = 0;
+            }
+        }
+        check_batch(&mut entries, &mut batch);
+        Ok(Hfs0Report { entries })
+    }
+
+    /// Open the entry named `name` as a nested HFS0 (an XCI root
+    /// sub-partition).
+    ///
+    /// The nested reader borrows this one's stream; its offsets are
+    /// absolute within that stream. Returns [`Error::NotFound`] if there is
+    /// no such entry.
+    pub fn open_partition(&mut self, name: &str) -> Result<Hfs0Reader<&mut R>> {
+        let file = self.try_get(name)?.clone();
+        Hfs0Reader::new(self.read_file(&file)?.into_inner())
+    }
+
+    /// List the files of every sub-partition, treating each entry of this
+    /// HFS0 as a nested HFS0.
+    ///
+    /// Files are returned partition by partition, in entry order. Read them
+    /// with [`read_partition_file`](Self::read_partition_file).
+    pub fn walk(&mut self) -> Result<Vec<PartitionFile>> {
+        let mut out = Vec::new();
+        for partition in self.hfs0.files.clone() {
+            let nested = Hfs0::parse(&mut self.read_file(&partition)?.into_inner())?;
+            out.extend(nested.files.into_iter().map(|file| PartitionFile {
+                partition: partition.name.clone(),
+                data_offset: nested.data_offset + file.offset,
+                file,
+            }));
+        }
+        Ok(out)
+    }
+
+    /// Open a file returned by [`walk`](Self::walk) for streaming access.
+    pub fn read_partition_file(&mut self, file: &PartitionFile) -> Result<Take<&mut R>> {
+        self.inner.seek(SeekFrom::Start(file.data_offset))?;
+        Ok(self.inner.by_ref().take(file.file.size))
+    }
+}
+
+impl<R: ReadAt> Hfs0Reader<R> {
+    /// Open a file for reading through a shared reference.
+    ///
+    /// Unlike [`read_file`](Self::read_file), any number of files can be
+    /// open at once; see [`crate::read_at`].
+    pub fn read_file_at(&self, file: &Hfs0File) -> RangeReader<&R> {
+        RangeReader::new(&self.inner, self.hfs0.data_offset + file.offset, file.size)
+    }
+
+    /// Open a file returned by [`walk`](Self::walk) through a shared
+    /// reference.
+    pub fn read_partition_file_at(&self, file: &PartitionFile) -> RangeReader<&R> {
+        RangeReader::new(&self.inner, file.data_offset, file.file.size)
+    }
+}
+
+impl ArchiveEntry for Hfs0File {
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<R: Read + Seek> ArchiveRead for Hfs0Reader<R> {
+    type Entry = Hfs0File;
+    type EntryReader<'a>
+        = Take<&'a mut R>
+    where
+        Self: 'a;
+
+    fn entries(&self) -> impl Iterator<Item = &Hfs0File> {
+        self.files()
+    }
+
+    fn get_entry(&self, name: &str) -> Option<&Hfs0File> {
+        self.get_file(name)
+    }
+
+    fn open_entry(&mut self, entry: &Hfs0File) -> Result<Take<&mut R>> {
+        self.read_file(entry)
+    }
+}
+
+/// Outcome of checking one entry's hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HashStatus {
+    /// The hashed region matches the stored hash.
+    Ok,
+    /// The hashed region does not match the stored hash.
+    Mismatch,
+    /// The stream ended inside the hashed region.
+    Truncated,
+}
+
+/// Result of checking one entry, from [`Hfs0Reader::verify_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntryCheck {
+    pub name: String,
+    pub status: HashStatus,
+    /// Bytes covered by the hash (the entry's hashed region size).
+    pub hashed_bytes: u64,
+    /// Bytes of the entry past the hashed region, which were not checked.
+    pub unhashed_bytes: u64,
+}
+
+/// Per-entry results of [`Hfs0Reader::verify_report`], in entry order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hfs0Report {
+    pub entries: Vec<EntryCheck>,
+}
+
+impl Hfs0Report {
+    /// `true` if every entry is [`HashStatus::Ok`].
+    pub fn is_ok(&self) -> bool {
+        self.entries.iter().all(|e| e.status == HashStatus::Ok)
+    }
+
+    /// Entries that are not [`HashStatus::Ok`].
+    pub fn failures(&self) -> impl Iterator<Item = &EntryCheck> {
+        self.entries.iter().filter(|e| e.status != HashStatus::Ok)
+    }
+
+    /// Total bytes checked across all entries.
+    pub fn hashed_bytes(&self) -> u64 {
+        self.entries.iter().map(|e| e.hashed_bytes).sum()
+    }
+
+    /// Total bytes not covered by any hash.
+    pub fn unhashed_bytes(&self) -> u64 {
+        self.entries.iter().map(|e| e.unhashed_bytes).sum()
+    }
+}
+
+/// A file inside a sub-partition, as listed by [`Hfs0Reader::walk`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartitionFile {
+    /// Name of the sub-partition (e.g. `secure`).
+    pub partition: String,
+    /// Entry within the sub-partition.
+    pub file: Hfs0File,
+    /// Absolute byte offset of the file data in the stream.
+    pub data_offset: u64,
 }
 
 impl<R: Read + Seek> Index<&str> for Hfs0Reader<R> {
@@ -162,8 +557,396 @@ impl<R: Read + Seek> Index<&str> for Hfs0Reader<R> {
     /// Index by file name.
     ///
     /// # Panics
-    /// Panics if the file name does not exist.
+    /// Panics if the file name does not exist; use
+    /// [`try_get`](Hfs0Reader::try_get) to handle that case.
     fn index(&self, index: &str) -> &Self::Output {
-        self.get_file(index).expect("no such file in HFS0")
+        self.try_get(index).unwrap_or_else(|e| panic!("HFS0: {e}"))
+    }
+}
+
+/// HFS0 container parsed from a byte slice.
+///
+/// Names and file data borrow from the input, so nothing beyond the entry
+/// list is copied.
+#[derive(Debug, Clone)]
+pub struct Hfs0Slice<'a> {
+    /// All file entries in declaration order.
+    pub files: Vec<Hfs0SliceFile<'a>>,
+}
+
+/// A single file inside an [`Hfs0Slice`].
+#[derive(Debug, Clone)]
+pub struct Hfs0SliceFile<'a> {
+    /// File name decoded from the string table.
+    pub name: Cow<'a, str>,
+    /// Offset relative to the HFS0 data section.
+    pub offset: u64,
+    /// Number of leading bytes covered by `sha256`.
+    pub hashed_region_size: u32,
+    /// SHA-256 of the first `hashed_region_size` bytes.
+    pub sha256: [u8; 32],
+    /// File contents.
+    pub data: &'a [u8],
+}
+
+impl<'a> Hfs0Slice<'a> {
+    /// Parse an HFS0 container from `data`, which must start at the `HFS0`
+    /// magic.
+    ///
+    /// Returns [`Error::InvalidRange`] if a file extends past the end of
+    /// `data`.
+    pub fn parse_bytes(data: &'a [u8]) -> Result<Self> {
+        let r = &mut &data[..];
+        magic(r, b"HFS0")?;
+
+        let file_count = le_u32(r)?;
+        let string_table_size = le_u32(r)?;
+        let _reserved = le_u32(r)?;
+
+        let entry_table_size = file_count as u64 * 0x40;
+        let string_table = subslice(data, 0x10 + entry_table_size, string_table_size as u64)?;
+        let data_offset = 0x10 + entry_table_size + string_table_size as u64;
+
+        let mut files = Vec::with_capacity(file_count as usize);
+        for _ in 0..file_count {
+            let offset = le_u64(r)?;
+            let size = le_u64(r)?;
+            let name_offset = le_u32(r)?;
+            let hashed_region_size = le_u32(r)?;
+            let _reserved1 = le_u32(r)?;
+            let _reserved2 = le_u32(r)?;
+            let sha256 = bytesa::<32>(r)?;
+            let start = data_offset.checked_add(offset).ok_or(Error::InvalidRange)?;
+            files.push(Hfs0SliceFile {
+                name: null_str(string_table, name_offset as usize)?,
+                offset,
+                hashed_region_size,
+                sha256,
+                data: subslice(data, start, size)?,
+            });
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<&Hfs0SliceFile<'a>> {
+        self.files().find(|f| f.name == name)
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&Hfs0SliceFile<'a>> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    /// Contents of the file named `name`. Returns [`None`] if not found.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.get_file(name).map(|f| f.data)
+    }
+
+    /// Contents of the file named `name`. Returns [`Error::NotFound`] if
+    /// not found.
+    pub fn open(&self, name: &str) -> Result<&'a [u8]> {
+        self.try_get(name).map(|f| f.data)
+    }
+
+    /// Iterate over all file entries.
+    pub fn files(&self) -> impl Iterator<Item = &Hfs0SliceFile<'a>> {
+        self.files.iter()
+    }
+
+    /// Parse the entry named `name` as a nested HFS0 (an XCI root
+    /// sub-partition).
+    ///
+    /// Returns [`Error::NotFound`] if there is no such entry.
+    pub fn open_partition(&self, name: &str) -> Result<Hfs0Slice<'a>> {
+        Hfs0Slice::parse_bytes(self.open(name)?)
+    }
+
+    /// Check every entry's hashed region against its stored SHA-256.
+    ///
+    /// An entry shorter than its hashed region is reported as
+    /// [`HashStatus::Truncated`].
+    pub fn verify_report(&self) -> Hfs0Report {
+        let regions: Vec<Option<&[u8]>> = self
+            .files
+            .iter()
+            .map(|file| file.data.get(..file.hashed_region_size as usize))
+            .collect();
+        let digests = sha256_many(&regions.iter().map(|r| r.unwrap_or(&[])).collect::<Vec<_>>());
+        let entries = self
+            .files
+            .iter()
+            .zip(regions.iter().zip(digests))
+            .map(|(file, (region, digest))| {
+                let hashed = file.hashed_region_size as u64;
+                let status = match region {
+                    None => HashStatus::Truncated,
+                    Some(_) if digest == file.sha256 => HashStatus::Ok,
+                    Some(_) => HashStatus::Mismatch,
+                };
+                EntryCheck {
+                    name: file.name.clone().into_owned(),
+                    status,
+                    hashed_bytes: hashed,
+                    unhashed_bytes: (file.data.len() as u64).saturating_sub(hashed),
+                }
+            })
+            .collect();
+        Hfs0Report { entries }
+    }
+}
+
+/// Hash the regions collected by [`Hfs0Reader::verify_report_with_progress`]
+/// and mark each entry whose digest differs as a mismatch.
+fn check_batch(entries: &mut [EntryCheck], batch: &mut Vec<(usize, [u8; 32], Vec<u8>)>) {
+    let regions: Vec<&[u8]> = batch.iter().map(|(_, _, region)| &region[..]).collect();
+    for ((index, expected, _), digest) in batch.iter().zip(sha256_many(&regions)) {
+        if digest != *expected {
+            entries[*index].status = HashStatus::Mismatch;
+        }
+    }
+    batch.clear();
+}
+
+impl<'a> Index<&str> for Hfs0Slice<'a> {
+    type Output = Hfs0SliceFile<'a>;
+
+    /// Index by file name.
+    ///
+    /// # Panics
+    /// Panics if the file name does not exist; use
+    /// [`try_get`](Hfs0Slice::try_get) to handle that case.
+    fn index(&self, index: &str) -> &Self::Output {
+        self.try_get(index).unwrap_or_else(|e| panic!("HFS0: {e}"))
+    }
+}
+
+/// How much of an entry [`Hfs0Writer`] covers with its SHA-256 hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashedRegion {
+    /// Hash the whole entry. Entries larger than 4 GiB cannot be fully
+    /// hashed.
+    Full,
+    /// Hash at most this many leading bytes.
+    Prefix(u32),
+}
+
+impl Default for HashedRegion {
+    /// 0x200 bytes, as on game cards: the prefix of an NCA covering its
+    /// signatures, not the whole 0xC00-byte header.
+    fn default() -> Self {
+        Self::Prefix(0x200)
+    }
+}
+
+/// Builder that writes an HFS0 partition, hashing each entry as it is
+/// streamed.
+///
+/// The header is padded to a multiple of 0x200 bytes, as on game cards.
+#[derive(Default)]
+pub struct Hfs0Writer<'a> {
+    entries: Vec<(String, EntrySource<'a>, Option<HashedRegion>)>,
+    hashed_region: HashedRegion,
+}
+
+impl<'a> Hfs0Writer<'a> {
+    /// Create an empty writer using [`HashedRegion::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the hashed region for entries added without one.
+    pub fn hashed_region(&mut self, region: HashedRegion) -> &mut Self {
+        self.hashed_region = region;
+        self
+    }
+
+    /// Add an entry whose data is read from `reader`.
+    pub fn add_reader(&mut self, name: impl Into<String>, reader: impl Read + 'a) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Reader(Box::new(reader)), None));
+        self
+    }
+
+    /// Add an entry read from `reader` with its own hashed region.
+    pub fn add_reader_hashed(
+        &mut self,
+        name: impl Into<String>,
+        reader: impl Read + 'a,
+        region: HashedRegion,
+    ) -> &mut Self {
+        self.entries.push((
+            name.into(),
+            EntrySource::Reader(Box::new(reader)),
+            Some(region),
+        ));
+        self
+    }
+
+    /// Add an entry whose data is the file at `path`.
+    ///
+    /// The file is opened when [`write`](Self::write) reaches it.
+    pub fn add_path(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Path(path.into()), None));
+        self
+    }
+
+    /// Add the file at `path` with its own hashed region.
+    pub fn add_path_hashed(
+        &mut self,
+        name: impl Into<String>,
+        path: impl Into<PathBuf>,
+        region: HashedRegion,
+    ) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Path(path.into()), Some(region)));
+        self
+    }
+
+    /// Write the partition to `w` at its current position.
+    ///
+    /// Returns the metadata of the written partition, including the computed
+    /// hashes. Returns [`Error::InvalidRange`] if the header does not fit its
+    /// 32-bit fields or a [`HashedRegion::Full`] entry exceeds 4 GiB. Path
+    /// entries are checked before anything is written; reader entries only
+    /// once they have been streamed.
+    pub fn write<W: Write + Seek>(self, w: &mut W) -> Result<Hfs0> {
+        for (_, source, region) in &self.entries {
+            if let EntrySource::Path(path) = source
+                && region.unwrap_or(self.hashed_region) == HashedRegion::Full
+                && std::fs::metadata(path)?.len() > u32::MAX as u64
+            {
+                return Err(Error::InvalidRange);
+            }
+        }
+
+        let base = w.stream_position()?;
+        let (mut strings, name_offsets) =
+            string_table(self.entries.iter().map(|(n, _, _)| n.as_str()));
+        let entries_end = 0x10 + self.entries.len() * 0x40;
+        strings.resize(
+            (entries_end + strings.len()).next_multiple_of(0x200) - entries_end,
+            0,
+        );
+        let file_count = u32::try_from(self.entries.len()).map_err(|_| Error::InvalidRange)?;
+        let string_table_size = u32::try_from(strings.len()).map_err(|_| Error::InvalidRange)?;
+
+        let data_offset = base + (entries_end + strings.len()) as u64;
+        w.seek(SeekFrom::Start(data_offset))?;
+        let mut files = Vec::with_capacity(self.entries.len());
+        let mut offset = 0;
+        for (name, source, region) in self.entries {
+            let limit = match region.unwrap_or(self.hashed_region) {
+                HashedRegion::Full => u32::MAX as u64 + 1,
+                HashedRegion::Prefix(n) => n as u64,
+            };
+            let mut hashing = PrefixHasher {
+                inner: &mut *w,
+                hasher: Sha256::new(),
+                remaining: limit,
+            };
+            let size = io::copy(&mut source.open()?, &mut hashing)?;
+            let hashed_region_size =
+                u32::try_from(size.min(limit)).map_err(|_| Error::InvalidRange)?;
+            files.push(Hfs0File {
+                name,
+                offset,
+                size,
+                hashed_region_size,
+                sha256: hashing.hasher.finalize(),
+            });
+            offset += size;
+        }
+        let end = w.stream_position()?;
+
+        w.seek(SeekFrom::Start(base))?;
+        w.write_all(b"HFS0")?;
+        w.write_all(&file_count.to_le_bytes())?;
+        w.write_all(&string_table_size.to_le_bytes())?;
+        w.write_all(&[0; 4])?;
+        for (file, name_offset) in files.iter().zip(name_offsets) {
+            w.write_all(&file.offset.to_le_bytes())?;
+            w.write_all(&file.size.to_le_bytes())?;
+            w.write_all(&name_offset.to_le_bytes())?;
+            w.write_all(&file.hashed_region_size.to_le_bytes())?;
+            w.write_all(&[0; 8])?;
+            w.write_all(&file.sha256)?;
+        }
+        w.write_all(&strings)?;
+        w.seek(SeekFrom::Start(end))?;
+
+        Ok(Hfs0 { files, data_offset })
+    }
+}
+
+/// Writer that hashes the first `remaining` bytes passing through it.
+struct PrefixHasher<W> {
+    inner: W,
+    hasher: Sha256,
+    remaining: u64,
+}
+
+impl<W: Write> Write for PrefixHasher<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let hashed = n.min(self.remaining.try_into().unwrap_or(usize::MAX));
+        self.hasher.update(&buf[..hashed]);
+        self.remaining -= hashed as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::crypto::sha256::sha256;
+
+    #[test]
+    fn round_trip() {
+        let big: Vec<u8> = (0..0x1234).map(|i| i as u8).collect();
+        let mut writer = Hfs0Writer::new();
+        writer.add_reader("a.nca", &big[..]).add_reader_hashed(
+            "b.bin",
+            &b"small"[..],
+            HashedRegion::Full,
+        );
+        let mut out = Cursor::new(Vec::new());
+        let written = writer.write(&mut out).unwrap();
+        let mut data = out.into_inner();
+
+        let mut reader = Hfs0Reader::new(Cursor::new(&data)).unwrap();
+        let a = reader.try_get("a.nca").unwrap().clone();
+        assert_eq!(a.size, big.len() as u64);
+        assert_eq!(a.hashed_region_size, 0x200);
+        assert_eq!(a.sha256, sha256(&big[..0x200]));
+        assert_eq!(reader.try_get("b.bin").unwrap().hashed_region_size, 5);
+        let mut contents = Vec::new();
+        reader
+            .read_file(&a)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, big);
+        assert!(reader.verify_report().unwrap().is_ok());
+
+        // Corrupt the first byte of the second entry's data.
+        let b = &written.files[1];
+        let at = (written.data_offset + b.offset) as usize;
+        data[at] ^= 1;
+        let report = Hfs0Reader::new(Cursor::new(&data))
+            .unwrap()
+            .verify_report()
+            .unwrap();
+        let failures: Vec<_> = report.failures().map(|e| e.name.as_str()).collect();
+        assert_eq!(failures, ["b.bin"]);
     }
 }
@@ -9,14 +9,31 @@
 //! * **Metadata only** - the `parse` method reads headers and builds an
 //!   in-memory description of the archive's contents. File data is never
 //!   eagerly loaded.
-//! * **Reader wrappers** - archive formats ([`pfs0::Pfs0`], [`hfs0::Hfs0`], [`sarc::Sarc`]) have a
-//!   matching `*Reader<R>` type that owns the underlying reader and provides
-//!   zero-copy bounded access to individual file contents via
+//! * **Reader wrappers** - archive formats ([`pfs0::Pfs0`], [`hfs0::Hfs0`], [`sarc::Sarc`],
+//!   [`bntx::Bntx`], NSP) have a matching `*Reader<R>` type that owns the
+//!   underlying reader and provides zero-copy bounded access to individual
+//!   file contents via
 //!   [`std::io::Take<&mut R>`].
+//! * **Slices** - [`pfs0::Pfs0Slice`], [`hfs0::Hfs0Slice`], and
+//!   [`sarc::SarcSlice`] parse an archive that is already in memory with
+//!   `parse_bytes`, borrowing names and file data instead of reading.
+//! * **Listings** - [`pfs0::Pfs0Listing`], [`hfs0::Hfs0Listing`], and
+//!   [`sarc::SarcListing`] keep the string table read by `parse` and borrow
+//!   entry names from it, for listing large archives without allocating a
+//!   name per entry.
+//! * **One interface** - the archive readers implement
+//!   [`archive::ArchiveRead`], for code generic over the container type.
+//! * **Detection** - [`detect`] identifies a container from its leading
+//!   bytes, and [`open_any`] opens it with the matching reader.
+//! * **Writers** - [`pfs0::Pfs0Writer`], [`hfs0::Hfs0Writer`], and
+//!   [`sarc::SarcWriter`] build an archive from named entries (readers or
+//!   paths) and write it out. [`ncz::NczWriter`] compresses an NCA into an
+//!   NCZ (requires the `compression` feature).
 //! * **Crypto and compression are separate** - parsers receive
 //!   already-decrypted / already-decompressed bytes. Use
 //!   [`crate::crypto::nca`] and [`crate::compression`] before parsing when
-//!   necessary.
+//!   necessary. The exception is [`nca::NcaReader`], which decrypts an NCA
+//!   and its sections on the fly from a [`crate::keys::KeySet`].
 //!
 //! ## Format overview
 //!
@@ -24,24 +41,35 @@
 //! |-----------|-------------|-------------|
 //! | [`bfttf`] | BFTTF/BFOTF | XOR-obfuscated TrueType/OpenType system font |
 //! | [`bntx`]  | BNTX        | GPU texture container; one or more textures with mip chains |
+//! | [`cnmt`]  | CNMT        | Content metadata; the NCAs (with hashes) that make up a title |
 //! | [`hfs0`]  | HFS0        | SHA-256-hashed archive embedded in XCI game cards |
 //! | [`nacp`]  | NACP        | Application control property; title names, ratings, save data sizes |
 //! | [`pfs0`]  | PFS0 / NSP  | Flat archive; outer container for NSP files and NCA ExeFS/Logo sections |
 //! | [`nca`]   | NCA         | Primary encrypted content container; holds program, meta, control, and data content |
 //! | [`ncz`]   | NCZ / NSZ   | Zstandard-compressed NCA sections packed inside an NSP/PFS0 |
+//! | [`nsp`]   | NSP         | eShop package; a PFS0 of NCAs, tickets, and certificates named by content ID |
 //! | [`npdm`]  | NPDM        | Process security metadata (`main.npdm`) found in NCA ExeFS sections |
 //! | [`romfs`] | RomFS       | Read-only game asset filesystem; Level 3 of the IVFC hash tree inside NCA RomFS sections |
 //! | [`sarc`]  | SARC        | General-purpose game asset archive; often Zstd-compressed (`.zs` / `.szs`) |
+//! | [`ticket`] | Ticket     | eShop licence holding the encrypted title key for a rights ID |
 //! | [`xci`]   | XCI         | Physical game card dump; root contains an HFS0 partition table |
 
+pub mod archive;
 pub mod bfttf;
 pub mod bntx;
+pub mod cnmt;
 pub mod hfs0;
 pub mod nacp;
 pub mod nca;
 pub mod ncz;
 pub mod npdm;
+pub mod nsp;
 pub mod pfs0;
 pub mod romfs;
 pub mod sarc;
+pub mod ticket;
 pub mod xci;
+
+mod detect;
+
+pub use detect::{AnyFormat, FormatKind, detect, detect_with_keys, open_any};
@@ -86,6 +86,7 @@ pub const NACP_LANGUAGE_COUNT: usize = 16;
 
 /// Language indices for NACP title entries.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(usize)]
 pub enum Language {
     AmericanEnglish = 0,
@@ -152,6 +153,7 @@ impl Language {
 
 /// Localised title and developer name for one language.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NacpTitle {
     /// Application name, null-padded UTF-8, 0x200 bytes.
     pub name: String,
@@ -170,6 +172,7 @@ impl NacpTitle {
 
 /// Screenshot permission.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Screenshot {
     Allow,
     Deny,
@@ -188,6 +191,7 @@ impl From<u8> for Screenshot {
 
 /// Video capture permission.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VideoCapture {
     Disabled,
     Enabled,
@@ -208,6 +212,7 @@ impl From<u8> for VideoCapture {
 
 /// Logo type shown on startup.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LogoType {
     LicensedByNintendo,
     DistributedByNintendo,
@@ -227,7 +232,8 @@ impl From<u8> for LogoType {
 }
 
 /// Parsed NACP (Nintendo Application Control Property).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nacp {
     /// Localised titles, one per language (index = [`Language`] as usize).
     pub titles: [NacpTitle; NACP_LANGUAGE_COUNT],
@@ -30,14 +30,36 @@
 //! [0x800] FsHeader[2]      (0x200 bytes)
 //! [0xA00] FsHeader[3]      (0x200 bytes)
 //! ```
+//!
+//! ## Section Decryption
+//! [`NcaReader`] wraps the still-encrypted NCA together with a [`KeySet`]: it
+//! decrypts and parses the header itself, unwraps the key area, and exposes
+//! each section as a [`Read`] + [`Seek`] stream that is AES-CTR decrypted on
+//! the fly. The counter for any byte is the section's [`FsHeader::build_ctr_base`]
+//! with the lower 8 bytes set to the absolute NCA offset of that byte / 0x10.
+//!
+//! ## Verification
+//! [`NcaReader::verify`] checks each FsHeader against its hash in the NCA
+//! header and each section against its hash tree (`HierarchicalSha256` or
+//! `HierarchicalIntegrity`), reporting the first bad block per section.
 
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom};
 
-use crate::utils::{bytesa, le_u16, le_u32, le_u64, u8};
+use super::npdm::Npdm;
+use super::pfs0::Pfs0Reader;
+use super::romfs::{IVFC_MASTER_HASH_OFFSET, IvfcHeader, RomFsReader};
+use crate::crypto::nca::{AesCtrReader, build_ctr, decrypt_block_ecb, decrypt_header};
+use crate::crypto::rsa::verify_pss_sha256;
+use crate::crypto::sha256::{DIGEST_SIZE, HASH_BATCH_SIZE, sha256, sha256_many};
+use crate::error::Context;
+use crate::keys::{KaekIndex, KeySet};
+use crate::progress::{NoProgress, Progress, ProgressReader};
+use crate::utils::{bytesa, bytesv, le_u16, le_u32, le_u64, to_usize, u8};
 use crate::{Error, Result};
 
 /// Distribution type for an NCA.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum DistributionType {
     Download,
@@ -57,6 +79,7 @@ impl From<u8> for DistributionType {
 
 /// Content type for an NCA.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ContentType {
     Program,
@@ -84,6 +107,7 @@ impl From<u8> for ContentType {
 
 /// Filesystem type stored in an [`FsHeader`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FsType {
     RomFs,
     PartitionFs,
@@ -102,6 +126,7 @@ impl From<u8> for FsType {
 
 /// Hash type stored in an [`FsHeader`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HashType {
     Auto,
     None,
@@ -130,6 +155,7 @@ impl From<u8> for HashType {
 
 /// Encryption type stored in an [`FsHeader`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EncryptionType {
     Auto,
     None,
@@ -156,10 +182,26 @@ impl From<u8> for EncryptionType {
     }
 }
 
+impl From<EncryptionType> for u8 {
+    fn from(v: EncryptionType) -> Self {
+        match v {
+            EncryptionType::Auto => 0,
+            EncryptionType::None => 1,
+            EncryptionType::AesXts => 2,
+            EncryptionType::AesCtr => 3,
+            EncryptionType::AesCtrEx => 4,
+            EncryptionType::AesCtrSkipLayerHash => 5,
+            EncryptionType::AesCtrExSkipLayerHash => 6,
+            EncryptionType::Unknown(x) => x,
+        }
+    }
+}
+
 /// A section entry pointing to a filesystem region within the NCA.
 ///
 /// Offsets are in 0x200-byte media blocks.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FsEntry {
     /// Start offset in media blocks (multiply by 0x200 for bytes).
     pub start_block: u32,
@@ -173,6 +215,7 @@ pub struct FsEntry {
 /// Contains the information needed to select the decryption algorithm and
 /// build the AES-CTR counter for section data.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FsHeader {
     /// Always 2.
     pub version: u16,
@@ -180,18 +223,23 @@ pub struct FsHeader {
     pub hash_type: HashType,
     pub encryption_type: EncryptionType,
     /// Raw 0xF8-byte hash data region (layout depends on `hash_type`).
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
     pub hash_data: [u8; 0xF8],
     /// Raw 0x40-byte patch info region.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
     pub patch_info: [u8; 0x40],
     /// Upper 32 bits of the AES-CTR counter (stored big-endian in the counter).
     pub generation: u32,
     /// Bits [32..64] of the AES-CTR counter base (stored big-endian).
     pub secure_value: u32,
     /// Raw 0x30-byte sparse info region.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
     pub sparse_info: [u8; 0x30],
     /// Raw 0x28-byte compression info region (present from 12.0.0).
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
     pub compression_info: [u8; 0x28],
     /// Raw 0x30-byte metadata hash data info region (present from 14.0.0).
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
     pub metadata_hash_data_info: [u8; 0x30],
 }
 
@@ -205,18 +253,81 @@ impl FsHeader {
     /// ```
     ///
     /// Set bytes `[8..16]` to `section_byte_offset / 0x10` in big-endian
-    /// order before passing to `crypto::nca::decrypt_section_ctr`.
+    /// order before passing to `crypto::nca::decrypt_section_ctr`, or use
+    /// [`FsHeader::build_ctr`].
     pub fn build_ctr_base(&self) -> [u8; 16] {
-        let mut ctr = [0u8; 16];
-        ctr[0..4].copy_from_slice(&self.secure_value.to_be_bytes());
-        ctr[4..8].copy_from_slice(&self.generation.to_be_bytes());
-        // bytes [8..16] are the per-block offset, filled in by the caller
-        ctr
+        self.build_ctr(0)
+    }
+
+    /// Build the AES-CTR counter for the block containing `nca_offset`, an
+    /// absolute byte offset within the NCA.
+    pub fn build_ctr(&self, nca_offset: u64) -> [u8; 16] {
+        build_ctr(self.ctr_upper(), nca_offset)
+    }
+
+    /// Upper half of the section's AES-CTR counter (`SecureValue` and
+    /// `Generation`), the `secure_value` argument of
+    /// [`crypto::nca::decrypt_section_ctr_at`](crate::crypto::nca::decrypt_section_ctr_at).
+    pub fn ctr_upper(&self) -> u64 {
+        ((self.secure_value as u64) << 32) | self.generation as u64
+    }
+}
+
+/// Hash data of a `HierarchicalSha256` section (PartitionFS sections such as
+/// ExeFS and Logo), stored in [`FsHeader::hash_data`].
+///
+/// ```text
+/// [0x00] MasterHash          (0x20 bytes, SHA-256 of the hash table)
+/// [0x20] BlockSize           (u32 LE)
+/// [0x24] LayerCount          (u32 LE, always 2)
+/// [0x28] HashTableOffset     (u64 LE)
+/// [0x30] HashTableSize       (u64 LE)
+/// [0x38] Pfs0Offset          (u64 LE)
+/// [0x40] Pfs0Size            (u64 LE)
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HierarchicalSha256 {
+    /// SHA-256 of the hash table.
+    pub master_hash: [u8; 32],
+    /// Size of each hashed block in bytes.
+    pub block_size: u32,
+    /// Number of layers (always 2).
+    pub layer_count: u32,
+    /// Offset of the hash table within the section.
+    pub hash_table_offset: u64,
+    /// Size of the hash table in bytes.
+    pub hash_table_size: u64,
+    /// Offset of the PFS0 header within the section.
+    pub pfs0_offset: u64,
+    /// Size of the PFS0 region in bytes.
+    pub pfs0_size: u64,
+}
+
+impl HierarchicalSha256 {
+    /// Parse from a byte slice (e.g., `FsHeader.hash_data`).
+    ///
+    /// The slice must be at least 0x48 bytes long.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 0x48 {
+            return Err(Error::UnexpectedEof);
+        }
+        let r = &mut io::Cursor::new(data);
+        Ok(Self {
+            master_hash: bytesa::<0x20>(r)?,
+            block_size: le_u32(r)?,
+            layer_count: le_u32(r)?,
+            hash_table_offset: le_u64(r)?,
+            hash_table_size: le_u64(r)?,
+            pfs0_offset: le_u64(r)?,
+            pfs0_size: le_u64(r)?,
+        })
     }
 }
 
 /// Parsed NCA header (from decrypted bytes).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Nca {
     /// NCA format version: 0, 1, 2, or 3.
     pub version: u8,
@@ -224,6 +335,8 @@ pub struct Nca {
     pub content_type: ContentType,
     /// Effective key generation (max of KeyGenerationOld and KeyGeneration).
     pub key_generation: u8,
+    /// Selects the fixed RSA key that signs the header (9.0.0+).
+    pub signature_key_generation: u8,
     /// Key area encryption key index (0=App, 1=Ocean, 2=System).
     pub key_area_enc_key_index: u8,
     /// Total content size in bytes.
@@ -242,15 +355,30 @@ pub struct Nca {
     pub encrypted_key_area: [[u8; 16]; 4],
     /// Up to 4 filesystem section headers.
     pub fs_headers: [Option<FsHeader>; 4],
+    /// Decrypted 0xC00-byte header region; see [`Nca::raw_header`].
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
+    raw_header: Box<[u8; 0xC00]>,
 }
 
 impl Nca {
     /// Parse an NCA from `r` over **already-decrypted** NCA bytes.
     ///
     /// The reader must be positioned at the start of the decrypted NCA
-    /// (i.e., before the first RSA signature at logical offset 0x000).
+    /// (i.e., before the first RSA signature at logical offset 0x000), and
+    /// at least 0xC00 bytes must be available.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
-        let base = r.stream_position()?;
+        let start = r.stream_position()?;
+        let header = bytesv(r, 0xC00).at("NCA", start, || "header".into())?;
+        Self::from_header(header, start)
+    }
+
+    /// Parse a decrypted 0xC00-byte header that was read from absolute
+    /// stream offset `start`.
+    fn from_header(header: Vec<u8>, start: u64) -> Result<Self> {
+        // Keep the whole header region so callers can inspect or re-serialize it.
+        let raw_header: Box<[u8; 0xC00]> = header.into_boxed_slice().try_into().unwrap();
+        let r = &mut io::Cursor::new(&raw_header[..]);
+        let base = 0;
 
         // Skip the two RSA-2048 signatures (2 × 0x100 = 0x200 bytes).
         r.seek(SeekFrom::Current(0x200))?;
@@ -261,7 +389,9 @@ impl Nca {
             b"NCA2" => 2,
             b"NCA1" => 1,
             b"NCA0" => 0,
-            _ => return Err(Error::BadMagic),
+            _ => {
+                return Err(Error::BadMagic).at_field("NCA", start, || "header".into(), "magic");
+            }
         };
 
         let distribution_type = DistributionType::from(u8(r)?);
@@ -273,7 +403,7 @@ impl Nca {
         let content_index = le_u32(r)?;
         let sdk_addon_version = le_u32(r)?;
         let key_gen_new = u8(r)?;
-        let _sig_key_gen = u8(r)?;
+        let signature_key_generation = u8(r)?;
         let _reserved = bytesa::<0xE>(r)?;
 
         // Effective key generation: whichever is newer.
@@ -286,10 +416,13 @@ impl Nca {
             let start_block = le_u32(r)?;
             let end_block = le_u32(r)?;
             let _reserved = le_u64(r)?;
-            *entry = Some(FsEntry {
-                start_block,
-                end_block,
-            });
+            // Unused sections have an all-zero entry.
+            if end_block > start_block {
+                *entry = Some(FsEntry {
+                    start_block,
+                    end_block,
+                });
+            }
         }
 
         let mut fs_header_hashes = [[0u8; 0x20]; 4];
@@ -316,6 +449,7 @@ impl Nca {
             distribution_type,
             content_type,
             key_generation,
+            signature_key_generation,
             key_area_enc_key_index,
             content_size,
             program_id,
@@ -326,9 +460,23 @@ impl Nca {
             fs_header_hashes,
             encrypted_key_area,
             fs_headers,
+            raw_header,
         })
     }
 
+    /// SDK version the content was built with, as `major.minor.micro.relstep`
+    /// (e.g. `"12.3.0.0"`), decoded from `sdk_addon_version`.
+    pub fn sdk_version(&self) -> String {
+        let [relstep, micro, minor, major] = self.sdk_addon_version.to_le_bytes();
+        format!("{major}.{minor}.{micro}.{relstep}")
+    }
+
+    /// The full 0xC00-byte decrypted header region this `Nca` was parsed
+    /// from, including signatures and reserved fields.
+    pub fn raw_header(&self) -> &[u8; 0xC00] {
+        &self.raw_header
+    }
+
     /// Returns `true` if the NCA uses titlekey crypto (RightsId is not all zeros).
     pub fn uses_titlekey_crypto(&self) -> bool {
         self.rights_id.iter().any(|&b| b != 0)
@@ -351,6 +499,66 @@ impl Nca {
     pub fn fs_header(&self, section: usize) -> Option<&FsHeader> {
         self.fs_headers.get(section)?.as_ref()
     }
+
+    /// `true` if the FsHeader of `section` matches its SHA-256 in the NCA
+    /// header. Returns `false` for an absent section.
+    pub fn fs_header_hash_matches(&self, section: usize) -> bool {
+        if self.fs_header(section).is_none() {
+            return false;
+        }
+        let start = 0x400 + section * 0x200;
+        sha256(&self.raw_header[start..start + 0x200]) == self.fs_header_hashes[section]
+    }
+
+    /// Verify the fixed-key RSA-2048-PSS signature (`sig[0]`) over the
+    /// header region `[0x200..0x400]` of [`raw_header`](Self::raw_header).
+    ///
+    /// The modulus is taken from `keys.nca_hdr_fixed_key_modulus`, selected
+    /// by [`Nca::signature_key_generation`].
+    ///
+    /// Returns `Ok(false)` if the signature does not match, or
+    /// [`Error::MissingKey`] if the modulus is not loaded.
+    pub fn verify_header_signature(&self, keys: &KeySet) -> Result<bool> {
+        let generation = self.signature_key_generation;
+        let modulus = keys
+            .nca_hdr_fixed_key_modulus
+            .get(generation as usize)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| {
+                Error::MissingKey(format!("nca_hdr_fixed_key_modulus_{generation:02x}"))
+            })?;
+        let signature: &[u8; 0x100] = self.raw_header[..0x100].try_into().unwrap();
+        Ok(verify_pss_sha256(
+            &self.raw_header[0x200..0x400],
+            signature,
+            modulus,
+        ))
+    }
+
+    /// Master key revision used to select key-area and title keys.
+    ///
+    /// Key generations 0 and 1 both map to revision 0; every later
+    /// generation `n` maps to revision `n - 1`.
+    pub fn master_key_revision(&self) -> u8 {
+        self.key_generation.saturating_sub(1)
+    }
+
+    /// Decrypt the four key-area entries with the matching key area key.
+    ///
+    /// The KAEK is selected by `key_area_enc_key_index` and
+    /// [`Nca::master_key_revision`], and each entry is unwrapped with
+    /// AES-128-ECB. Entry 2 is the AES-CTR section key.
+    ///
+    /// Returns [`Error::MissingKey`] naming the absent
+    /// `key_area_key_<kind>_XX` entry if `keys` does not hold it.
+    pub fn decrypt_key_area(&self, keys: &KeySet) -> Result<[[u8; 16]; 4]> {
+        let index = KaekIndex::try_from(self.key_area_enc_key_index)?;
+        let revision = self.master_key_revision();
+        let kaek = keys.get_kaek(index, revision).ok_or_else(|| {
+            Error::MissingKey(format!("key_area_key_{}_{revision:02x}", index.name()))
+        })?;
+        Ok(self.encrypted_key_area.map(|k| decrypt_block_ecb(&k, kaek)))
+    }
 }
 
 /// Parse one 0x200-byte FsHeader from the current stream position.
@@ -392,6 +600,7 @@ fn parse_fs_header<R: Read + Seek>(r: &mut R) -> Result<FsHeader> {
 ///
 /// Section 0 = ExeFS (code + `main.npdm`), section 1 = RomFS.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProgramNca {
     pub header: Nca,
 }
@@ -432,6 +641,7 @@ impl ProgramNca {
 ///
 /// Section 0 is a PartitionFS holding the content meta XML and binary.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MetaNca {
     pub header: Nca,
 }
@@ -452,6 +662,7 @@ impl MetaNca {
 ///
 /// Section 0 is a RomFS containing `control.nacp` and icon images.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ControlNca {
     pub header: Nca,
 }
@@ -472,6 +683,7 @@ impl ControlNca {
 ///
 /// Section 1 is a RomFS containing the HTML manual documents.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ManualNca {
     pub header: Nca,
 }
@@ -490,12 +702,14 @@ impl ManualNca {
 
 /// A data NCA (`ContentType::Data`).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataNca {
     pub header: Nca,
 }
 
 /// A public data NCA (`ContentType::PublicData`).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PublicDataNca {
     pub header: Nca,
 }
@@ -516,6 +730,7 @@ pub struct PublicDataNca {
 /// }
 /// ```
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypedNca {
     Program(ProgramNca),
     Meta(MetaNca),
@@ -555,3 +770,558 @@ impl TryFrom<Nca> for TypedNca {
         }
     }
 }
+
+/// Resolve the AES-CTR section key for `nca`.
+///
+/// Titlekey-crypto NCAs use the title key registered for their rights ID,
+/// unwrapped with `titlekek_XX`; all others use key-area entry 2.
+pub(crate) fn section_key(nca: &Nca, keys: &KeySet) -> Result<[u8; 16]> {
+    if !nca.uses_titlekey_crypto() {
+        return Ok(nca.decrypt_key_area(keys)?[2]);
+    }
+
+    keys.unwrap_title_key(&nca.rights_id, nca.key_generation)
+}
+
+/// Streaming reader over an encrypted NCA.
+///
+/// Decrypts and parses the header on construction and resolves the section
+/// key: from the key area, or - when the NCA has a rights ID - by unwrapping
+/// the title key with the matching titlekek. Sections are opened with
+/// [`NcaReader::open_section`], which decrypts AES-CTR data on the fly.
+///
+/// Headers that are already plaintext and sections with
+/// [`EncryptionType::None`] are passed through untouched, so keys are only
+/// required for the parts that are actually encrypted.
+pub struct NcaReader<R> {
+    inner: R,
+    /// Absolute stream offset of the first NCA byte.
+    base: u64,
+    /// Parsed header metadata.
+    pub nca: Nca,
+    /// AES-CTR section key, or the reason it could not be resolved.
+    key: std::result::Result<[u8; 16], Error>,
+}
+
+impl<R: Read + Seek> NcaReader<R> {
+    /// Decrypt the NCA header from `reader` and wrap it.
+    ///
+    /// The reader must be positioned at the start of the NCA. Returns
+    /// [`Error::MissingKey`] if the header is encrypted and `keys` lacks the
+    /// header key. Missing section keys are reported by
+    /// [`NcaReader::open_section`] instead.
+    pub fn new(mut reader: R, keys: &KeySet) -> Result<Self> {
+        let base = reader.stream_position()?;
+        let nca = read_header(&mut reader, base, keys)?;
+        let key = section_key(&nca, keys);
+
+        Ok(Self {
+            inner: reader,
+            base,
+            nca,
+            key,
+        })
+    }
+
+    /// Open a section for streaming, decrypted access.
+    ///
+    /// Returns [`Error::InvalidRange`] if the section is absent, the error
+    /// that prevented resolving the section key if the section is encrypted
+    /// ([`Error::MissingKey`], or [`Error::Parse`] for an invalid key area
+    /// index), or [`Error::Parse`] if its encryption type is not supported.
+    pub fn open_section(&mut self, section: usize) -> Result<NcaSectionReader<'_, R>> {
+        self.open_section_as(section, false)
+    }
+
+    /// Open a section, treating [`EncryptionType::AesCtrEx`] as plain
+    /// AES-CTR with the FsHeader generation.
+    ///
+    /// Used for the BKTR tables of patch sections, which are not covered by
+    /// the AES-CTR-Ex subsections; see [`crate::patching`].
+    pub(crate) fn open_section_ctr(&mut self, section: usize) -> Result<NcaSectionReader<'_, R>> {
+        self.open_section_as(section, true)
+    }
+
+    fn open_section_as(
+        &mut self,
+        section: usize,
+        ctr_ex_as_ctr: bool,
+    ) -> Result<NcaSectionReader<'_, R>> {
+        let offset = self
+            .nca
+            .section_offset(section)
+            .ok_or(Error::InvalidRange)?;
+        let size = self.nca.section_size(section).ok_or(Error::InvalidRange)?;
+        let fs_header = self.nca.fs_header(section).ok_or(Error::InvalidRange)?;
+
+        let key = match fs_header.encryption_type {
+            EncryptionType::None => None,
+            EncryptionType::AesCtrEx if !ctr_ex_as_ctr => {
+                return Err(Error::Parse(
+                    "AES-CTR-Ex sections must be opened through crate::patching",
+                ));
+            }
+            EncryptionType::AesCtr | EncryptionType::AesCtrEx => {
+                Some(*self.key.as_ref().map_err(Error::duplicate)?)
+            }
+            _ => return Err(Error::Parse("unsupported NCA section encryption type")),
+        };
+
+        let start = self.base + offset;
+        let stream = match key {
+            None => SectionStream::Plain {
+                inner: &mut self.inner,
+                start,
+                size,
+                pos: 0,
+            },
+            Some(key) => {
+                let ctr = fs_header.build_ctr(offset);
+                SectionStream::Ctr(Box::new(AesCtrReader::new(
+                    &mut self.inner,
+                    &key,
+                    ctr,
+                    start,
+                    size,
+                )))
+            }
+        };
+        Ok(NcaSectionReader { stream })
+    }
+
+    /// Open a PartitionFS section as a [`Pfs0Reader`].
+    ///
+    /// The PFS0 offset is taken from the section's [`HierarchicalSha256`]
+    /// hash data. Returns [`Error::Parse`] if the section is not a
+    /// PartitionFS.
+    pub fn open_pfs0(&mut self, section: usize) -> Result<Pfs0Reader<NcaSectionReader<'_, R>>> {
+        let fs_header = self.nca.fs_header(section).ok_or(Error::InvalidRange)?;
+        if fs_header.fs_type != FsType::PartitionFs {
+            return Err(Error::Parse("NCA section is not a PartitionFS"));
+        }
+        let hash_data = HierarchicalSha256::from_bytes(&fs_header.hash_data)?;
+
+        let mut section = self.open_section(section)?;
+        section.seek(SeekFrom::Start(hash_data.pfs0_offset))?;
+        Pfs0Reader::new(section)
+    }
+
+    /// Open the ExeFS (section 0 of a Program NCA) as a [`Pfs0Reader`].
+    pub fn exefs(&mut self) -> Result<Pfs0Reader<NcaSectionReader<'_, R>>> {
+        if self.nca.content_type != ContentType::Program {
+            return Err(Error::Parse("NCA is not a Program NCA"));
+        }
+        self.open_pfs0(0)
+    }
+
+    /// Read and parse `main.npdm` from the ExeFS.
+    ///
+    /// Returns [`Error::NotFound`] if the ExeFS has no `main.npdm`.
+    pub fn main_npdm(&mut self) -> Result<Npdm> {
+        let mut exefs = self.exefs()?;
+        let mut data = Vec::new();
+        exefs.open("main.npdm")?.read_to_end(&mut data)?;
+        Npdm::parse(&mut io::Cursor::new(data))
+    }
+
+    /// Open a RomFS section as a [`RomFsReader`].
+    ///
+    /// The Level 3 offset is taken from the [`IvfcHeader`] in the section's
+    /// hash data. Returns [`Error::Parse`] if the section is not a RomFS.
+    pub fn open_romfs(&mut self, section: usize) -> Result<RomFsReader<NcaSectionReader<'_, R>>> {
+        let fs_header = self.nca.fs_header(section).ok_or(Error::InvalidRange)?;
+        if fs_header.fs_type != FsType::RomFs {
+            return Err(Error::Parse("NCA section is not a RomFS"));
+        }
+        let ivfc = IvfcHeader::from_bytes(&fs_header.hash_data)?;
+
+        let mut section = self.open_section(section)?;
+        section.seek(SeekFrom::Start(ivfc.level3_offset))?;
+        RomFsReader::new(section)
+    }
+
+    /// Open the first RomFS section of the NCA as a [`RomFsReader`].
+    ///
+    /// This is section 1 of a Program or Manual NCA and section 0 of a
+    /// Control or Data NCA. Returns [`Error::InvalidRange`] if the NCA has
+    /// no RomFS section.
+    pub fn romfs(&mut self) -> Result<RomFsReader<NcaSectionReader<'_, R>>> {
+        let section = self
+            .nca
+            .fs_headers
+            .iter()
+            .position(|h| h.is_some_and(|h| h.fs_type == FsType::RomFs))
+            .ok_or(Error::InvalidRange)?;
+        self.open_romfs(section)
+    }
+
+    /// Check every section against the hashes in its FsHeader.
+    ///
+    /// AES-CTR-Ex (patch) sections are reported as
+    /// [`SectionStatus::Unchecked`], since their hash trees cover the
+    /// patched data. Returns [`Error::MissingKey`] if an encrypted
+    /// section's key could not be resolved.
+    pub fn verify(&mut self) -> Result<NcaVerifyReport> {
+        self.verify_with_progress(&mut NoProgress)
+    }
+
+    /// Like [`verify`](Self::verify), reporting the bytes read against the
+    /// total size of the sections, with items named `section N`.
+    pub fn verify_with_progress(&mut self, progress: &mut dyn Progress) -> Result<NcaVerifyReport> {
+        let total = (0..4).filter_map(|s| self.nca.section_size(s)).sum();
+        let mut done = 0;
+        let mut sections = Vec::new();
+        for section in 0..4 {
+            let Some(fs_header) = self.nca.fs_header(section).copied() else {
+                continue;
+            };
+            let end = done + self.nca.section_size(section).unwrap_or(0);
+            let item = format!("section {section}");
+            let status = if !self.nca.fs_header_hash_matches(section) {
+                SectionStatus::HeaderMismatch
+            } else if fs_header.encryption_type == EncryptionType::AesCtrEx {
+                SectionStatus::Unchecked
+            } else {
+                let mut r = ProgressReader {
+                    inner: self.open_section(section)?,
+                    progress: &mut *progress,
+                    item: &item,
+                    done: &mut done,
+                    limit: end,
+                    total,
+                };
+                verify_section_hashes(&fs_header, &mut r, 0)?
+            };
+            // Hash trees need not cover the whole section.
+            done = end;
+            progress.update(&item, done, total);
+            sections.push(SectionCheck { section, status });
+        }
+        Ok(NcaVerifyReport { sections })
+    }
+
+    /// Consume the reader, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Read the 0xC00-byte header at the current position of `r`, decrypting
+/// it with the header key unless it is already plaintext. `base` is the
+/// absolute offset of that position, for error context.
+pub(crate) fn read_header<R: Read>(r: &mut R, base: u64, keys: &KeySet) -> Result<Nca> {
+    let mut header = bytesv(r, 0xC00).at("NCA", base, || "header".into())?;
+    if !matches!(&header[0x200..0x204], b"NCA3" | b"NCA2" | b"NCA1" | b"NCA0") {
+        let header_key = keys
+            .header_key
+            .as_ref()
+            .ok_or_else(|| Error::MissingKey("header_key".into()))?;
+        header = decrypt_header(&header, header_key)?.to_vec();
+    }
+    Nca::from_header(header, base)
+}
+
+/// Outcome of checking one NCA section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SectionStatus {
+    /// The FsHeader and every hashed block match.
+    Ok,
+    /// The FsHeader does not match its hash in the NCA header.
+    HeaderMismatch,
+    /// The top hash level does not match the master hash in the FsHeader.
+    MasterHashMismatch,
+    /// A block does not match its hash. Holds the block's offset within
+    /// the section.
+    BlockMismatch(u64),
+    /// The section has no hash tree this crate can check.
+    Unchecked,
+}
+
+/// Result of checking one NCA section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionCheck {
+    /// Section index (0-3).
+    pub section: usize,
+    pub status: SectionStatus,
+}
+
+/// Result of [`NcaReader::verify`], one entry per present section.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NcaVerifyReport {
+    pub sections: Vec<SectionCheck>,
+}
+
+impl NcaVerifyReport {
+    /// `true` if no section failed. [`SectionStatus::Unchecked`] sections
+    /// do not count as failures.
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Sections that are neither [`SectionStatus::Ok`] nor
+    /// [`SectionStatus::Unchecked`].
+    pub fn failures(&self) -> impl Iterator<Item = &SectionCheck> {
+        self.sections
+            .iter()
+            .filter(|c| !matches!(c.status, SectionStatus::Ok | SectionStatus::Unchecked))
+    }
+}
+
+/// Check a decrypted section against the hash tree in `fs_header`.
+///
+/// `r` yields the plaintext section, which starts at stream offset `base`.
+/// Returns [`SectionStatus::Unchecked`] for hash types other than
+/// `HierarchicalSha256` and `HierarchicalIntegrity`.
+pub fn verify_section_hashes<R: Read + Seek>(
+    fs_header: &FsHeader,
+    r: &mut R,
+    base: u64,
+) -> Result<SectionStatus> {
+    match fs_header.hash_type {
+        HashType::HierarchicalSha256 => {
+            let h = HierarchicalSha256::from_bytes(&fs_header.hash_data)?;
+            r.seek(SeekFrom::Start(base + h.hash_table_offset))?;
+            let table = bytesv(r, to_usize(h.hash_table_size)?)?;
+            if sha256(&table) != h.master_hash {
+                return Ok(SectionStatus::MasterHashMismatch);
+            }
+            let level = (h.pfs0_offset, h.pfs0_size, h.block_size as u64);
+            let bad = check_hash_level(r, base, level, &table, false)?;
+            Ok(bad.map_or(SectionStatus::Ok, SectionStatus::BlockMismatch))
+        }
+        HashType::HierarchicalIntegrity => {
+            let ivfc = IvfcHeader::from_bytes(&fs_header.hash_data)?;
+            let master = fs_header
+                .hash_data
+                .get(
+                    IVFC_MASTER_HASH_OFFSET
+                        ..IVFC_MASTER_HASH_OFFSET + ivfc.master_hash_size as usize,
+                )
+                .ok_or(Error::InvalidRange)?;
+            let levels = [
+                (
+                    ivfc.level1_offset,
+                    ivfc.level1_size,
+                    ivfc.level1_block_size_log2,
+                ),
+                (
+                    ivfc.level2_offset,
+                    ivfc.level2_size,
+                    ivfc.level2_block_size_log2,
+                ),
+                (
+                    ivfc.level3_offset,
+                    ivfc.level3_size,
+                    ivfc.level3_block_size_log2,
+                ),
+            ];
+            let mut hashes = master.to_vec();
+            for (i, (offset, size, log2)) in levels.into_iter().enumerate() {
+                if log2 >= 32 {
+                    return Err(Error::Parse("IVFC block size out of range"));
+                }
+                let level = (offset, size, 1 << log2);
+                if let Some(bad) = check_hash_level(r, base, level, &hashes, true)? {
+                    return Ok(if i == 0 {
+                        SectionStatus::MasterHashMismatch
+                    } else {
+                        SectionStatus::BlockMismatch(bad)
+                    });
+                }
+                // Each level holds the hashes of the next; the last is the data.
+                if i < 2 {
+                    r.seek(SeekFrom::Start(base + offset))?;
+                    hashes = bytesv(r, to_usize(size)?)?;
+                }
+            }
+            Ok(SectionStatus::Ok)
+        }
+        _ => Ok(SectionStatus::Unchecked),
+    }
+}
+
+/// Hash the blocks of one level, `(offset, size, block_size)` within the
+/// section, against the consecutive SHA-256 digests in `hashes`.
+///
+/// With `pad`, a short last block is zero-padded to the block size before
+/// hashing (IVFC); otherwise only its bytes are hashed. Returns the
+/// section offset of the first block that does not match.
+fn check_hash_level<R: Read + Seek>(
+    r: &mut R,
+    base: u64,
+    (offset, size, block_size): (u64, u64, u64),
+    hashes: &[u8],
+    pad: bool,
+) -> Result<Option<u64>> {
+    if block_size == 0 {
+        return Err(Error::Parse("hash block size is zero"));
+    }
+    r.seek(SeekFrom::Start(base + offset))?;
+    // Read a batch of blocks at a time so they can be hashed together.
+    let batch_size = (HASH_BATCH_SIZE / block_size).max(1) * block_size;
+    for batch_start in (0..size).step_by(batch_size as usize) {
+        let batch_end = (batch_start + batch_size).min(size);
+        let mut blocks = Vec::new();
+        for start in (batch_start..batch_end).step_by(block_size as usize) {
+            let mut block = bytesv(r, block_size.min(size - start) as usize)?;
+            if pad {
+                block.resize(block_size as usize, 0);
+            }
+            blocks.push(block);
+        }
+        let first = (batch_start / block_size) as usize;
+        for (j, digest) in sha256_many(&blocks).iter().enumerate() {
+            let i = first + j;
+            let expected = hashes.get(i * DIGEST_SIZE..(i + 1) * DIGEST_SIZE);
+            if expected != Some(&digest[..]) {
+                return Ok(Some(offset + i as u64 * block_size));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Decrypted [`Read`] + [`Seek`] view of one NCA section.
+///
+/// Positions are relative to the start of the section. Produced by
+/// [`NcaReader::open_section`]; the borrow ends when this is dropped.
+pub struct NcaSectionReader<'a, R> {
+    stream: SectionStream<'a, R>,
+}
+
+enum SectionStream<'a, R> {
+    Plain {
+        inner: &'a mut R,
+        /// Absolute stream offset of the first section byte.
+        start: u64,
+        size: u64,
+        pos: u64,
+    },
+    Ctr(Box<AesCtrReader<&'a mut R>>),
+}
+
+impl<R> NcaSectionReader<'_, R> {
+    /// Size of the section in bytes.
+    pub fn size(&self) -> u64 {
+        match &self.stream {
+            SectionStream::Plain { size, .. } => *size,
+            SectionStream::Ctr(r) => r.size(),
+        }
+    }
+
+    /// Replace the generation half of the counter (bytes `[4..8]`), as
+    /// AES-CTR-Ex subsections do.
+    pub(crate) fn set_ctr_generation(&mut self, generation: u32) {
+        if let SectionStream::Ctr(r) = &mut self.stream {
+            r.set_generation(generation);
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for NcaSectionReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.stream {
+            SectionStream::Plain {
+                inner,
+                start,
+                size,
+                pos,
+            } => {
+                let remaining = size.saturating_sub(*pos);
+                if remaining == 0 || buf.is_empty() {
+                    return Ok(0);
+                }
+                let len = (buf.len() as u64).min(remaining) as usize;
+                inner.seek(SeekFrom::Start(*start + *pos))?;
+                let n = inner.read(&mut buf[..len])?;
+                *pos += n as u64;
+                Ok(n)
+            }
+            SectionStream::Ctr(r) => r.read(buf),
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for NcaSectionReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (size, cur) = match &mut self.stream {
+            SectionStream::Plain { size, pos, .. } => (*size, pos),
+            SectionStream::Ctr(r) => return r.seek(pos),
+        };
+        let new = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => size.checked_add_signed(d),
+            SeekFrom::Current(d) => cur.checked_add_signed(d),
+        };
+        *cur = new.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before section start")
+        })?;
+        Ok(*cur)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// A test RSA-2048 modulus, not Nintendo's.
+    const MODULUS: &str = concat!(
+        "da57d390f79af91b568b089f9b69cc0267564b66df8195cc9f854e7d289e4862",
+        "ba8b3c56be4ad206ce17addb20dcb60769e9c4fa70a543fb9639f4782bf8cd86",
+        "63dbff0458f7bd1a8574c9b9861b82211b55035970c84f8de3989a2e81fbc3b9",
+        "3f4c199cb0620a42d62cf5cefadff2f1f146a3c91945428a2e39df692a82ffc8",
+        "0de03979a707982d9beb972c8af1abe418ee86cedf8795220a3983a420b35be6",
+        "673d24ae813a29dba6139782c6394c2e28c8c7e979e63a94399da8355854ac40",
+        "0be2731ca78401da61cbadf6882b90af945c568a70e48f825e24741fa3045ed2",
+        "b6cc870737fbc79d56908e3ea967423d902ade502f5db234a8711bae4ab1630d",
+    );
+
+    /// PSS signature with [`MODULUS`] over an otherwise empty NCA3 header.
+    const SIGNATURE: &str = concat!(
+        "46ddbcd05aea60d6913ea5c3e7191cda020e6f8a072cc5a2992e8ae63852dba3",
+        "0206bf638619d94ba4d1329736d2a99fcf8c45e2eec61999f1c36d293e151a7d",
+        "5b5d46940d4d1c1013d1bd31d53de2aa626a15dfc74ec8c9d7feeeff708d5138",
+        "2fc59c18543956420d05189afbc884cbc0893373bdee53996c06aa0b76479d26",
+        "a2395cf2ac047ee380c5b88f2ef879e7fe87593322bfcf91510be922dbfb7379",
+        "2adae542e72fa6056a3b91af345909960f43eb2783c4ee6f76fc7a7b7e54b805",
+        "7afd0d59f32ccf4e970b59cf4934cda6a73437c907a5627a3bd87226dae4b2ff",
+        "31ce903d5a8401acb71048c83bbf3eb2adc5c12a01895e57784588f44c0a5175",
+    );
+
+    fn header() -> Vec<u8> {
+        let mut header = vec![0; 0xC00];
+        header[..0x100].copy_from_slice(&hex(SIGNATURE));
+        header[0x200..0x204].copy_from_slice(b"NCA3");
+        header
+    }
+
+    #[test]
+    fn header_signature() {
+        let mut keys = KeySet::new();
+        assert!(matches!(
+            Nca::from_header(header(), 0)
+                .unwrap()
+                .verify_header_signature(&keys),
+            Err(Error::MissingKey(_))
+        ));
+
+        keys.nca_hdr_fixed_key_modulus[0] = Some(hex(MODULUS).try_into().unwrap());
+        let nca = Nca::from_header(header(), 0).unwrap();
+        assert!(nca.verify_header_signature(&keys).unwrap());
+
+        let mut tampered = header();
+        tampered[0x210] ^= 1;
+        let nca = Nca::from_header(tampered, 0).unwrap();
+        assert!(!nca.verify_header_signature(&keys).unwrap());
+    }
+}
@@ -6,56 +6,215 @@
 //!
 //! ## NCZ Layout
 //! ```text
-//! [0x000]          Standard NCA header (0x400 bytes, still encrypted)
-//! [0x400]          Magic "NCZSECTN"                    (8 bytes)
-//! [0x408]          SectionCount                        (u64 LE)
-//! [0x410 + N×0x38] Section descriptors                 (N × 0x38 bytes)
-//! [...]            Zstandard-compressed data blocks
+//! [0x0000]          First 0x4000 bytes of the NCA, unmodified (the header
+//!                   is still encrypted)
+//! [0x4000]          Magic "NCZSECTN"                  (8 bytes)
+//! [0x4008]          SectionCount                      (u64 LE)
+//! [0x4010]          Section descriptors               (N × 0x40 bytes)
+//! [...]             Block table (block mode only)
+//! [...]             Compressed data
 //! ```
 //!
-//! Each block starts with a `u32 LE` giving the compressed byte length,
-//! followed by that many bytes of Zstd-compressed data.
+//! The compressed data holds the rest of the NCA, from 0x4000 to its end,
+//! with the sections decrypted. In solid mode (the `nsz` default) it is a
+//! single Zstandard stream. Bytes between 0x4000 and the first section are
+//! stored as they are in the NCA.
 //!
-//! ## Section Descriptor (0x38 bytes)
+//! ## Block mode (`NCZBLOCK`)
+//! NCZs made with `nsz --block` instead follow the section descriptors with
+//! a block table, and the data is split into independently compressed
+//! blocks stored back to back, so any block can be located directly:
 //! ```text
-//! [0x00] Offset      - within the plaintext NCA (u64 LE)
-//! [0x08] Size        - decompressed (u64 LE)
-//! [0x10] CryptoType  (u8)
-//! [0x11] Reserved    (7 bytes)
-//! [0x18] CryptoKey   (16 bytes)
-//! [0x28] CryptoCounter (16 bytes)
+//! [0x00] Magic "NCZBLOCK"                  (8 bytes)
+//! [0x08] Version                           (u8, 2)
+//! [0x09] Type                              (u8, 1)
+//! [0x0A] Unused                            (u8)
+//! [0x0B] BlockSizeExponent                 (u8, 14-32)
+//! [0x0C] BlockCount                        (u32 LE)
+//! [0x10] DecompressedSize                  (u64 LE)
+//! [0x18] CompressedBlockSizes              (BlockCount × u32 LE)
+//! ```
+//! Every block decompresses to `1 << BlockSizeExponent` bytes except the
+//! last. A block whose compressed size is not smaller than its
+//! decompressed size is stored uncompressed.
+//!
+//! ## Section Descriptor (0x40 bytes)
+//! ```text
+//! [0x00] Offset        - within the NCA (u64 LE)
+//! [0x08] Size          (u64 LE)
+//! [0x10] CryptoType    (u64 LE, NCA FsHeader EncryptionType)
+//! [0x18] Padding       (8 bytes)
+//! [0x20] CryptoKey     (16 bytes)
+//! [0x30] CryptoCounter (16 bytes)
 //! ```
 //!
 //! ## Typical usage with hakkit
 //! 1. Parse the NSZ as a `Pfs0`.
 //! 2. For entries with a `.ncz` extension, read the raw bytes.
 //! 3. Parse the NCZ header with [`NczHeader::parse`].
-//! 4. Decompress each block with `compression::zstd`.
+//! 4. Read the blocks with [`read_compressed_blocks`] and decompress them
+//!    with `compression::zstd::decompress_zstd_blocks` (concurrently with
+//!    the `parallel` feature).
 //! 5. Reconstruct the plaintext NCA and feed it to `Nca::parse`.
+//!
+//! [`NczReader`] (requires the `compression` feature) does steps 3-5 on
+//! demand: it presents the reconstructed NCA as a [`Read`] + [`Seek`]
+//! stream, decompressing one block (or, in solid mode, one chunk of the
+//! stream) at a time, so it can be handed straight
+//! to [`NcaReader`](crate::formats::nca::NcaReader). [`NczReader::verify`]
+//! checks the result against the NCA's section hashes.
+//!
+//! ## Writing
+//! [`NczWriter`] (requires the `compression` feature) performs the reverse:
+//! it takes the NCA's first 0x4000 bytes and the rest of the NCA with its
+//! sections already decrypted, and writes the NCZ in the same layout as
+//! `nsz`: a solid Zstd stream by default, or an `NCZBLOCK` table and blocks
+//! with [`NczWriter::block_mode`]. [`NczWriter::for_nca`] derives the
+//! section descriptors from an NCA header and [`NczWriter::write_nca`]
+//! decrypts the sections itself, so an encrypted NCA and its keys suffice.
+//! Add the result to a [`Pfs0Writer`](crate::formats::pfs0::Pfs0Writer)
+//! under a `.ncz` name to build an NSZ.
+
+#[cfg(feature = "compression")]
+use std::io::{self, Write};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+#[cfg(feature = "compression")]
+use super::nca::{
+    EncryptionType, Nca, NcaVerifyReport, SectionCheck, SectionStatus, read_header, section_key,
+    verify_section_hashes,
+};
+#[cfg(feature = "compression")]
+use crate::compression::zstd::{
+    DEFAULT_LEVEL, ZstdDecoder, ZstdWriter, compress_zstd, decompress_zstd_with_size,
+};
+#[cfg(feature = "compression")]
+use crate::crypto::nca::{build_ctr, encrypt_section_ctr};
+use crate::error::Context;
+#[cfg(feature = "compression")]
+use crate::keys::KeySet;
+#[cfg(feature = "compression")]
+use crate::progress::{NoProgress, Progress, ProgressReader};
+#[cfg(feature = "compression")]
+use crate::utils::to_usize;
+use crate::utils::{bytesa, bytesv, le_u32, le_u64, magic, table, u8};
+use crate::{Error, Result};
 
-use std::io::{Read, Seek, SeekFrom};
+/// Bytes at the start of an NCA that an NCZ stores unmodified: the header
+/// and the space up to where the sections usually begin.
+pub const UNCOMPRESSABLE_HEADER_SIZE: usize = 0x4000;
 
-use crate::Result;
-use crate::utils::{bytesa, bytesv, le_u64, magic, u8};
+/// Block size exponent `nsz --block` uses by default (1 MiB blocks).
+pub const DEFAULT_BLOCK_SIZE_EXPONENT: u8 = 20;
 
 /// Parsed NCZ header (the part after the standard NCA header).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NczHeader {
     /// Section descriptors describing each encrypted/compressed region.
     pub sections: Vec<NczSection>,
+    /// Block table, for NCZs in block mode.
+    pub block_header: Option<NczBlockHeader>,
     /// Absolute byte offset (within the NCZ stream) where the compressed
-    /// data blocks begin.
+    /// data begins: the first block, or the solid Zstd stream.
     pub blocks_offset: u64,
 }
 
+/// `NCZBLOCK` table of a block-mode NCZ.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NczBlockHeader {
+    pub version: u8,
+    pub block_type: u8,
+    /// Blocks decompress to `1 << block_size_exponent` bytes.
+    pub block_size_exponent: u8,
+    /// Total decompressed size of all blocks.
+    pub decompressed_size: u64,
+    /// Compressed size of each block.
+    pub compressed_sizes: Vec<u32>,
+}
+
+impl NczBlockHeader {
+    /// Parse a block table from `r`, positioned at the `NCZBLOCK` magic.
+    pub fn parse<R: Read>(r: &mut R) -> Result<Self> {
+        magic(r, b"NCZBLOCK")?;
+        let version = u8(r)?;
+        let block_type = u8(r)?;
+        let _unused = u8(r)?;
+        let block_size_exponent = u8(r)?;
+        if !(14..=32).contains(&block_size_exponent) {
+            return Err(Error::Parse("NCZ block size exponent out of range"));
+        }
+        let block_count = le_u32(r)?;
+        let decompressed_size = le_u64(r)?;
+        if block_count as u64 != decompressed_size.div_ceil(1 << block_size_exponent) {
+            return Err(Error::Parse(
+                "NCZ block count does not match decompressed size",
+            ));
+        }
+        let compressed_sizes: Vec<u32> = table(r, block_count as u64 * 4)?
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        if compressed_sizes.len() != block_count as usize {
+            return Err(Error::Parse("NCZ block table is truncated"));
+        }
+        Ok(Self {
+            version,
+            block_type,
+            block_size_exponent,
+            decompressed_size,
+            compressed_sizes,
+        })
+    }
+
+    /// Decompressed size of a full block.
+    pub fn block_size(&self) -> u64 {
+        1 << self.block_size_exponent
+    }
+
+    /// Number of blocks.
+    pub fn block_count(&self) -> usize {
+        self.compressed_sizes.len()
+    }
+
+    /// Decompressed size of block `index`.
+    pub fn decompressed_block_size(&self, index: usize) -> u64 {
+        let start = index as u64 * self.block_size();
+        self.decompressed_size
+            .saturating_sub(start)
+            .min(self.block_size())
+    }
+
+    /// `true` if block `index` is stored uncompressed; `false` if `index`
+    /// is out of range.
+    pub fn is_stored(&self, index: usize) -> bool {
+        self.compressed_sizes
+            .get(index)
+            .is_some_and(|&size| size as u64 >= self.decompressed_block_size(index))
+    }
+
+    /// Offset of block `index` relative to the first block (the end of the
+    /// last block if `index` is out of range).
+    pub fn block_offset(&self, index: usize) -> u64 {
+        self.compressed_sizes
+            .iter()
+            .take(index)
+            .map(|&s| s as u64)
+            .sum()
+    }
+}
+
 /// Descriptor for one NCA section within an NCZ file.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NczSection {
-    /// Byte offset of this section within the plaintext NCA.
+    /// Byte offset of this section within the NCA.
     pub offset: u64,
-    /// Decompressed size in bytes.
+    /// Size in bytes.
     pub size: u64,
-    /// Encryption type identifier (matches NCA FsHeader EncryptionType).
+    /// Encryption type identifier (matches NCA FsHeader EncryptionType),
+    /// stored as a u64.
     pub crypto_type: u8,
     /// AES key for this section (16 bytes).
     pub crypto_key: [u8; 16],
@@ -66,20 +225,28 @@ pub struct NczSection {
 impl NczHeader {
     /// Parse the NCZ-specific header from `r`.
     ///
-    /// The reader must be positioned immediately **after** the 0x400-byte NCA header,
-    /// i.e. at the `NCZSECTN` magic.
+    /// The reader must be positioned at the `NCZSECTN` magic, which follows
+    /// the first [`UNCOMPRESSABLE_HEADER_SIZE`] bytes of the NCA.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
-        magic(r, b"NCZSECTN")?;
-
-        let section_count = le_u64(r)?;
-        let mut sections = Vec::with_capacity(section_count as usize);
-        for _ in 0..section_count {
-            let offset = le_u64(r)?;
-            let size = le_u64(r)?;
-            let crypto_type = u8(r)?;
-            let _reserved = bytesa::<7>(r)?;
-            let crypto_key = bytesa::<16>(r)?;
-            let crypto_counter = bytesa::<16>(r)?;
+        let base = r.stream_position()?;
+        let header = || "section header".to_string();
+        magic(r, b"NCZSECTN").at_field("NCZ", base, header, "magic")?;
+
+        let section_count = le_u64(r).at_field("NCZ", base, header, "SectionCount")?;
+        let mut sections = Vec::new();
+        for i in 0..section_count {
+            let at = base + 0x10 + i * 0x40;
+            let entry = || format!("section {i}");
+            let offset = le_u64(r).at_field("NCZ", at, entry, "Offset")?;
+            let size = le_u64(r).at_field("NCZ", at, entry, "Size")?;
+            let crypto_type = le_u64(r)
+                .and_then(|t| {
+                    u8::try_from(t).map_err(|_| Error::Parse("NCZ crypto type out of range"))
+                })
+                .at_field("NCZ", at, entry, "CryptoType")?;
+            let _padding = bytesa::<8>(r).at_field("NCZ", at, entry, "Padding")?;
+            let crypto_key = bytesa::<16>(r).at_field("NCZ", at, entry, "CryptoKey")?;
+            let crypto_counter = bytesa::<16>(r).at_field("NCZ", at, entry, "CryptoCounter")?;
             sections.push(NczSection {
                 offset,
                 size,
@@ -89,44 +256,1001 @@ impl NczHeader {
             });
         }
 
+        let mut block_header = None;
+        let table_offset = r.stream_position()?;
+        let mut probe = [0; 8];
+        // The data may be too short to hold a block table at all.
+        let has_table = match r.read_exact(&mut probe) {
+            Ok(()) => probe == *b"NCZBLOCK",
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => false,
+            Err(e) => return Err(e.into()),
+        };
+        if has_table {
+            r.seek(SeekFrom::Start(table_offset))?;
+            let table =
+                NczBlockHeader::parse(r).at("NCZ", table_offset, || "block table".into())?;
+            block_header = Some(table);
+        } else {
+            r.seek(SeekFrom::Start(table_offset))?;
+        }
         let blocks_offset = r.stream_position()?;
 
         Ok(Self {
             sections,
+            block_header,
             blocks_offset,
         })
     }
+
+    /// Read the compressed bytes of block `index` of a block-mode NCZ.
+    ///
+    /// Returns [`Error::Parse`] if the NCZ is not in block mode and
+    /// [`Error::InvalidRange`] if `index` is out of range. Check
+    /// [`NczBlockHeader::is_stored`] before decompressing.
+    pub fn read_block<R: Read + Seek>(&self, r: &mut R, index: usize) -> Result<Vec<u8>> {
+        let table = self
+            .block_header
+            .as_ref()
+            .ok_or(Error::Parse("NCZ is not in block mode"))?;
+        let size = *table
+            .compressed_sizes
+            .get(index)
+            .ok_or(Error::InvalidRange)?;
+        r.seek(SeekFrom::Start(
+            self.blocks_offset + table.block_offset(index),
+        ))?;
+        bytesv(r, size as usize)
+    }
+}
+
+/// One compressed block read by [`read_compressed_blocks`].
+#[derive(Debug, Clone)]
+pub struct CompressedBlock {
+    /// Index of the block, counting from 0.
+    pub index: usize,
+    /// Absolute offset of the block's data in the NCZ stream.
+    pub offset: u64,
+    /// The block's bytes, Zstd-compressed unless stored (block mode only;
+    /// see [`NczBlockHeader::is_stored`]).
+    pub data: Vec<u8>,
+}
+
+impl AsRef<[u8]> for CompressedBlock {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
 }
 
 /// Read all Zstandard-compressed blocks from an NCZ stream.
 ///
-/// The reader must be positioned at `blocks_offset`. Returns the raw
-/// compressed payloads in order; callers decompress them individually.
+/// Returns the raw payloads in order; callers decompress them
+/// individually. In block mode the sizes come from the block table, and
+/// stored blocks are returned as is. A solid NCZ is returned as a single
+/// block holding everything from [`NczHeader::blocks_offset`] to the end
+/// of the stream.
 ///
-/// Each block is prefixed with a `u32 LE` giving its compressed byte length.
+/// I/O errors (including a block cut short) are returned as [`Error::Io`].
 pub fn read_compressed_blocks<R: Read + Seek>(
     r: &mut R,
     header: &NczHeader,
-) -> Result<Vec<Vec<u8>>> {
+) -> Result<Vec<CompressedBlock>> {
     r.seek(SeekFrom::Start(header.blocks_offset))?;
-    let mut blocks = Vec::new();
-    loop {
-        // Peek at four bytes to detect end-of-stream.
-        let size_buf = {
-            let mut b = [0u8; 4];
-            match r.read(&mut b) {
-                Ok(0) => break,
-                Ok(4) => b,
-                Ok(_) => break, // short read = end of data
-                Err(_) => break,
+    let Some(table) = &header.block_header else {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+        return Ok(vec![CompressedBlock {
+            index: 0,
+            offset: header.blocks_offset,
+            data,
+        }]);
+    };
+    let mut offset = header.blocks_offset;
+    table
+        .compressed_sizes
+        .iter()
+        .enumerate()
+        .map(|(index, &size)| {
+            let block = CompressedBlock {
+                index,
+                offset,
+                data: bytesv(r, size as usize)?,
+            };
+            offset += size as u64;
+            Ok(block)
+        })
+        .collect()
+}
+
+/// Builder that writes an NCZ from an NCA's header and decrypted body.
+///
+/// By default the body is compressed as a single Zstd stream, as `nsz`
+/// does in solid mode. [`block_mode`](Self::block_mode) instead splits it
+/// into independently compressed blocks listed in an `NCZBLOCK` table, so
+/// that readers can locate and decompress them in parallel.
+///
+/// [`for_nca`](Self::for_nca) and [`write_nca`](Self::write_nca) compress
+/// an encrypted NCA directly, decrypting its sections on the way.
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone)]
+pub struct NczWriter {
+    sections: Vec<NczSection>,
+    level: i32,
+    /// Block size exponent, if writing a block-mode NCZ.
+    block_size_exponent: Option<u8>,
+}
+
+#[cfg(feature = "compression")]
+impl NczWriter {
+    /// Create a writer for an NCA whose encrypted regions are described by
+    /// `sections`. A reader re-encrypts each section with its key and
+    /// counter to restore the original NCA.
+    pub fn new(sections: Vec<NczSection>) -> Self {
+        Self {
+            sections,
+            level: DEFAULT_LEVEL,
+            block_size_exponent: None,
+        }
+    }
+
+    /// Create a writer for `nca`, with one section descriptor per section
+    /// holding its encryption type, key, and counter.
+    ///
+    /// Returns [`Error::MissingKey`] if an AES-CTR section's key cannot be
+    /// resolved from `keys`, or [`Error::Parse`] if a section uses an
+    /// encryption type other than AES-CTR or none.
+    pub fn for_nca(nca: &Nca, keys: &KeySet) -> Result<Self> {
+        let mut sections = Vec::new();
+        for section in 0..4 {
+            let (Some(fs_header), Some(offset), Some(size)) = (
+                nca.fs_header(section),
+                nca.section_offset(section),
+                nca.section_size(section),
+            ) else {
+                continue;
+            };
+            let mut descriptor = NczSection {
+                offset,
+                size,
+                crypto_type: fs_header.encryption_type.into(),
+                crypto_key: [0; 16],
+                crypto_counter: fs_header.build_ctr_base(),
+            };
+            match is_ctr(&descriptor) {
+                Some(true) => descriptor.crypto_key = section_key(nca, keys)?,
+                Some(false) => {}
+                None => return Err(Error::Parse("unsupported NCA section encryption type")),
+            }
+            sections.push(descriptor);
+        }
+        Ok(Self::new(sections))
+    }
+
+    /// Set the Zstd compression level.
+    pub fn level(&mut self, level: i32) -> &mut Self {
+        self.level = level;
+        self
+    }
+
+    /// Write a block-mode NCZ with blocks of `1 << exponent` bytes;
+    /// `nsz --block` uses [`DEFAULT_BLOCK_SIZE_EXPONENT`].
+    ///
+    /// `exponent` must be in `14..=31`, so that a block stored uncompressed
+    /// still has a size that fits the table's `u32` fields;
+    /// [`write`](Self::write) returns [`Error::InvalidRange`] otherwise.
+    pub fn block_mode(&mut self, exponent: u8) -> &mut Self {
+        self.block_size_exponent = Some(exponent);
+        self
+    }
+
+    /// Write the NCZ to `w`.
+    ///
+    /// `header` is the NCA's first [`UNCOMPRESSABLE_HEADER_SIZE`] bytes, still
+    /// encrypted. `body` yields the rest of the NCA from its current
+    /// position, with the ranges in the section descriptors decrypted.
+    /// Returns the header as written, with `blocks_offset` relative to the
+    /// start of the NCZ.
+    ///
+    /// Outside block mode `body` is compressed as one Zstd stream. In block
+    /// mode the size of `body` is taken by seeking to its end, and
+    /// the block table is filled in by seeking back once the blocks are
+    /// written. Blocks that do not shrink are stored uncompressed.
+    pub fn write<R: Read + Seek, W: Write + Seek>(
+        &self,
+        header: &[u8; UNCOMPRESSABLE_HEADER_SIZE],
+        mut body: R,
+        w: &mut W,
+    ) -> Result<NczHeader> {
+        w.write_all(header)?;
+        w.write_all(b"NCZSECTN")?;
+        w.write_all(&(self.sections.len() as u64).to_le_bytes())?;
+        for section in &self.sections {
+            w.write_all(&section.offset.to_le_bytes())?;
+            w.write_all(&section.size.to_le_bytes())?;
+            w.write_all(&u64::from(section.crypto_type).to_le_bytes())?;
+            w.write_all(&[0; 8])?;
+            w.write_all(&section.crypto_key)?;
+            w.write_all(&section.crypto_counter)?;
+        }
+        let mut blocks_offset =
+            (UNCOMPRESSABLE_HEADER_SIZE + 0x10 + self.sections.len() * 0x40) as u64;
+
+        let Some(exponent) = self.block_size_exponent else {
+            let mut zstd = ZstdWriter::new(&mut *w, self.level)?;
+            io::copy(&mut body, &mut zstd)?;
+            zstd.finish()?;
+            return Ok(NczHeader {
+                sections: self.sections.clone(),
+                block_header: None,
+                blocks_offset,
+            });
+        };
+
+        if !(14..=31).contains(&exponent) {
+            return Err(Error::InvalidRange);
+        }
+        let block_size = 1u64 << exponent;
+        let start = body.stream_position()?;
+        let decompressed_size = body.seek(SeekFrom::End(0))? - start;
+        body.seek(SeekFrom::Start(start))?;
+        let block_count = u32::try_from(decompressed_size.div_ceil(block_size))
+            .map_err(|_| Error::InvalidRange)?;
+
+        let table_offset = w.stream_position()?;
+        w.write_all(b"NCZBLOCK")?;
+        w.write_all(&[2, 1, 0, exponent])?;
+        w.write_all(&block_count.to_le_bytes())?;
+        w.write_all(&decompressed_size.to_le_bytes())?;
+        w.write_all(&vec![0; block_count as usize * 4])?;
+        blocks_offset += 0x18 + block_count as u64 * 4;
+
+        let mut compressed_sizes = Vec::with_capacity(block_count as usize);
+        let mut block = Vec::new();
+        for _ in 0..block_count {
+            block.clear();
+            (&mut body).take(block_size).read_to_end(&mut block)?;
+            let compressed = compress_zstd(&block, self.level)?;
+            let data = if compressed.len() < block.len() {
+                &compressed
+            } else {
+                &block
+            };
+            w.write_all(data)?;
+            compressed_sizes.push(u32::try_from(data.len()).map_err(|_| Error::InvalidRange)?);
+        }
+        let end = w.stream_position()?;
+        w.seek(SeekFrom::Start(table_offset + 0x18))?;
+        for size in &compressed_sizes {
+            w.write_all(&size.to_le_bytes())?;
+        }
+        w.seek(SeekFrom::Start(end))?;
+
+        Ok(NczHeader {
+            sections: self.sections.clone(),
+            block_header: Some(NczBlockHeader {
+                version: 2,
+                block_type: 1,
+                block_size_exponent: exponent,
+                decompressed_size,
+                compressed_sizes,
+            }),
+            blocks_offset,
+        })
+    }
+
+    /// Compress the encrypted NCA in `nca`, from its current position to
+    /// the end of the stream, and write the NCZ to `w`.
+    ///
+    /// The sections are decrypted with the keys in the section descriptors
+    /// as they are read; build the writer with [`for_nca`](Self::for_nca).
+    pub fn write_nca<R: Read + Seek, W: Write + Seek>(
+        &self,
+        nca: R,
+        w: &mut W,
+    ) -> Result<NczHeader> {
+        self.write_nca_with_progress(nca, w, &mut NoProgress)
+    }
+
+    /// Like [`write_nca`](Self::write_nca), reporting the bytes of the NCA
+    /// decrypted and compressed against its size.
+    pub fn write_nca_with_progress<R: Read + Seek, W: Write + Seek>(
+        &self,
+        mut nca: R,
+        w: &mut W,
+        progress: &mut dyn Progress,
+    ) -> Result<NczHeader> {
+        let base = nca.stream_position()?;
+        let total = nca.seek(SeekFrom::End(0))? - base;
+        nca.seek(SeekFrom::Start(base))?;
+        let header = bytesa::<UNCOMPRESSABLE_HEADER_SIZE>(&mut nca)?;
+        let body = DecryptingReader {
+            inner: nca,
+            sections: &self.sections,
+            base,
+            pos: UNCOMPRESSABLE_HEADER_SIZE as u64,
+        };
+        let mut done = UNCOMPRESSABLE_HEADER_SIZE as u64;
+        let body = ProgressReader::new(body, progress, "", &mut done, total);
+        self.write(&header, body, w)
+    }
+}
+
+/// Reader over an encrypted NCA that decrypts the AES-CTR sections.
+///
+/// Positions are NCA offsets; `base` is the stream offset of the NCA.
+#[cfg(feature = "compression")]
+struct DecryptingReader<'a, R> {
+    inner: R,
+    sections: &'a [NczSection],
+    base: u64,
+    pos: u64,
+}
+
+#[cfg(feature = "compression")]
+impl<R: Read> Read for DecryptingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for section in self.sections {
+            if is_ctr(section) == Some(true) {
+                // AES-CTR is symmetric, so encrypting decrypts.
+                encrypt_range(section, self.pos, &mut buf[..n]);
+            }
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<R: Seek> Seek for DecryptingReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(n) => SeekFrom::Start(self.base + n),
+            pos => pos,
+        };
+        let abs = self.inner.seek(pos)?;
+        self.pos = abs.checked_sub(self.base).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of NCA")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Form in which [`NczReader`] presents the reconstructed NCA.
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NczOutput {
+    /// The original NCA, with every section re-encrypted. This is what
+    /// [`Nca::parse`](crate::formats::nca::Nca::parse) and
+    /// [`NcaReader`](crate::formats::nca::NcaReader) expect.
+    Encrypted,
+    /// The NCA with its sections left decrypted. The NCA header is still
+    /// encrypted.
+    Decrypted,
+}
+
+/// Decompressed bytes [`NczReader`] holds at a time from a solid NCZ.
+#[cfg(feature = "compression")]
+const SOLID_CHUNK_SIZE: u64 = 0x10_0000;
+
+/// Compressed bytes [`NczReader`] reads at a time from a solid NCZ.
+#[cfg(feature = "compression")]
+const SOLID_INPUT_SIZE: usize = 0x2_0000;
+
+/// Location of one block of an NCZ, compressed and reconstructed.
+///
+/// A solid NCZ is split into chunks of [`SOLID_CHUNK_SIZE`], which have no
+/// compressed location of their own.
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone, Copy)]
+struct BlockEntry {
+    /// Absolute offset of the block's data in the NCZ.
+    offset: u64,
+    compressed_size: u64,
+    /// Offset of the block's first byte within the NCA.
+    start: u64,
+    size: u64,
+    stored: bool,
+}
+
+/// Reader presenting the NCA reconstructed from an NCZ as a [`Read`] +
+/// [`Seek`] stream.
+///
+/// Blocks are decompressed on demand and the most recent one is cached, so
+/// sequential reads decompress each block once. Block-mode NCZs are
+/// indexed from their block table. A solid NCZ is one Zstd stream, which
+/// is decompressed front to back in chunks; seeking back before the
+/// cached chunk restarts it from the beginning, so read solid NCZs in
+/// order where possible, as [`decompress_to`](Self::decompress_to) does.
+#[cfg(feature = "compression")]
+pub struct NczReader<R> {
+    inner: R,
+    /// Parsed NCZ header.
+    pub header: NczHeader,
+    /// The first [`UNCOMPRESSABLE_HEADER_SIZE`] bytes of the NCA.
+    nca_header: Vec<u8>,
+    output: NczOutput,
+    blocks: Vec<BlockEntry>,
+    /// Decoder state of a solid NCZ.
+    solid: Option<SolidStream>,
+    size: u64,
+    pos: u64,
+    /// Index of the block held in `cache`.
+    cached: Option<usize>,
+    cache: Vec<u8>,
+}
+
+#[cfg(feature = "compression")]
+impl<R: Read + Seek> NczReader<R> {
+    /// Parse the NCZ in `inner` and index its blocks.
+    ///
+    /// With [`NczOutput::Encrypted`], returns [`Error::Parse`] if a section
+    /// uses an encryption type other than AES-CTR or none.
+    pub fn new(mut inner: R, output: NczOutput) -> Result<Self> {
+        inner.seek(SeekFrom::Start(0))?;
+        let nca_header = bytesv(&mut inner, UNCOMPRESSABLE_HEADER_SIZE)?;
+        let header = NczHeader::parse(&mut inner)?;
+        if output == NczOutput::Encrypted && header.sections.iter().any(|s| is_ctr(s).is_none()) {
+            return Err(Error::Parse("unsupported NCZ section encryption type"));
+        }
+
+        let data_start = UNCOMPRESSABLE_HEADER_SIZE as u64;
+        let (blocks, solid) = match &header.block_header {
+            Some(table) => {
+                let blocks: Vec<BlockEntry> = (0..table.block_count())
+                    .map(|i| BlockEntry {
+                        offset: header.blocks_offset + table.block_offset(i),
+                        compressed_size: table.compressed_sizes[i] as u64,
+                        start: data_start + i as u64 * table.block_size(),
+                        size: table.decompressed_block_size(i),
+                        stored: table.is_stored(i),
+                    })
+                    .collect();
+                (blocks, None)
+            }
+            None => {
+                // The stream does not record its size; it ends with the
+                // last section.
+                let end = header
+                    .sections
+                    .iter()
+                    .map(|s| s.offset.saturating_add(s.size))
+                    .fold(data_start, u64::max);
+                let blocks: Vec<BlockEntry> = (data_start..end)
+                    .step_by(SOLID_CHUNK_SIZE as usize)
+                    .map(|start| BlockEntry {
+                        offset: 0,
+                        compressed_size: 0,
+                        start,
+                        size: (end - start).min(SOLID_CHUNK_SIZE),
+                        stored: false,
+                    })
+                    .collect();
+                let solid = SolidStream {
+                    decoder: ZstdDecoder::new()?,
+                    input: Vec::new(),
+                    consumed: 0,
+                    next_in: header.blocks_offset,
+                    next_out: data_start,
+                };
+                (blocks, Some(solid))
+            }
+        };
+        let size = blocks.last().map_or(data_start, |b| b.start + b.size);
+
+        Ok(Self {
+            inner,
+            header,
+            nca_header,
+            output,
+            blocks,
+            solid,
+            size,
+            pos: 0,
+            cached: None,
+            cache: Vec::new(),
+        })
+    }
+
+    /// Size of the reconstructed NCA.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    /// `true` if the reconstructed NCA is empty (never the case for a
+    /// valid NCZ, which always holds the NCA header).
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Check the reconstructed NCA against the hashes in its header.
+    ///
+    /// The sections are checked as stored in the NCZ, before
+    /// re-encryption, so only the header key is needed from `keys`. The
+    /// reader's position is left unspecified. See [`NcaReader::verify`]
+    /// for how each section is reported.
+    ///
+    /// [`NcaReader::verify`]: crate::formats::nca::NcaReader::verify
+    pub fn verify(&mut self, keys: &KeySet) -> Result<NcaVerifyReport> {
+        self.verify_with_progress(keys, &mut NoProgress)
+    }
+
+    /// Like [`verify`](Self::verify), reporting progress as
+    /// [`NcaReader::verify_with_progress`] does.
+    ///
+    /// [`NcaReader::verify_with_progress`]: crate::formats::nca::NcaReader::verify_with_progress
+    pub fn verify_with_progress(
+        &mut self,
+        keys: &KeySet,
+        progress: &mut dyn Progress,
+    ) -> Result<NcaVerifyReport> {
+        let output = self.output;
+        self.output = NczOutput::Decrypted;
+        self.cached = None;
+        let report = self.verify_decrypted(keys, progress);
+        self.output = output;
+        self.cached = None;
+        report
+    }
+
+    fn verify_decrypted(
+        &mut self,
+        keys: &KeySet,
+        progress: &mut dyn Progress,
+    ) -> Result<NcaVerifyReport> {
+        self.seek(SeekFrom::Start(0))?;
+        let nca = read_header(self, 0, keys)?;
+        let total = (0..4).filter_map(|s| nca.section_size(s)).sum();
+        let mut done = 0;
+        let mut sections = Vec::new();
+        for section in 0..4 {
+            let (Some(fs_header), Some(offset)) =
+                (nca.fs_header(section), nca.section_offset(section))
+            else {
+                continue;
+            };
+            let end = done + nca.section_size(section).unwrap_or(0);
+            let item = format!("section {section}");
+            let status = if !nca.fs_header_hash_matches(section) {
+                SectionStatus::HeaderMismatch
+            } else if fs_header.encryption_type == EncryptionType::AesCtrEx {
+                SectionStatus::Unchecked
+            } else {
+                let mut r = ProgressReader {
+                    inner: &mut *self,
+                    progress: &mut *progress,
+                    item: &item,
+                    done: &mut done,
+                    limit: end,
+                    total,
+                };
+                verify_section_hashes(fs_header, &mut r, offset)?
+            };
+            done = end;
+            progress.update(&item, done, total);
+            sections.push(SectionCheck { section, status });
+        }
+        Ok(NcaVerifyReport { sections })
+    }
+
+    /// Write the whole reconstructed NCA to `w`, from the start, reporting
+    /// the bytes written against [`len`](Self::len).
+    ///
+    /// Returns the number of bytes written.
+    pub fn decompress_to<W: Write>(
+        &mut self,
+        w: &mut W,
+        progress: &mut dyn Progress,
+    ) -> Result<u64> {
+        self.seek(SeekFrom::Start(0))?;
+        let total = self.size;
+        let mut done = 0;
+        let mut r = ProgressReader::new(&mut *self, progress, "", &mut done, total);
+        Ok(io::copy(&mut r, w)?)
+    }
+
+    /// Consume the reader, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decompress block `index` into the cache, re-encrypting it if asked.
+    fn load_block(&mut self, index: usize) -> Result<()> {
+        if self.cached == Some(index) {
+            return Ok(());
+        }
+        self.cached = None;
+        let block = self.blocks[index];
+        let mut data = if let Some(solid) = &mut self.solid {
+            solid.read(
+                &mut self.inner,
+                self.header.blocks_offset,
+                block.start,
+                to_usize(block.size)?,
+            )?
+        } else {
+            self.inner.seek(SeekFrom::Start(block.offset))?;
+            let raw = bytesv(&mut self.inner, to_usize(block.compressed_size)?)?;
+            if block.stored {
+                raw
+            } else {
+                decompress_zstd_with_size(&raw, to_usize(block.size)?)?
+            }
+        };
+        if data.len() as u64 != block.size {
+            return Err(Error::Parse("NCZ block decompressed to an unexpected size"));
+        }
+        if self.output == NczOutput::Encrypted {
+            for section in &self.header.sections {
+                if is_ctr(section) == Some(true) {
+                    encrypt_range(section, block.start, &mut data);
+                }
             }
+        }
+        self.cache = data;
+        self.cached = Some(index);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<R: Read + Seek> Read for NczReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.size {
+            return Ok(0);
+        }
+        let n = if self.pos < UNCOMPRESSABLE_HEADER_SIZE as u64 {
+            let start = self.pos as usize;
+            let n = buf.len().min(UNCOMPRESSABLE_HEADER_SIZE - start);
+            buf[..n].copy_from_slice(&self.nca_header[start..start + n]);
+            n
+        } else {
+            let corrupt = || into_io(Error::Parse("NCZ blocks do not cover the NCA"));
+            let index = self
+                .blocks
+                .partition_point(|b| b.start <= self.pos)
+                .checked_sub(1)
+                .ok_or_else(corrupt)?;
+            self.load_block(index).map_err(into_io)?;
+            let start = (self.pos - self.blocks[index].start) as usize;
+            let available = self.cache.len().checked_sub(start).ok_or_else(corrupt)?;
+            let n = buf.len().min(available);
+            buf[..n].copy_from_slice(&self.cache[start..start + n]);
+            n
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<R: Read + Seek> Seek for NczReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.size.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
         };
-        let compressed_size = u32::from_le_bytes(size_buf) as usize;
-        if compressed_size == 0 {
-            break;
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of stream")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+/// Decoder state of the Zstd stream of a solid NCZ.
+#[cfg(feature = "compression")]
+struct SolidStream {
+    decoder: ZstdDecoder,
+    /// Compressed bytes read from the NCZ; `input[consumed..]` are not yet
+    /// decoded.
+    input: Vec<u8>,
+    consumed: usize,
+    /// Absolute NCZ offset of the next compressed byte to read.
+    next_in: u64,
+    /// NCA offset of the next byte the decoder produces.
+    next_out: u64,
+}
+
+#[cfg(feature = "compression")]
+impl SolidStream {
+    /// Decompress `size` bytes starting at NCA offset `start`, restarting
+    /// the stream (which begins at NCZ offset `data_offset`) if `start`
+    /// has already been passed.
+    fn read<R: Read + Seek>(
+        &mut self,
+        r: &mut R,
+        data_offset: u64,
+        start: u64,
+        size: usize,
+    ) -> Result<Vec<u8>> {
+        if self.next_out > start {
+            self.decoder.reset()?;
+            self.input.clear();
+            self.consumed = 0;
+            self.next_in = data_offset;
+            self.next_out = UNCOMPRESSABLE_HEADER_SIZE as u64;
+        }
+        let mut data = vec![0; size];
+        while self.next_out < start {
+            let skip = (start - self.next_out).min(size as u64) as usize;
+            self.fill(r, &mut data[..skip])?;
+        }
+        self.fill(r, &mut data)?;
+        Ok(data)
+    }
+
+    /// Decompress exactly `out.len()` bytes into `out`.
+    fn fill<R: Read + Seek>(&mut self, r: &mut R, out: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+        while filled < out.len() {
+            let (consumed, produced) = self
+                .decoder
+                .decompress(&self.input[self.consumed..], &mut out[filled..])?;
+            self.consumed += consumed;
+            filled += produced;
+            self.next_out += produced as u64;
+            if consumed != 0 || produced != 0 {
+                continue;
+            }
+            if self.consumed < self.input.len() {
+                return Err(Error::Zstd);
+            }
+            r.seek(SeekFrom::Start(self.next_in))?;
+            self.input.resize(SOLID_INPUT_SIZE, 0);
+            let n = r.read(&mut self.input)?;
+            if n == 0 {
+                return Err(Error::Parse("NCZ data ends before its last section"));
+            }
+            self.input.truncate(n);
+            self.consumed = 0;
+            self.next_in += n as u64;
         }
-        let block = bytesv(r, compressed_size)?;
-        blocks.push(block);
+        Ok(())
+    }
+}
+
+/// Encrypt the part of `data` (which starts at NCA offset `start`) that
+/// falls inside `section`, with the section's AES-CTR key and counter.
+#[cfg(feature = "compression")]
+fn encrypt_range(section: &NczSection, start: u64, data: &mut [u8]) {
+    let lo = start.max(section.offset);
+    let hi = (start + data.len() as u64).min(section.offset + section.size);
+    if lo >= hi {
+        return;
+    }
+    let range = &mut data[(lo - start) as usize..(hi - start) as usize];
+    let secure_value = u64::from_be_bytes(section.crypto_counter[..8].try_into().unwrap());
+    let counter = build_ctr(secure_value, lo);
+    // The counter addresses 16-byte blocks; pad to the block boundary.
+    let skip = (lo % 16) as usize;
+    if skip == 0 {
+        encrypt_section_ctr(range, &section.crypto_key, &counter);
+    } else {
+        let mut padded = vec![0; skip + range.len()];
+        padded[skip..].copy_from_slice(range);
+        encrypt_section_ctr(&mut padded, &section.crypto_key, &counter);
+        range.copy_from_slice(&padded[skip..]);
+    }
+}
+
+/// `Some(true)` if `section` is encrypted with AES-CTR, `Some(false)` if it
+/// is plaintext, [`None`] if [`NczReader`] cannot re-encrypt it.
+#[cfg(feature = "compression")]
+fn is_ctr(section: &NczSection) -> Option<bool> {
+    match EncryptionType::from(section.crypto_type) {
+        EncryptionType::None => Some(false),
+        EncryptionType::AesCtr
+        | EncryptionType::AesCtrEx
+        | EncryptionType::AesCtrSkipLayerHash
+        | EncryptionType::AesCtrExSkipLayerHash => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "compression")]
+fn into_io(e: Error) -> io::Error {
+    match e {
+        Error::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn block_table(exponent: u8, count: u32, decompressed_size: u64, sizes: &[u32]) -> Vec<u8> {
+        let mut b = b"NCZBLOCK".to_vec();
+        b.extend([2, 1, 0, exponent]);
+        b.extend(count.to_le_bytes());
+        b.extend(decompressed_size.to_le_bytes());
+        for size in sizes {
+            b.extend(size.to_le_bytes());
+        }
+        b
+    }
+
+    #[test]
+    fn block_count_must_match_size() {
+        // Two 0x4000-byte blocks cannot hold 0x8001 bytes, nor are they
+        // both needed for 0x4000.
+        for size in [0x4000, 0x8001] {
+            let table = block_table(14, 2, size, &[0x100, 0x100]);
+            assert!(matches!(
+                NczBlockHeader::parse(&mut Cursor::new(table)),
+                Err(Error::Parse(_))
+            ));
+        }
+        let table = block_table(14, 2, 0x8000, &[0x100, 0x100]);
+        assert!(NczBlockHeader::parse(&mut Cursor::new(table)).is_ok());
+    }
+
+    #[test]
+    fn truncated_block_table() {
+        let table = block_table(14, 2, 0x8000, &[0x100]);
+        assert!(NczBlockHeader::parse(&mut Cursor::new(table)).is_err());
+    }
+
+    /// NCZ header with no sections, followed by `data`.
+    fn ncz_header(data: &[u8]) -> Vec<u8> {
+        let mut b = b"NCZSECTN".to_vec();
+        b.extend(0u64.to_le_bytes());
+        b.extend(data);
+        b
+    }
+
+    /// Reader that fails once it gets past `limit` bytes.
+    struct FailingReader {
+        inner: Cursor<Vec<u8>>,
+        limit: u64,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.inner.position() >= self.limit {
+                return Err(ErrorKind::PermissionDenied.into());
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn short_data_has_no_block_table() {
+        let header = NczHeader::parse(&mut Cursor::new(ncz_header(b"\x28\xb5"))).unwrap();
+        assert!(header.block_header.is_none());
+        assert_eq!(header.blocks_offset, 0x10);
+    }
+
+    #[test]
+    fn read_error_while_probing_for_block_table() {
+        let mut r = FailingReader {
+            inner: Cursor::new(ncz_header(&[0; 0x20])),
+            limit: 0x10,
+        };
+        assert!(matches!(NczHeader::parse(&mut r), Err(Error::Io(_))));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn writer_block_size_exponent_range() {
+        for exponent in [13, 32] {
+            let mut out = Cursor::new(Vec::new());
+            let result = NczWriter::new(Vec::new()).block_mode(exponent).write(
+                &[0; UNCOMPRESSABLE_HEADER_SIZE],
+                Cursor::new([0; 0x10]),
+                &mut out,
+            );
+            assert!(matches!(result, Err(Error::InvalidRange)));
+        }
+    }
+
+    /// A plaintext NCA with an AES-CTR section after a gap and a plain
+    /// section to the end, and the same NCA with the CTR section encrypted.
+    #[cfg(feature = "compression")]
+    fn nca() -> (Vec<NczSection>, Vec<u8>, Vec<u8>) {
+        let plain: Vec<u8> = (0..0x4000 + 0x31234)
+            .map(|i: u32| (i / 3 % 251) as u8)
+            .collect();
+        let sections = vec![
+            NczSection {
+                offset: 0x5000,
+                size: 0x20000,
+                crypto_type: EncryptionType::AesCtr.into(),
+                crypto_key: [0x42; 16],
+                crypto_counter: [1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0, 0, 0, 0, 0],
+            },
+            NczSection {
+                offset: 0x25000,
+                size: plain.len() as u64 - 0x25000,
+                crypto_type: EncryptionType::None.into(),
+                crypto_key: [0; 16],
+                crypto_counter: [0; 16],
+            },
+        ];
+        let mut encrypted = plain.clone();
+        encrypt_range(&sections[0], 0x5000, &mut encrypted[0x5000..0x25000]);
+        assert_ne!(encrypted, plain);
+        (sections, plain, encrypted)
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn writer_round_trip() {
+        let (sections, plain, encrypted) = nca();
+        for exponent in [None, Some(14)] {
+            let mut writer = NczWriter::new(sections.clone());
+            if let Some(exponent) = exponent {
+                writer.block_mode(exponent);
+            }
+            let mut ncz = Cursor::new(Vec::new());
+            let header = writer.write_nca(Cursor::new(&encrypted), &mut ncz).unwrap();
+            let ncz = ncz.into_inner();
+            assert_eq!(&ncz[..UNCOMPRESSABLE_HEADER_SIZE], &encrypted[..0x4000]);
+            assert_eq!(&ncz[0x4000..0x4008], b"NCZSECTN");
+            let table_size = header
+                .block_header
+                .as_ref()
+                .map_or(0, |t| 0x18 + t.block_count() as u64 * 4);
+            assert_eq!(header.blocks_offset, 0x4090 + table_size);
+            assert_eq!(header.block_header.is_some(), exponent.is_some());
+
+            let mut reader = NczReader::new(Cursor::new(&ncz), NczOutput::Encrypted).unwrap();
+            assert_eq!(reader.header.sections.len(), 2);
+            assert_eq!(reader.len(), encrypted.len() as u64);
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            assert_eq!(out, encrypted, "{exponent:?}");
+
+            // Seeking back, including into the header and across sections.
+            for offset in [0x24FF0, 0x10, 0x4FF8, 0x30000] {
+                let mut buf = [0; 0x20];
+                reader.seek(SeekFrom::Start(offset)).unwrap();
+                reader.read_exact(&mut buf).unwrap();
+                let offset = offset as usize;
+                assert_eq!(buf[..], encrypted[offset..offset + 0x20]);
+            }
+
+            let mut reader = NczReader::new(Cursor::new(&ncz), NczOutput::Decrypted).unwrap();
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            assert_eq!(out, plain);
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn reader_rejects_inconsistent_block_table() {
+        let (sections, _, encrypted) = nca();
+        let mut ncz = Cursor::new(Vec::new());
+        NczWriter::new(sections)
+            .block_mode(14)
+            .write_nca(Cursor::new(&encrypted), &mut ncz)
+            .unwrap();
+        let mut ncz = ncz.into_inner();
+        // Shrink DecompressedSize below what the blocks cover.
+        let table = 0x4010 + 2 * 0x40;
+        ncz[table + 0x10..table + 0x18].copy_from_slice(&0x100u64.to_le_bytes());
+        assert!(NczReader::new(Cursor::new(ncz), NczOutput::Encrypted).is_err());
+    }
+
+    #[test]
+    fn block_index_out_of_range() {
+        let table = NczBlockHeader {
+            version: 2,
+            block_type: 1,
+            block_size_exponent: 14,
+            decompressed_size: 0x100,
+            compressed_sizes: Vec::new(),
+        };
+        assert!(!table.is_stored(3));
+        assert_eq!(table.block_offset(3), 0);
     }
-    Ok(blocks)
 }
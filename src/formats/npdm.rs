@@ -7,7 +7,7 @@
 //! ## File Layout
 //! ```text
 //! [0x00] Magic "META"                                   (4 bytes)
-//! [0x04] Unknown / signature key generation             (u32 LE)
+//! [0x04] SignatureKeyGeneration (ACID signing key)     (u32 LE)
 //! [0x08] Reserved                                       (4 bytes)
 //! [0x0C] MMUFlags   (bit 0 = 64-bit mode)               (1 byte)
 //! [0x0D] Reserved                                       (1 byte)
@@ -34,8 +34,8 @@
 //! [0x18] Reserved              (8 bytes)
 //! [0x20] FsAccessControlOffset (u32 LE)
 //! [0x24] FsAccessControlSize   (u32 LE)
-//! [0x28] SvcAccessControlOffset(u32 LE)
-//! [0x2C] SvcAccessControlSize  (u32 LE)
+//! [0x28] ServiceAccessControlOffset (u32 LE)
+//! [0x2C] ServiceAccessControlSize   (u32 LE)
 //! [0x30] KernelAccessControlOffset (u32 LE)
 //! [0x34] KernelAccessControlSize   (u32 LE)
 //! [0x38] Reserved              (8 bytes)
@@ -51,17 +51,89 @@
 //! [0x20C] Reserved             (u32 LE)
 //! [0x210] ProgramIdMin         (u64 LE)
 //! [0x218] ProgramIdMax         (u64 LE)
-//! … FsAccessControl, SvcAccessControl, KernelAccessControl follow
+//! [0x220] FsAccessControlOffset    (u32 LE)
+//! [0x224] FsAccessControlSize      (u32 LE)
+//! [0x228] ServiceAccessControlOffset (u32 LE)
+//! [0x22C] ServiceAccessControlSize (u32 LE)
+//! [0x230] KernelAccessControlOffset(u32 LE)
+//! [0x234] KernelAccessControlSize  (u32 LE)
+//! [0x238] Reserved             (8 bytes)
 //! ```
+//!
+//! Offsets in ACI0 and ACID are relative to the start of that structure.
+//!
+//! ## Signatures
+//! The ACID signature (RSA-2048 PSS, SHA-256) covers `Size` bytes starting
+//! at the public key (+0x100) and is made with Nintendo's fixed ACID key,
+//! selected by SignatureKeyGeneration; see [`Npdm::verify_acid`]. The
+//! embedded public key in turn verifies the second signature of the NCA
+//! header that holds the NPDM; see [`Acid::verify_nca_header`].
+//!
+//! ## FsAccessHeader - ACI0 FsAccessControl
+//! ```text
+//! [0x00] Version               (u8)
+//! [0x01] Padding               (3 bytes)
+//! [0x04] PermissionsBitmask    (u64 LE)
+//! [0x0C] ContentOwnerInfoOffset (u32 LE, relative to the header)
+//! [0x10] ContentOwnerInfoSize  (u32 LE)
+//! [0x14] SaveDataOwnerInfoOffset (u32 LE, relative to the header)
+//! [0x18] SaveDataOwnerInfoSize (u32 LE)
+//! ContentOwnerInfo:  u32 count, count × u64 id
+//! SaveDataOwnerInfo: u32 count, count × u8 accessibility (padded to 4),
+//!                    count × u64 id
+//! ```
+//!
+//! ## FsAccessControl - ACID FsAccessControl
+//! ```text
+//! [0x00] Version               (u8)
+//! [0x01] ContentOwnerIdCount   (u8)
+//! [0x02] SaveDataOwnerIdCount  (u8)
+//! [0x03] Padding               (1 byte)
+//! [0x04] PermissionsBitmask    (u64 LE)
+//! [0x0C] ContentOwnerIdMin     (u64 LE)
+//! [0x14] ContentOwnerIdMax     (u64 LE)
+//! [0x1C] SaveDataOwnerIdMin    (u64 LE)
+//! [0x24] SaveDataOwnerIdMax    (u64 LE)
+//! [0x2C] ContentOwnerIds       (ContentOwnerIdCount × u64 LE)
+//! [...]  SaveDataOwnerIds      (SaveDataOwnerIdCount × u64 LE)
+//! ```
+//!
+//! ## KernelAccessControl
+//! A list of u32 capability descriptors, decoded into
+//! [`KernelCapability`]. The descriptor type is given by the number of
+//! trailing 1 bits; `0xFFFFFFFF` is padding.
+//!
+//! | Trailing 1s | Type              | Fields (bit ranges)                           |
+//! |-------------|-------------------|-----------------------------------------------|
+//! | 3           | ThreadInfo        | 4-9 lowest prio, 10-15 highest prio, 16-23 min core, 24-31 max core |
+//! | 4           | EnableSystemCalls | 5-28 mask (bit n = syscall index×24+n), 29-31 index |
+//! | 6           | MemoryMap         | two words: 7-30 page, 31 read-only; 7-26 page count, 31 normal (not I/O) |
+//! | 7           | IoMemoryMap       | 8-31 page                                     |
+//! | 10          | MapRegion         | three × (6-bit region type, 1-bit read-only) from bit 11 |
+//! | 11          | InterruptPair     | 12-21, 22-31 interrupt numbers (0x3FF = none) |
+//! | 13          | ProgramType       | 14-16 type                                    |
+//! | 14          | KernelVersion     | 15-18 minor, 19-31 major                      |
+//! | 15          | HandleTableSize   | 16-25 size                                    |
+//! | 16          | DebugFlags        | 17 allow debug, 18 force debug                |
+//!
+//! [`KernelCapability::encode_all`] packs decoded descriptors back into
+//! words. [`Npdm::allowed_svcs`] collects the EnableSystemCalls descriptors;
+//! [`svc_name`] names the syscall numbers. (The ServiceAccessControl region
+//! lists `sm` service names, not syscalls.)
 
 use std::io::{Read, Seek, SeekFrom};
 
-use crate::Result;
-use crate::utils::{bytesa, le_u32, le_u64, magic, null_padded_string, u8};
+use crate::crypto::rsa::verify_pss_sha256;
+use crate::keys::KeySet;
+use crate::utils::{bytesa, bytesv, le_u32, le_u64, magic, null_padded_string, table, u8};
+use crate::{Error, Result};
 
 /// Parsed NPDM file.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Npdm {
+    /// Generation of the fixed key that signs the ACID.
+    pub signature_key_generation: u32,
     /// Whether the process runs in 64-bit mode.
     pub is_64bit: bool,
     /// Priority of the main thread (0–63).
@@ -86,13 +158,19 @@ pub struct Npdm {
 
 /// ACI0 - per-title access control info.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Aci0 {
     /// Program (title) ID for this build.
     pub program_id: u64,
+    /// Filesystem permissions granted to the title.
+    pub fs_access: FsAccessHeader,
+    /// Decoded KernelAccessControl descriptors.
+    pub kernel_capabilities: Vec<KernelCapability>,
 }
 
 /// ACID - signed access control descriptor.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Acid {
     /// ACID flags field.
     pub flags: u32,
@@ -100,6 +178,56 @@ pub struct Acid {
     pub program_id_min: u64,
     /// Maximum allowed program ID for this descriptor.
     pub program_id_max: u64,
+    /// Filesystem permissions the descriptor allows.
+    pub fs_access: FsAccessControl,
+    /// Decoded KernelAccessControl descriptors.
+    pub kernel_capabilities: Vec<KernelCapability>,
+    /// RSA-2048 PSS signature over `signed`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
+    pub signature: [u8; 0x100],
+    /// Modulus of the key that signs the NCA header's second signature.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
+    pub public_key: [u8; 0x100],
+    /// The signed region: `Size` bytes from the public key on.
+    signed: Vec<u8>,
+}
+
+/// ACI0 filesystem access control (FsAccessHeader).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FsAccessHeader {
+    pub version: u8,
+    /// Filesystem permission bits.
+    pub permissions: u64,
+    /// Program IDs whose content the title may mount.
+    pub content_owner_ids: Vec<u64>,
+    /// Save data the title may access.
+    pub save_data_owners: Vec<SaveDataOwner>,
+}
+
+/// One save data owner entry of an [`FsAccessHeader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveDataOwner {
+    /// Owner program ID.
+    pub id: u64,
+    /// Access bits (1 = read, 2 = write).
+    pub accessibility: u8,
+}
+
+/// ACID filesystem access control descriptor (FsAccessControl).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FsAccessControl {
+    pub version: u8,
+    /// Filesystem permission bits.
+    pub permissions: u64,
+    pub content_owner_id_min: u64,
+    pub content_owner_id_max: u64,
+    pub save_data_owner_id_min: u64,
+    pub save_data_owner_id_max: u64,
+    pub content_owner_ids: Vec<u64>,
+    pub save_data_owner_ids: Vec<u64>,
 }
 
 impl Npdm {
@@ -110,7 +238,7 @@ impl Npdm {
         let base = r.stream_position()?;
         magic(r, b"META")?;
 
-        let _unknown = le_u32(r)?;
+        let signature_key_generation = le_u32(r)?;
         let _reserved0 = le_u32(r)?;
         let mmu_flags = u8(r)?;
         let is_64bit = (mmu_flags & 0x01) != 0;
@@ -151,6 +279,7 @@ impl Npdm {
         };
 
         Ok(Self {
+            signature_key_generation,
             is_64bit,
             main_thread_priority,
             main_thread_core,
@@ -163,33 +292,552 @@ impl Npdm {
             acid,
         })
     }
+
+    /// Verify the ACID signature with the fixed ACID key.
+    ///
+    /// The modulus is taken from `keys.acid_fixed_key_modulus`, selected by
+    /// [`signature_key_generation`](Self::signature_key_generation).
+    /// Returns `Ok(false)` if the signature does not match,
+    /// [`Error::Parse`] if the NPDM has no ACID, or [`Error::MissingKey`] if
+    /// the modulus is not loaded.
+    pub fn verify_acid(&self, keys: &KeySet) -> Result<bool> {
+        let acid = self.acid.as_ref().ok_or(Error::Parse("NPDM has no ACID"))?;
+        let generation = self.signature_key_generation;
+        let modulus = keys
+            .acid_fixed_key_modulus
+            .get(generation as usize)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| Error::MissingKey(format!("acid_fixed_key_modulus_{generation:02x}")))?;
+        Ok(verify_pss_sha256(&acid.signed, &acid.signature, modulus))
+    }
+
+    /// Syscall numbers the title may call, in ascending order.
+    ///
+    /// Taken from the EnableSystemCalls descriptors in the ACI0 kernel
+    /// capabilities. Use [`svc_name`] for display.
+    pub fn allowed_svcs(&self) -> Vec<u8> {
+        svcs_from_capabilities(&self.aci.kernel_capabilities)
+    }
 }
 
 impl Aci0 {
-    pub(crate) fn parse<R: Read>(r: &mut R) -> Result<Self> {
+    pub(crate) fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
         magic(r, b"ACI0")?;
         let _reserved = bytesa::<0xC>(r)?;
         let program_id = le_u64(r)?;
-        Ok(Self { program_id })
+        let _reserved = bytesa::<8>(r)?;
+        let fs_offset = le_u32(r)?;
+        let fs_size = le_u32(r)?;
+        let _service_offset = le_u32(r)?;
+        let _service_size = le_u32(r)?;
+        let kernel_offset = le_u32(r)?;
+        let kernel_size = le_u32(r)?;
+
+        let fs_access = if fs_size > 0 {
+            r.seek(SeekFrom::Start(base + fs_offset as u64))?;
+            FsAccessHeader::parse(r)?
+        } else {
+            FsAccessHeader::default()
+        };
+        r.seek(SeekFrom::Start(base + kernel_offset as u64))?;
+        let kernel_capabilities = KernelCapability::parse_all(&kernel_words(r, kernel_size)?);
+        Ok(Self {
+            program_id,
+            fs_access,
+            kernel_capabilities,
+        })
     }
 }
 
 impl Acid {
-    pub(crate) fn parse<R: Read>(r: &mut R) -> Result<Self> {
+    pub(crate) fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
         // RSA-2048 signature (0x100) + public key (0x100) = 0x200 bytes before
         // the "ACID" magic.
-        let _sig = bytesa::<0x100>(r)?;
-        let _pubkey = bytesa::<0x100>(r)?;
+        let signature = bytesa::<0x100>(r)?;
+        let public_key = bytesa::<0x100>(r)?;
         magic(r, b"ACID")?;
-        let _size = le_u32(r)?;
+        let size = le_u32(r)?;
         let flags = le_u32(r)?;
         let _reserved = le_u32(r)?;
         let program_id_min = le_u64(r)?;
         let program_id_max = le_u64(r)?;
+        let fs_offset = le_u32(r)?;
+        let fs_size = le_u32(r)?;
+        let _service_offset = le_u32(r)?;
+        let _service_size = le_u32(r)?;
+        let kernel_offset = le_u32(r)?;
+        let kernel_size = le_u32(r)?;
+
+        let fs_access = if fs_size > 0 {
+            r.seek(SeekFrom::Start(base + fs_offset as u64))?;
+            FsAccessControl::parse(r)?
+        } else {
+            FsAccessControl::default()
+        };
+        r.seek(SeekFrom::Start(base + kernel_offset as u64))?;
+        let kernel_capabilities = KernelCapability::parse_all(&kernel_words(r, kernel_size)?);
+
+        r.seek(SeekFrom::Start(base + 0x100))?;
+        let mut signed = Vec::new();
+        r.take(size as u64).read_to_end(&mut signed)?;
+        if signed.len() != size as usize {
+            return Err(Error::UnexpectedEof);
+        }
         Ok(Self {
             flags,
             program_id_min,
             program_id_max,
+            fs_access,
+            kernel_capabilities,
+            signature,
+            public_key,
+            signed,
         })
     }
+
+    /// Verify the second signature of an NCA header (`[0x100..0x200]`,
+    /// over `[0x200..0x400]`) with the ACID's embedded public key.
+    ///
+    /// `header` is the decrypted header of the Program NCA this NPDM came
+    /// from. Returns `false` for a short header.
+    pub fn verify_nca_header(&self, header: &[u8]) -> bool {
+        let (Some(signature), Some(signed)) = (header.get(0x100..0x200), header.get(0x200..0x400))
+        else {
+            return false;
+        };
+        verify_pss_sha256(signed, signature.try_into().unwrap(), &self.public_key)
+    }
+}
+
+impl FsAccessHeader {
+    pub(crate) fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
+        let version = u8(r)?;
+        let _padding = bytesa::<3>(r)?;
+        let permissions = le_u64(r)?;
+        let content_offset = le_u32(r)?;
+        let content_size = le_u32(r)?;
+        let save_offset = le_u32(r)?;
+        let save_size = le_u32(r)?;
+
+        let mut content_owner_ids = Vec::new();
+        if content_size >= 4 {
+            r.seek(SeekFrom::Start(base + content_offset as u64))?;
+            let count = le_u32(r)?;
+            for _ in 0..count {
+                content_owner_ids.push(le_u64(r)?);
+            }
+        }
+
+        let mut save_data_owners = Vec::new();
+        if save_size >= 4 {
+            r.seek(SeekFrom::Start(base + save_offset as u64))?;
+            let count = le_u32(r)? as usize;
+            let accessibility = table(r, count as u64)?;
+            let _padding = bytesv(r, count.next_multiple_of(4) - count)?;
+            for accessibility in accessibility {
+                save_data_owners.push(SaveDataOwner {
+                    id: le_u64(r)?,
+                    accessibility,
+                });
+            }
+        }
+
+        Ok(Self {
+            version,
+            permissions,
+            content_owner_ids,
+            save_data_owners,
+        })
+    }
+}
+
+impl FsAccessControl {
+    pub(crate) fn parse<R: Read>(r: &mut R) -> Result<Self> {
+        let version = u8(r)?;
+        let content_owner_id_count = u8(r)?;
+        let save_data_owner_id_count = u8(r)?;
+        let _padding = u8(r)?;
+        let permissions = le_u64(r)?;
+        let content_owner_id_min = le_u64(r)?;
+        let content_owner_id_max = le_u64(r)?;
+        let save_data_owner_id_min = le_u64(r)?;
+        let save_data_owner_id_max = le_u64(r)?;
+        let content_owner_ids = (0..content_owner_id_count)
+            .map(|_| le_u64(r))
+            .collect::<Result<_>>()?;
+        let save_data_owner_ids = (0..save_data_owner_id_count)
+            .map(|_| le_u64(r))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            version,
+            permissions,
+            content_owner_id_min,
+            content_owner_id_max,
+            save_data_owner_id_min,
+            save_data_owner_id_max,
+            content_owner_ids,
+            save_data_owner_ids,
+        })
+    }
+}
+
+/// Place `value` in the `len`-bit field at `start`.
+fn pack(value: u64, start: u32, len: u32) -> Result<u32> {
+    if value >> len != 0 {
+        return Err(Error::InvalidRange);
+    }
+    Ok((value as u32) << start)
+}
+
+/// Bit `n` if `set`.
+fn flag(set: bool, n: u32) -> u32 {
+    (set as u32) << n
+}
+
+/// Page number of a page-aligned address or size.
+fn page(value: u64) -> Result<u64> {
+    if value & 0xFFF != 0 {
+        return Err(Error::InvalidRange);
+    }
+    Ok(value >> 12)
+}
+
+/// Read `size` bytes of KernelAccessControl as descriptor words.
+fn kernel_words<R: Read>(r: &mut R, size: u32) -> Result<Vec<u32>> {
+    (0..size / 4).map(|_| le_u32(r)).collect()
+}
+
+/// Syscall numbers enabled by the EnableSystemCalls descriptors in `caps`,
+/// in ascending order.
+fn svcs_from_capabilities(caps: &[KernelCapability]) -> Vec<u8> {
+    let mut svcs: Vec<u8> = caps
+        .iter()
+        .filter_map(|cap| match *cap {
+            KernelCapability::EnableSystemCalls { index, mask } => Some((index, mask)),
+            _ => None,
+        })
+        .flat_map(|(index, mask)| {
+            (0..24u8)
+                .filter(move |bit| mask & (1 << bit) != 0)
+                .map(move |bit| index * 24 + bit)
+        })
+        .collect();
+    svcs.sort_unstable();
+    svcs.dedup();
+    svcs
+}
+
+/// One decoded KernelAccessControl descriptor (see the table in the module
+/// docs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KernelCapability {
+    /// Allowed thread priority and core ranges.
+    ThreadInfo {
+        lowest_priority: u8,
+        highest_priority: u8,
+        min_core: u8,
+        max_core: u8,
+    },
+    /// Allows syscalls `index * 24 + n` for each set bit `n` of `mask`.
+    EnableSystemCalls { index: u8, mask: u32 },
+    /// Maps a physical range; `io` is set for device memory.
+    MemoryMap {
+        address: u64,
+        size: u64,
+        read_only: bool,
+        io: bool,
+    },
+    /// Maps a single page of device memory.
+    IoMemoryMap { address: u64 },
+    /// Maps up to three predefined regions as `(region type, read_only)`;
+    /// type 0 is none.
+    MapRegion { regions: [(u8, bool); 3] },
+    /// Up to two interrupts the process may receive.
+    InterruptPair { interrupts: [Option<u16>; 2] },
+    /// Program type (0 = system, 1 = application, 2 = applet).
+    ProgramType(u8),
+    /// Kernel version the program was built for.
+    KernelVersion { major: u16, minor: u8 },
+    /// Maximum number of handles.
+    HandleTableSize(u16),
+    DebugFlags {
+        allow_debug: bool,
+        force_debug: bool,
+    },
+    /// A descriptor of unknown type, or a MemoryMap word without its
+    /// second half.
+    Unknown(u32),
+}
+
+impl KernelCapability {
+    /// Decode a list of descriptor words, dropping padding.
+    pub fn parse_all(words: &[u32]) -> Vec<Self> {
+        let bit = |w: u32, n: u32| w & (1 << n) != 0;
+        let field = |w: u32, start: u32, len: u32| (w >> start) & ((1 << len) - 1);
+
+        let mut caps = Vec::with_capacity(words.len());
+        let mut words = words.iter().copied().peekable();
+        while let Some(w) = words.next() {
+            let cap = match w.trailing_ones() {
+                3 => Self::ThreadInfo {
+                    lowest_priority: field(w, 4, 6) as u8,
+                    highest_priority: field(w, 10, 6) as u8,
+                    min_core: field(w, 16, 8) as u8,
+                    max_core: field(w, 24, 8) as u8,
+                },
+                4 => Self::EnableSystemCalls {
+                    index: field(w, 29, 3) as u8,
+                    mask: field(w, 5, 24),
+                },
+                6 => match words.next_if(|next| next.trailing_ones() == 6) {
+                    Some(size) => Self::MemoryMap {
+                        address: (field(w, 7, 24) as u64) << 12,
+                        size: (field(size, 7, 20) as u64) << 12,
+                        read_only: bit(w, 31),
+                        io: !bit(size, 31),
+                    },
+                    None => Self::Unknown(w),
+                },
+                7 => Self::IoMemoryMap {
+                    address: (field(w, 8, 24) as u64) << 12,
+                },
+                10 => Self::MapRegion {
+                    regions: [11, 18, 25]
+                        .map(|start| (field(w, start, 6) as u8, bit(w, start + 6))),
+                },
+                11 => Self::InterruptPair {
+                    interrupts: [12, 22].map(|start| {
+                        let irq = field(w, start, 10) as u16;
+                        (irq != 0x3FF).then_some(irq)
+                    }),
+                },
+                13 => Self::ProgramType(field(w, 14, 3) as u8),
+                14 => Self::KernelVersion {
+                    major: field(w, 19, 13) as u16,
+                    minor: field(w, 15, 4) as u8,
+                },
+                15 => Self::HandleTableSize(field(w, 16, 10) as u16),
+                16 => Self::DebugFlags {
+                    allow_debug: bit(w, 17),
+                    force_debug: bit(w, 18),
+                },
+                32 => continue,
+                _ => Self::Unknown(w),
+            };
+            caps.push(cap);
+        }
+        caps
+    }
+
+    /// Pack descriptors into words, the inverse of
+    /// [`parse_all`](Self::parse_all).
+    ///
+    /// [`MemoryMap`](Self::MemoryMap) yields two words; everything else one.
+    /// Returns [`Error::InvalidRange`] if a field does not fit its bits or
+    /// an address or size is not page-aligned.
+    pub fn encode_all(caps: &[Self]) -> Result<Vec<u32>> {
+        let mut words = Vec::with_capacity(caps.len());
+        for cap in caps {
+            let w = match *cap {
+                Self::MemoryMap {
+                    address,
+                    size,
+                    read_only,
+                    io,
+                } => {
+                    words.push(0x3F | pack(page(address)?, 7, 24)? | flag(read_only, 31));
+                    0x3F | pack(page(size)?, 7, 20)? | flag(!io, 31)
+                }
+                Self::ThreadInfo {
+                    lowest_priority,
+                    highest_priority,
+                    min_core,
+                    max_core,
+                } => {
+                    0x07 | pack(lowest_priority as u64, 4, 6)?
+                        | pack(highest_priority as u64, 10, 6)?
+                        | pack(min_core as u64, 16, 8)?
+                        | pack(max_core as u64, 24, 8)?
+                }
+                Self::EnableSystemCalls { index, mask } => {
+                    0x0F | pack(mask as u64, 5, 24)? | pack(index as u64, 29, 3)?
+                }
+                Self::IoMemoryMap { address } => 0x7F | pack(page(address)?, 8, 24)?,
+                Self::MapRegion { regions } => {
+                    let mut w = 0x3FF;
+                    for ((region, read_only), start) in regions.into_iter().zip([11, 18, 25]) {
+                        w |= pack(region as u64, start, 6)? | flag(read_only, start + 6);
+                    }
+                    w
+                }
+                Self::InterruptPair { interrupts } => {
+                    let irq = |i: Option<u16>| i.map_or(0x3FF, u64::from);
+                    0x7FF | pack(irq(interrupts[0]), 12, 10)? | pack(irq(interrupts[1]), 22, 10)?
+                }
+                Self::ProgramType(ty) => 0x1FFF | pack(ty as u64, 14, 3)?,
+                Self::KernelVersion { major, minor } => {
+                    0x3FFF | pack(minor as u64, 15, 4)? | pack(major as u64, 19, 13)?
+                }
+                Self::HandleTableSize(size) => 0x7FFF | pack(size as u64, 16, 10)?,
+                Self::DebugFlags {
+                    allow_debug,
+                    force_debug,
+                } => 0xFFFF | flag(allow_debug, 17) | flag(force_debug, 18),
+                Self::Unknown(w) => w,
+            };
+            words.push(w);
+        }
+        Ok(words)
+    }
+}
+
+/// Name of Horizon syscall `id`, or [`None`] if unassigned.
+pub fn svc_name(id: u8) -> Option<&'static str> {
+    Some(match id {
+        0x01 => "SetHeapSize",
+        0x02 => "SetMemoryPermission",
+        0x03 => "SetMemoryAttribute",
+        0x04 => "MapMemory",
+        0x05 => "UnmapMemory",
+        0x06 => "QueryMemory",
+        0x07 => "ExitProcess",
+        0x08 => "CreateThread",
+        0x09 => "StartThread",
+        0x0A => "ExitThread",
+        0x0B => "SleepThread",
+        0x0C => "GetThreadPriority",
+        0x0D => "SetThreadPriority",
+        0x0E => "GetThreadCoreMask",
+        0x0F => "SetThreadCoreMask",
+        0x10 => "GetCurrentProcessorNumber",
+        0x11 => "SignalEvent",
+        0x12 => "ClearEvent",
+        0x13 => "MapSharedMemory",
+        0x14 => "UnmapSharedMemory",
+        0x15 => "CreateTransferMemory",
+        0x16 => "CloseHandle",
+        0x17 => "ResetSignal",
+        0x18 => "WaitSynchronization",
+        0x19 => "CancelSynchronization",
+        0x1A => "ArbitrateLock",
+        0x1B => "ArbitrateUnlock",
+        0x1C => "WaitProcessWideKeyAtomic",
+        0x1D => "SignalProcessWideKey",
+        0x1E => "GetSystemTick",
+        0x1F => "ConnectToNamedPort",
+        0x20 => "SendSyncRequestLight",
+        0x21 => "SendSyncRequest",
+        0x22 => "SendSyncRequestWithUserBuffer",
+        0x23 => "SendAsyncRequestWithUserBuffer",
+        0x24 => "GetProcessId",
+        0x25 => "GetThreadId",
+        0x26 => "Break",
+        0x27 => "OutputDebugString",
+        0x28 => "ReturnFromException",
+        0x29 => "GetInfo",
+        0x2A => "FlushEntireDataCache",
+        0x2B => "FlushDataCache",
+        0x2C => "MapPhysicalMemory",
+        0x2D => "UnmapPhysicalMemory",
+        0x2E => "GetDebugFutureThreadInfo",
+        0x2F => "GetLastThreadInfo",
+        0x30 => "GetResourceLimitLimitValue",
+        0x31 => "GetResourceLimitCurrentValue",
+        0x32 => "SetThreadActivity",
+        0x33 => "GetThreadContext3",
+        0x34 => "WaitForAddress",
+        0x35 => "SignalToAddress",
+        0x36 => "SynchronizePreemptionState",
+        0x37 => "GetResourceLimitPeakValue",
+        0x39 => "CreateIoPool",
+        0x3A => "CreateIoRegion",
+        0x3C => "KernelDebug",
+        0x3D => "ChangeKernelTraceState",
+        0x40 => "CreateSession",
+        0x41 => "AcceptSession",
+        0x42 => "ReplyAndReceiveLight",
+        0x43 => "ReplyAndReceive",
+        0x44 => "ReplyAndReceiveWithUserBuffer",
+        0x45 => "CreateEvent",
+        0x46 => "MapIoRegion",
+        0x47 => "UnmapIoRegion",
+        0x48 => "MapPhysicalMemoryUnsafe",
+        0x49 => "UnmapPhysicalMemoryUnsafe",
+        0x4A => "SetUnsafeLimit",
+        0x4B => "CreateCodeMemory",
+        0x4C => "ControlCodeMemory",
+        0x4D => "SleepSystem",
+        0x4E => "ReadWriteRegister",
+        0x4F => "SetProcessActivity",
+        0x50 => "CreateSharedMemory",
+        0x51 => "MapTransferMemory",
+        0x52 => "UnmapTransferMemory",
+        0x53 => "CreateInterruptEvent",
+        0x54 => "QueryPhysicalAddress",
+        0x55 => "QueryIoMapping",
+        0x56 => "CreateDeviceAddressSpace",
+        0x57 => "AttachDeviceAddressSpace",
+        0x58 => "DetachDeviceAddressSpace",
+        0x59 => "MapDeviceAddressSpaceByForce",
+        0x5A => "MapDeviceAddressSpaceAligned",
+        0x5B => "MapDeviceAddressSpace",
+        0x5C => "UnmapDeviceAddressSpace",
+        0x5D => "InvalidateProcessDataCache",
+        0x5E => "StoreProcessDataCache",
+        0x5F => "FlushProcessDataCache",
+        0x60 => "DebugActiveProcess",
+        0x61 => "BreakDebugProcess",
+        0x62 => "TerminateDebugProcess",
+        0x63 => "GetDebugEvent",
+        0x64 => "ContinueDebugEvent",
+        0x65 => "GetProcessList",
+        0x66 => "GetThreadList",
+        0x67 => "GetDebugThreadContext",
+        0x68 => "SetDebugThreadContext",
+        0x69 => "QueryDebugProcessMemory",
+        0x6A => "ReadDebugProcessMemory",
+        0x6B => "WriteDebugProcessMemory",
+        0x6C => "SetHardwareBreakPoint",
+        0x6D => "GetDebugThreadParam",
+        0x6F => "GetSystemInfo",
+        0x70 => "CreatePort",
+        0x71 => "ManageNamedPort",
+        0x72 => "ConnectToPort",
+        0x73 => "SetProcessMemoryPermission",
+        0x74 => "MapProcessMemory",
+        0x75 => "UnmapProcessMemory",
+        0x76 => "QueryProcessMemory",
+        0x77 => "MapProcessCodeMemory",
+        0x78 => "UnmapProcessCodeMemory",
+        0x79 => "CreateProcess",
+        0x7A => "StartProcess",
+        0x7B => "TerminateProcess",
+        0x7C => "GetProcessInfo",
+        0x7D => "CreateResourceLimit",
+        0x7E => "SetResourceLimitLimitValue",
+        0x7F => "CallSecureMonitor",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn fs_access_header_huge_save_owner_count() {
+        let mut fah = vec![1, 0, 0, 0];
+        fah.extend(0u64.to_le_bytes());
+        for field in [0x1C, 0, 0x1C, 4, u32::MAX] {
+            fah.extend(u32::to_le_bytes(field));
+        }
+        fah.extend([1, 2, 3]);
+        assert!(FsAccessHeader::parse(&mut Cursor::new(fah)).is_err());
+    }
 }
@@ -0,0 +1,349 @@
+//! NSP (Nintendo Submission Package) - eShop title package.
+//!
+//! An NSP is a [`pfs0`](super::pfs0) container whose entries are named by
+//! convention rather than described by a header:
+//!
+//! ```text
+//! <ContentId>.nca        Content NCA (program, control, data, ...)
+//! <ContentId>.cnmt.nca   Meta NCA holding the title's CNMT
+//! <ContentId>.ncz        Compressed NCA (NSZ only; see ncz)
+//! <RightsId>.tik         Ticket with the title key
+//! <RightsId>.cert        Certificate chain for the ticket
+//! <ContentId>.cnmt.xml   Human-readable copy of the CNMT (optional)
+//! ```
+//!
+//! [`NspReader`] classifies the entries, parses the tickets and CNMTs up
+//! front, and resolves content records to entries, so "the Program NCA of
+//! title X" is one call. A merged NSP can hold several titles (base game,
+//! update, DLC), each with its own CNMT.
+//!
+//! ## Splitting merged NSPs
+//! [`NspReader::write_title`] writes one title's entries as a standalone
+//! NSP, and [`NspReader::split`] does so for every title. A title owns
+//! the NCAs its CNMT lists, its meta NCA and `.cnmt.xml`, and the tickets
+//! and certificates whose rights ID starts with its title ID.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Read, Seek, Take, Write};
+use std::ops::Index;
+use std::path::{Path, PathBuf};
+
+use super::cnmt::{Cnmt, ContentRecordType};
+use super::nca::NcaReader;
+use super::pfs0::{Pfs0, Pfs0File, Pfs0Reader, Pfs0Writer};
+use super::ticket::Ticket;
+use crate::keys::KeySet;
+use crate::read_at::{RangeReader, ReadAt};
+use crate::utils::{SharedRange, SubReader};
+use crate::{Error, Result};
+
+/// Largest `.tik` entry [`NspReader::new`] reads. Tickets are under 1 KiB
+/// (0x2C0 bytes for a common RSA-2048 ticket), plus any section records.
+const MAX_TICKET_SIZE: u64 = 0x1_0000;
+
+/// Kind of an NSP entry, from its file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntryKind {
+    Nca,
+    MetaNca,
+    Ncz,
+    Ticket,
+    Certificate,
+    CnmtXml,
+    Other,
+}
+
+impl EntryKind {
+    /// Classify an entry by its file name.
+    pub fn from_name(name: &str) -> Self {
+        if name.ends_with(".cnmt.nca") {
+            Self::MetaNca
+        } else if name.ends_with(".nca") {
+            Self::Nca
+        } else if name.ends_with(".ncz") {
+            Self::Ncz
+        } else if name.ends_with(".tik") {
+            Self::Ticket
+        } else if name.ends_with(".cert") {
+            Self::Certificate
+        } else if name.ends_with(".cnmt.xml") {
+            Self::CnmtXml
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Streaming reader for an NSP.
+///
+/// Owns the underlying reader. Tickets and CNMTs are parsed by
+/// [`new`](Self::new); NCA data is only read on demand.
+pub struct NspReader<R> {
+    pfs0: Pfs0Reader<R>,
+    /// Tickets from the `.tik` entries.
+    pub tickets: Vec<Ticket>,
+    /// CNMTs from the `.cnmt.nca` entries, one per title.
+    pub metas: Vec<Cnmt>,
+    /// The `.cnmt.nca` entry each of `metas` was read from.
+    meta_files: Vec<Pfs0File>,
+}
+
+impl<R: Read + Seek> NspReader<R> {
+    /// Parse an NSP and wrap the provided reader.
+    ///
+    /// `keys` must hold the header key and the key area keys for the meta
+    /// NCAs; title keys are not needed. Returns [`Error::Parse`] if a
+    /// `.tik` entry is implausibly large for a ticket.
+    pub fn new(reader: R, keys: &KeySet) -> Result<Self> {
+        let mut pfs0 = Pfs0Reader::new(reader)?;
+        let files: Vec<Pfs0File> = pfs0.files().cloned().collect();
+
+        let mut tickets = Vec::new();
+        let mut metas = Vec::new();
+        let mut meta_files = Vec::new();
+        for file in &files {
+            match EntryKind::from_name(&file.name) {
+                EntryKind::Ticket => {
+                    if file.size > MAX_TICKET_SIZE {
+                        return Err(Error::Parse("ticket entry too large"));
+                    }
+                    let data = pfs0.read_file_to_vec(file)?;
+                    tickets.push(Ticket::parse(&mut Cursor::new(data))?);
+                }
+                EntryKind::MetaNca => {
+                    metas.push(Cnmt::from_meta_nca(
+                        pfs0.read_file(file)?.into_inner(),
+                        keys,
+                    )?);
+                    meta_files.push(file.clone());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            pfs0,
+            tickets,
+            metas,
+            meta_files,
+        })
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<&Pfs0File> {
+        self.pfs0.get_file(name)
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&Pfs0File> {
+        self.pfs0.try_get(name)
+    }
+
+    /// Open a file for streaming access.
+    ///
+    /// Seeks to the file's start and returns a [`Take`] limited to its byte
+    /// range. The borrow ends when the [`Take`] is dropped.
+    pub fn read_file(&mut self, file: &Pfs0File) -> Result<Take<&mut R>> {
+        self.pfs0.read_file(file)
+    }
+
+    /// Open the file named `name` for streaming access.
+    ///
+    /// Returns [`Error::NotFound`] if there is no such file.
+    pub fn open(&mut self, name: &str) -> Result<Take<&mut R>> {
+        self.pfs0.open(name)
+    }
+
+    /// Iterate over all file entries.
+    pub fn files(&self) -> impl Iterator<Item = &Pfs0File> {
+        self.pfs0.files()
+    }
+
+    /// Iterate over the entries of one kind.
+    pub fn files_of_kind(&self, kind: EntryKind) -> impl Iterator<Item = &Pfs0File> {
+        self.files()
+            .filter(move |f| EntryKind::from_name(&f.name) == kind)
+    }
+
+    /// Title IDs of all titles in the package.
+    pub fn title_ids(&self) -> impl Iterator<Item = u64> {
+        self.metas.iter().map(|m| m.title_id)
+    }
+
+    /// CNMT of the title `title_id`.
+    pub fn meta(&self, title_id: u64) -> Option<&Cnmt> {
+        self.metas.iter().find(|m| m.title_id == title_id)
+    }
+
+    /// Entry holding the content of type `content_type` for `title_id`.
+    ///
+    /// Prefers the `.nca` entry and falls back to a `.ncz`. Returns
+    /// [`None`] if the title has no such content or its entry is absent.
+    pub fn content(&self, title_id: u64, content_type: ContentRecordType) -> Option<&Pfs0File> {
+        let record = self
+            .meta(title_id)?
+            .contents
+            .iter()
+            .find(|c| c.content_type == content_type)?;
+        let id = record.content_id_hex();
+        self.get_file(&format!("{id}.nca"))
+            .or_else(|| self.get_file(&format!("{id}.ncz")))
+    }
+
+    /// Program NCA of `title_id`.
+    pub fn program_nca(&self, title_id: u64) -> Option<&Pfs0File> {
+        self.content(title_id, ContentRecordType::Program)
+    }
+
+    /// Control NCA (NACP and icons) of `title_id`.
+    pub fn control_nca(&self, title_id: u64) -> Option<&Pfs0File> {
+        self.content(title_id, ContentRecordType::Control)
+    }
+
+    /// Open an NCA entry for decrypted access.
+    ///
+    /// If the NCA uses titlekey crypto, first register the package's
+    /// [`tickets`](Self::tickets) with [`KeySet::import_ticket`]. Does not
+    /// handle `.ncz` entries.
+    pub fn open_nca(&mut self, file: &Pfs0File, keys: &KeySet) -> Result<NcaReader<&mut R>> {
+        NcaReader::new(self.pfs0.read_file(file)?.into_inner(), keys)
+    }
+
+    /// Entries belonging to the title `title_id`, in package order.
+    ///
+    /// Returns an empty list if the package has no such title.
+    pub fn title_files(&self, title_id: u64) -> Vec<Pfs0File> {
+        let Some(i) = self.metas.iter().position(|m| m.title_id == title_id) else {
+            return Vec::new();
+        };
+        let meta_id = self.meta_files[i].name.trim_end_matches(".cnmt.nca");
+        let rights_prefix = format!("{title_id:016x}");
+        self.files()
+            .filter(|f| {
+                let stem = f.name.split('.').next().unwrap_or_default();
+                match EntryKind::from_name(&f.name) {
+                    EntryKind::Nca | EntryKind::Ncz => self.metas[i]
+                        .contents
+                        .iter()
+                        .any(|c| c.content_id_hex() == stem.to_ascii_lowercase()),
+                    EntryKind::MetaNca | EntryKind::CnmtXml => stem == meta_id,
+                    EntryKind::Ticket | EntryKind::Certificate => {
+                        stem.to_ascii_lowercase().starts_with(&rights_prefix)
+                    }
+                    EntryKind::Other => false,
+                }
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Write the title `title_id` to `w` as a standalone NSP.
+    ///
+    /// Returns the metadata of the written container, or
+    /// [`Error::NotFound`] if the package has no such title.
+    pub fn write_title<W: Write + Seek>(&mut self, title_id: u64, w: &mut W) -> Result<Pfs0> {
+        let files = self.title_files(title_id);
+        if files.is_empty() {
+            return Err(Error::NotFound {
+                name: format!("{title_id:016x}"),
+            });
+        }
+        let data_offset = self.pfs0.pfs0.data_offset;
+        let inner = RefCell::new(self.pfs0.get_mut());
+        let mut writer = Pfs0Writer::new();
+        for file in files {
+            let range = SharedRange::new(&inner, data_offset + file.offset, file.size);
+            writer.add_reader(file.name, range);
+        }
+        writer.write(w)
+    }
+
+    /// Write every title to its own NSP in `dir`, named
+    /// `<TitleId>_v<Version>.nsp`.
+    ///
+    /// Returns the paths written, in the order of [`metas`](Self::metas).
+    pub fn split(&mut self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        let titles: Vec<(u64, u32)> = self.metas.iter().map(|m| (m.title_id, m.version)).collect();
+        let mut paths = Vec::with_capacity(titles.len());
+        for (title_id, version) in titles {
+            let path = dir.join(format!("{title_id:016x}_v{version}.nsp"));
+            let mut out = BufWriter::new(File::create(&path)?);
+            self.write_title(title_id, &mut out)?;
+            out.flush()?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Borrow the underlying [`Pfs0Reader`].
+    pub fn pfs0(&mut self) -> &mut Pfs0Reader<R> {
+        &mut self.pfs0
+    }
+
+    /// Consume the reader, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.pfs0.into_inner()
+    }
+
+    /// Consume the reader, returning a stream over just `file`.
+    ///
+    /// Seeks within the returned [`SubReader`] are relative to the file, so
+    /// it can be passed to another parser without borrowing this reader.
+    pub fn into_file(self, file: &Pfs0File) -> SubReader<R> {
+        self.pfs0.into_file(file)
+    }
+}
+
+impl<R: ReadAt> NspReader<R> {
+    /// Open a file for reading through a shared reference.
+    ///
+    /// Unlike [`read_file`](Self::read_file), any number of files can be
+    /// open at once; see [`crate::read_at`].
+    pub fn read_file_at(&self, file: &Pfs0File) -> RangeReader<&R> {
+        self.pfs0.read_file_at(file)
+    }
+
+    /// Open an NCA entry for decrypted access through a shared reference.
+    ///
+    /// Like [`open_nca`](Self::open_nca), but several NCAs can be open at
+    /// once.
+    pub fn open_nca_at(
+        &self,
+        file: &Pfs0File,
+        keys: &KeySet,
+    ) -> Result<NcaReader<RangeReader<&R>>> {
+        NcaReader::new(self.read_file_at(file), keys)
+    }
+}
+
+impl<R: Read + Seek> Index<&str> for NspReader<R> {
+    type Output = Pfs0File;
+
+    /// Index by file name.
+    ///
+    /// # Panics
+    /// Panics if the file name does not exist in the package; use
+    /// [`try_get`](NspReader::try_get) to handle that case.
+    fn index(&self, index: &str) -> &Self::Output {
+        self.try_get(index).unwrap_or_else(|e| panic!("NSP: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::pfs0::tests::{build, set_size};
+
+    #[test]
+    fn oversized_ticket() {
+        let mut nsp = build(&[("0100000000000000.tik", &[0; 0x2C0])]);
+        set_size(&mut nsp, 0, u64::MAX);
+        assert!(matches!(
+            NspReader::new(Cursor::new(nsp), &KeySet::new()),
+            Err(Error::Parse(_))
+        ));
+    }
+}
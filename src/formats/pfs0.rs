@@ -27,17 +27,36 @@
 //! ## Notes
 //! * No directory support; no per-file hashing (contrast with HFS0).
 //! * The data section begins at `0x10 + FileCount×0x18 + StringTableSize`.
+//! * [`Pfs0Writer`] pads the string table so the data section starts on a
+//!   0x20-byte boundary, as Nintendo's tools do.
+//! * [`Pfs0Reader::extract_all`] refuses entry names that are not plain
+//!   file names, so a crafted archive cannot write outside the target.
+//! * [`Pfs0Slice`] parses an archive already in memory (e.g. mmapped),
+//!   borrowing names and file data from it instead of reading.
+//! * [`Pfs0Listing`] keeps the string table and borrows names from it, for
+//!   listing large archives without an allocation per entry.
 
-use std::io::{Read, Seek, SeekFrom, Take};
-use std::ops::Index;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Take, Write};
+use std::ops::{Index, Range};
+use std::path::{Component, Path, PathBuf};
 
-use crate::Result;
-use crate::utils::{bytesv, le_u32, le_u64, magic, null_string};
+use super::archive::{ArchiveEntry, ArchiveRead};
+use crate::error::Context;
+use crate::progress::{NoProgress, Progress, ProgressReader};
+use crate::read_at::{RangeReader, ReadAt};
+use crate::utils::{
+    EntrySource, SubReader, bytesa, le_u32, le_u64, magic, null_range, null_str, string_table,
+    subslice, table,
+};
+use crate::{Error, Result};
 
 /// Parsed PFS0 container (metadata only).
 ///
 /// File data is accessed via [`Pfs0Reader`].
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pfs0 {
     /// All file entries in declaration order.
     pub files: Vec<Pfs0File>,
@@ -48,6 +67,7 @@ pub struct Pfs0 {
 
 /// Metadata for a single file inside a PFS0.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pfs0File {
     /// File name decoded from the string table.
     pub name: String,
@@ -63,36 +83,145 @@ impl Pfs0 {
     /// The reader must be positioned at the `PFS0` magic.
     /// File contents are not read; use [`Pfs0Reader`] for data access.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
-        let base = r.stream_position()?;
-        magic(r, b"PFS0")?;
-
-        let file_count = le_u32(r)?;
-        let string_table_size = le_u32(r)?;
-        let _reserved = le_u32(r)?;
+        Pfs0Listing::parse(r).map(Self::from)
+    }
+}
 
-        let mut entries = Vec::with_capacity(file_count as usize);
-        for _ in 0..file_count {
-            let offset = le_u64(r)?;
-            let size = le_u64(r)?;
-            let name_offset = le_u32(r)?;
-            let _reserved = le_u32(r)?;
-            entries.push((offset, size, name_offset));
+impl From<Pfs0Listing> for Pfs0 {
+    fn from(listing: Pfs0Listing) -> Self {
+        Self {
+            files: listing.files().map(Pfs0ListingFile::into_owned).collect(),
+            data_offset: listing.data_offset,
         }
+    }
+}
 
-        let string_table = bytesv(r, string_table_size as usize)?;
+/// PFS0 entry list that keeps the raw string table.
+///
+/// Unlike [`Pfs0`], names are not allocated per entry: [`files`] borrows
+/// each one from the table, which cuts allocations when listing archives
+/// with many entries. Convert into a [`Pfs0`] with [`From`] to get owned
+/// entries.
+///
+/// [`files`]: Pfs0Listing::files
+#[derive(Debug, Clone)]
+pub struct Pfs0Listing {
+    entries: Vec<ListedFile>,
+    string_table: Vec<u8>,
+    data_offset: u64,
+}
 
-        let mut files = Vec::with_capacity(file_count as usize);
-        for (offset, size, name_offset) in entries {
-            let name = null_string(&string_table, name_offset as usize)?;
-            files.push(Pfs0File { name, offset, size });
+/// Entry of a [`Pfs0Listing`]; `name` is a range of the string table.
+#[derive(Debug, Clone)]
+struct ListedFile {
+    name: Range<usize>,
+    offset: u64,
+    size: u64,
+}
+
+/// A single file of a [`Pfs0Listing`].
+#[derive(Debug, Clone)]
+pub struct Pfs0ListingFile<'a> {
+    /// File name, borrowed from the string table unless it is not valid
+    /// UTF-8.
+    pub name: Cow<'a, str>,
+    /// Offset relative to the PFS0 data section.
+    pub offset: u64,
+    /// File size in bytes.
+    pub size: u64,
+}
+
+impl Pfs0ListingFile<'_> {
+    /// Copy into an owned [`Pfs0File`].
+    pub fn into_owned(self) -> Pfs0File {
+        Pfs0File {
+            name: self.name.into_owned(),
+            offset: self.offset,
+            size: self.size,
         }
+    }
+}
+
+impl Pfs0Listing {
+    /// Parse the entries of a PFS0 container from `r`.
+    ///
+    /// The reader must be positioned at the `PFS0` magic. Fails like
+    /// [`Pfs0::parse`].
+    pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
+        let header = || "header".to_string();
+        magic(r, b"PFS0").at_field("PFS0", base, header, "magic")?;
+
+        // Read each table in one go and decode it from memory; a read per
+        // field is a syscall each on an unbuffered `File`.
+        let head = bytesa::<0xC>(r).at("PFS0", base, header)?;
+        let h = &mut &head[..];
+        let file_count = le_u32(h)?;
+        let string_table_size = le_u32(h)?;
 
         // data_offset is absolute within the stream.
         let header_size = 0x10u64;
         let entries_size = file_count as u64 * 0x18;
+        let entry_table =
+            table(r, entries_size).at("PFS0", base + header_size, || "entry table".into())?;
+        let string_table = table(r, string_table_size as u64).at(
+            "PFS0",
+            base + header_size + entries_size,
+            || "string table".into(),
+        )?;
+
+        let mut entries = Vec::with_capacity(file_count as usize);
+        for (i, entry) in entry_table.chunks_exact(0x18).enumerate() {
+            let e = &mut &entry[..];
+            let offset = le_u64(e)?;
+            let size = le_u64(e)?;
+            let name_offset = le_u32(e)?;
+            let name = null_range(&string_table, name_offset as usize).at_field(
+                "PFS0",
+                base + header_size + i as u64 * 0x18,
+                || format!("entry {i}"),
+                "NameOffset",
+            )?;
+            entries.push(ListedFile { name, offset, size });
+        }
+
         let data_offset = base + header_size + entries_size + string_table_size as u64;
 
-        Ok(Self { files, data_offset })
+        Ok(Self {
+            entries,
+            string_table,
+            data_offset,
+        })
+    }
+
+    /// Number of files.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `true` if the archive has no files.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over all file entries, borrowing their names.
+    pub fn files(&self) -> impl Iterator<Item = Pfs0ListingFile<'_>> {
+        self.entries.iter().map(|e| Pfs0ListingFile {
+            name: String::from_utf8_lossy(&self.string_table[e.name.clone()]),
+            offset: e.offset,
+            size: e.size,
+        })
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<Pfs0ListingFile<'_>> {
+        self.files().find(|f| f.name == name)
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<Pfs0ListingFile<'_>> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
     }
 }
 
@@ -121,6 +250,12 @@ impl<R: Read + Seek> Pfs0Reader<R> {
         self.files().find(|f| f.name == name)
     }
 
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&Pfs0File> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
     /// Open a file for streaming access.
     ///
     /// Seeks to the file's start and returns a [`Take`] limited to its byte
@@ -131,6 +266,30 @@ impl<R: Read + Seek> Pfs0Reader<R> {
         Ok(self.inner.by_ref().take(file.size))
     }
 
+    /// Open the file named `name` for streaming access.
+    ///
+    /// Returns [`Error::NotFound`] if there is no such file.
+    pub fn open(&mut self, name: &str) -> Result<Take<&mut R>> {
+        let file = self.try_get(name)?.clone();
+        self.read_file(&file)
+    }
+
+    /// Same as [`open`](Self::open).
+    pub fn read_file_by_name(&mut self, name: &str) -> Result<Take<&mut R>> {
+        self.open(name)
+    }
+
+    /// Read a whole file into memory.
+    ///
+    /// The buffer grows as data arrives rather than being sized from the
+    /// entry, so a bogus size costs no more memory than the data actually
+    /// present.
+    pub fn read_file_to_vec(&mut self, file: &Pfs0File) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.read_file(file)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
     /// Iterate over all file entries.
     pub fn files(&self) -> impl Iterator<Item = &Pfs0File> {
         self.pfs0.files.iter()
@@ -140,6 +299,117 @@ impl<R: Read + Seek> Pfs0Reader<R> {
     pub fn into_inner(self) -> R {
         self.inner
     }
+
+    /// Consume the reader, returning a stream over just `file`.
+    ///
+    /// Seeks within the returned [`SubReader`] are relative to the file, so
+    /// it can be passed to another parser without borrowing this reader.
+    pub fn into_file(self, file: &Pfs0File) -> SubReader<R> {
+        SubReader::new(self.inner, self.pfs0.data_offset + file.offset, file.size)
+    }
+
+    /// Borrow the inner reader.
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Extract every file into `dir`, replacing existing files.
+    ///
+    /// Returns the number of files written.
+    pub fn extract_all(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        self.extract_filtered(dir, |_| true)
+    }
+
+    /// Extract the files for which `filter` returns `true` into `dir`,
+    /// replacing existing files.
+    ///
+    /// Returns the number of files written. Returns [`Error::Parse`] for an
+    /// entry whose name is not a plain file name (empty, containing a path
+    /// separator, or `.`/`..`); nothing after it is extracted.
+    pub fn extract_filtered(
+        &mut self,
+        dir: impl AsRef<Path>,
+        filter: impl FnMut(&Pfs0File) -> bool,
+    ) -> Result<usize> {
+        self.extract_with_progress(dir, filter, &mut NoProgress)
+    }
+
+    /// Like [`extract_filtered`](Self::extract_filtered), reporting the
+    /// bytes written against the total size of the selected files.
+    pub fn extract_with_progress(
+        &mut self,
+        dir: impl AsRef<Path>,
+        mut filter: impl FnMut(&Pfs0File) -> bool,
+        progress: &mut dyn Progress,
+    ) -> Result<usize> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let files: Vec<Pfs0File> = self.files().filter(|f| filter(f)).cloned().collect();
+        let total = files.iter().map(|f| f.size).sum();
+        let mut done = 0;
+        for file in &files {
+            let path = dir.join(entry_name(&file.name)?);
+            let mut r = ProgressReader::new(
+                self.read_file(file)?,
+                &mut *progress,
+                &file.name,
+                &mut done,
+                total,
+            );
+            io::copy(&mut r, &mut File::create(path)?)?;
+        }
+        Ok(files.len())
+    }
+}
+
+impl<R: ReadAt> Pfs0Reader<R> {
+    /// Open a file for reading through a shared reference.
+    ///
+    /// Unlike [`read_file`](Self::read_file), any number of files can be
+    /// open at once; see [`crate::read_at`].
+    pub fn read_file_at(&self, file: &Pfs0File) -> RangeReader<&R> {
+        RangeReader::new(&self.inner, self.pfs0.data_offset + file.offset, file.size)
+    }
+}
+
+impl ArchiveEntry for Pfs0File {
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<R: Read + Seek> ArchiveRead for Pfs0Reader<R> {
+    type Entry = Pfs0File;
+    type EntryReader<'a>
+        = Take<&'a mut R>
+    where
+        Self: 'a;
+
+    fn entries(&self) -> impl Iterator<Item = &Pfs0File> {
+        self.files()
+    }
+
+    fn get_entry(&self, name: &str) -> Option<&Pfs0File> {
+        self.get_file(name)
+    }
+
+    fn open_entry(&mut self, entry: &Pfs0File) -> Result<Take<&mut R>> {
+        self.read_file(entry)
+    }
+}
+
+/// Check that a PFS0 entry name is a single path component, so it cannot
+/// leave the output directory.
+fn entry_name(name: &str) -> Result<&str> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\\']) => Ok(name),
+        _ => Err(Error::Parse("PFS0 entry name escapes the output directory")),
+    }
 }
 
 impl<R: Read + Seek> Index<&str> for Pfs0Reader<R> {
@@ -148,8 +418,233 @@ impl<R: Read + Seek> Index<&str> for Pfs0Reader<R> {
     /// Index by file name.
     ///
     /// # Panics
-    /// Panics if the file name does not exist in the archive.
+    /// Panics if the file name does not exist in the archive; use
+    /// [`try_get`](Pfs0Reader::try_get) to handle that case.
     fn index(&self, index: &str) -> &Self::Output {
-        self.get_file(index).expect("no such file in PFS0")
+        self.try_get(index).unwrap_or_else(|e| panic!("PFS0: {e}"))
+    }
+}
+
+/// PFS0 container parsed from a byte slice.
+///
+/// Names and file data borrow from the input, so nothing beyond the entry
+/// list is copied.
+#[derive(Debug, Clone)]
+pub struct Pfs0Slice<'a> {
+    /// All file entries in declaration order.
+    pub files: Vec<Pfs0SliceFile<'a>>,
+}
+
+/// A single file inside a [`Pfs0Slice`].
+#[derive(Debug, Clone)]
+pub struct Pfs0SliceFile<'a> {
+    /// File name decoded from the string table.
+    pub name: Cow<'a, str>,
+    /// Offset relative to the PFS0 data section.
+    pub offset: u64,
+    /// File contents.
+    pub data: &'a [u8],
+}
+
+impl<'a> Pfs0Slice<'a> {
+    /// Parse a PFS0 container from `data`, which must start at the `PFS0`
+    /// magic.
+    ///
+    /// Returns [`Error::InvalidRange`] if a file extends past the end of
+    /// `data`.
+    pub fn parse_bytes(data: &'a [u8]) -> Result<Self> {
+        let r = &mut &data[..];
+        magic(r, b"PFS0")?;
+
+        let file_count = le_u32(r)?;
+        let string_table_size = le_u32(r)?;
+        let _reserved = le_u32(r)?;
+
+        let entries_size = file_count as u64 * 0x18;
+        let string_table = subslice(data, 0x10 + entries_size, string_table_size as u64)?;
+        let data_offset = 0x10 + entries_size + string_table_size as u64;
+
+        let mut files = Vec::with_capacity(file_count as usize);
+        for _ in 0..file_count {
+            let offset = le_u64(r)?;
+            let size = le_u64(r)?;
+            let name_offset = le_u32(r)?;
+            let _reserved = le_u32(r)?;
+            let start = data_offset.checked_add(offset).ok_or(Error::InvalidRange)?;
+            files.push(Pfs0SliceFile {
+                name: null_str(string_table, name_offset as usize)?,
+                offset,
+                data: subslice(data, start, size)?,
+            });
+        }
+
+        Ok(Self { files })
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<&Pfs0SliceFile<'a>> {
+        self.files().find(|f| f.name == name)
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&Pfs0SliceFile<'a>> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    /// Contents of the file named `name`. Returns [`None`] if not found.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.get_file(name).map(|f| f.data)
+    }
+
+    /// Contents of the file named `name`. Returns [`Error::NotFound`] if
+    /// not found.
+    pub fn open(&self, name: &str) -> Result<&'a [u8]> {
+        self.try_get(name).map(|f| f.data)
+    }
+
+    /// Iterate over all file entries.
+    pub fn files(&self) -> impl Iterator<Item = &Pfs0SliceFile<'a>> {
+        self.files.iter()
+    }
+}
+
+impl<'a> Index<&str> for Pfs0Slice<'a> {
+    type Output = Pfs0SliceFile<'a>;
+
+    /// Index by file name.
+    ///
+    /// # Panics
+    /// Panics if the file name does not exist in the archive; use
+    /// [`try_get`](Pfs0Slice::try_get) to handle that case.
+    fn index(&self, index: &str) -> &Self::Output {
+        self.try_get(index).unwrap_or_else(|e| panic!("PFS0: {e}"))
+    }
+}
+
+/// Builder that writes a PFS0 container (e.g. an NSP).
+///
+/// Entries are written in the order they are added. Sizes need not be known
+/// up front: the header is reserved, the data streamed, and the header
+/// filled in afterwards.
+#[derive(Default)]
+pub struct Pfs0Writer<'a> {
+    entries: Vec<(String, EntrySource<'a>)>,
+}
+
+impl<'a> Pfs0Writer<'a> {
+    /// Create an empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry whose data is read from `reader`.
+    pub fn add_reader(&mut self, name: impl Into<String>, reader: impl Read + 'a) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Reader(Box::new(reader))));
+        self
+    }
+
+    /// Add an entry whose data is the file at `path`.
+    ///
+    /// The file is opened when [`write`](Self::write) reaches it.
+    pub fn add_path(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Path(path.into())));
+        self
+    }
+
+    /// Write the container to `w` at its current position.
+    ///
+    /// Returns the metadata of the written container. Returns
+    /// [`Error::InvalidRange`] if the header does not fit its 32-bit fields.
+    pub fn write<W: Write + Seek>(self, w: &mut W) -> Result<Pfs0> {
+        let base = w.stream_position()?;
+        let (mut strings, name_offsets) =
+            string_table(self.entries.iter().map(|(n, _)| n.as_str()));
+        let entries_end = 0x10 + self.entries.len() * 0x18;
+        strings.resize(
+            (entries_end + strings.len()).next_multiple_of(0x20) - entries_end,
+            0,
+        );
+        let file_count = u32::try_from(self.entries.len()).map_err(|_| Error::InvalidRange)?;
+        let string_table_size = u32::try_from(strings.len()).map_err(|_| Error::InvalidRange)?;
+
+        let data_offset = base + (entries_end + strings.len()) as u64;
+        w.seek(SeekFrom::Start(data_offset))?;
+        let mut files = Vec::with_capacity(self.entries.len());
+        let mut offset = 0;
+        for (name, source) in self.entries {
+            let size = io::copy(&mut source.open()?, w)?;
+            files.push(Pfs0File { name, offset, size });
+            offset += size;
+        }
+        let end = w.stream_position()?;
+
+        w.seek(SeekFrom::Start(base))?;
+        w.write_all(b"PFS0")?;
+        w.write_all(&file_count.to_le_bytes())?;
+        w.write_all(&string_table_size.to_le_bytes())?;
+        w.write_all(&[0; 4])?;
+        for (file, name_offset) in files.iter().zip(name_offsets) {
+            w.write_all(&file.offset.to_le_bytes())?;
+            w.write_all(&file.size.to_le_bytes())?;
+            w.write_all(&name_offset.to_le_bytes())?;
+            w.write_all(&[0; 4])?;
+        }
+        w.write_all(&strings)?;
+        w.seek(SeekFrom::Start(end))?;
+
+        Ok(Pfs0 { files, data_offset })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A PFS0 holding `entries`, written with [`Pfs0Writer`].
+    pub(crate) fn build(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = Pfs0Writer::new();
+        for (name, data) in entries {
+            writer.add_reader(*name, *data);
+        }
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+        out.into_inner()
+    }
+
+    /// Overwrite the size field of entry `index`.
+    pub(crate) fn set_size(pfs0: &mut [u8], index: usize, size: u64) {
+        let at = 0x10 + index * 0x18 + 8;
+        pfs0[at..at + 8].copy_from_slice(&size.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip() {
+        let big: Vec<u8> = (0..0x1234).map(|i| i as u8).collect();
+        let pfs0 = build(&[("a.nca", &big), ("empty", b""), ("b.tik", b"ticket")]);
+        let mut reader = Pfs0Reader::new(Cursor::new(pfs0)).unwrap();
+        let names: Vec<&str> = reader.files().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["a.nca", "empty", "b.tik"]);
+        for (name, data) in [("a.nca", &big[..]), ("empty", b""), ("b.tik", b"ticket")] {
+            let file = reader.try_get(name).unwrap().clone();
+            assert_eq!(reader.read_file_to_vec(&file).unwrap(), data);
+        }
+        assert!(matches!(
+            reader.try_get("missing"),
+            Err(Error::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn oversized_entry_is_not_preallocated() {
+        let mut pfs0 = build(&[("a.bin", b"data")]);
+        set_size(&mut pfs0, 0, u64::MAX);
+        let mut reader = Pfs0Reader::new(Cursor::new(pfs0)).unwrap();
+        let file = reader.try_get("a.bin").unwrap().clone();
+        assert_eq!(reader.read_file_to_vec(&file).unwrap(), b"data");
     }
 }
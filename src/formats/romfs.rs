@@ -26,6 +26,7 @@
 //! [0x50] Reserved                    (4 bytes)
 //! [0x54] Reserved                    (4 bytes)
 //! [0x58] OptionalInfoSize            (u32 LE)
+//! [0xC0] MasterHash                  (MasterHashSize bytes, hashes Level 1)
 //! ```
 //!
 //! ## Level 3 Layout (at Level3LogicalOffset within the section)
@@ -76,12 +77,17 @@
 
 use std::io::{Cursor, Read, Seek, SeekFrom, Take};
 
-use crate::utils::{bytesv, le_u32, le_u64, magic};
+use super::archive::{ArchiveEntry, ArchiveRead};
+use crate::error::Context;
+use crate::utils::{SubReader, bytesv, le_u32, le_u64, magic};
 use crate::{Error, Result};
 
 /// Sentinel value meaning "no entry" in all offset fields.
 pub const ROMFS_ENTRY_EMPTY: u32 = 0xFFFF_FFFF;
 
+/// Offset of the master hash within `FsHeader.hash_data` of a RomFS section.
+pub const IVFC_MASTER_HASH_OFFSET: usize = 0xC0;
+
 /// Expected size of the Level 3 header.
 const LEVEL3_HEADER_SIZE: u32 = 0x28;
 
@@ -89,8 +95,10 @@ const LEVEL3_HEADER_SIZE: u32 = 0x28;
 ///
 /// This is embedded in `FsHeader.hash_data` for sections whose `hash_type` is
 /// `HierarchicalIntegrity`. Only the Level 3 offset and block size are needed
-/// for parsing; the hash levels are used for verification (not implemented here).
+/// for parsing; the hash levels are checked by
+/// [`verify_section_hashes`](crate::formats::nca::verify_section_hashes).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IvfcHeader {
     /// Master hash size in bytes.
     pub master_hash_size: u32,
@@ -174,6 +182,7 @@ impl IvfcHeader {
 
 /// Parsed Level 3 header - the root of the actual RomFS directory tree.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Level3Header {
     /// Offset of the directory hash table relative to Level 3 start.
     pub dir_hash_table_offset: u32,
@@ -197,6 +206,7 @@ pub struct Level3Header {
 
 /// A directory entry in the RomFS tree.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomFsDir {
     /// Directory name. Empty string for the root directory.
     pub name: String,
@@ -213,6 +223,7 @@ pub struct RomFsDir {
 
 /// A file entry in the RomFS tree.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomFsFile {
     /// File name (just the base name, not the full path).
     pub name: String,
@@ -246,6 +257,7 @@ pub struct RomFsFile {
 ///
 /// Or use the [`RomFsReader`] wrapper for a more ergonomic API.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RomFs {
     /// All directories, root at index 0.
     pub dirs: Vec<RomFsDir>,
@@ -265,32 +277,44 @@ impl RomFs {
         let level3_base = r.stream_position()?;
 
         // Level 3 header (0x28 bytes)
-        let header_length = le_u32(r)?;
+        let header = || "header".to_string();
+        let field =
+            |r: &mut R, name: &'static str| le_u32(r).at_field("RomFS", level3_base, header, name);
+        let header_length = field(r, "HeaderSize")?;
         if header_length != LEVEL3_HEADER_SIZE {
-            return Err(Error::Parse("unexpected RomFS Level 3 header size"));
+            return Err(Error::Parse("unexpected RomFS Level 3 header size")).at_field(
+                "RomFS",
+                level3_base,
+                header,
+                "HeaderSize",
+            );
         }
-        let dir_hash_table_offset = le_u32(r)?;
-        let dir_hash_table_size = le_u32(r)?;
-        let dir_meta_table_offset = le_u32(r)?;
-        let dir_meta_table_size = le_u32(r)?;
-        let file_hash_table_offset = le_u32(r)?;
-        let file_hash_table_size = le_u32(r)?;
-        let file_meta_table_offset = le_u32(r)?;
-        let file_meta_table_size = le_u32(r)?;
-        let file_data_offset = le_u32(r)?;
+        let dir_hash_table_offset = field(r, "DirHashTableOffset")?;
+        let dir_hash_table_size = field(r, "DirHashTableSize")?;
+        let dir_meta_table_offset = field(r, "DirMetaTableOffset")?;
+        let dir_meta_table_size = field(r, "DirMetaTableSize")?;
+        let file_hash_table_offset = field(r, "FileHashTableOffset")?;
+        let file_hash_table_size = field(r, "FileHashTableSize")?;
+        let file_meta_table_offset = field(r, "FileMetaTableOffset")?;
+        let file_meta_table_size = field(r, "FileMetaTableSize")?;
+        let file_data_offset = field(r, "FileDataOffset")?;
 
         let _ = (dir_hash_table_offset, dir_hash_table_size);
         let _ = (file_hash_table_offset, file_hash_table_size);
 
         let file_data_base = level3_base + file_data_offset as u64;
 
-        r.seek(SeekFrom::Start(level3_base + dir_meta_table_offset as u64))?;
-        let dir_table = bytesv(r, dir_meta_table_size as usize)?;
+        let dir_base = level3_base + dir_meta_table_offset as u64;
+        r.seek(SeekFrom::Start(dir_base))?;
+        let dir_table = bytesv(r, dir_meta_table_size as usize)
+            .at("RomFS", dir_base, || "directory table".into())?;
 
-        r.seek(SeekFrom::Start(level3_base + file_meta_table_offset as u64))?;
-        let file_table = bytesv(r, file_meta_table_size as usize)?;
+        let file_base = level3_base + file_meta_table_offset as u64;
+        r.seek(SeekFrom::Start(file_base))?;
+        let file_table = bytesv(r, file_meta_table_size as usize)
+            .at("RomFS", file_base, || "file table".into())?;
 
-        let (dirs, files) = build_tree(&dir_table, &file_table)?;
+        let (dirs, files) = build_tree(&dir_table, dir_base, &file_table, file_base)?;
 
         Ok(Self {
             dirs,
@@ -321,7 +345,14 @@ impl RomFs {
 /// Build the full directory and file trees from the raw metadata tables.
 ///
 /// Returns `(dirs, files)` where `dirs[0]` is always the root directory.
-fn build_tree(dir_table: &[u8], file_table: &[u8]) -> Result<(Vec<RomFsDir>, Vec<RomFsFile>)> {
+/// `dir_base` and `file_base` are the absolute stream offsets of the two
+/// tables, for error context.
+fn build_tree(
+    dir_table: &[u8],
+    dir_base: u64,
+    file_table: &[u8],
+    file_base: u64,
+) -> Result<(Vec<RomFsDir>, Vec<RomFsFile>)> {
     // First pass: parse every directory entry from the binary table.
     // We collect them in table-offset order; the root is always at offset 0.
     let mut raw_dirs: Vec<(u32, RawDirEntry)> = Vec::new(); // (meta_offset, entry)
@@ -331,7 +362,10 @@ fn build_tree(dir_table: &[u8], file_table: &[u8]) -> Result<(Vec<RomFsDir>, Vec
             if pos + 0x18 > dir_table.len() {
                 break;
             }
-            let entry = parse_raw_dir(&dir_table[pos..])?;
+            let entry =
+                parse_raw_dir(&dir_table[pos..]).at("RomFS", dir_base + pos as u64, || {
+                    format!("directory entry {}", raw_dirs.len())
+                })?;
             let name_len = entry.name_length as usize;
             raw_dirs.push((pos as u32, entry));
             // Advance past the fixed fields (0x18) + name (aligned to 4 bytes).
@@ -353,10 +387,16 @@ fn build_tree(dir_table: &[u8], file_table: &[u8]) -> Result<(Vec<RomFsDir>, Vec
     // appear earlier in the table (the format is built recursively from root),
     // a single forward pass is sufficient.
     for i in 0..raw_dirs.len() {
-        let (_, ref entry) = raw_dirs[i];
+        let (off, ref entry) = raw_dirs[i];
         let parent_idx = *dir_idx_of
             .get(&entry.parent_offset)
-            .ok_or(Error::InvalidRange)?;
+            .ok_or(Error::InvalidRange)
+            .at_field(
+                "RomFS",
+                dir_base + off as u64,
+                || format!("directory entry {i}"),
+                "ParentOffset",
+            )?;
         let path = if i == 0 {
             // Root directory.
             String::new()
@@ -375,7 +415,10 @@ fn build_tree(dir_table: &[u8], file_table: &[u8]) -> Result<(Vec<RomFsDir>, Vec
             if pos + 0x20 > file_table.len() {
                 break;
             }
-            let entry = parse_raw_file(&file_table[pos..])?;
+            let entry =
+                parse_raw_file(&file_table[pos..]).at("RomFS", file_base + pos as u64, || {
+                    format!("file entry {}", raw_files.len())
+                })?;
             let name_len = entry.name_length as usize;
             raw_files.push((pos as u32, entry));
             let _ = raw_files.last().unwrap(); // suppress unused warning
@@ -567,18 +610,25 @@ impl<R: Read + Seek> RomFsReader<R> {
         Ok(self.inner.by_ref().take(file.data_size))
     }
 
+    /// Look up a file by its absolute path. Returns [`Error::NotFound`] if
+    /// not found.
+    pub fn try_get(&self, path: &str) -> Result<&RomFsFile> {
+        self.romfs
+            .get_file(path)
+            .ok_or_else(|| Error::NotFound { name: path.into() })
+    }
+
     /// Open a file by path for streaming access.
     ///
-    /// Returns [`Error::InvalidRange`] if the path does not exist.
+    /// Returns [`Error::NotFound`] if the path does not exist.
+    pub fn open(&mut self, path: &str) -> Result<Take<&mut R>> {
+        let file = self.try_get(path)?.clone();
+        self.read_file(&file)
+    }
+
+    /// Same as [`open`](Self::open).
     pub fn read_file_by_path(&mut self, path: &str) -> Result<Take<&mut R>> {
-        // Resolve the file first before borrowing self.inner.
-        let (abs, size) = self
-            .romfs
-            .get_file(path)
-            .map(|f| (self.romfs.file_data_base + f.data_offset, f.data_size))
-            .ok_or(Error::InvalidRange)?;
-        self.inner.seek(SeekFrom::Start(abs))?;
-        Ok(self.inner.by_ref().take(size))
+        self.open(path)
     }
 
     /// Iterate over all files.
@@ -595,4 +645,46 @@ impl<R: Read + Seek> RomFsReader<R> {
     pub fn into_inner(self) -> R {
         self.inner
     }
+
+    /// Consume the reader, returning a stream over just `file`.
+    ///
+    /// Seeks within the returned [`SubReader`] are relative to the file, so
+    /// it can be passed to another parser without borrowing this reader.
+    pub fn into_file(self, file: &RomFsFile) -> SubReader<R> {
+        SubReader::new(
+            self.inner,
+            self.romfs.file_data_base + file.data_offset,
+            file.data_size,
+        )
+    }
+}
+
+impl ArchiveEntry for RomFsFile {
+    fn name(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+
+    fn size(&self) -> u64 {
+        self.data_size
+    }
+}
+
+impl<R: Read + Seek> ArchiveRead for RomFsReader<R> {
+    type Entry = RomFsFile;
+    type EntryReader<'a>
+        = Take<&'a mut R>
+    where
+        Self: 'a;
+
+    fn entries(&self) -> impl Iterator<Item = &RomFsFile> {
+        self.files()
+    }
+
+    fn get_entry(&self, name: &str) -> Option<&RomFsFile> {
+        self.romfs.get_file(name)
+    }
+
+    fn open_entry(&mut self, entry: &RomFsFile) -> Result<Take<&mut R>> {
+        self.read_file(entry)
+    }
 }
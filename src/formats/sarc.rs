@@ -12,16 +12,24 @@
 //! ```
 //!
 //! ## Endianness
-//! Determined by BOM: `0xFEFF` = Big Endian, `0xFFFE` = Little Endian.
+//! Determined by the BOM bytes: `FE FF` = Big Endian (Wii U), `FF FE` =
+//! Little Endian (Switch, 3DS). [`SarcReader::convert_endianness`] rewrites
+//! an archive in the other byte order.
+//!
+//! ## In-memory archives
+//! [`SarcSlice`] parses an archive already in memory (e.g. mmapped, or just
+//! decompressed), borrowing names and file data from it instead of reading.
+//! [`SarcListing`] reads only the entries and keeps the name table,
+//! borrowing names from it instead of allocating one per entry.
 //!
 //! ## SARC Header (0x14 bytes)
 //! ```text
 //! [0x00] Magic "SARC"       (4 bytes)
-//! [0x04] HeaderSize (0x14)  (u16 LE)
-//! [0x06] BOM                (u16 LE)
+//! [0x04] HeaderSize (0x14)  (u16, endian per BOM)
+//! [0x06] BOM                (2 bytes)
 //! [0x08] TotalFileSize      (u32, endian per BOM)
 //! [0x0C] DataOffset         (u32, endian per BOM)
-//! [0x10] Version (0x0100)   (u16 LE)
+//! [0x10] Version (0x0100)   (u16, endian per BOM)
 //! [0x12] Padding
 //! ```
 //!
@@ -41,7 +49,8 @@
 //! [0x08] DataStart              (u32, endian per BOM) - relative to data section
 //! [0x0C] DataEnd                (u32, endian per BOM)
 //! ```
-//! Entries are sorted by hash; runtime uses binary search.
+//! Entries are sorted by hash; runtime uses binary search. `AA` counts up
+//! from 1 among entries sharing a hash.
 //!
 //! ## SFNT Header (0x08 bytes)
 //! ```text
@@ -51,6 +60,11 @@
 //! [0x08] Null-terminated filenames, 4-byte aligned
 //! ```
 //!
+//! ## Data alignment
+//! Some file types must start on a larger boundary than others (fonts and
+//! textures are uploaded to the GPU in place). [`SarcWriter`] picks each
+//! file's alignment from its extension or magic; see [`alignment_for`].
+//!
 //! ## Filename Hash
 //! Each byte is sign-extended as i8 before accumulating:
 //! ```rust
@@ -63,16 +77,28 @@
 //! }
 //! ```
 
-use std::io::{Read, Seek, SeekFrom, Take};
-use std::ops::Index;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Take, Write};
+use std::ops::{Index, Range};
+use std::path::{Component, Path, PathBuf};
 
-use crate::utils::{end_u16, end_u32, le_u16, magic, read_null_string};
+use super::archive::{ArchiveEntry, ArchiveRead};
+use crate::error::Context;
+use crate::progress::{NoProgress, Progress, ProgressReader};
+use crate::read_at::{RangeReader, ReadAt};
+use crate::utils::{
+    EntrySource, SubReader, bytesa, bytesv, end_u16, end_u32, le_u16, magic, null_range, null_str,
+    subslice, table,
+};
 use crate::{Error, Result};
 
 /// Parsed SARC archive (metadata only).
 ///
 /// File data is accessed via [`SarcReader`].
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sarc {
     /// All file entries.
     pub files: Vec<SarcFile>,
@@ -82,17 +108,31 @@ pub struct Sarc {
     pub version: u16,
     /// Hash multiplier from the SFAT header (always 101 = 0x65).
     pub hash_multiplier: u32,
+    /// Hashes shared by more than one entry, in ascending order.
+    ///
+    /// Colliding entries remain reachable by name through
+    /// [`SarcReader::get_file`], and all of them through
+    /// [`SarcReader::get_files_by_hash`].
+    pub collisions: Vec<u32>,
     /// Absolute stream offset where file data begins.
     pub(crate) data_offset: u64,
+    /// Whether `files` is sorted by hash, allowing binary search.
+    pub(crate) sorted: bool,
+    /// Absolute stream offset of the `SARC` magic.
+    pub(crate) base: u64,
 }
 
 /// A single file entry inside a SARC archive.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SarcFile {
     /// Filename ([`None`] if the archive has no name table entry for this file).
     pub name: Option<String>,
     /// CRC hash of the filename.
     pub hash: u32,
+    /// Position among entries sharing `hash`, counting from 1 (the `AA`
+    /// byte of the name attributes); 0 for unnamed entries.
+    pub collision_index: u8,
     /// Start byte offset within the SARC data section.
     pub data_start: u32,
     /// End byte offset within the SARC data section (exclusive).
@@ -112,92 +152,298 @@ impl Sarc {
     /// The reader must be positioned the `SARC` magic.
     /// File contents are not read; use [`SarcReader`] for data access.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
-        let base = r.stream_position()?;
-        magic(r, b"SARC")?;
+        SarcListing::parse(r).map(Self::from)
+    }
 
-        let header_size = le_u16(r)?;
-        if header_size != 0x14 {
-            return Err(Error::Parse("unexpected SARC header size"));
+    /// All entries whose name hash is `hash`.
+    ///
+    /// Binary search over the SFAT order; falls back to a linear scan for
+    /// archives whose entries are not sorted.
+    pub(crate) fn files_with_hash(&self, hash: u32) -> impl Iterator<Item = &SarcFile> {
+        let (start, end) = if self.sorted {
+            let start = self.files.partition_point(|f| f.hash < hash);
+            let end = start + self.files[start..].partition_point(|f| f.hash == hash);
+            (start, end)
+        } else {
+            (0, self.files.len())
+        };
+        self.files[start..end]
+            .iter()
+            .filter(move |f| f.hash == hash)
+    }
+}
+
+/// SARC entry list that keeps the raw name table.
+///
+/// Unlike [`Sarc`], names are not allocated per entry: [`files`] borrows
+/// each one from the table. Convert into a [`Sarc`] with [`From`] to get
+/// owned entries.
+///
+/// [`files`]: SarcListing::files
+#[derive(Debug, Clone)]
+pub struct SarcListing {
+    /// Whether the archive uses little-endian encoding.
+    pub le: bool,
+    /// Format version from the SARC header (normally 0x0100).
+    pub version: u16,
+    /// Hash multiplier from the SFAT header (always 101 = 0x65).
+    pub hash_multiplier: u32,
+    entries: Vec<ListedFile>,
+    name_table: Vec<u8>,
+    data_offset: u64,
+    sorted: bool,
+    base: u64,
+}
+
+/// Entry of a [`SarcListing`]; `name` is a range of the name table.
+#[derive(Debug, Clone)]
+struct ListedFile {
+    name: Option<Range<usize>>,
+    hash: u32,
+    collision_index: u8,
+    data_start: u32,
+    data_end: u32,
+}
+
+/// A single file of a [`SarcListing`].
+#[derive(Debug, Clone)]
+pub struct SarcListingFile<'a> {
+    /// Filename, borrowed from the name table unless it is not valid UTF-8
+    /// ([`None`] if the archive has no name table entry for this file).
+    pub name: Option<Cow<'a, str>>,
+    /// CRC hash of the filename.
+    pub hash: u32,
+    /// Position among entries sharing `hash`, counting from 1; 0 for
+    /// unnamed entries.
+    pub collision_index: u8,
+    /// Start byte offset within the SARC data section.
+    pub data_start: u32,
+    /// End byte offset within the SARC data section (exclusive).
+    pub data_end: u32,
+}
+
+impl SarcListingFile<'_> {
+    /// Size of this file's data in bytes.
+    pub fn size(&self) -> u64 {
+        self.data_end.saturating_sub(self.data_start) as u64
+    }
+
+    /// Copy into an owned [`SarcFile`].
+    pub fn into_owned(self) -> SarcFile {
+        SarcFile {
+            name: self.name.map(Cow::into_owned),
+            hash: self.hash,
+            collision_index: self.collision_index,
+            data_start: self.data_start,
+            data_end: self.data_end,
         }
+    }
+}
 
-        // BOM is always written LE regardless of archive endianness.
-        let bom = le_u16(r)?;
-        let le = match bom {
-            0xFFFE => true,
-            0xFEFF => false,
-            _ => return Err(Error::Parse("invalid SARC BOM")),
+impl SarcListing {
+    /// Parse the entries of a SARC archive from `r`.
+    ///
+    /// The reader must be positioned at the `SARC` magic. Fails like
+    /// [`Sarc::parse`].
+    pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let base = r.stream_position()?;
+        let header = || "SARC header".to_string();
+        magic(r, b"SARC").at_field("SARC", base, header, "magic")?;
+
+        // Read each header and the SFAT in one go and decode them from
+        // memory; a read per field is a syscall each on an unbuffered `File`.
+        let head = bytesa::<0x10>(r).at("SARC", base, header)?;
+        let h = &mut &head[..];
+
+        // The header size precedes the BOM, so read it once the endianness
+        // is known.
+        let header_size = bytesa::<2>(h)?;
+        let le = match bytesa::<2>(h)? {
+            [0xFF, 0xFE] => true,
+            [0xFE, 0xFF] => false,
+            _ => {
+                return Err(Error::Parse("invalid SARC BOM")).at_field("SARC", base, header, "BOM");
+            }
         };
+        if end_u16(&mut &header_size[..], le)? != 0x14 {
+            return Err(Error::Parse("unexpected SARC header size")).at_field(
+                "SARC",
+                base,
+                header,
+                "HeaderSize",
+            );
+        }
 
-        let _total_size = end_u32(r, le)?;
-        let data_offset = end_u32(r, le)? as u64;
-        let version = le_u16(r)?;
-        let _padding = le_u16(r)?;
+        let _total_size = end_u32(h, le)?;
+        let data_offset = end_u32(h, le)? as u64;
+        let version = end_u16(h, le)?;
 
         // SFAT header (0x0C bytes)
-        magic(r, b"SFAT")?;
-        let sfat_size = le_u16(r)?;
+        let sfat = base + 0x14;
+        let header = || "SFAT header".to_string();
+        magic(r, b"SFAT").at_field("SARC", sfat, header, "magic")?;
+        let head = bytesa::<8>(r).at("SARC", sfat, header)?;
+        let h = &mut &head[..];
+        let sfat_size = end_u16(h, le)?;
         if sfat_size != 0x0C {
-            return Err(Error::Parse("unexpected SFAT header size"));
+            return Err(Error::Parse("unexpected SFAT header size")).at_field(
+                "SARC",
+                sfat,
+                header,
+                "HeaderSize",
+            );
         }
-        let file_count = end_u16(r, le)?;
-        let hash_multiplier = end_u32(r, le)?;
+        let file_count = end_u16(h, le)?;
+        let hash_multiplier = end_u32(h, le)?;
 
         if file_count > 0x3FFF {
-            return Err(Error::Parse("SARC file count exceeds maximum"));
+            return Err(Error::Parse("SARC file count exceeds maximum")).at_field(
+                "SARC",
+                sfat,
+                header,
+                "FileCount",
+            );
         }
 
         // FAT entries
-        let mut fat = Vec::with_capacity(file_count as usize);
-        for _ in 0..file_count {
-            let hash = end_u32(r, le)?;
-            let name_attrs = end_u32(r, le)?;
-            let data_start = end_u32(r, le)?;
-            let data_end = end_u32(r, le)?;
-            fat.push((hash, name_attrs, data_start, data_end));
-        }
+        let entry_at = |i: usize| sfat + 0x0C + i as u64 * 0x10;
+        let fat = bytesv(r, file_count as usize * 0x10)
+            .at("SARC", entry_at(0), || "SFAT entries".into())?;
 
         // SFNT header (0x08 bytes)
-        magic(r, b"SFNT")?;
-        let sfnt_size = le_u16(r)?;
+        let sfnt = entry_at(file_count as usize);
+        let header = || "SFNT header".to_string();
+        magic(r, b"SFNT").at_field("SARC", sfnt, header, "magic")?;
+        let head = bytesa::<4>(r).at("SARC", sfnt, header)?;
+        let sfnt_size = end_u16(&mut &head[..], le)?;
         if sfnt_size != 8 {
-            return Err(Error::Parse("unexpected SFNT header size"));
+            return Err(Error::Parse("unexpected SFNT header size")).at_field(
+                "SARC",
+                sfnt,
+                header,
+                "HeaderSize",
+            );
         }
-        let _sfnt_padding = le_u16(r)?;
 
-        // Name table starts immediately after SFNT header.
-        let name_table_start = r.stream_position()?;
+        // The name table runs from the end of the SFNT header to the data
+        // section; read it in one go rather than seeking per entry.
+        let name_table_start = sfnt + 8;
+        let name_table = (base + data_offset)
+            .checked_sub(name_table_start)
+            .ok_or(Error::InvalidRange)
+            .and_then(|size| table(r, size))
+            .at("SARC", name_table_start, || "name table".into())?;
 
-        let mut files = Vec::with_capacity(file_count as usize);
-        for (hash, name_attrs, data_start, data_end) in fat {
+        let mut entries = Vec::with_capacity(file_count as usize);
+        for (i, entry) in fat.chunks_exact(0x10).enumerate() {
+            let e = &mut &entry[..];
+            let hash = end_u32(e, le)?;
+            let name_attrs = end_u32(e, le)?;
+            let data_start = end_u32(e, le)?;
+            let data_end = end_u32(e, le)?;
             let name = if name_attrs == 0 {
                 None
             } else {
                 // name_attrs = 0xAABBBBBB; BBBBBB is the word offset (× 4) into
                 // the name table.
-                let word_off = (name_attrs & 0x00FFFFFF) as u64;
-                let byte_off = word_off * 4;
-                let saved_pos = r.stream_position()?;
-                r.seek(SeekFrom::Start(name_table_start + byte_off))?;
-                let name = read_null_string(r)?;
-                r.seek(SeekFrom::Start(saved_pos))?;
+                let word_off = (name_attrs & 0x00FFFFFF) as usize;
+                let name = null_range(&name_table, word_off * 4).at_field(
+                    "SARC",
+                    entry_at(i),
+                    || format!("SFAT entry {i}"),
+                    "FilenameAttrs",
+                )?;
                 Some(name)
             };
-            files.push(SarcFile {
+            entries.push(ListedFile {
                 name,
                 hash,
+                collision_index: (name_attrs >> 24) as u8,
                 data_start,
                 data_end,
             });
         }
 
+        let sorted = entries.is_sorted_by_key(|f| f.hash);
         Ok(Self {
-            files,
             le,
             version,
             hash_multiplier,
+            entries,
+            name_table,
             data_offset: base + data_offset,
+            sorted,
+            base,
         })
     }
+
+    /// Number of files.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `true` if the archive has no files.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over all file entries in SFAT order, borrowing their names.
+    pub fn files(&self) -> impl Iterator<Item = SarcListingFile<'_>> {
+        self.entries.iter().map(|e| self.file(e))
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<SarcListingFile<'_>> {
+        let target = hash(name.as_bytes(), self.hash_multiplier);
+        let (start, end) = if self.sorted {
+            let start = self.entries.partition_point(|f| f.hash < target);
+            let end = start + self.entries[start..].partition_point(|f| f.hash == target);
+            (start, end)
+        } else {
+            (0, self.entries.len())
+        };
+        self.entries[start..end]
+            .iter()
+            .filter(|e| e.hash == target)
+            .map(|e| self.file(e))
+            .find(|f| f.name.as_deref() == Some(name))
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<SarcListingFile<'_>> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    fn file(&self, e: &ListedFile) -> SarcListingFile<'_> {
+        SarcListingFile {
+            name: e
+                .name
+                .clone()
+                .map(|range| String::from_utf8_lossy(&self.name_table[range])),
+            hash: e.hash,
+            collision_index: e.collision_index,
+            data_start: e.data_start,
+            data_end: e.data_end,
+        }
+    }
+}
+
+impl From<SarcListing> for Sarc {
+    fn from(listing: SarcListing) -> Self {
+        let files: Vec<SarcFile> = listing.files().map(SarcListingFile::into_owned).collect();
+        let collisions = find_collisions(&files);
+        Self {
+            files,
+            le: listing.le,
+            version: listing.version,
+            hash_multiplier: listing.hash_multiplier,
+            collisions,
+            data_offset: listing.data_offset,
+            sorted: listing.sorted,
+            base: listing.base,
+        }
+    }
 }
 
 /// Streaming reader wrapper over a parsed [`Sarc`] archive.
@@ -218,10 +464,48 @@ impl<R: Read + Seek> SarcReader<R> {
     }
 
     /// Find a file by name. Returns [`None`] if not found.
+    ///
+    /// Looks the name's hash up by binary search, then compares names among
+    /// entries sharing that hash.
     pub fn get_file(&self, name: &str) -> Option<&SarcFile> {
         let target = hash(name.as_bytes(), self.sarc.hash_multiplier);
-        self.files()
-            .find(|f| f.hash == target && f.name.as_deref() == Some(name))
+        self.sarc
+            .files_with_hash(target)
+            .find(|f| f.name.as_deref() == Some(name))
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&SarcFile> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    /// Find a file by its name hash.
+    ///
+    /// Works for archives without a name table, where [`get_file`] can never
+    /// match. If several entries share the hash, the first is returned; use
+    /// [`get_files_by_hash`] to get all of them.
+    ///
+    /// [`get_file`]: Self::get_file
+    /// [`get_files_by_hash`]: Self::get_files_by_hash
+    pub fn get_file_by_hash(&self, hash: u32) -> Option<&SarcFile> {
+        self.sarc.files_with_hash(hash).next()
+    }
+
+    /// All files with name hash `hash`, in SFAT order.
+    ///
+    /// More than one entry is returned only for hashes listed in
+    /// [`Sarc::collisions`].
+    pub fn get_files_by_hash(&self, hash: u32) -> impl Iterator<Item = &SarcFile> {
+        self.sarc.files_with_hash(hash)
+    }
+
+    /// Find a file by the hash of `path`, ignoring stored names.
+    ///
+    /// Use this to address entries of nameless archives by their known
+    /// paths.
+    pub fn get_file_by_path_hash(&self, path: &str) -> Option<&SarcFile> {
+        self.get_file_by_hash(hash(path.as_bytes(), self.sarc.hash_multiplier))
     }
 
     /// Open a file for streaming access.
@@ -235,15 +519,416 @@ impl<R: Read + Seek> SarcReader<R> {
         Ok(self.inner.by_ref().take(file.size()))
     }
 
+    /// Open the file named `name` for streaming access.
+    ///
+    /// Returns [`Error::NotFound`] if there is no such file.
+    pub fn open(&mut self, name: &str) -> Result<Take<&mut R>> {
+        let file = self.try_get(name)?.clone();
+        self.read_file(&file)
+    }
+
+    /// Read a file into memory, decompressing it if it is Zstd-, Yaz0-, or
+    /// LZ4-compressed (as nested `.zs` / `.szs` archives are).
+    ///
+    /// The codec is identified by magic, not by extension; other data is
+    /// returned as stored. Wrap the result in a [`std::io::Cursor`] and pass
+    /// it to [`SarcReader::new`] to walk a nested archive.
+    #[cfg(feature = "compression")]
+    pub fn read_file_decompressed(&mut self, file: &SarcFile) -> Result<Vec<u8>> {
+        use crate::compression::{Codec, decompress_auto, detect};
+
+        let mut data = Vec::with_capacity(file.size() as usize);
+        self.read_file(file)?.read_to_end(&mut data)?;
+        // zlib's two-byte header is too weak a signature for arbitrary
+        // entries.
+        match detect(&data) {
+            Some(Codec::Zlib) | None => Ok(data),
+            Some(_) => Ok(decompress_auto(&data)?.unwrap_or(data)),
+        }
+    }
+
     /// Iterate over all file entries.
     pub fn files(&self) -> impl Iterator<Item = &SarcFile> {
         self.sarc.files.iter()
     }
 
+    /// Iterate over named entries whose name starts with `prefix`.
+    pub fn files_with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = &'a SarcFile> + 'a {
+        self.files()
+            .filter(move |f| f.name.as_deref().is_some_and(|n| n.starts_with(prefix)))
+    }
+
+    /// Iterate over named entries whose name matches the glob `pattern`.
+    ///
+    /// `?` matches one character and `*` any run of characters, neither
+    /// crossing a `/`; `**` matches across `/`. For example
+    /// `Actor/Pack/*.sbactorpack` or `**/*.bfres`.
+    pub fn files_matching<'a>(
+        &'a self,
+        pattern: &'a str,
+    ) -> impl Iterator<Item = &'a SarcFile> + 'a {
+        self.files()
+            .filter(move |f| f.name.as_deref().is_some_and(|n| glob_match(pattern, n)))
+    }
+
     /// Consume the reader, returning the inner reader.
     pub fn into_inner(self) -> R {
         self.inner
     }
+
+    /// Consume the reader, returning a stream over just `file`.
+    ///
+    /// Seeks within the returned [`SubReader`] are relative to the file, so
+    /// it can be passed to another parser without borrowing this reader.
+    pub fn into_file(self, file: &SarcFile) -> SubReader<R> {
+        let start = self.sarc.data_offset + file.data_start as u64;
+        SubReader::new(self.inner, start, file.size())
+    }
+
+    /// Extract every named entry under `dir` with default [`ExtractOptions`].
+    ///
+    /// Returns the number of files written.
+    pub fn extract_all(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        self.extract_all_with(dir, &ExtractOptions::default())
+    }
+
+    /// Extract the archive under `dir`, recreating the `/`-separated path of
+    /// each entry.
+    ///
+    /// Returns the number of files written. Returns [`Error::Parse`] for an
+    /// entry whose name would escape `dir` (absolute, or containing `..`).
+    pub fn extract_all_with(
+        &mut self,
+        dir: impl AsRef<Path>,
+        options: &ExtractOptions,
+    ) -> Result<usize> {
+        self.extract_all_with_progress(dir, options, &mut NoProgress)
+    }
+
+    /// Like [`extract_all_with`](Self::extract_all_with), reporting the
+    /// bytes written against the total size of the entries to extract.
+    ///
+    /// Entries skipped because their file exists count as done.
+    pub fn extract_all_with_progress(
+        &mut self,
+        dir: impl AsRef<Path>,
+        options: &ExtractOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<usize> {
+        let dir = dir.as_ref();
+        let files: Vec<SarcFile> = self
+            .files()
+            .filter(|f| f.name.is_some() || options.unnamed == UnnamedEntries::ByHash)
+            .cloned()
+            .collect();
+        let total = files.iter().map(|f| f.size()).sum();
+        let mut done = 0;
+        let mut written = 0;
+        for file in &files {
+            let relative = match &file.name {
+                Some(name) => entry_path(name)?,
+                None => PathBuf::from(format!("{:08x}.bin", file.hash)),
+            };
+            let path = dir.join(relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let item = file.name.as_deref().unwrap_or_default();
+            let mut out = match options.overwrite {
+                Overwrite::Replace => File::create(&path)?,
+                Overwrite::Skip if path.exists() => {
+                    done += file.size();
+                    progress.update(item, done, total);
+                    continue;
+                }
+                Overwrite::Skip | Overwrite::Fail => File::create_new(&path)?,
+            };
+            let mut r = ProgressReader::new(
+                self.read_file(file)?,
+                &mut *progress,
+                item,
+                &mut done,
+                total,
+            );
+            io::copy(&mut r, &mut out)?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Write a copy of the archive to `w` in the byte order selected by `le`
+    /// (`true` for Switch, `false` for Wii U).
+    ///
+    /// Only the SARC, SFAT, and SFNT header fields are rewritten; names,
+    /// layout, and file data are copied unchanged. File contents that are
+    /// themselves endian-dependent are not converted.
+    pub fn convert_endianness<W: Write>(&mut self, le: bool, w: &mut W) -> Result<()> {
+        let header_size = self.sarc.data_offset - self.sarc.base;
+        self.inner.seek(SeekFrom::Start(self.sarc.base))?;
+        let mut header = bytesv(&mut self.inner, header_size as usize)?;
+        let total_size = end_u32(&mut &header[0x08..], self.sarc.le)? as u64;
+
+        if le != self.sarc.le {
+            let swap = |header: &mut [u8], offset: usize, len: usize| {
+                header[offset..offset + len].reverse();
+            };
+            // SARC header, then SFAT header.
+            let fields = [(0x04, 2), (0x06, 2), (0x08, 4), (0x0C, 4), (0x10, 2)];
+            for (offset, len) in fields.into_iter().chain([(0x18, 2), (0x1A, 2), (0x1C, 4)]) {
+                swap(&mut header, offset, len);
+            }
+            let count = self.sarc.files.len();
+            for i in 0..count * 4 {
+                swap(&mut header, 0x20 + i * 4, 4);
+            }
+            swap(&mut header, 0x20 + count * 0x10 + 4, 2);
+        }
+
+        w.write_all(&header)?;
+        let data_size = total_size.saturating_sub(header_size);
+        io::copy(&mut self.inner.by_ref().take(data_size), w)?;
+        Ok(())
+    }
+
+    /// `true` if `path` names a file or a directory in the archive.
+    pub fn exists(&self, path: &str) -> bool {
+        self.get_file(path).is_some() || self.dir(path).is_some()
+    }
+
+    /// Open a directory view of `path` (`""` for the root).
+    ///
+    /// SARC has no real directories; a directory exists when at least one
+    /// entry name starts with `path/`. Leading and trailing `/` are ignored.
+    /// Returns [`None`] if no entry lies under `path`.
+    pub fn dir(&self, path: &str) -> Option<SarcDir<'_>> {
+        let dir = SarcDir {
+            files: &self.sarc.files,
+            path: path.trim_matches('/').to_owned(),
+        };
+        (!dir.is_empty()).then_some(dir)
+    }
+
+    /// Iterate over the immediate children of directory `path`.
+    ///
+    /// Yields nothing if the directory does not exist.
+    pub fn iter_dir(&self, path: &str) -> impl Iterator<Item = SarcDirEntry<'_>> {
+        self.dir(path).into_iter().flat_map(|d| d.iter())
+    }
+}
+
+/// What [`SarcReader::extract_all_with`] does when an output file already
+/// exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Replace the existing file.
+    #[default]
+    Replace,
+    /// Keep the existing file and skip the entry.
+    Skip,
+    /// Stop with an [`Error::Io`] of kind
+    /// [`AlreadyExists`](io::ErrorKind::AlreadyExists).
+    Fail,
+}
+
+/// What [`SarcReader::extract_all_with`] does with entries that have no name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnnamedEntries {
+    /// Leave them out.
+    #[default]
+    Skip,
+    /// Write them to the output root as `<hash>.bin` (hash in 8-digit hex).
+    ByHash,
+}
+
+/// Options for [`SarcReader::extract_all_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractOptions {
+    pub overwrite: Overwrite,
+    pub unnamed: UnnamedEntries,
+}
+
+/// Hashes shared by more than one of `files`, in ascending order.
+fn find_collisions(files: &[SarcFile]) -> Vec<u32> {
+    let mut hashes: Vec<u32> = files.iter().map(|f| f.hash).collect();
+    hashes.sort_unstable();
+    let mut collisions: Vec<u32> = hashes
+        .windows(2)
+        .filter(|w| w[0] == w[1])
+        .map(|w| w[0])
+        .collect();
+    collisions.dedup();
+    collisions
+}
+
+/// Match `name` against a glob of `?`, `*`, and `**` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    fn matches(p: &[char], n: &[char]) -> bool {
+        match p {
+            [] => n.is_empty(),
+            // `**/` also matches zero directories.
+            ['*', '*', '/', rest @ ..] => {
+                matches(rest, n) || (0..n.len()).any(|i| n[i] == '/' && matches(rest, &n[i + 1..]))
+            }
+            ['*', '*', rest @ ..] => (0..=n.len()).any(|i| matches(rest, &n[i..])),
+            ['*', rest @ ..] => (0..=n.len())
+                .take_while(|&i| i == 0 || n[i - 1] != '/')
+                .any(|i| matches(rest, &n[i..])),
+            ['?', rest @ ..] => n.first().is_some_and(|&c| c != '/') && matches(rest, &n[1..]),
+            [c, rest @ ..] => n.first() == Some(c) && matches(rest, &n[1..]),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches(&pattern, &name)
+}
+
+/// Convert an entry name to a relative path, rejecting names that would
+/// leave the output directory.
+fn entry_path(name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from_iter(name.split('/').filter(|c| !c.is_empty()));
+    let safe = path.components().all(|c| matches!(c, Component::Normal(_)));
+    if !safe || path.as_os_str().is_empty() {
+        return Err(Error::Parse("SARC entry name escapes the output directory"));
+    }
+    Ok(path)
+}
+
+/// Child of a [`SarcDir`].
+#[derive(Debug, Clone, Copy)]
+pub enum SarcDirEntry<'a> {
+    /// A file directly inside the directory.
+    File(&'a SarcFile),
+    /// A subdirectory, by its name (the last path component).
+    Dir(&'a str),
+}
+
+/// Hierarchical view of the `/`-separated names in a SARC.
+///
+/// Unnamed entries are not part of any directory.
+#[derive(Debug, Clone)]
+pub struct SarcDir<'a> {
+    files: &'a [SarcFile],
+    path: String,
+}
+
+impl<'a> SarcDir<'a> {
+    /// Path of this directory without leading or trailing `/` (`""` for the
+    /// root).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Immediate children: subdirectories, then files, each sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = SarcDirEntry<'a>> + use<'a> {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        for (file, rest) in self.children() {
+            match rest.split_once('/') {
+                Some((dir, _)) => dirs.push(dir),
+                None => files.push(file),
+            }
+        }
+        dirs.sort_unstable();
+        dirs.dedup();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        dirs.into_iter()
+            .map(SarcDirEntry::Dir)
+            .chain(files.into_iter().map(SarcDirEntry::File))
+    }
+
+    /// Files directly inside this directory.
+    pub fn files(&self) -> impl Iterator<Item = &'a SarcFile> + use<'a> {
+        self.iter().filter_map(|e| match e {
+            SarcDirEntry::File(f) => Some(f),
+            SarcDirEntry::Dir(_) => None,
+        })
+    }
+
+    /// Names of the subdirectories directly inside this directory.
+    pub fn dirs(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        self.iter().filter_map(|e| match e {
+            SarcDirEntry::Dir(d) => Some(d),
+            SarcDirEntry::File(_) => None,
+        })
+    }
+
+    /// Open subdirectory `name`, which may itself contain `/`.
+    pub fn dir(&self, name: &str) -> Option<SarcDir<'a>> {
+        let name = name.trim_matches('/');
+        let path = if self.path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}/{name}", self.path)
+        };
+        let dir = SarcDir {
+            files: self.files,
+            path,
+        };
+        (!dir.is_empty()).then_some(dir)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.children().next().is_none()
+    }
+
+    /// Every file under this directory with its path relative to it.
+    fn children(&self) -> impl Iterator<Item = (&'a SarcFile, &'a str)> + '_ {
+        self.files.iter().filter_map(|f| {
+            let name = f.name.as_deref()?;
+            if self.path.is_empty() {
+                return Some((f, name));
+            }
+            let rest = name.strip_prefix(self.path.as_str())?.strip_prefix('/')?;
+            Some((f, rest))
+        })
+    }
+}
+
+impl ArchiveEntry for SarcFile {
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn size(&self) -> u64 {
+        SarcFile::size(self)
+    }
+}
+
+impl<R: Read + Seek> ArchiveRead for SarcReader<R> {
+    type Entry = SarcFile;
+    type EntryReader<'a>
+        = Take<&'a mut R>
+    where
+        Self: 'a;
+
+    fn entries(&self) -> impl Iterator<Item = &SarcFile> {
+        self.files()
+    }
+
+    fn get_entry(&self, name: &str) -> Option<&SarcFile> {
+        self.get_file(name)
+    }
+
+    fn open_entry(&mut self, entry: &SarcFile) -> Result<Take<&mut R>> {
+        self.read_file(entry)
+    }
+}
+
+impl<R: ReadAt> SarcReader<R> {
+    /// Open a file for reading through a shared reference.
+    ///
+    /// Unlike [`read_file`](Self::read_file), any number of files can be
+    /// open at once; see [`crate::read_at`].
+    pub fn read_file_at(&self, file: &SarcFile) -> RangeReader<&R> {
+        RangeReader::new(
+            &self.inner,
+            self.sarc.data_offset + file.data_start as u64,
+            file.size(),
+        )
+    }
 }
 
 impl<R: Read + Seek> Index<&str> for SarcReader<R> {
@@ -252,12 +937,381 @@ impl<R: Read + Seek> Index<&str> for SarcReader<R> {
     /// Index by file name.
     ///
     /// # Panics
-    /// Panics if the file name does not exist in the archive.
+    /// Panics if the file name does not exist in the archive; use
+    /// [`try_get`](SarcReader::try_get) to handle that case.
+    fn index(&self, index: &str) -> &Self::Output {
+        self.try_get(index).unwrap_or_else(|e| panic!("SARC: {e}"))
+    }
+}
+
+/// SARC archive parsed from a byte slice.
+///
+/// Names and file data borrow from the input, so nothing beyond the entry
+/// list is copied.
+#[derive(Debug, Clone)]
+pub struct SarcSlice<'a> {
+    /// All file entries.
+    pub files: Vec<SarcSliceFile<'a>>,
+    /// Whether the archive uses little-endian encoding.
+    pub le: bool,
+    /// Format version from the SARC header (normally 0x0100).
+    pub version: u16,
+    /// Hash multiplier from the SFAT header (always 101 = 0x65).
+    pub hash_multiplier: u32,
+    /// Whether `files` is sorted by hash, allowing binary search.
+    sorted: bool,
+}
+
+/// A single file entry inside a [`SarcSlice`].
+#[derive(Debug, Clone)]
+pub struct SarcSliceFile<'a> {
+    /// Filename ([`None`] if the archive has no name table entry for this file).
+    pub name: Option<Cow<'a, str>>,
+    /// CRC hash of the filename.
+    pub hash: u32,
+    /// Position among entries sharing `hash`, counting from 1; 0 for
+    /// unnamed entries.
+    pub collision_index: u8,
+    /// File contents.
+    pub data: &'a [u8],
+}
+
+impl<'a> SarcSlice<'a> {
+    /// Parse a SARC archive from `data`, which must start at the `SARC`
+    /// magic.
+    ///
+    /// Returns [`Error::InvalidRange`] if a file or the name table lies
+    /// outside `data`.
+    pub fn parse_bytes(data: &'a [u8]) -> Result<Self> {
+        let r = &mut &data[..];
+        magic(r, b"SARC")?;
+
+        let header_size = bytesa::<2>(r)?;
+        let le = match bytesa::<2>(r)? {
+            [0xFF, 0xFE] => true,
+            [0xFE, 0xFF] => false,
+            _ => return Err(Error::Parse("invalid SARC BOM")),
+        };
+        if end_u16(&mut &header_size[..], le)? != 0x14 {
+            return Err(Error::Parse("unexpected SARC header size"));
+        }
+
+        let _total_size = end_u32(r, le)?;
+        let data_offset = end_u32(r, le)? as u64;
+        let version = end_u16(r, le)?;
+        let _padding = le_u16(r)?;
+
+        magic(r, b"SFAT")?;
+        if end_u16(r, le)? != 0x0C {
+            return Err(Error::Parse("unexpected SFAT header size"));
+        }
+        let file_count = end_u16(r, le)?;
+        let hash_multiplier = end_u32(r, le)?;
+        if file_count > 0x3FFF {
+            return Err(Error::Parse("SARC file count exceeds maximum"));
+        }
+
+        // The SFNT header follows the FAT; read it first so names can be
+        // resolved while walking the entries.
+        let sfnt_start = 0x20 + file_count as u64 * 0x10;
+        let sfnt = &mut subslice(data, sfnt_start, 8)?;
+        magic(sfnt, b"SFNT")?;
+        if end_u16(sfnt, le)? != 8 {
+            return Err(Error::Parse("unexpected SFNT header size"));
+        }
+        let name_table_start = sfnt_start + 8;
+        let name_table_size = data_offset
+            .checked_sub(name_table_start)
+            .ok_or(Error::InvalidRange)?;
+        let name_table = subslice(data, name_table_start, name_table_size)?;
+
+        let mut files = Vec::with_capacity(file_count as usize);
+        for _ in 0..file_count {
+            let hash = end_u32(r, le)?;
+            let name_attrs = end_u32(r, le)?;
+            let data_start = end_u32(r, le)?;
+            let data_end = end_u32(r, le)?;
+            let name = if name_attrs == 0 {
+                None
+            } else {
+                let word_off = (name_attrs & 0x00FFFFFF) as usize;
+                Some(null_str(name_table, word_off * 4)?)
+            };
+            files.push(SarcSliceFile {
+                name,
+                hash,
+                collision_index: (name_attrs >> 24) as u8,
+                data: subslice(
+                    data,
+                    data_offset + data_start as u64,
+                    data_end.saturating_sub(data_start) as u64,
+                )?,
+            });
+        }
+
+        let sorted = files.is_sorted_by_key(|f| f.hash);
+        Ok(Self {
+            files,
+            le,
+            version,
+            hash_multiplier,
+            sorted,
+        })
+    }
+
+    /// Find a file by name. Returns [`None`] if not found.
+    ///
+    /// Looks the name's hash up by binary search, then compares names among
+    /// entries sharing that hash.
+    pub fn get_file(&self, name: &str) -> Option<&SarcSliceFile<'a>> {
+        let target = hash(name.as_bytes(), self.hash_multiplier);
+        self.get_files_by_hash(target)
+            .find(|f| f.name.as_deref() == Some(name))
+    }
+
+    /// Find a file by name. Returns [`Error::NotFound`] if not found.
+    pub fn try_get(&self, name: &str) -> Result<&SarcSliceFile<'a>> {
+        self.get_file(name)
+            .ok_or_else(|| Error::NotFound { name: name.into() })
+    }
+
+    /// Contents of the file named `name`. Returns [`None`] if not found.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        self.get_file(name).map(|f| f.data)
+    }
+
+    /// Contents of the file named `name`. Returns [`Error::NotFound`] if
+    /// not found.
+    pub fn open(&self, name: &str) -> Result<&'a [u8]> {
+        self.try_get(name).map(|f| f.data)
+    }
+
+    /// All files with name hash `hash`, in SFAT order.
+    pub fn get_files_by_hash(&self, hash: u32) -> impl Iterator<Item = &SarcSliceFile<'a>> {
+        let (start, end) = if self.sorted {
+            let start = self.files.partition_point(|f| f.hash < hash);
+            let end = start + self.files[start..].partition_point(|f| f.hash == hash);
+            (start, end)
+        } else {
+            (0, self.files.len())
+        };
+        self.files[start..end]
+            .iter()
+            .filter(move |f| f.hash == hash)
+    }
+
+    /// Iterate over all file entries.
+    pub fn files(&self) -> impl Iterator<Item = &SarcSliceFile<'a>> {
+        self.files.iter()
+    }
+}
+
+impl<'a> Index<&str> for SarcSlice<'a> {
+    type Output = SarcSliceFile<'a>;
+
+    /// Index by file name.
+    ///
+    /// # Panics
+    /// Panics if the file name does not exist in the archive; use
+    /// [`try_get`](SarcSlice::try_get) to handle that case.
     fn index(&self, index: &str) -> &Self::Output {
-        self.get_file(index).expect("no such file in SARC")
+        self.try_get(index).unwrap_or_else(|e| panic!("SARC: {e}"))
+    }
+}
+
+/// Hash multiplier used by every known SARC archive.
+pub const DEFAULT_HASH_MULTIPLIER: u32 = 101;
+
+/// Alignment used by [`SarcWriter`] for files no rule matches.
+pub const DEFAULT_ALIGNMENT: u32 = 0x80;
+
+/// Data alignment a file needs inside a SARC, judged from its name and
+/// leading bytes.
+///
+/// Returns [`None`] when no rule matches. Fonts (BFFNT) need 0x2000 bytes;
+/// textures (BNTX) and model resources (BFRES) need 0x1000.
+pub fn alignment_for(name: &str, data: &[u8]) -> Option<u32> {
+    let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+    match (ext.as_deref(), data.get(..4)) {
+        (Some("bffnt"), _) | (_, Some(b"FFNT")) => Some(0x2000),
+        (Some("bntx"), _) | (_, Some(b"BNTX")) => Some(0x1000),
+        (Some("bfres" | "sbfres"), _) | (_, Some(b"FRES")) => Some(0x1000),
+        _ => None,
     }
 }
 
+/// Builder that writes a SARC archive.
+///
+/// Entries are sorted by name hash as the format requires. Each file's data
+/// is aligned according to, in order of precedence, a rule set with
+/// [`alignment_for_extension`](Self::alignment_for_extension), the built-in
+/// [`alignment_for`] heuristics, and the default alignment (0x80).
+///
+/// Entry data is read into memory before writing, since the data offset
+/// depends on the largest alignment of any file. This lets `write` target a
+/// plain [`Write`], such as a Zstd encoder producing `.zs` output.
+pub struct SarcWriter<'a> {
+    entries: Vec<(String, EntrySource<'a>)>,
+    le: bool,
+    default_alignment: u32,
+    extension_alignment: HashMap<String, u32>,
+}
+
+impl<'a> SarcWriter<'a> {
+    /// Create an empty writer. `le` selects little-endian (Switch) output;
+    /// `false` writes big-endian (Wii U) archives.
+    pub fn new(le: bool) -> Self {
+        Self {
+            entries: Vec::new(),
+            le,
+            default_alignment: DEFAULT_ALIGNMENT,
+            extension_alignment: HashMap::new(),
+        }
+    }
+
+    /// Set the alignment for files no other rule matches.
+    pub fn default_alignment(&mut self, alignment: u32) -> &mut Self {
+        self.default_alignment = alignment.max(1);
+        self
+    }
+
+    /// Align files with extension `ext` (without the dot, case-insensitive)
+    /// to `alignment` bytes, overriding the built-in heuristics.
+    pub fn alignment_for_extension(&mut self, ext: &str, alignment: u32) -> &mut Self {
+        self.extension_alignment
+            .insert(ext.to_ascii_lowercase(), alignment.max(1));
+        self
+    }
+
+    /// Add an entry whose data is read from `reader`.
+    pub fn add_reader(&mut self, name: impl Into<String>, reader: impl Read + 'a) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Reader(Box::new(reader))));
+        self
+    }
+
+    /// Add an entry whose data is the file at `path`.
+    ///
+    /// The file is read when [`write`](Self::write) is called.
+    pub fn add_path(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> &mut Self {
+        self.entries
+            .push((name.into(), EntrySource::Path(path.into())));
+        self
+    }
+
+    /// Write the archive to `w`.
+    ///
+    /// Returns the metadata of the written archive, with the data offset
+    /// relative to the start of the archive. Returns [`Error::Parse`] for
+    /// more than 0x3FFF files and [`Error::InvalidRange`] if the archive
+    /// exceeds 4 GiB.
+    pub fn write<W: Write>(self, w: &mut W) -> Result<Sarc> {
+        if self.entries.len() > 0x3FFF {
+            return Err(Error::Parse("SARC file count exceeds maximum"));
+        }
+        let le = self.le;
+
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for (name, source) in self.entries {
+            let mut data = Vec::new();
+            source.open()?.read_to_end(&mut data)?;
+            let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+            let alignment = ext
+                .and_then(|e| self.extension_alignment.get(&e).copied())
+                .or_else(|| alignment_for(&name, &data))
+                .unwrap_or(self.default_alignment);
+            let hash = hash(name.as_bytes(), DEFAULT_HASH_MULTIPLIER);
+            entries.push((hash, name, data, alignment));
+        }
+        entries.sort_by_key(|(hash, ..)| *hash);
+
+        let mut names = Vec::new();
+        let mut name_offsets = Vec::with_capacity(entries.len());
+        for (_, name, _, _) in &entries {
+            name_offsets.push(names.len() as u32 / 4);
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+            names.resize(names.len().next_multiple_of(4), 0);
+        }
+
+        let max_alignment = entries.iter().map(|e| e.3).max().unwrap_or(1) as usize;
+        let names_end = 0x14 + 0x0C + entries.len() * 0x10 + 8 + names.len();
+        let data_offset = names_end.next_multiple_of(max_alignment);
+
+        let mut files = Vec::with_capacity(entries.len());
+        let mut data_end = 0usize;
+        for (i, (hash, name, data, alignment)) in entries.iter().enumerate() {
+            let start = data_end.next_multiple_of(*alignment as usize);
+            data_end = start + data.len();
+            let (data_start, end) = (u32_range(start)?, u32_range(data_end)?);
+            let earlier = entries[..i].iter().filter(|e| e.0 == *hash).count();
+            files.push(SarcFile {
+                name: Some(name.clone()),
+                hash: *hash,
+                collision_index: u8::try_from(earlier + 1).map_err(|_| Error::InvalidRange)?,
+                data_start,
+                data_end: end,
+            });
+        }
+        let total_size = u32_range(data_offset + data_end)?;
+
+        let u16b = |v: u16| if le { v.to_le_bytes() } else { v.to_be_bytes() };
+        let u32b = |v: u32| if le { v.to_le_bytes() } else { v.to_be_bytes() };
+
+        let mut header = Vec::with_capacity(data_offset);
+        header.extend_from_slice(b"SARC");
+        header.extend_from_slice(&u16b(0x14));
+        header.extend_from_slice(&u16b(0xFEFF));
+        header.extend_from_slice(&u32b(total_size));
+        header.extend_from_slice(&u32b(data_offset as u32));
+        header.extend_from_slice(&u16b(0x0100));
+        header.extend_from_slice(&[0; 2]);
+
+        header.extend_from_slice(b"SFAT");
+        header.extend_from_slice(&u16b(0x0C));
+        header.extend_from_slice(&u16b(entries.len() as u16));
+        header.extend_from_slice(&u32b(DEFAULT_HASH_MULTIPLIER));
+        for (i, file) in files.iter().enumerate() {
+            header.extend_from_slice(&u32b(file.hash));
+            header.extend_from_slice(&u32b((file.collision_index as u32) << 24 | name_offsets[i]));
+            header.extend_from_slice(&u32b(file.data_start));
+            header.extend_from_slice(&u32b(file.data_end));
+        }
+
+        header.extend_from_slice(b"SFNT");
+        header.extend_from_slice(&u16b(8));
+        header.extend_from_slice(&[0; 2]);
+        header.extend_from_slice(&names);
+        header.resize(data_offset, 0);
+        w.write_all(&header)?;
+
+        let mut pos = 0;
+        for (file, (_, _, data, _)) in files.iter().zip(&entries) {
+            let start = file.data_start as usize;
+            w.write_all(&vec![0; start - pos])?;
+            w.write_all(data)?;
+            pos = file.data_end as usize;
+        }
+
+        let collisions = find_collisions(&files);
+        Ok(Sarc {
+            files,
+            le,
+            version: 0x0100,
+            hash_multiplier: DEFAULT_HASH_MULTIPLIER,
+            collisions,
+            data_offset: data_offset as u64,
+            sorted: true,
+            base: 0,
+        })
+    }
+}
+
+/// Convert an archive offset to `u32`, failing for archives over 4 GiB.
+fn u32_range(offset: usize) -> Result<u32> {
+    u32::try_from(offset).map_err(|_| Error::InvalidRange)
+}
+
 /// SARC filename hash algorithm.
 ///
 /// Each byte is sign-extended (cast to `i8`) before accumulating. This is
@@ -269,3 +1323,45 @@ pub fn hash(name: &[u8], multiplier: u32) -> u32 {
     }
     h
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let big: Vec<u8> = (0..0x1234).map(|i| i as u8).collect();
+        for le in [true, false] {
+            let mut writer = SarcWriter::new(le);
+            writer
+                .alignment_for_extension("bin", 0x100)
+                .add_reader("dir/a.bin", &big[..])
+                .add_reader("b.txt", &b"text"[..])
+                .add_reader("empty", &b""[..]);
+            let mut out = Vec::new();
+            writer.write(&mut out).unwrap();
+
+            let mut reader = SarcReader::new(Cursor::new(out)).unwrap();
+            assert_eq!(reader.sarc.le, le);
+            assert_eq!(reader.files().count(), 3);
+            for (name, data) in [("dir/a.bin", &big[..]), ("b.txt", b"text"), ("empty", b"")] {
+                let mut contents = Vec::new();
+                reader
+                    .open(name)
+                    .unwrap()
+                    .read_to_end(&mut contents)
+                    .unwrap();
+                assert_eq!(contents, data, "{name}");
+            }
+            let a = reader.try_get("dir/a.bin").unwrap();
+            assert_eq!((reader.sarc.data_offset + a.data_start as u64) % 0x100, 0);
+            assert!(reader.exists("dir"));
+            assert!(matches!(
+                reader.try_get("missing"),
+                Err(Error::NotFound { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Ticket (`.tik`) - eShop title key licence.
+//!
+//! NSPs for titles with a rights ID carry a ticket holding the encrypted
+//! title key. Register it with [`KeySet::import_ticket`] to decrypt the
+//! matching NCAs.
+//!
+//! ## Layout
+//! ```text
+//! [0x000] SignatureType              (u32 LE, 0x10000-0x10006)
+//! [0x004] Signature                  (size depends on type)
+//!         Padding                    (to a 0x40-byte boundary)
+//! Ticket data (offsets relative to its start):
+//! [0x000] Issuer                     (0x40 bytes, null-padded ASCII)
+//! [0x040] TitleKeyBlock              (0x100 bytes)
+//! [0x140] FormatVersion              (u8)
+//! [0x141] TitleKeyType               (u8, 0 = common, 1 = personalized)
+//! [0x142] TicketVersion              (u16 LE)
+//! [0x144] LicenseType                (u8)
+//! [0x145] MasterKeyRevision          (u8)
+//! [0x146] PropertyMask               (u16 LE)
+//! [0x148] Reserved                   (8 bytes)
+//! [0x150] TicketId                   (u64 LE)
+//! [0x158] DeviceId                   (u64 LE)
+//! [0x160] RightsId                   (16 bytes)
+//! [0x170] AccountId                  (u32 LE)
+//! ```
+//!
+//! ## Title key block
+//! A common ticket stores the titlekek-wrapped title key in the first 16
+//! bytes. A personalized ticket stores it RSA-OAEP encrypted with the
+//! console-unique eTicket key, which this library does not handle.
+//!
+//! [`KeySet::import_ticket`]: crate::keys::KeySet::import_ticket
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::utils::{bytesa, bytesv, le_u16, le_u32, le_u64, null_padded_string, u8};
+use crate::{Error, Result};
+
+/// Signature scheme of a ticket (or certificate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignatureType {
+    Rsa4096Sha1,
+    Rsa2048Sha1,
+    EcdsaSha1,
+    Rsa4096Sha256,
+    Rsa2048Sha256,
+    EcdsaSha256,
+    HmacSha1,
+    Unknown(u32),
+}
+
+impl From<u32> for SignatureType {
+    fn from(v: u32) -> Self {
+        match v {
+            0x10000 => Self::Rsa4096Sha1,
+            0x10001 => Self::Rsa2048Sha1,
+            0x10002 => Self::EcdsaSha1,
+            0x10003 => Self::Rsa4096Sha256,
+            0x10004 => Self::Rsa2048Sha256,
+            0x10005 => Self::EcdsaSha256,
+            0x10006 => Self::HmacSha1,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl SignatureType {
+    /// Size of the signature plus its padding, excluding the type field.
+    ///
+    /// Returns [`None`] for unknown types.
+    pub fn block_size(self) -> Option<usize> {
+        match self {
+            Self::Rsa4096Sha1 | Self::Rsa4096Sha256 => Some(0x200 + 0x3C),
+            Self::Rsa2048Sha1 | Self::Rsa2048Sha256 => Some(0x100 + 0x3C),
+            Self::EcdsaSha1 | Self::EcdsaSha256 => Some(0x3C + 0x40),
+            Self::HmacSha1 => Some(0x14 + 0x28),
+            Self::Unknown(_) => None,
+        }
+    }
+}
+
+/// How the title key in a ticket is encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TitleKeyType {
+    /// Wrapped with the titlekek only; usable on any console.
+    Common,
+    /// RSA-OAEP encrypted for one console.
+    Personalized,
+    Unknown(u8),
+}
+
+impl From<u8> for TitleKeyType {
+    fn from(v: u8) -> Self {
+        match v {
+            0 => Self::Common,
+            1 => Self::Personalized,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+/// Parsed ticket.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ticket {
+    pub signature_type: SignatureType,
+    /// Raw signature bytes (without padding).
+    pub signature: Vec<u8>,
+    /// Issuer, e.g. `Root-CA00000003-XS00000020`.
+    pub issuer: String,
+    /// Raw 0x100-byte title key block.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::byte_array"))]
+    pub title_key_block: [u8; 0x100],
+    pub format_version: u8,
+    pub title_key_type: TitleKeyType,
+    pub ticket_version: u16,
+    pub license_type: u8,
+    /// Master key revision of the titlekek that wraps the title key.
+    pub master_key_revision: u8,
+    pub property_mask: u16,
+    pub ticket_id: u64,
+    pub device_id: u64,
+    pub rights_id: [u8; 16],
+    pub account_id: u32,
+}
+
+impl Ticket {
+    /// Parse a ticket from `r`.
+    ///
+    /// The reader must be positioned at the signature type. Returns
+    /// [`Error::Parse`] for unknown signature types.
+    pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let signature_type = SignatureType::from(le_u32(r)?);
+        let block_size = signature_type
+            .block_size()
+            .ok_or(Error::Parse("unknown ticket signature type"))?;
+        let signature_size = match signature_type {
+            SignatureType::Rsa4096Sha1 | SignatureType::Rsa4096Sha256 => 0x200,
+            SignatureType::Rsa2048Sha1 | SignatureType::Rsa2048Sha256 => 0x100,
+            SignatureType::EcdsaSha1 | SignatureType::EcdsaSha256 => 0x3C,
+            _ => 0x14,
+        };
+        let signature = bytesv(r, signature_size)?;
+        r.seek(SeekFrom::Current((block_size - signature_size) as i64))?;
+
+        let issuer = null_padded_string(&bytesa::<0x40>(r)?);
+        let title_key_block = bytesa::<0x100>(r)?;
+        let format_version = u8(r)?;
+        let title_key_type = TitleKeyType::from(u8(r)?);
+        let ticket_version = le_u16(r)?;
+        let license_type = u8(r)?;
+        let master_key_revision = u8(r)?;
+        let property_mask = le_u16(r)?;
+        let _reserved = bytesa::<8>(r)?;
+        let ticket_id = le_u64(r)?;
+        let device_id = le_u64(r)?;
+        let rights_id = bytesa::<16>(r)?;
+        let account_id = le_u32(r)?;
+
+        Ok(Self {
+            signature_type,
+            signature,
+            issuer,
+            title_key_block,
+            format_version,
+            title_key_type,
+            ticket_version,
+            license_type,
+            master_key_revision,
+            property_mask,
+            ticket_id,
+            device_id,
+            rights_id,
+            account_id,
+        })
+    }
+
+    /// The titlekek-wrapped title key of a common ticket.
+    ///
+    /// Returns [`Error::Parse`] for personalized tickets, whose key block
+    /// needs the console's eTicket RSA key.
+    pub fn encrypted_title_key(&self) -> Result<[u8; 16]> {
+        match self.title_key_type {
+            TitleKeyType::Common => Ok(self.title_key_block[..16].try_into().unwrap()),
+            _ => Err(Error::Parse(
+                "personalized tickets require the console eTicket RSA key",
+            )),
+        }
+    }
+}
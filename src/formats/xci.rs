@@ -12,6 +12,12 @@
 //! [0x10000+]      NormalArea → root HFS0
 //! ```
 //!
+//! Most dumps omit the CardKeyArea and begin directly with the CardHeader,
+//! so every offset above is 0x1000 lower; "full" dumps keep it.
+//! [`Xci::parse`] accepts both and reports which it found in
+//! [`Xci::key_area`]. Addresses stored in the CardHeader are relative to
+//! the CardHeader either way.
+//!
 //! ## CardHeader (at 0x1000, 0x200 bytes total)
 //! ```text
 //! [+0x000] RSA-2048 signature over [+0x100..+0x200]     (0x100 bytes)
@@ -46,20 +52,98 @@
 //! | 0xE0  | 8 GB     |
 //! | 0xE1  | 16 GB    |
 //! | 0xE2  | 32 GB    |
+//!
+//! ## Flags byte bits
+//! | Bit | Meaning                          |
+//! |-----|----------------------------------|
+//! | 0   | AutoBoot                         |
+//! | 1   | HistoryErase                     |
+//! | 2   | RepairTool                       |
+//! | 3   | DifferentRegionCupToTerraDevice  |
+//! | 4   | DifferentRegionCupToGlobalDevice |
+//! | 7   | HasNewCardHeader (CardHeaderT2)  |
+//!
+//! ## Bundled firmware
+//! The `update` partition carries the system update the card requires.
+//! [`Xci::firmware_version`] finds its SystemUpdate meta NCA and decodes
+//! the version from the CNMT (see [`cnmt`](super::cnmt)).
+//!
+//! ## Trimming
+//! Dumps are usually the full card capacity, with everything past
+//! `ValidDataEndAddress` filled with 0xFF. A *trimmed* dump drops that
+//! padding; [`Xci::trim`] and [`Xci::untrim`] convert between the two.
+//! The full size of a card is 952 MiB per GB of capacity.
 
-use std::io::{Read, Seek, SeekFrom};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
 
+use super::cnmt::{Cnmt, ContentMetaType, system_version_string};
 use super::hfs0::Hfs0;
-use crate::Result;
+use crate::error::Context;
+use crate::keys::KeySet;
 use crate::utils::{bytesa, le_u32, le_u64, magic, u8};
+use crate::{Error, Result};
+
+/// Size of a card page; header addresses are in pages.
+pub const PAGE_SIZE: u64 = 0x200;
+
+/// Size of the CardKeyArea prepended to "full" dumps.
+pub const KEY_AREA_SIZE: u64 = 0x1000;
+
+/// CardHeader Flags byte (see table in module docs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XciFlags(pub u8);
+
+impl XciFlags {
+    pub const AUTO_BOOT: Self = Self(1 << 0);
+    pub const HISTORY_ERASE: Self = Self(1 << 1);
+    pub const REPAIR_TOOL: Self = Self(1 << 2);
+    pub const DIFFERENT_REGION_CUP_TO_TERRA_DEVICE: Self = Self(1 << 3);
+    pub const DIFFERENT_REGION_CUP_TO_GLOBAL_DEVICE: Self = Self(1 << 4);
+    pub const HAS_NEW_CARD_HEADER: Self = Self(1 << 7);
+
+    /// `true` if every bit set in `other` is also set in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn auto_boot(self) -> bool {
+        self.contains(Self::AUTO_BOOT)
+    }
+
+    pub const fn history_erase(self) -> bool {
+        self.contains(Self::HISTORY_ERASE)
+    }
+
+    pub const fn repair_tool(self) -> bool {
+        self.contains(Self::REPAIR_TOOL)
+    }
+
+    pub const fn different_region_cup_to_terra_device(self) -> bool {
+        self.contains(Self::DIFFERENT_REGION_CUP_TO_TERRA_DEVICE)
+    }
+
+    pub const fn different_region_cup_to_global_device(self) -> bool {
+        self.contains(Self::DIFFERENT_REGION_CUP_TO_GLOBAL_DEVICE)
+    }
+
+    pub const fn has_new_card_header(self) -> bool {
+        self.contains(Self::HAS_NEW_CARD_HEADER)
+    }
+}
 
 /// Parsed XCI game card image.
 ///
 /// Only the unencrypted fields of the CardHeader are captured here.
 /// The AES-128-CBC encrypted `CardHeaderEncryptedData` region is not parsed.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Xci {
-    /// Absolute file offset of the root HFS0 header (from CardHeader +0x130).
+    /// Whether the dump starts with a CardKeyArea.
+    pub key_area: bool,
+    /// Absolute file offset of the root HFS0 header (from CardHeader +0x130,
+    /// adjusted for the key area).
     pub hfs0_offset: u64,
     /// Size of the root HFS0 region (from CardHeader +0x138).
     pub hfs0_size: u64,
@@ -69,6 +153,10 @@ pub struct Xci {
     pub rom_size: u8,
     /// PackageId used for challenge-response authentication.
     pub package_id: u64,
+    /// Flags byte (from CardHeader +0x10F).
+    pub flags: XciFlags,
+    /// Page address of the last page holding valid data.
+    pub valid_data_end: u32,
     /// Parsed root HFS0 listing the sub-partitions.
     pub root_partition: Hfs0,
 }
@@ -76,53 +164,166 @@ pub struct Xci {
 impl Xci {
     /// Parse an XCI file.
     ///
-    /// The reader must be positioned at the beginning of the XCI file.
+    /// The reader must be positioned at the beginning of the XCI file,
+    /// with or without a CardKeyArea.
     /// No crypto is performed; fields within the encrypted `CardHeaderEncryptedData`
     /// region are not extracted.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
-        // Skip CardKeyArea (0x1000 bytes) + RSA signature (0x100 bytes).
-        // Magic "HEAD" is at absolute offset 0x1100.
-        r.seek(SeekFrom::Start(0x1100))?;
-        magic(r, b"HEAD")?;
-
-        // 0x1104: RomAreaStartPageAddress
-        let _rom_start = le_u32(r)?;
-        // 0x1108: BackupAreaStartPageAddress (always 0xFFFFFFFF)
-        let _backup = le_u32(r)?;
-        // 0x110C: TitleKeyDecIndex (high nibble) | KekIndex (low nibble)
-        let _key_indices = u8(r)?;
-        // 0x110D: RomSize
-        let rom_size = u8(r)?;
-        // 0x110E: Version
-        let _version = u8(r)?;
-        // 0x110F: Flags
-        let _flags = u8(r)?;
-        // 0x1110: PackageId
-        let package_id = le_u64(r)?;
-        // 0x1118: ValidDataEndAddress
-        let _valid_end = le_u32(r)?;
-        // 0x111C: Reserved
-        let _reserved = le_u32(r)?;
-        // 0x1120: IV (0x10 bytes)
-        let _iv = bytesa::<0x10>(r)?;
-        // 0x1130: PartitionFsHeaderAddress
-        let hfs0_offset = le_u64(r)?;
-        // 0x1138: PartitionFsHeaderSize
-        let hfs0_size = le_u64(r)?;
-        // 0x1140: PartitionFsHeaderHash
-        let hfs0_header_hash = bytesa::<0x20>(r)?;
+        // Magic "HEAD" follows the RSA signature (0x100 bytes), after the
+        // CardKeyArea (0x1000 bytes) if there is one.
+        r.seek(SeekFrom::Start(0x100))?;
+        let key_area =
+            bytesa::<4>(r).at_field("XCI", 0, || "card header".into(), "magic")? != *b"HEAD";
+        let base = if key_area { KEY_AREA_SIZE } else { 0 };
+        let header = || "card header".to_string();
+        if key_area {
+            r.seek(SeekFrom::Start(base + 0x100))?;
+            magic(r, b"HEAD").at_field("XCI", base, header, "magic")?;
+        }
+
+        // +0x104: RomAreaStartPageAddress
+        let _rom_start = le_u32(r).at_field("XCI", base, header, "RomAreaStartPageAddress")?;
+        // +0x108: BackupAreaStartPageAddress (always 0xFFFFFFFF)
+        let _backup = le_u32(r).at_field("XCI", base, header, "BackupAreaStartPageAddress")?;
+        // +0x10C: TitleKeyDecIndex (high nibble) | KekIndex (low nibble)
+        let _key_indices = u8(r).at_field("XCI", base, header, "KekIndex")?;
+        // +0x10D: RomSize
+        let rom_size = u8(r).at_field("XCI", base, header, "RomSize")?;
+        // +0x10E: Version
+        let _version = u8(r).at_field("XCI", base, header, "Version")?;
+        // +0x10F: Flags
+        let flags = XciFlags(u8(r).at_field("XCI", base, header, "Flags")?);
+        // +0x110: PackageId
+        let package_id = le_u64(r).at_field("XCI", base, header, "PackageId")?;
+        // +0x118: ValidDataEndAddress
+        let valid_data_end = le_u32(r).at_field("XCI", base, header, "ValidDataEndAddress")?;
+        // +0x11C: Reserved
+        let _reserved = le_u32(r).at_field("XCI", base, header, "Reserved")?;
+        // +0x120: IV (0x10 bytes)
+        let _iv = bytesa::<0x10>(r).at_field("XCI", base, header, "IV")?;
+        // +0x130: PartitionFsHeaderAddress
+        let hfs0_offset =
+            base + le_u64(r).at_field("XCI", base, header, "PartitionFsHeaderAddress")?;
+        // +0x138: PartitionFsHeaderSize
+        let hfs0_size = le_u64(r).at_field("XCI", base, header, "PartitionFsHeaderSize")?;
+        // +0x140: PartitionFsHeaderHash
+        let hfs0_header_hash =
+            bytesa::<0x20>(r).at_field("XCI", base, header, "PartitionFsHeaderHash")?;
 
         // Seek to root HFS0 and parse it.
         r.seek(SeekFrom::Start(hfs0_offset))?;
-        let root_partition = Hfs0::parse(r)?;
+        let root_partition = Hfs0::parse(r).at("XCI", hfs0_offset, || "root partition".into())?;
 
         Ok(Self {
+            key_area,
             hfs0_offset,
             hfs0_size,
             hfs0_header_hash,
             rom_size,
             package_id,
+            flags,
+            valid_data_end,
             root_partition,
         })
     }
+
+    /// Version of the system update bundled in the `update` partition, as
+    /// `major.minor.micro`.
+    ///
+    /// `r` must be the stream this XCI was parsed from; `keys` must hold
+    /// the header key and the key area keys for the meta NCAs. Returns
+    /// `Ok(None)` if the card has no `update` partition or no SystemUpdate
+    /// meta.
+    pub fn firmware_version<R: Read + Seek>(
+        &self,
+        r: &mut R,
+        keys: &KeySet,
+    ) -> Result<Option<String>> {
+        let Some(partition) = self
+            .root_partition
+            .files
+            .iter()
+            .find(|f| f.name == "update")
+        else {
+            return Ok(None);
+        };
+        r.seek(SeekFrom::Start(
+            self.root_partition.data_offset + partition.offset,
+        ))?;
+        let update = Hfs0::parse(r)?;
+
+        for file in update
+            .files
+            .iter()
+            .filter(|f| f.name.ends_with(".cnmt.nca"))
+        {
+            r.seek(SeekFrom::Start(update.data_offset + file.offset))?;
+            let cnmt = Cnmt::from_meta_nca(&mut *r, keys)?;
+            if cnmt.meta_type == ContentMetaType::SystemUpdate {
+                return Ok(Some(system_version_string(cnmt.version)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Bytes preceding the CardHeader: [`KEY_AREA_SIZE`] or 0.
+    fn base(&self) -> u64 {
+        if self.key_area { KEY_AREA_SIZE } else { 0 }
+    }
+
+    /// Size in bytes of the dump with the trailing padding removed.
+    pub fn trimmed_size(&self) -> u64 {
+        self.base() + (self.valid_data_end as u64 + 1) * PAGE_SIZE
+    }
+
+    /// Size in bytes of an untrimmed dump, from the RomSize byte.
+    ///
+    /// Returns [`None`] for an unrecognised RomSize.
+    pub fn full_size(&self) -> Option<u64> {
+        let gb = match self.rom_size {
+            0xFA => 1,
+            0xF8 => 2,
+            0xF0 => 4,
+            0xE0 => 8,
+            0xE1 => 16,
+            0xE2 => 32,
+            _ => return None,
+        };
+        Some(self.base() + gb * 952 * 0x10_0000)
+    }
+
+    /// `true` if a dump of `file_len` bytes holds nothing past the valid
+    /// data.
+    pub fn is_trimmed(&self, file_len: u64) -> bool {
+        file_len <= self.trimmed_size()
+    }
+
+    /// Trim the dump in `file` by truncating it to
+    /// [`trimmed_size`](Self::trimmed_size).
+    ///
+    /// Does nothing if the file is already trimmed.
+    pub fn trim(&self, file: &File) -> Result<()> {
+        if file.metadata()?.len() > self.trimmed_size() {
+            file.set_len(self.trimmed_size())?;
+        }
+        Ok(())
+    }
+
+    /// Restore a trimmed dump by padding `w` with 0xFF up to
+    /// [`full_size`](Self::full_size).
+    ///
+    /// Returns [`Error::Parse`] if the RomSize is unrecognised.
+    pub fn untrim<W: Write + Seek>(&self, w: &mut W) -> Result<()> {
+        let full = self
+            .full_size()
+            .ok_or(Error::Parse("unknown XCI RomSize"))?;
+        let mut len = w.seek(SeekFrom::End(0))?;
+        let pad = [0xFF; 0x1_0000];
+        while len < full {
+            let n = (full - len).min(pad.len() as u64) as usize;
+            w.write_all(&pad[..n])?;
+            len += n as u64;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,416 @@
+//! Key derivation from console root keys.
+//!
+//! Reproduces the derivation performed by Lockpick / hactool, so a key file
+//! holding only root keys and key sources can be expanded into everything
+//! needed to decrypt content:
+//!
+//! ```text
+//! secure_boot_key + tsec_key + keyblob_key_source_XX  → keyblob_key_XX
+//!   keyblob_key_XX + encrypted_keyblob_XX (0x00-0x05)  → master_kek_XX
+//! tsec_root_key_XX + master_kek_source_YY (0x06-)     → master_kek_YY  (YY = XX + 6)
+//! mariko_kek + mariko_master_kek_source_XX            → master_kek_XX
+//!   master_kek_XX + master_key_source                  → master_key_XX
+//!     master_key_XX + titlekek_source                  → titlekek_XX
+//!     master_key_XX + key_area_key_*_source            → key_area_key_*_XX
+//!     master_key_00 + header_kek_source                → header_key
+//! ```
+//!
+//! No key material is built in: root keys and sources are loaded into
+//! [`KeySet::sources`] by [`KeySet::load_prod_keys`] like any other key.
+//! Keys that are already present in the [`KeySet`] are never overwritten.
+
+use std::result::Result as StdResult;
+
+use super::{IgnoredReason, KeySet, MAX_KEY_GENERATION, decode_hex_n, set_indexed};
+use crate::crypto::nca::{Aes128, decrypt_section_ctr};
+use crate::{Error, Result};
+
+/// Number of master key revisions whose master kek is stored in a keyblob
+/// (firmware 1.0.0 - 6.0.0); later revisions use `tsec_root_key_XX`.
+pub const KEYBLOB_COUNT: usize = 6;
+
+/// Size of an `encrypted_keyblob_XX` entry: CMAC, AES-CTR counter, and the
+/// 0x90-byte encrypted keyblob.
+pub const ENCRYPTED_KEYBLOB_SIZE: usize = 0xB0;
+
+/// Root keys and key sources from which the rest of a [`KeySet`] is derived.
+///
+/// Field names match their `prod.keys` names.
+#[derive(Debug, Default)]
+pub struct KeySources {
+    pub secure_boot_key: Option<[u8; 16]>,
+    pub tsec_key: Option<[u8; 16]>,
+    /// Indexed by `master key revision - 6`.
+    pub tsec_root_key: [Option<[u8; 16]>; MAX_KEY_GENERATION],
+    pub mariko_kek: Option<[u8; 16]>,
+
+    pub keyblob_key_source: [Option<[u8; 16]>; KEYBLOB_COUNT],
+    pub encrypted_keyblob: [Option<[u8; ENCRYPTED_KEYBLOB_SIZE]>; KEYBLOB_COUNT],
+    pub keyblob_mac_key_source: Option<[u8; 16]>,
+
+    pub master_kek_source: [Option<[u8; 16]>; MAX_KEY_GENERATION],
+    pub mariko_master_kek_source: [Option<[u8; 16]>; MAX_KEY_GENERATION],
+    pub master_key_source: Option<[u8; 16]>,
+
+    pub titlekek_source: Option<[u8; 16]>,
+    pub aes_kek_generation_source: Option<[u8; 16]>,
+    pub aes_key_generation_source: Option<[u8; 16]>,
+    /// Indexed by [`KaekIndex`](super::KaekIndex).
+    pub key_area_key_source: [Option<[u8; 16]>; 3],
+    pub header_kek_source: Option<[u8; 16]>,
+    pub header_key_source: Option<[u8; 32]>,
+}
+
+impl KeySources {
+    /// Store a `prod.keys` entry if it names a root key or key source.
+    ///
+    /// Returns `None` for names this struct does not hold.
+    pub(super) fn set(&mut self, name: &str, value: &str) -> Option<StdResult<(), IgnoredReason>> {
+        let single = match name {
+            "secure_boot_key" => Some(&mut self.secure_boot_key),
+            "tsec_key" => Some(&mut self.tsec_key),
+            "mariko_kek" => Some(&mut self.mariko_kek),
+            "keyblob_mac_key_source" => Some(&mut self.keyblob_mac_key_source),
+            "master_key_source" => Some(&mut self.master_key_source),
+            "titlekek_source" => Some(&mut self.titlekek_source),
+            "aes_kek_generation_source" => Some(&mut self.aes_kek_generation_source),
+            "aes_key_generation_source" => Some(&mut self.aes_key_generation_source),
+            "key_area_key_application_source" => Some(&mut self.key_area_key_source[0]),
+            "key_area_key_ocean_source" => Some(&mut self.key_area_key_source[1]),
+            "key_area_key_system_source" => Some(&mut self.key_area_key_source[2]),
+            "header_kek_source" => Some(&mut self.header_kek_source),
+            _ => None,
+        };
+        if let Some(slot) = single {
+            return Some(decode_hex_n(value).map(|key| *slot = Some(key)));
+        }
+        if name == "header_key_source" {
+            return Some(decode_hex_n(value).map(|key| self.header_key_source = Some(key)));
+        }
+
+        if let Some(index) = name.strip_prefix("encrypted_keyblob_") {
+            return Some(set_indexed(&mut self.encrypted_keyblob, index, value));
+        }
+        for (prefix, slots) in [
+            ("tsec_root_key_", &mut self.tsec_root_key[..]),
+            ("keyblob_key_source_", &mut self.keyblob_key_source[..]),
+            ("master_kek_source_", &mut self.master_kek_source[..]),
+            (
+                "mariko_master_kek_source_",
+                &mut self.mariko_master_kek_source[..],
+            ),
+        ] {
+            if let Some(index) = name.strip_prefix(prefix) {
+                return Some(set_indexed(slots, index, value));
+            }
+        }
+        None
+    }
+}
+
+/// Derive every key obtainable from the root keys and sources in
+/// `keys.sources`, filling in master keks, master keys, titlekeks, key area
+/// keys, and the header key.
+///
+/// Inputs that are missing simply leave their outputs unset. Returns
+/// [`Error::Parse`] if an encrypted keyblob fails its CMAC check, which
+/// means `secure_boot_key` or `tsec_key` is wrong.
+pub fn derive_keys(keys: &mut KeySet) -> Result<()> {
+    derive_master_keys(keys)?;
+    derive_dependent_keys(keys);
+    Ok(())
+}
+
+/// Derive master keks and master keys from keyblobs, TSEC root keys, and
+/// the Mariko KEK.
+pub fn derive_master_keys(keys: &mut KeySet) -> Result<()> {
+    let src = &keys.sources;
+
+    if let (Some(sbk), Some(tsec), Some(mac_source)) = (
+        &src.secure_boot_key,
+        &src.tsec_key,
+        &src.keyblob_mac_key_source,
+    ) {
+        let sbk = Aes128::new(sbk);
+        let tsec = Aes128::new(tsec);
+        for i in 0..KEYBLOB_COUNT {
+            let (Some(key_source), Some(blob)) =
+                (&src.keyblob_key_source[i], &src.encrypted_keyblob[i])
+            else {
+                continue;
+            };
+            let keyblob_key = sbk.decrypt_block(&tsec.decrypt_block(key_source));
+            let mac_key = Aes128::new(&Aes128::new(&keyblob_key).decrypt_block(mac_source));
+            if cmac(&mac_key, &blob[0x10..]) != blob[..0x10] {
+                return Err(Error::Parse("keyblob MAC mismatch"));
+            }
+
+            // Keyblob: [0x00] master_kek, [0x10] reserved, [0x80] package1_key.
+            let counter: [u8; 16] = blob[0x10..0x20].try_into().unwrap();
+            let mut keyblob = blob[0x20..0x30].to_vec();
+            decrypt_section_ctr(&mut keyblob, &keyblob_key, &counter);
+            keys.master_kek[i].get_or_insert(keyblob[..0x10].try_into().unwrap());
+        }
+    }
+
+    for i in KEYBLOB_COUNT..MAX_KEY_GENERATION {
+        if let (Some(root), Some(source)) = (
+            &src.tsec_root_key[i - KEYBLOB_COUNT],
+            &src.master_kek_source[i],
+        ) {
+            keys.master_kek[i].get_or_insert(Aes128::new(root).decrypt_block(source));
+        }
+    }
+    if let Some(mariko_kek) = &src.mariko_kek {
+        let mariko_kek = Aes128::new(mariko_kek);
+        for i in 0..MAX_KEY_GENERATION {
+            if let Some(source) = &src.mariko_master_kek_source[i] {
+                keys.master_kek[i].get_or_insert(mariko_kek.decrypt_block(source));
+            }
+        }
+    }
+
+    if let Some(source) = &src.master_key_source {
+        for i in 0..MAX_KEY_GENERATION {
+            if let Some(kek) = &keys.master_kek[i] {
+                keys.master_key[i].get_or_insert(Aes128::new(kek).decrypt_block(source));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Derive titlekeks, key area keys, and the header key from the master
+/// keys in `keys.master_key`.
+pub fn derive_dependent_keys(keys: &mut KeySet) {
+    let src = &keys.sources;
+
+    for i in 0..MAX_KEY_GENERATION {
+        let Some(master_key) = keys.master_key[i] else {
+            continue;
+        };
+        if let Some(source) = &src.titlekek_source {
+            keys.titlekek[i].get_or_insert(Aes128::new(&master_key).decrypt_block(source));
+        }
+        for (idx, source) in src.key_area_key_source.iter().enumerate() {
+            if let (Some(source), Some(kek_seed)) = (source, &src.aes_kek_generation_source) {
+                keys.kaek[idx][i].get_or_insert(generate_kek(
+                    source,
+                    &master_key,
+                    kek_seed,
+                    src.aes_key_generation_source.as_ref(),
+                ));
+            }
+        }
+    }
+
+    if keys.header_key.is_none()
+        && let (Some(master_key), Some(kek_source), Some(key_source), Some(kek_seed)) = (
+            &keys.master_key[0],
+            &src.header_kek_source,
+            &src.header_key_source,
+            &src.aes_kek_generation_source,
+        )
+    {
+        let header_kek = Aes128::new(&generate_kek(
+            kek_source,
+            master_key,
+            kek_seed,
+            src.aes_key_generation_source.as_ref(),
+        ));
+        let mut header_key = [0u8; 32];
+        for (out, block) in header_key
+            .chunks_exact_mut(16)
+            .zip(key_source.chunks_exact(16))
+        {
+            out.copy_from_slice(&header_kek.decrypt_block(block.try_into().unwrap()));
+        }
+        keys.header_key = Some(header_key);
+    }
+}
+
+/// The console's `GenerateAesKek` / `GenerateAesKey` pair: unwrap the
+/// per-master-key KEK, then the source key, then optionally the key seed.
+fn generate_kek(
+    source: &[u8; 16],
+    master_key: &[u8; 16],
+    kek_seed: &[u8; 16],
+    key_seed: Option<&[u8; 16]>,
+) -> [u8; 16] {
+    let kek = Aes128::new(&Aes128::new(master_key).decrypt_block(kek_seed));
+    let source_kek = kek.decrypt_block(source);
+    match key_seed {
+        Some(seed) => Aes128::new(&source_kek).decrypt_block(seed),
+        None => source_kek,
+    }
+}
+
+/// AES-128-CMAC (NIST SP 800-38B) of `data`.
+fn cmac(cipher: &Aes128, data: &[u8]) -> [u8; 16] {
+    // Subkeys K1 = 2·E(0), K2 = 4·E(0) in GF(2^128) (big-endian bit order, R = 0x87).
+    let double = |b: [u8; 16]| {
+        let v = u128::from_be_bytes(b);
+        ((v << 1) ^ if v >> 127 != 0 { 0x87 } else { 0 }).to_be_bytes()
+    };
+    let k1 = double(cipher.encrypt_block(&[0u8; 16]));
+    let k2 = double(k1);
+
+    let n = data.len().div_ceil(16).max(1);
+    let mut x = [0u8; 16];
+    for block in data.chunks(16).take(n - 1) {
+        for (x, b) in x.iter_mut().zip(block) {
+            *x ^= b;
+        }
+        x = cipher.encrypt_block(&x);
+    }
+
+    let last = &data[(n - 1) * 16..];
+    let mut m = [0u8; 16];
+    m[..last.len()].copy_from_slice(last);
+    let subkey = if last.len() == 16 {
+        k1
+    } else {
+        m[last.len()] = 0x80;
+        k2
+    };
+    for i in 0..16 {
+        x[i] ^= m[i] ^ subkey[i];
+    }
+    cipher.encrypt_block(&x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex<const N: usize>(s: &str) -> [u8; N] {
+        decode_hex_n(s).unwrap()
+    }
+
+    /// Synthetic root keys and sources; the expected outputs below were
+    /// computed with an independent AES implementation.
+    fn sources() -> KeySet {
+        let mut keys = KeySet::new();
+        let src = &mut keys.sources;
+        src.secure_boot_key = Some([0x11; 16]);
+        src.tsec_key = Some([0x22; 16]);
+        src.keyblob_key_source[0] = Some([0x33; 16]);
+        src.keyblob_mac_key_source = Some([0x44; 16]);
+        src.encrypted_keyblob[0] = Some(hex(concat!(
+            "ac5893f0496c240c0116a5f5161cf4ffc0c1c2c3c4c5c6c7c8c9cacbcccdcecf",
+            "b4ac1ce3de1711ba80e2b02586418d47bdfc9bf14a5d6037c8f4212a16b0a3ef",
+            "a309751201143a77e91802ded6fb139b52f60a086284348f2f1ecdb07faa64d0",
+            "837c72ae33777fce41249023596411f1e08e0e625bc62dc4c44fa542156d3d4d",
+            "328660ee6d024fbb40b1f5092dbe72bfbd9fba2c750b9a553fcba11c11abcbdd",
+            "ef0f64f5bb506fa460bc2340cc0f574c",
+        )));
+        src.master_key_source = Some([0x66; 16]);
+        src.titlekek_source = Some([0x77; 16]);
+        src.aes_kek_generation_source = Some([0x88; 16]);
+        src.aes_key_generation_source = Some([0x99; 16]);
+        src.key_area_key_source[0] = Some([0xAA; 16]);
+        src.header_kek_source = Some([0xBB; 16]);
+        src.header_key_source = Some(std::array::from_fn(|i| i as u8));
+        keys
+    }
+
+    #[test]
+    fn cmac_rfc4493() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c"));
+        let message: [u8; 64] = hex(concat!(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51",
+            "30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        ));
+        for (len, mac) in [
+            (0, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ] {
+            assert_eq!(cmac(&cipher, &message[..len]), hex(mac), "length {len}");
+        }
+    }
+
+    #[test]
+    fn keyblob_master_keys() {
+        let mut keys = sources();
+        derive_master_keys(&mut keys).unwrap();
+        assert_eq!(
+            keys.master_kek[0],
+            Some(hex("808182838485868788898a8b8c8d8e8f"))
+        );
+        assert_eq!(
+            keys.master_key[0],
+            Some(hex("47e12fc8f75b3b865f5e2b186b50c54f"))
+        );
+    }
+
+    #[test]
+    fn keyblob_mac_mismatch() {
+        let mut keys = sources();
+        keys.sources.encrypted_keyblob[0].as_mut().unwrap()[0x40] ^= 1;
+        assert!(matches!(
+            derive_master_keys(&mut keys),
+            Err(Error::Parse("keyblob MAC mismatch"))
+        ));
+    }
+
+    #[test]
+    fn tsec_and_mariko_master_keks() {
+        let mut keys = KeySet::new();
+        keys.sources.tsec_root_key[0] = Some([0xD0; 16]);
+        keys.sources.master_kek_source[6] = Some([0xD6; 16]);
+        keys.sources.mariko_kek = Some([0xE0; 16]);
+        keys.sources.mariko_master_kek_source[7] = Some([0xE7; 16]);
+        derive_master_keys(&mut keys).unwrap();
+        assert_eq!(
+            keys.master_kek[6],
+            Some(hex("be1259c0c659dc69d331a68e678f7446"))
+        );
+        assert_eq!(
+            keys.master_kek[7],
+            Some(hex("b73be9f0fdb650f60e1f5d8b99cd8f89"))
+        );
+    }
+
+    #[test]
+    fn generate_kek_with_and_without_seed() {
+        let master_key = hex("47e12fc8f75b3b865f5e2b186b50c54f");
+        assert_eq!(
+            generate_kek(&[0xAA; 16], &master_key, &[0x88; 16], Some(&[0x99; 16])),
+            hex("ff033962b4d99c7e70ca09e017c3e9e7")
+        );
+        assert_eq!(
+            generate_kek(&[0xAA; 16], &master_key, &[0x88; 16], None),
+            hex("516603730fe3639964fbfb343c992842")
+        );
+    }
+
+    #[test]
+    fn dependent_keys() {
+        let mut keys = sources();
+        derive_keys(&mut keys).unwrap();
+        assert_eq!(
+            keys.titlekek[0],
+            Some(hex("9750a732bfa18d1444488a404e74c6f7"))
+        );
+        assert_eq!(
+            keys.kaek[0][0],
+            Some(hex("ff033962b4d99c7e70ca09e017c3e9e7"))
+        );
+        assert_eq!(
+            keys.header_key,
+            Some(hex(
+                "90746ec254186a02db7016f50b9ee2d95c5df8eeaafaa23b46a2d7e630147b22"
+            ))
+        );
+    }
+
+    #[test]
+    fn existing_keys_are_kept() {
+        let mut keys = sources();
+        keys.master_key[0] = Some([0xFF; 16]);
+        derive_master_keys(&mut keys).unwrap();
+        assert_eq!(keys.master_key[0], Some([0xFF; 16]));
+    }
+}
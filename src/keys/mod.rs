@@ -0,0 +1,565 @@
+//! Key management for Nintendo Switch cryptography.
+//!
+//! Nintendo Switch titles use a layered key derivation scheme:
+//!
+//! * **Master keys** (`master_key_XX`) are the root secrets obtained from
+//!   the security processor. There is one per firmware generation.
+//! * **Key area encryption keys** (KAEK) are derived per content type
+//!   (Application / Ocean / System) from the master key.
+//! * **Title keys** decrypt individual NCAs; they are themselves wrapped
+//!   with KAEK or with a per-title key.
+//! * **Header key** decrypts the AES-XTS NCA header (0xC00 bytes).
+//!
+//! This module is mostly a plain data container. Callers load keys from
+//! `prod.keys` / `title.keys` and pass them to the crypto functions in
+//! [`crate::crypto`]; the only cryptography done here is unwrapping title
+//! keys ([`KeySet::unwrap_title_key`]).
+//!
+//! Keys that are missing from the file can be computed from root keys and
+//! key sources with [`derive::derive_keys`], and loaded keys can be checked
+//! against reference digests with [`KeySet::validate`].
+//!
+//! ## Key file format
+//! Nintendo key files are simple `name = hex_value` text files, one entry
+//! per line, comments prefixed with `;`.
+
+pub mod derive;
+pub mod validate;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
+use std::path::PathBuf;
+use std::result::Result as StdResult;
+
+use self::derive::KeySources;
+use crate::crypto::nca::Aes128;
+use crate::formats::pfs0::{Pfs0File, Pfs0Reader};
+use crate::formats::ticket::{Ticket, TitleKeyType};
+use crate::{Error, Result};
+
+/// Maximum number of master key generations understood by this library.
+pub const MAX_KEY_GENERATION: usize = 32;
+
+/// Key area encryption key index (determines which KAEK derivation chain is
+/// used for a particular NCA).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KaekIndex {
+    /// Application content (most games).
+    Application = 0,
+    /// Ocean content (game-card specific).
+    Ocean = 1,
+    /// System content (OS modules).
+    System = 2,
+}
+
+impl KaekIndex {
+    /// Name of this key family as used in `prod.keys`
+    /// (`key_area_key_<name>_XX`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Application => "application",
+            Self::Ocean => "ocean",
+            Self::System => "system",
+        }
+    }
+}
+
+impl TryFrom<u8> for KaekIndex {
+    type Error = Error;
+    fn try_from(v: u8) -> Result<Self> {
+        match v {
+            0 => Ok(Self::Application),
+            1 => Ok(Self::Ocean),
+            2 => Ok(Self::System),
+            _ => Err(Error::Parse("invalid KAEK index")),
+        }
+    }
+}
+
+/// Key family a [`KeySet`] belongs to.
+///
+/// Retail consoles use the production keys; development units sign and
+/// encrypt content with a separate family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyEnvironment {
+    #[default]
+    Prod,
+    Dev,
+}
+
+/// All keys needed to decrypt Switch content.
+///
+/// Fields that are absent will be [`None`] / zero-length; the crypto layer
+/// will return an error rather than silently producing garbage output.
+#[derive(Debug, Default)]
+pub struct KeySet {
+    /// AES-XTS key pair (two 16-byte keys) used to decrypt NCA headers.
+    pub header_key: Option<[u8; 32]>,
+
+    /// Key area encryption keys, indexed by [`KaekIndex`] then by generation.
+    ///
+    /// `kaek[index][generation]` is a 16-byte AES key.
+    pub kaek: [[Option<[u8; 16]>; MAX_KEY_GENERATION]; 3],
+
+    /// Public moduli of the fixed RSA-2048 keys that sign NCA headers,
+    /// indexed by the header's signature key generation.
+    pub nca_hdr_fixed_key_modulus: [Option<[u8; 0x100]>; 2],
+
+    /// Public moduli of the fixed RSA-2048 keys that sign NPDM ACIDs,
+    /// indexed by the NPDM's signature key generation.
+    pub acid_fixed_key_modulus: [Option<[u8; 0x100]>; 2],
+
+    /// Title key encryption keys, indexed by master key revision.
+    ///
+    /// `titlekek[revision]` unwraps title keys with AES-128-ECB.
+    pub titlekek: [Option<[u8; 16]>; MAX_KEY_GENERATION],
+
+    /// Master key encryption keys, indexed by master key revision.
+    pub master_kek: [Option<[u8; 16]>; MAX_KEY_GENERATION],
+
+    /// Master keys, indexed by master key revision.
+    pub master_key: [Option<[u8; 16]>; MAX_KEY_GENERATION],
+
+    /// Console-unique BIS (NAND partition) keys, each an AES-XTS key pair.
+    ///
+    /// `bis_key[0]` is PRODINFO/PRODINFOF, `[1]` SAFE, `[2]` SYSTEM, and
+    /// `[3]` USER.
+    pub bis_key: [Option<[u8; 32]>; 4],
+
+    /// Console-unique seed used to derive SD card (NAX0) keys.
+    pub sd_seed: Option<[u8; 16]>,
+
+    /// Console-unique key for save data CMACs.
+    pub save_mac_key: Option<[u8; 16]>,
+
+    /// Root keys and key sources used by [`derive`](mod@derive).
+    pub sources: KeySources,
+
+    /// Title keys, keyed by 16-byte rights ID (hex string) → 16-byte key.
+    pub title_keys: HashMap<[u8; 16], [u8; 16]>,
+
+    /// Which key family this set holds.
+    pub environment: KeyEnvironment,
+
+    /// Development key family, loaded with [`KeySet::load_dev_keys`].
+    ///
+    /// Kept separate so dev-signed content can be processed without
+    /// clobbering the retail keys in this set.
+    pub dev: Option<Box<KeySet>>,
+}
+
+impl KeySet {
+    /// Create an empty key set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the 32-byte NCA header key.
+    pub fn set_header_key(&mut self, key: [u8; 32]) -> &mut Self {
+        self.header_key = Some(key);
+        self
+    }
+
+    /// Set the key area key for `index` and `generation`.
+    ///
+    /// Returns [`Error::InvalidRange`] if `generation` is not below
+    /// [`MAX_KEY_GENERATION`].
+    pub fn insert_kaek(
+        &mut self,
+        index: KaekIndex,
+        generation: u8,
+        key: [u8; 16],
+    ) -> Result<&mut Self> {
+        *self.kaek[index as usize]
+            .get_mut(generation as usize)
+            .ok_or(Error::InvalidRange)? = Some(key);
+        Ok(self)
+    }
+
+    /// Set the titlekek for master key revision `generation`.
+    ///
+    /// Returns [`Error::InvalidRange`] if `generation` is not below
+    /// [`MAX_KEY_GENERATION`].
+    pub fn insert_titlekek(&mut self, generation: u8, key: [u8; 16]) -> Result<&mut Self> {
+        *self
+            .titlekek
+            .get_mut(generation as usize)
+            .ok_or(Error::InvalidRange)? = Some(key);
+        Ok(self)
+    }
+
+    /// Set the master key for `revision`.
+    ///
+    /// Returns [`Error::InvalidRange`] if `revision` is not below
+    /// [`MAX_KEY_GENERATION`].
+    pub fn insert_master_key(&mut self, revision: u8, key: [u8; 16]) -> Result<&mut Self> {
+        *self
+            .master_key
+            .get_mut(revision as usize)
+            .ok_or(Error::InvalidRange)? = Some(key);
+        Ok(self)
+    }
+
+    /// Register the (encrypted) title key for `rights_id`, replacing any
+    /// existing entry.
+    pub fn insert_title_key(&mut self, rights_id: [u8; 16], key: [u8; 16]) -> &mut Self {
+        self.title_keys.insert(rights_id, key);
+        self
+    }
+
+    /// Load keys from a `prod.keys`-style reader.
+    ///
+    /// Lines beginning with `;` and blank lines are ignored. Each valid line
+    /// has the form `key_name = hexvalue`. Unknown key names are silently
+    /// skipped so that the library remains forward-compatible; use
+    /// [`KeySet::load_prod_keys_with_report`] to see which lines were
+    /// skipped.
+    ///
+    /// Titlekeks, key area keys, and the header key that the file does not
+    /// list are derived from `master_key_XX` and the key sources (see
+    /// [`derive::derive_dependent_keys`]), so a file with only master keys
+    /// and sources is enough.
+    pub fn load_prod_keys<R: Read>(&mut self, reader: R) -> Result<()> {
+        self.load_prod_keys_with_report(reader).map(drop)
+    }
+
+    /// Like [`KeySet::load_prod_keys`], but return a report listing every
+    /// line that was ignored and why.
+    pub fn load_prod_keys_with_report<R: Read>(&mut self, reader: R) -> Result<KeyFileReport> {
+        let report = for_each_entry(reader, |name, value| self.set_named(name, value))?;
+        derive::derive_dependent_keys(self);
+        Ok(report)
+    }
+
+    /// Store one `prod.keys` entry.
+    fn set_named(&mut self, name: &str, value: &str) -> StdResult<(), IgnoredReason> {
+        if let Some(result) = self.sources.set(name, value) {
+            return result;
+        }
+        if name == "header_key" {
+            self.header_key = Some(decode_hex_n(value)?);
+            return Ok(());
+        }
+        if name == "sd_seed" {
+            self.sd_seed = Some(decode_hex_n(value)?);
+            return Ok(());
+        }
+        if name == "save_mac_key" {
+            self.save_mac_key = Some(decode_hex_n(value)?);
+            return Ok(());
+        }
+        if let Some(index) = name.strip_prefix("bis_key_") {
+            return set_indexed(&mut self.bis_key, index, value);
+        }
+        if let Some(index) = name.strip_prefix("nca_hdr_fixed_key_modulus_") {
+            return set_indexed(&mut self.nca_hdr_fixed_key_modulus, index, value);
+        }
+        if let Some(index) = name.strip_prefix("acid_fixed_key_modulus_") {
+            return set_indexed(&mut self.acid_fixed_key_modulus, index, value);
+        }
+
+        let [application, ocean, system] = &mut self.kaek;
+        for (prefix, slots) in [
+            ("master_key_", &mut self.master_key),
+            ("master_kek_", &mut self.master_kek),
+            ("titlekek_", &mut self.titlekek),
+            ("key_area_key_application_", application),
+            ("key_area_key_ocean_", ocean),
+            ("key_area_key_system_", system),
+        ] {
+            if let Some(index) = name.strip_prefix(prefix) {
+                return set_indexed(slots, index, value);
+            }
+        }
+        Err(IgnoredReason::UnknownName)
+    }
+
+    /// Load development keys from a `dev.keys`-style reader into
+    /// [`KeySet::dev`], creating it if needed.
+    ///
+    /// The format is the same as [`KeySet::load_prod_keys`].
+    pub fn load_dev_keys<R: Read>(&mut self, reader: R) -> Result<()> {
+        let dev = self.dev.get_or_insert_with(|| {
+            Box::new(KeySet {
+                environment: KeyEnvironment::Dev,
+                ..KeySet::default()
+            })
+        });
+        dev.load_prod_keys(reader)
+    }
+
+    /// The key set for `environment`: `self` if it matches, otherwise the
+    /// loaded [`KeySet::dev`] family.
+    ///
+    /// Pass the result to [`NcaReader::new`](crate::formats::nca::NcaReader::new)
+    /// and friends to process content of that environment.
+    pub fn env(&self, environment: KeyEnvironment) -> Option<&KeySet> {
+        if self.environment == environment {
+            Some(self)
+        } else {
+            self.dev
+                .as_deref()
+                .filter(|dev| dev.environment == environment)
+        }
+    }
+
+    /// Load title keys from a `title.keys`-style reader.
+    ///
+    /// Each line: `<32-hex-char rights_id> = <32-hex-char title_key>`.
+    pub fn load_title_keys<R: Read>(&mut self, reader: R) -> Result<()> {
+        self.load_title_keys_with_report(reader).map(drop)
+    }
+
+    /// Like [`KeySet::load_title_keys`], but return a report listing every
+    /// line that was ignored and why.
+    pub fn load_title_keys_with_report<R: Read>(&mut self, reader: R) -> Result<KeyFileReport> {
+        for_each_entry(reader, |rights, key| {
+            let rights = decode_hex_n(rights)?;
+            self.title_keys.insert(rights, decode_hex_n(key)?);
+            Ok(())
+        })
+    }
+
+    /// Register the title key of a common ticket under its rights ID.
+    ///
+    /// Returns [`Error::Parse`] for personalized tickets.
+    pub fn import_ticket(&mut self, ticket: &Ticket) -> Result<()> {
+        let key = ticket.encrypted_title_key()?;
+        self.title_keys.insert(ticket.rights_id, key);
+        Ok(())
+    }
+
+    /// Import every `.tik` entry of an NSP.
+    ///
+    /// Personalized tickets are skipped. Returns the number of title keys
+    /// imported.
+    pub fn import_tickets<R: Read + Seek>(&mut self, nsp: &mut Pfs0Reader<R>) -> Result<usize> {
+        let tickets: Vec<Pfs0File> = nsp
+            .files()
+            .filter(|f| f.name.ends_with(".tik"))
+            .cloned()
+            .collect();
+
+        let mut imported = 0;
+        for file in &tickets {
+            let data = nsp.read_file_to_vec(file)?;
+            let ticket = Ticket::parse(&mut Cursor::new(data))?;
+            if ticket.title_key_type == TitleKeyType::Common {
+                self.import_ticket(&ticket)?;
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+
+    /// Load `prod.keys`, `title.keys`, and `dev.keys` from the conventional
+    /// locations.
+    ///
+    /// Each file is taken from the first of [`default_key_dirs`] that
+    /// contains it; a missing file is not an error. Returns the paths that
+    /// were loaded.
+    pub fn load_default(&mut self) -> Result<LoadedKeyFiles> {
+        let find = |name: &str| {
+            default_key_dirs()
+                .into_iter()
+                .map(|dir| dir.join(name))
+                .find(|path| path.is_file())
+        };
+
+        let loaded = LoadedKeyFiles {
+            prod_keys: find("prod.keys"),
+            title_keys: find("title.keys"),
+            dev_keys: find("dev.keys"),
+        };
+        if let Some(path) = &loaded.prod_keys {
+            self.load_prod_keys(File::open(path)?)?;
+        }
+        if let Some(path) = &loaded.title_keys {
+            self.load_title_keys(File::open(path)?)?;
+        }
+        if let Some(path) = &loaded.dev_keys {
+            self.load_dev_keys(File::open(path)?)?;
+        }
+        Ok(loaded)
+    }
+
+    /// Look up the KAEK for the given index and firmware generation.
+    pub fn get_kaek(&self, index: KaekIndex, generation: u8) -> Option<&[u8; 16]> {
+        let r#gen = generation as usize;
+        if r#gen >= MAX_KEY_GENERATION {
+            return None;
+        }
+        self.kaek[index as usize][r#gen].as_ref()
+    }
+
+    /// Look up the titlekek for the given master key revision.
+    pub fn get_titlekek(&self, generation: u8) -> Option<&[u8; 16]> {
+        self.titlekek.get(generation as usize)?.as_ref()
+    }
+
+    /// Look up a title key by rights ID.
+    pub fn get_title_key(&self, rights_id: &[u8; 16]) -> Option<&[u8; 16]> {
+        self.title_keys.get(rights_id)
+    }
+
+    /// Decrypt the stored title key for `rights_id` into a usable section
+    /// key.
+    ///
+    /// Title keys from tickets and `title.keys` are wrapped with AES-128-ECB
+    /// under `titlekek_XX`, where `XX` is the master key revision for
+    /// `key_generation` (the NCA header's key generation; 0 and 1 both map to
+    /// revision 0). Returns [`Error::MissingKey`] if either key is absent.
+    pub fn unwrap_title_key(&self, rights_id: &[u8; 16], key_generation: u8) -> Result<[u8; 16]> {
+        let title_key = self.get_title_key(rights_id).ok_or_else(|| {
+            let rights_id: String = rights_id.iter().map(|b| format!("{b:02x}")).collect();
+            Error::MissingKey(format!("title key for rights ID {rights_id}"))
+        })?;
+        let revision = key_generation.saturating_sub(1);
+        let titlekek = self
+            .get_titlekek(revision)
+            .ok_or_else(|| Error::MissingKey(format!("titlekek_{revision:02x}")))?;
+        Ok(Aes128::new(titlekek).decrypt_block(title_key))
+    }
+}
+
+/// Key files found by [`KeySet::load_default`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadedKeyFiles {
+    pub prod_keys: Option<PathBuf>,
+    pub title_keys: Option<PathBuf>,
+    pub dev_keys: Option<PathBuf>,
+}
+
+/// Directories searched for key files, in priority order:
+///
+/// 1. `$XDG_CONFIG_HOME/switch`
+/// 2. `$HOME/.switch`
+/// 3. `%USERPROFILE%\.switch`
+///
+/// Variables that are unset are skipped.
+pub fn default_key_dirs() -> Vec<PathBuf> {
+    [
+        ("XDG_CONFIG_HOME", "switch"),
+        ("HOME", ".switch"),
+        ("USERPROFILE", ".switch"),
+    ]
+    .into_iter()
+    .filter_map(|(var, dir)| {
+        let base = std::env::var_os(var).filter(|v| !v.is_empty())?;
+        Some(PathBuf::from(base).join(dir))
+    })
+    .collect()
+}
+
+/// Why a key file line was ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoredReason {
+    /// The line has no `=`.
+    MissingSeparator,
+    /// The key name is not one this library understands.
+    UnknownName,
+    /// The index suffix of an indexed key (`_XX`) is too large.
+    IndexOutOfRange,
+    /// The value contains a non-hex character.
+    InvalidHex,
+    /// The value has the wrong number of hex digits.
+    WrongLength { expected: usize, actual: usize },
+}
+
+/// A key file line that was not loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredLine {
+    /// 1-based line number.
+    pub line: usize,
+    /// Key name (or rights ID), empty if the line has no `=`.
+    pub name: String,
+    pub reason: IgnoredReason,
+}
+
+/// Summary of a key file load.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyFileReport {
+    /// Number of entries loaded.
+    pub loaded: usize,
+    pub ignored: Vec<IgnoredLine>,
+}
+
+impl KeyFileReport {
+    /// `true` if every non-comment line was loaded.
+    pub fn is_clean(&self) -> bool {
+        self.ignored.is_empty()
+    }
+}
+
+/// Split a key file into `name = value` entries, passing each to `set` and
+/// recording the ones it rejects.
+fn for_each_entry<R: Read>(
+    reader: R,
+    mut set: impl FnMut(&str, &str) -> StdResult<(), IgnoredReason>,
+) -> Result<KeyFileReport> {
+    let mut report = KeyFileReport::default();
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.map_err(Error::Io)?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let (name, result) = match line.split_once('=') {
+            Some((name, value)) => (name.trim(), set(name.trim(), value.trim())),
+            None => ("", Err(IgnoredReason::MissingSeparator)),
+        };
+        match result {
+            Ok(()) => report.loaded += 1,
+            Err(reason) => report.ignored.push(IgnoredLine {
+                line: i + 1,
+                name: name.to_owned(),
+                reason,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Store `value` in `slots[index]`, where `index` is the hex `_XX` suffix.
+fn set_indexed<const N: usize>(
+    slots: &mut [Option<[u8; N]>],
+    index: &str,
+    value: &str,
+) -> StdResult<(), IgnoredReason> {
+    let index = usize::from_str_radix(index, 16).map_err(|_| IgnoredReason::UnknownName)?;
+    let slot = slots.get_mut(index).ok_or(IgnoredReason::IndexOutOfRange)?;
+    *slot = Some(decode_hex_n(value)?);
+    Ok(())
+}
+
+fn decode_hex_32(s: &str) -> StdResult<[u8; 32], IgnoredReason> {
+    decode_hex_n::<32>(s)
+}
+
+fn decode_hex_n<const N: usize>(s: &str) -> StdResult<[u8; N], IgnoredReason> {
+    let s = s.trim();
+    if s.len() != N * 2 {
+        return Err(IgnoredReason::WrongLength {
+            expected: N * 2,
+            actual: s.len(),
+        });
+    }
+    let mut out = [0u8; N];
+    for (i, chunk) in s.as_bytes().chunks(2).enumerate() {
+        let hi = hex_nibble(chunk[0]).map_err(|()| IgnoredReason::InvalidHex)?;
+        let lo = hex_nibble(chunk[1]).map_err(|()| IgnoredReason::InvalidHex)?;
+        out[i] = (hi << 4) | lo;
+    }
+    Ok(out)
+}
+
+fn hex_nibble(b: u8) -> StdResult<u8, ()> {
+    match b {
+        b'0'..=b'9' => Ok(b - b'0'),
+        b'a'..=b'f' => Ok(b - b'a' + 10),
+        b'A'..=b'F' => Ok(b - b'A' + 10),
+        _ => Err(()),
+    }
+}
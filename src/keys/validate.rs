@@ -0,0 +1,161 @@
+//! Checking loaded keys against SHA-256 digests of their correct values.
+//!
+//! A mistyped key does not fail on load; it surfaces later as a
+//! [`BadMagic`](crate::Error::BadMagic) deep inside a parser. Comparing each
+//! key's digest with a reference list catches this up front without the
+//! reference list containing any key material.
+//!
+//! hakkit does not ship digests. Load them with [`KeyDigests::load`] from a
+//! `name = sha256hex` file (the same layout as `prod.keys`), or insert them
+//! directly.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+
+use super::{KaekIndex, KeySet, decode_hex_32};
+use crate::crypto::sha256::{DIGEST_SIZE, sha256};
+use crate::{Error, Result};
+
+/// Reference SHA-256 digests of correct key values, keyed by `prod.keys`
+/// name.
+#[derive(Debug, Clone, Default)]
+pub struct KeyDigests {
+    pub digests: HashMap<String, [u8; DIGEST_SIZE]>,
+}
+
+impl KeyDigests {
+    /// Create an empty digest list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load digests from `name = sha256hex` lines.
+    ///
+    /// Blank lines, `;` comments, and lines that do not parse are skipped.
+    pub fn load<R: Read>(&mut self, reader: R) -> Result<()> {
+        for line in BufReader::new(reader).lines() {
+            let line = line.map_err(Error::Io)?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some((name, value)) = line.split_once('=')
+                && let Ok(digest) = decode_hex_32(value)
+            {
+                self.digests.insert(name.trim().to_owned(), digest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add or replace the digest for `name`.
+    pub fn insert(&mut self, name: impl Into<String>, digest: [u8; DIGEST_SIZE]) {
+        self.digests.insert(name.into(), digest);
+    }
+}
+
+/// Outcome of validating one key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// The key is loaded and matches its reference digest.
+    Ok,
+    /// The key is loaded but does not match its reference digest.
+    Wrong,
+    /// A reference digest exists but the key is not loaded.
+    Missing,
+    /// The key is loaded but there is no reference digest for it.
+    Unchecked,
+}
+
+/// Per-key results of [`KeySet::validate`], sorted by key name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyReport {
+    pub entries: Vec<(String, KeyStatus)>,
+}
+
+impl KeyReport {
+    /// `true` if no key is [`KeyStatus::Wrong`].
+    pub fn is_ok(&self) -> bool {
+        self.entries.iter().all(|(_, s)| *s != KeyStatus::Wrong)
+    }
+
+    /// Names of keys with the given status.
+    pub fn with_status(&self, status: KeyStatus) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(move |(_, s)| *s == status)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl KeySet {
+    /// Compare every loaded key with `digests`.
+    ///
+    /// Each key named in `digests` is reported as [`KeyStatus::Ok`],
+    /// [`KeyStatus::Wrong`], or [`KeyStatus::Missing`]; loaded keys without a
+    /// digest are reported as [`KeyStatus::Unchecked`].
+    pub fn validate(&self, digests: &KeyDigests) -> KeyReport {
+        let loaded = self.named_keys();
+        let mut entries: Vec<(String, KeyStatus)> = digests
+            .digests
+            .iter()
+            .map(|(name, digest)| {
+                let status = match loaded.get(name.as_str()) {
+                    Some(value) if sha256(value) == *digest => KeyStatus::Ok,
+                    Some(_) => KeyStatus::Wrong,
+                    None => KeyStatus::Missing,
+                };
+                (name.clone(), status)
+            })
+            .collect();
+        entries.extend(
+            loaded
+                .into_keys()
+                .filter(|name| !digests.digests.contains_key(name))
+                .map(|name| (name, KeyStatus::Unchecked)),
+        );
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        KeyReport { entries }
+    }
+
+    /// All loaded keys by `prod.keys` name.
+    fn named_keys(&self) -> HashMap<String, Vec<u8>> {
+        let mut keys = HashMap::new();
+        if let Some(key) = &self.header_key {
+            keys.insert("header_key".to_owned(), key.to_vec());
+        }
+        for (name, key) in [
+            ("sd_seed", &self.sd_seed),
+            ("save_mac_key", &self.save_mac_key),
+        ] {
+            if let Some(key) = key {
+                keys.insert(name.to_owned(), key.to_vec());
+            }
+        }
+        for (i, key) in self.bis_key.iter().enumerate() {
+            if let Some(key) = key {
+                keys.insert(format!("bis_key_{i:02x}"), key.to_vec());
+            }
+        }
+        for index in [KaekIndex::Application, KaekIndex::Ocean, KaekIndex::System] {
+            for (r#gen, key) in self.kaek[index as usize].iter().enumerate() {
+                if let Some(key) = key {
+                    let name = format!("key_area_key_{}_{:02x}", index.name(), r#gen);
+                    keys.insert(name, key.to_vec());
+                }
+            }
+        }
+        for (prefix, slots) in [
+            ("titlekek", &self.titlekek),
+            ("master_key", &self.master_key),
+            ("master_kek", &self.master_kek),
+        ] {
+            for (r#gen, key) in slots.iter().enumerate() {
+                if let Some(key) = key {
+                    keys.insert(format!("{prefix}_{:02x}", r#gen), key.to_vec());
+                }
+            }
+        }
+        keys
+    }
+}
@@ -5,21 +5,52 @@
 //! |--------|--------|
 //! | [`formats::bfttf`] | BFTTF/BFOTF - XOR-encrypted font |
 //! | [`formats::bntx`]  | BNTX - Binary NX Texture |
+//! | [`formats::cnmt`]  | CNMT - Content metadata (title contents and hashes) |
 //! | [`formats::hfs0`]  | HFS0 - SHA-256-hashed archive (XCI) |
 //! | [`formats::nacp`]  | NACP - Application control property (title, ratings, save data) |
 //! | [`formats::nca`]   | NCA - Nintendo Content Archive |
 //! | [`formats::ncz`]   | NCZ - Zstandard-compressed NCA (NSZ) |
 //! | [`formats::npdm`]  | NPDM - Program Descriptor Meta |
+//! | [`formats::nsp`]   | NSP - eShop title package (NCAs, tickets, CNMT) |
 //! | [`formats::pfs0`]  | PFS0 / NSP - PartitionFS flat archive |
 //! | [`formats::romfs`] | RomFS - Read-only game asset filesystem |
 //! | [`formats::sarc`]  | SARC - SEAD ARChive |
+//! | [`formats::ticket`] | Ticket - eShop title key licence |
 //! | [`formats::xci`]   | XCI - Physical game card dump |
+//!
+//! [`switch_fs`] opens an NSP or XCI as a set of titles, resolving tickets
+//! and CNMTs so content NCAs can be opened by title ID and title names and
+//! icons read from their Control NCAs; [`verify`] runs every integrity
+//! check over such a package and collects the results in one report.
+//! [`patching`] applies update NCAs over their base NCAs (BKTR),
+//! [`split`] opens dumps split into FAT32-sized parts, and [`read_at`]
+//! reads several archive entries at once through a shared reader.
+//! [`SubReader`] views a byte range of a stream as a stream of its own, for
+//! opening a file inside an archive with another parser. [`progress`]
+//! reports how far extraction, verification, and NCZ conversion have got.
+//!
+//! # WebAssembly
+//! With default features the crate is pure Rust and builds for
+//! `wasm32-unknown-unknown`, so NSP, SARC, and BNTX uploads can be parsed
+//! in the browser. Wrap the bytes in a [`std::io::Cursor`], or use the
+//! `parse_bytes` entry points of the archive formats. Sizes that do not fit
+//! the 32-bit `usize` are reported as [`Error::InvalidRange`]. The
+//! `compression` feature links the C Zstandard library, and `parallel`
+//! needs threads; the file helpers ([`split`], extraction, key loading)
+//! build but fail at run time.
 
 pub mod compression;
 pub mod crypto;
 pub mod error;
 pub mod formats;
 pub mod keys;
+pub mod patching;
+pub mod progress;
+pub mod read_at;
+pub mod split;
+pub mod switch_fs;
 mod utils;
+pub mod verify;
 
-pub use error::{Error, Result};
+pub use error::{Error, ErrorCategory, Result};
+pub use utils::SubReader;
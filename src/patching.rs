@@ -0,0 +1,367 @@
+//! Applying update NCAs over their base NCAs.
+//!
+//! An update (patch) Program NCA does not contain a complete RomFS. Its RomFS
+//! section is a BKTR ("bucket tree") patch: a virtual storage whose contents
+//! are stitched together from the base NCA's RomFS and the update's own
+//! section data, the same way the console mounts patched titles.
+//!
+//! # Patch Info Layout
+//! The 0x40-byte [`FsHeader::patch_info`] of the update's RomFS section holds
+//! two bucket tree descriptors:
+//! ```text
+//! 0x00  u64  Indirect table offset (within the section)
+//! 0x08  u64  Indirect table size
+//! 0x10  0x10 Indirect BKTR header (magic, version, entry count, reserved)
+//! 0x20  u64  AES-CTR-Ex table offset (within the section)
+//! 0x28  u64  AES-CTR-Ex table size
+//! 0x30  0x10 AES-CTR-Ex BKTR header
+//! ```
+//!
+//! # Bucket Tree Layout
+//! Both tables are plain AES-CTR encrypted and made of 0x4000-byte nodes:
+//! ```text
+//! Node 0:     u32 index, u32 bucket count, u64 end offset, u64 bucket offsets[]
+//! Node 1..=N: u32 index, u32 entry count,  u64 end offset, entries[]
+//! ```
+//! Indirect entries are 0x14 bytes (virtual offset, physical offset, storage
+//! index where 0 = base and 1 = update). AES-CTR-Ex entries are 0x10 bytes
+//! (offset, size, generation); each overrides bytes `[4..8]` of the counter
+//! for update data in its range.
+//!
+//! The ExeFS of an update is stored whole, so [`PatchedNca::exefs`] simply
+//! opens the update's ExeFS.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::formats::nca::{EncryptionType, FsHeader, FsType, NcaReader, NcaSectionReader};
+use crate::formats::pfs0::Pfs0Reader;
+use crate::formats::romfs::{IvfcHeader, RomFsReader};
+use crate::utils::table;
+use crate::{Error, Result};
+
+const BUCKET_NODE_SIZE: usize = 0x4000;
+
+/// One BKTR descriptor from [`FsHeader::patch_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BucketTreeInfo {
+    /// Table offset within the section.
+    pub offset: u64,
+    /// Table size in bytes.
+    pub size: u64,
+    pub version: u32,
+    pub entry_count: u32,
+}
+
+/// Parsed [`FsHeader::patch_info`] of a BKTR patch section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchInfo {
+    pub indirect: BucketTreeInfo,
+    pub aes_ctr_ex: BucketTreeInfo,
+}
+
+impl PatchInfo {
+    /// Parse the 0x40-byte patch info region.
+    ///
+    /// Returns [`Error::BadMagic`] if either header lacks the `BKTR` magic.
+    pub fn from_bytes(data: &[u8; 0x40]) -> Result<Self> {
+        let tree = |d: &[u8]| -> Result<BucketTreeInfo> {
+            if &d[0x10..0x14] != b"BKTR" {
+                return Err(Error::BadMagic);
+            }
+            Ok(BucketTreeInfo {
+                offset: u64::from_le_bytes(d[0x00..0x08].try_into().unwrap()),
+                size: u64::from_le_bytes(d[0x08..0x10].try_into().unwrap()),
+                version: u32::from_le_bytes(d[0x14..0x18].try_into().unwrap()),
+                entry_count: u32::from_le_bytes(d[0x18..0x1C].try_into().unwrap()),
+            })
+        };
+        Ok(Self {
+            indirect: tree(&data[..0x20])?,
+            aes_ctr_ex: tree(&data[0x20..])?,
+        })
+    }
+}
+
+/// Which storage an [`IndirectEntry`] reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PatchStorage {
+    /// The base NCA's RomFS section.
+    Base,
+    /// The update NCA's patch section.
+    Update,
+}
+
+/// A relocation from the virtual patched RomFS to base or update data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndirectEntry {
+    pub virtual_offset: u64,
+    pub physical_offset: u64,
+    pub storage: PatchStorage,
+}
+
+/// A range of update section data with its own AES-CTR generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AesCtrExEntry {
+    pub offset: u64,
+    pub size: u32,
+    pub generation: u32,
+}
+
+/// A base Program NCA combined with its update.
+///
+/// The patched RomFS is exposed as a virtual [`PatchedStorage`] that reads
+/// from whichever NCA the BKTR tables point at.
+pub struct PatchedNca<B, U> {
+    base: NcaReader<B>,
+    update: NcaReader<U>,
+    /// Index of the base RomFS section.
+    base_section: usize,
+    /// Index of the update's BKTR RomFS section.
+    update_section: usize,
+    /// IVFC header describing the patched RomFS.
+    ivfc: IvfcHeader,
+    indirect: Vec<IndirectEntry>,
+    /// End of the virtual storage.
+    virtual_size: u64,
+    aes_ctr_ex: Vec<AesCtrExEntry>,
+    /// End of the AES-CTR-Ex covered region.
+    aes_ctr_ex_end: u64,
+}
+
+impl<B: Read + Seek, U: Read + Seek> PatchedNca<B, U> {
+    /// Combine a base Program NCA with its update and load the BKTR tables.
+    ///
+    /// Returns [`Error::InvalidRange`] if either NCA lacks a RomFS section or
+    /// the update's RomFS is not an AES-CTR-Ex patch section.
+    pub fn new(base: NcaReader<B>, mut update: NcaReader<U>) -> Result<Self> {
+        let base_section = romfs_section(&base.nca.fs_headers, |_| true)?;
+        let update_section = romfs_section(&update.nca.fs_headers, |h| {
+            matches!(
+                h.encryption_type,
+                EncryptionType::AesCtrEx | EncryptionType::AesCtrExSkipLayerHash
+            )
+        })?;
+
+        let fs_header = update.nca.fs_header(update_section).unwrap();
+        let ivfc = IvfcHeader::from_bytes(&fs_header.hash_data)?;
+        let info = PatchInfo::from_bytes(&fs_header.patch_info)?;
+
+        let mut section = update.open_section_ctr(update_section)?;
+        let (indirect, virtual_size) =
+            read_bucket_tree(&mut section, &info.indirect, 0x14, |e| IndirectEntry {
+                virtual_offset: u64::from_le_bytes(e[0x00..0x08].try_into().unwrap()),
+                physical_offset: u64::from_le_bytes(e[0x08..0x10].try_into().unwrap()),
+                storage: match u32::from_le_bytes(e[0x10..0x14].try_into().unwrap()) {
+                    0 => PatchStorage::Base,
+                    _ => PatchStorage::Update,
+                },
+            })?;
+        let (aes_ctr_ex, aes_ctr_ex_end) =
+            read_bucket_tree(&mut section, &info.aes_ctr_ex, 0x10, |e| AesCtrExEntry {
+                offset: u64::from_le_bytes(e[0x00..0x08].try_into().unwrap()),
+                size: u32::from_le_bytes(e[0x08..0x0C].try_into().unwrap()),
+                generation: u32::from_le_bytes(e[0x0C..0x10].try_into().unwrap()),
+            })?;
+
+        Ok(Self {
+            base,
+            update,
+            base_section,
+            update_section,
+            ivfc,
+            indirect,
+            virtual_size,
+            aes_ctr_ex,
+            aes_ctr_ex_end,
+        })
+    }
+
+    /// Indirect (relocation) entries, sorted by virtual offset.
+    pub fn indirect_entries(&self) -> &[IndirectEntry] {
+        &self.indirect
+    }
+
+    /// AES-CTR-Ex entries, sorted by offset.
+    pub fn aes_ctr_ex_entries(&self) -> &[AesCtrExEntry] {
+        &self.aes_ctr_ex
+    }
+
+    /// Open the whole patched RomFS section, including its IVFC hash levels.
+    pub fn open_storage(&mut self) -> Result<PatchedStorage<'_, B, U>> {
+        Ok(PatchedStorage {
+            base: self.base.open_section(self.base_section)?,
+            update: self.update.open_section_ctr(self.update_section)?,
+            indirect: &self.indirect,
+            size: self.virtual_size,
+            aes_ctr_ex: &self.aes_ctr_ex,
+            aes_ctr_ex_end: self.aes_ctr_ex_end,
+            pos: 0,
+        })
+    }
+
+    /// Open the patched RomFS as a [`RomFsReader`].
+    pub fn romfs(&mut self) -> Result<RomFsReader<PatchedStorage<'_, B, U>>> {
+        let level3_offset = self.ivfc.level3_offset;
+        let mut storage = self.open_storage()?;
+        storage.seek(SeekFrom::Start(level3_offset))?;
+        RomFsReader::new(storage)
+    }
+
+    /// Open the patched ExeFS, which is the update's ExeFS.
+    pub fn exefs(&mut self) -> Result<Pfs0Reader<NcaSectionReader<'_, U>>> {
+        self.update.exefs()
+    }
+
+    /// Consume the patch, returning the base and update readers.
+    pub fn into_inner(self) -> (NcaReader<B>, NcaReader<U>) {
+        (self.base, self.update)
+    }
+}
+
+fn romfs_section(
+    fs_headers: &[Option<FsHeader>; 4],
+    pred: impl Fn(&FsHeader) -> bool,
+) -> Result<usize> {
+    fs_headers
+        .iter()
+        .position(|h| h.is_some_and(|h| h.fs_type == FsType::RomFs && pred(&h)))
+        .ok_or(Error::InvalidRange)
+}
+
+/// Read a bucket tree, returning its entries and the end offset of the
+/// range it covers.
+fn read_bucket_tree<R: Read + Seek, T>(
+    r: &mut R,
+    info: &BucketTreeInfo,
+    entry_size: usize,
+    parse: impl Fn(&[u8]) -> T,
+) -> Result<(Vec<T>, u64)> {
+    if info.size < BUCKET_NODE_SIZE as u64 {
+        return Err(Error::InvalidRange);
+    }
+    r.seek(SeekFrom::Start(info.offset))?;
+    // The size comes from the update's patch info, so grow the buffer as
+    // data arrives rather than trusting it up front.
+    let table = table(r, info.size)?;
+
+    let bucket_count = u32::from_le_bytes(table[0x04..0x08].try_into().unwrap()) as usize;
+    let end_offset = u64::from_le_bytes(table[0x08..0x10].try_into().unwrap());
+
+    let mut buckets = Vec::new();
+    for i in 0..bucket_count {
+        let node = table
+            .get((i + 1) * BUCKET_NODE_SIZE..(i + 2) * BUCKET_NODE_SIZE)
+            .ok_or(Error::UnexpectedEof)?;
+        let count = u32::from_le_bytes(node[0x04..0x08].try_into().unwrap()) as usize;
+        let data = count
+            .checked_mul(entry_size)
+            .and_then(|len| node.get(0x10..0x10 + len))
+            .ok_or(Error::Parse("BKTR bucket entry count exceeds node size"))?;
+        buckets.push(data);
+    }
+
+    let mut entries = Vec::with_capacity(buckets.iter().map(|b| b.len() / entry_size).sum());
+    for data in buckets {
+        entries.extend(data.chunks_exact(entry_size).map(&parse));
+    }
+    Ok((entries, end_offset))
+}
+
+/// Virtual [`Read`] + [`Seek`] view of a patched RomFS section.
+///
+/// Produced by [`PatchedNca::open_storage`]. Each read is split at indirect
+/// entry boundaries and served from the base RomFS or the update section;
+/// update data is decrypted with the generation of its AES-CTR-Ex entry.
+pub struct PatchedStorage<'a, B, U> {
+    base: NcaSectionReader<'a, B>,
+    update: NcaSectionReader<'a, U>,
+    indirect: &'a [IndirectEntry],
+    size: u64,
+    aes_ctr_ex: &'a [AesCtrExEntry],
+    aes_ctr_ex_end: u64,
+    pos: u64,
+}
+
+impl<B, U> PatchedStorage<'_, B, U> {
+    /// Size of the patched section in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl<B: Read + Seek, U: Read + Seek> PatchedStorage<'_, B, U> {
+    fn read_update(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        // Entries are sorted, so the covering one is the last that starts at
+        // or before `offset`.
+        let i = self.aes_ctr_ex.partition_point(|e| e.offset <= offset);
+        let Some(entry) = i.checked_sub(1).map(|i| self.aes_ctr_ex[i]) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "update offset not covered by AES-CTR-Ex table",
+            ));
+        };
+        let end = self
+            .aes_ctr_ex
+            .get(i)
+            .map_or(self.aes_ctr_ex_end, |e| e.offset);
+        let len = (buf.len() as u64).min(end.saturating_sub(offset)) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.update.set_ctr_generation(entry.generation);
+        self.update.seek(SeekFrom::Start(offset))?;
+        self.update.read(&mut buf[..len])
+    }
+}
+
+impl<B: Read + Seek, U: Read + Seek> Read for PatchedStorage<'_, B, U> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let i = self
+            .indirect
+            .partition_point(|e| e.virtual_offset <= self.pos);
+        let Some(entry) = i.checked_sub(1).map(|i| self.indirect[i]) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "virtual offset not covered by indirect table",
+            ));
+        };
+        let end = self.indirect.get(i).map_or(self.size, |e| e.virtual_offset);
+        let len = (buf.len() as u64).min(remaining).min(end - self.pos) as usize;
+        let physical = entry.physical_offset + (self.pos - entry.virtual_offset);
+
+        let n = match entry.storage {
+            PatchStorage::Base => {
+                self.base.seek(SeekFrom::Start(physical))?;
+                self.base.read(&mut buf[..len])?
+            }
+            PatchStorage::Update => self.read_update(physical, &mut buf[..len])?,
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<B, U> Seek for PatchedStorage<'_, B, U> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::End(d) => self.size.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        self.pos = new.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before section start")
+        })?;
+        Ok(self.pos)
+    }
+}
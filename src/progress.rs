@@ -0,0 +1,92 @@
+//! Progress reporting for long-running operations.
+//!
+//! Extraction ([`Pfs0Reader`], [`SarcReader`]), verification
+//! ([`Hfs0Reader`], [`NcaReader`], `NczReader`), and NCZ compression and
+//! decompression have `*_with_progress` variants taking a [`Progress`]. It
+//! is called after every chunk read with the item being worked on (an entry
+//! name, `section N` for NCA sections, or empty for a single stream) and
+//! the bytes done and expected across the whole operation:
+//! ```text
+//! reader.extract_with_progress("out", |_| true, &mut |item: &str, done, total| {
+//!     eprint!("\r{item}: {}%", done * 100 / total.max(1));
+//! })?;
+//! ```
+//! Updates can be frequent; throttle redraws on the receiving side.
+//!
+//! [`Pfs0Reader`]: crate::formats::pfs0::Pfs0Reader
+//! [`SarcReader`]: crate::formats::sarc::SarcReader
+//! [`Hfs0Reader`]: crate::formats::hfs0::Hfs0Reader
+//! [`NcaReader`]: crate::formats::nca::NcaReader
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Receiver of progress updates.
+///
+/// Implemented for closures taking `(item, done, total)`.
+pub trait Progress {
+    /// `done` of `total` bytes of the operation have been processed;
+    /// `item` is the part being worked on. `done` never exceeds `total`.
+    fn update(&mut self, item: &str, done: u64, total: u64);
+}
+
+impl<F: FnMut(&str, u64, u64)> Progress for F {
+    fn update(&mut self, item: &str, done: u64, total: u64) {
+        self(item, done, total)
+    }
+}
+
+/// A [`Progress`] that ignores every update.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&mut self, _item: &str, _done: u64, _total: u64) {}
+}
+
+/// Reader that adds the bytes read from `inner` to `done`, up to `limit`,
+/// and reports the new count against `total`.
+pub(crate) struct ProgressReader<'a, R> {
+    pub(crate) inner: R,
+    pub(crate) progress: &'a mut dyn Progress,
+    pub(crate) item: &'a str,
+    pub(crate) done: &'a mut u64,
+    pub(crate) limit: u64,
+    pub(crate) total: u64,
+}
+
+impl<'a, R> ProgressReader<'a, R> {
+    /// Count every byte read from `inner` towards `total`.
+    pub(crate) fn new(
+        inner: R,
+        progress: &'a mut dyn Progress,
+        item: &'a str,
+        done: &'a mut u64,
+        total: u64,
+    ) -> Self {
+        Self {
+            inner,
+            progress,
+            item,
+            done,
+            limit: total,
+            total,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            *self.done = (*self.done + n as u64).min(self.limit);
+            self.progress.update(self.item, *self.done, self.total);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
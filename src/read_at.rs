@@ -0,0 +1,195 @@
+//! Positional reads through a shared reference.
+//!
+//! The archive readers hand out [`std::io::Take<&mut R>`], so only one file
+//! can be open at a time. [`ReadAt`] instead reads at an absolute offset
+//! without moving a cursor (`pread` on Unix, `seek_read` on Windows), and
+//! [`RangeReader`] turns a byte range of one into an independent [`Read`] +
+//! [`Seek`] stream.
+//!
+//! When the inner reader implements [`ReadAt`], the archive readers also
+//! offer `read_file_at(&self, ..)`, so several files can be read at once -
+//! from several threads if the reader is [`Sync`]:
+//! ```text
+//! let pfs0 = Pfs0Reader::new(File::open("game.nsp")?)?;
+//! std::thread::scope(|s| {
+//!     for file in pfs0.files() {
+//!         s.spawn(|| io::copy(&mut pfs0.read_file_at(file), &mut io::sink()));
+//!     }
+//! });
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+/// A source that can be read at an arbitrary offset through `&self`.
+pub trait ReadAt {
+    /// Read up to `buf.len()` bytes starting at `offset`.
+    ///
+    /// Returns the number of bytes read; 0 means `offset` is at or past the
+    /// end.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Read exactly `buf.len()` bytes starting at `offset`.
+    ///
+    /// Returns an error of kind [`UnexpectedEof`](io::ErrorKind::UnexpectedEof)
+    /// if the source ends first.
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl ReadAt for File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+}
+
+/// `seek_read` also moves the file cursor; this does not matter to
+/// [`ReadAt`] users, but do not mix it with [`Read`] on the same handle.
+#[cfg(windows)]
+impl ReadAt for File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+    }
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let Some(src) = usize::try_from(offset).ok().and_then(|o| self.get(o..)) else {
+            return Ok(0);
+        };
+        let n = buf.len().min(src.len());
+        buf[..n].copy_from_slice(&src[..n]);
+        Ok(n)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.as_slice().read_at(buf, offset)
+    }
+}
+
+/// Reads the underlying buffer; the cursor position is ignored.
+impl<T: AsRef<[u8]>> ReadAt for Cursor<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.get_ref().as_ref().read_at(buf, offset)
+    }
+}
+
+/// Reads the inner reader directly, bypassing the buffer.
+impl<R: ReadAt> ReadAt for BufReader<R> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.get_ref().read_at(buf, offset)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &mut T {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Box<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+}
+
+/// A byte range of a [`ReadAt`] source, read as a stream with its own
+/// position.
+///
+/// Offsets passed to [`Seek`] and [`ReadAt`] are relative to the start of
+/// the range. Any number of these may read the same source at once.
+#[derive(Debug, Clone)]
+pub struct RangeReader<R> {
+    inner: R,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: ReadAt> RangeReader<R> {
+    /// View `len` bytes of `inner` starting at `start`.
+    pub fn new(inner: R, start: u64, len: u64) -> Self {
+        Self {
+            inner,
+            start,
+            len,
+            pos: 0,
+        }
+    }
+
+    /// Length of the range in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// `true` if the range is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Consume the range, returning the inner source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: ReadAt> ReadAt for RangeReader<R> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(offset);
+        let n = (buf.len() as u64).min(remaining) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        self.inner.read_at(&mut buf[..n], self.start + offset)
+    }
+}
+
+impl<R: ReadAt> Read for RangeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = ReadAt::read_at(self, buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: ReadAt> Seek for RangeReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of range")
+        })?;
+        Ok(self.pos)
+    }
+}
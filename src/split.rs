@@ -0,0 +1,172 @@
+//! Opening dumps split into several files.
+//!
+//! Dumps written to FAT32 storage are cut into parts below 4 GiB. Two
+//! naming schemes are common:
+//! ```text
+//! game.nsp/00, game.nsp/01, ...    directory with the archive bit set
+//! game.xc0, game.xc1, ...          numbered extensions (also .ns0, .ns1)
+//! ```
+//!
+//! [`SplitReader`] joins the parts into one logical [`Read`] + [`Seek`]
+//! stream that any parser accepts, and [`SplitReader::open`] finds the parts
+//! from either naming scheme (or opens a plain file as a single part). If
+//! the parts implement [`ReadAt`], so does the joined stream.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::read_at::ReadAt;
+use crate::{Error, Result};
+
+/// Concatenation of several readers into one stream.
+pub struct SplitReader<R> {
+    parts: Vec<R>,
+    /// Start offset of each part in the joined stream, plus the total size.
+    starts: Vec<u64>,
+    pos: u64,
+    /// Part whose own position matches `pos`, so reads need no seek.
+    synced: Option<usize>,
+}
+
+impl<R: Read + Seek> SplitReader<R> {
+    /// Join `parts` in order.
+    ///
+    /// The size of each part is taken by seeking to its end.
+    pub fn new(mut parts: Vec<R>) -> Result<Self> {
+        let mut starts = Vec::with_capacity(parts.len() + 1);
+        let mut total = 0;
+        for part in &mut parts {
+            starts.push(total);
+            total += part.seek(SeekFrom::End(0))?;
+        }
+        starts.push(total);
+        Ok(Self {
+            parts,
+            starts,
+            pos: 0,
+            synced: None,
+        })
+    }
+
+    /// Total size of the joined stream.
+    pub fn len(&self) -> u64 {
+        self.starts[self.parts.len()]
+    }
+
+    /// `true` if the joined stream is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of parts.
+    pub fn part_count(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Consume the reader, returning the parts.
+    pub fn into_inner(self) -> Vec<R> {
+        self.parts
+    }
+}
+
+impl SplitReader<BufReader<File>> {
+    /// Open a dump that may be split.
+    ///
+    /// `path` may be a directory of numbered parts (`00`, `01`, ...), the
+    /// first part of a numbered-extension set (`.xc0`, `.ns0`, ...), or a
+    /// plain file. Returns [`Error::Parse`] for a directory without parts.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let parts = part_paths(path.as_ref())?
+            .into_iter()
+            .map(|p| Ok(BufReader::new(File::open(p)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(parts)
+    }
+}
+
+impl<R: Read + Seek> Read for SplitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.len() {
+            return Ok(0);
+        }
+        // Last part whose start is at or before `pos`; empty parts are
+        // skipped because the next start equals theirs.
+        let i = self.starts.partition_point(|&s| s <= self.pos) - 1;
+        let offset = self.pos - self.starts[i];
+        let len = (buf.len() as u64).min(self.starts[i + 1] - self.pos) as usize;
+        let part = &mut self.parts[i];
+        if self.synced != Some(i) {
+            part.seek(SeekFrom::Start(offset))?;
+            self.synced = Some(i);
+        }
+        let n = part.read(&mut buf[..len])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for SplitReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len().checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of stream")
+        })?;
+        self.synced = None;
+        Ok(self.pos)
+    }
+}
+
+impl<R: ReadAt> ReadAt for SplitReader<R> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        if buf.is_empty() || offset >= self.starts[self.parts.len()] {
+            return Ok(0);
+        }
+        let i = self.starts.partition_point(|&s| s <= offset) - 1;
+        let len = (buf.len() as u64).min(self.starts[i + 1] - offset) as usize;
+        self.parts[i].read_at(&mut buf[..len], offset - self.starts[i])
+    }
+}
+
+/// Paths of the parts of the dump at `path`, in order.
+///
+/// See [`SplitReader::open`] for the accepted layouts. A plain file yields
+/// just itself.
+pub fn part_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_dir() {
+        let mut parts = Vec::new();
+        for i in 0.. {
+            let part = path.join(format!("{i:02}"));
+            if !part.is_file() {
+                break;
+            }
+            parts.push(part);
+        }
+        if parts.is_empty() {
+            return Err(Error::Parse("split dump directory has no parts"));
+        }
+        return Ok(parts);
+    }
+
+    let numbered = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| e.strip_suffix('0'))
+        .filter(|prefix| !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphabetic()));
+    let Some(prefix) = numbered else {
+        return Ok(vec![path.to_path_buf()]);
+    };
+    let mut parts = vec![path.to_path_buf()];
+    for i in 1.. {
+        let part = path.with_extension(format!("{prefix}{i}"));
+        if !fs::exists(&part)? {
+            break;
+        }
+        parts.push(part);
+    }
+    Ok(parts)
+}
@@ -0,0 +1,416 @@
+//! One entry point for the titles of an NSP or XCI.
+//!
+//! [`SwitchFs`] ties the container, ticket, CNMT, and NCA parsers together:
+//! it opens either package type, imports the title keys of its common
+//! tickets, reads every CNMT, and opens content NCAs by title:
+//! ```text
+//! let mut fs = SwitchFs::new(File::open("game.xci")?, keys)?;
+//! for title in fs.titles() {
+//!     println!("{:016x} v{} {:?}", title.title_id, title.version, title.meta_type);
+//! }
+//! let mut nca = fs.open_content(title_id, ContentRecordType::Program)?;
+//! let romfs = nca.romfs()?;
+//! let info = fs.title_info(title_id)?;
+//! println!("{} {}", info.name().unwrap_or_default(), info.display_version);
+//! ```
+//!
+//! ## Package layouts
+//! ```text
+//! NSP   PFS0 ─ <ContentId>.nca, <ContentId>.cnmt.nca, <RightsId>.tik, ...
+//! XCI   root HFS0 ─ secure HFS0 ─ <ContentId>.nca, <ContentId>.cnmt.nca
+//! ```
+//! Only the `secure` partition of an XCI holds titles; `update` carries the
+//! bundled firmware (see [`Xci::firmware_version`]). NCZ entries of an NSZ
+//! are not listed; decompress them with
+//! [`NczReader`](crate::formats::ncz::NczReader) first.
+//!
+//! Update titles are listed like any other. To read an update's RomFS over
+//! its base game, open both Program NCAs with [`SwitchFs::open_content_at`]
+//! and combine them with [`crate::patching`].
+
+use std::io::{Cursor, Read, Seek, SeekFrom, Take};
+
+use crate::formats::cnmt::{Cnmt, ContentRecordType};
+use crate::formats::hfs0::Hfs0;
+use crate::formats::nacp::{Language, Nacp};
+use crate::formats::nca::NcaReader;
+use crate::formats::pfs0::Pfs0;
+use crate::formats::ticket::{Ticket, TitleKeyType};
+use crate::formats::xci::Xci;
+use crate::formats::{FormatKind, detect};
+use crate::keys::KeySet;
+use crate::read_at::{RangeReader, ReadAt};
+use crate::utils::table;
+use crate::{Error, Result};
+
+/// Container type opened by [`SwitchFs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PackageKind {
+    Nsp,
+    Xci,
+}
+
+/// An NCA inside the package.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentFile {
+    /// Entry name: `<ContentId>.nca`, or `<ContentId>.cnmt.nca` for a meta
+    /// NCA.
+    pub name: String,
+    /// Absolute stream offset of the NCA.
+    pub offset: u64,
+    /// NCA size in bytes.
+    pub size: u64,
+}
+
+/// Localised name, publisher, and icon of a title in one language.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TitleLanguage {
+    pub language: Language,
+    /// Application name.
+    pub name: String,
+    /// Publisher (NACP developer name).
+    pub publisher: String,
+    /// JPEG icon from `/icon_<Language>.dat`, or `None` if the Control NCA
+    /// has no icon for this language.
+    pub icon: Option<Vec<u8>>,
+}
+
+/// Display metadata of a title, read from its Control NCA.
+///
+/// Returned by [`SwitchFs::title_info`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TitleInfo {
+    pub title_id: u64,
+    /// Title version from the CNMT.
+    pub version: u32,
+    /// Display version string from the NACP (e.g. `"1.0.0"`).
+    pub display_version: String,
+    /// Languages with a non-empty NACP title entry, in index order.
+    pub languages: Vec<TitleLanguage>,
+    /// The full NACP.
+    pub nacp: Nacp,
+}
+
+impl TitleInfo {
+    /// Entry for `language`, or `None` if the title has none.
+    pub fn language(&self, language: Language) -> Option<&TitleLanguage> {
+        self.languages.iter().find(|l| l.language == language)
+    }
+
+    /// Preferred entry: `AmericanEnglish` if present, else the first
+    /// language, as in [`Nacp::first_title`].
+    pub fn preferred(&self) -> Option<&TitleLanguage> {
+        self.language(Language::AmericanEnglish)
+            .or_else(|| self.languages.first())
+    }
+
+    /// Name of the [`preferred`](Self::preferred) entry.
+    pub fn name(&self) -> Option<&str> {
+        self.preferred().map(|l| l.name.as_str())
+    }
+
+    /// Publisher of the [`preferred`](Self::preferred) entry.
+    pub fn publisher(&self) -> Option<&str> {
+        self.preferred().map(|l| l.publisher.as_str())
+    }
+
+    /// Icon of the [`preferred`](Self::preferred) entry.
+    pub fn icon(&self) -> Option<&[u8]> {
+        self.preferred()?.icon.as_deref()
+    }
+}
+
+/// Titles and content NCAs of an NSP or XCI.
+///
+/// Owns the underlying reader and the [`KeySet`], into which the title
+/// keys of the package's common tickets are imported. Tickets and CNMTs
+/// are read by [`new`](Self::new); NCA data is only read on demand. See
+/// [`crate::verify`] for checking a package's integrity.
+pub struct SwitchFs<R> {
+    inner: R,
+    keys: KeySet,
+    kind: PackageKind,
+    /// NCA entries in package order.
+    files: Vec<ContentFile>,
+    /// CNMTs from the meta NCAs, one per title.
+    titles: Vec<Cnmt>,
+    /// Tickets in the package, common and personalized.
+    tickets: Vec<Ticket>,
+}
+
+impl<R: Read + Seek> SwitchFs<R> {
+    /// Open an NSP or XCI.
+    ///
+    /// An NSP must start at the current position of `reader`; an XCI at
+    /// the start of the stream. `keys` must hold the header key and the
+    /// key area keys for the meta NCAs; title keys of common tickets in
+    /// the package are added to it. Returns [`Error::BadMagic`] if the
+    /// stream is neither, or [`Error::NotFound`] if an XCI has no `secure`
+    /// partition.
+    pub fn new(mut reader: R, mut keys: KeySet) -> Result<Self> {
+        let (kind, entries) = match detect(&mut reader)? {
+            FormatKind::Pfs0 => {
+                let pfs0 = Pfs0::parse(&mut reader)?;
+                let entries = pfs0
+                    .files
+                    .into_iter()
+                    .map(|f| ContentFile {
+                        name: f.name,
+                        offset: pfs0.data_offset + f.offset,
+                        size: f.size,
+                    })
+                    .collect();
+                (PackageKind::Nsp, entries)
+            }
+            FormatKind::Xci => (PackageKind::Xci, secure_entries(&mut reader)?),
+            _ => return Err(Error::BadMagic),
+        };
+
+        let mut tickets = Vec::new();
+        for entry in entries.iter().filter(|e| e.name.ends_with(".tik")) {
+            reader.seek(SeekFrom::Start(entry.offset))?;
+            let data = table(&mut reader, entry.size)?;
+            let ticket = Ticket::parse(&mut Cursor::new(data))?;
+            // Personalized tickets need console keys to unwrap.
+            if ticket.title_key_type == TitleKeyType::Common {
+                keys.import_ticket(&ticket)?;
+            }
+            tickets.push(ticket);
+        }
+
+        let files: Vec<ContentFile> = entries
+            .into_iter()
+            .filter(|e| e.name.ends_with(".nca"))
+            .collect();
+        let mut titles = Vec::new();
+        for file in files.iter().filter(|f| f.name.ends_with(".cnmt.nca")) {
+            reader.seek(SeekFrom::Start(file.offset))?;
+            titles.push(Cnmt::from_meta_nca(&mut reader, &keys)?);
+        }
+
+        Ok(Self {
+            inner: reader,
+            keys,
+            kind,
+            files,
+            titles,
+            tickets,
+        })
+    }
+
+    /// Open an NCA for raw access.
+    ///
+    /// Seeks to the NCA's start and returns a [`Take`] limited to its size.
+    /// The borrow ends when the [`Take`] is dropped.
+    pub fn read_file(&mut self, file: &ContentFile) -> Result<Take<&mut R>> {
+        self.inner.seek(SeekFrom::Start(file.offset))?;
+        Ok(self.inner.by_ref().take(file.size))
+    }
+
+    /// Open an NCA for decrypted access.
+    pub fn open_nca(&mut self, file: &ContentFile) -> Result<NcaReader<&mut R>> {
+        self.inner.seek(SeekFrom::Start(file.offset))?;
+        NcaReader::new(&mut self.inner, &self.keys)
+    }
+
+    /// Open the content of type `content_type` of the title `title_id`.
+    ///
+    /// Returns [`Error::NotFound`] if the title has no such content or its
+    /// NCA is not in the package.
+    pub fn open_content(
+        &mut self,
+        title_id: u64,
+        content_type: ContentRecordType,
+    ) -> Result<NcaReader<&mut R>> {
+        let file = self.try_content(title_id, content_type)?.clone();
+        self.open_nca(&file)
+    }
+
+    /// Read `control.nacp` from the Control NCA of `title_id`.
+    ///
+    /// Returns [`Error::NotFound`] if the title has no Control NCA.
+    pub fn nacp(&mut self, title_id: u64) -> Result<Nacp> {
+        let mut nca = self.open_content(title_id, ContentRecordType::Control)?;
+        let mut romfs = nca.romfs()?;
+        let mut data = Vec::new();
+        romfs.open("/control.nacp")?.read_to_end(&mut data)?;
+        Nacp::parse(&mut Cursor::new(data))
+    }
+
+    /// Read the name, publisher, version, and icons of `title_id` from its
+    /// Control NCA.
+    ///
+    /// Icons are read for every language with a non-empty title entry.
+    /// Returns [`Error::NotFound`] if the title is not in the package or
+    /// has no Control NCA (e.g. add-on content).
+    pub fn title_info(&mut self, title_id: u64) -> Result<TitleInfo> {
+        let version = self
+            .title(title_id)
+            .ok_or_else(|| Error::NotFound {
+                name: format!("{title_id:016x}"),
+            })?
+            .version;
+        let mut nca = self.open_content(title_id, ContentRecordType::Control)?;
+        let mut romfs = nca.romfs()?;
+        let mut data = Vec::new();
+        romfs.open("/control.nacp")?.read_to_end(&mut data)?;
+        let nacp = Nacp::parse(&mut Cursor::new(data))?;
+
+        let mut languages = Vec::new();
+        for language in Language::ALL {
+            let title = nacp.title(language);
+            if title.is_empty() {
+                continue;
+            }
+            let icon = match romfs.romfs.get_file(&format!("/icon_{language:?}.dat")) {
+                Some(file) => {
+                    let file = file.clone();
+                    let mut icon = Vec::new();
+                    romfs.read_file(&file)?.read_to_end(&mut icon)?;
+                    Some(icon)
+                }
+                None => None,
+            };
+            languages.push(TitleLanguage {
+                language,
+                name: title.name.clone(),
+                publisher: title.developer.clone(),
+                icon,
+            });
+        }
+
+        Ok(TitleInfo {
+            title_id,
+            version,
+            display_version: nacp.display_version.clone(),
+            languages,
+            nacp,
+        })
+    }
+}
+
+impl<R> SwitchFs<R> {
+    /// Container type of the package.
+    pub fn kind(&self) -> PackageKind {
+        self.kind
+    }
+
+    /// CNMTs of all titles in the package, one per meta NCA.
+    pub fn titles(&self) -> &[Cnmt] {
+        &self.titles
+    }
+
+    /// CNMT of the title `title_id`.
+    pub fn title(&self, title_id: u64) -> Option<&Cnmt> {
+        self.titles.iter().find(|t| t.title_id == title_id)
+    }
+
+    /// Iterate over all NCAs, meta NCAs included, in package order.
+    pub fn files(&self) -> impl Iterator<Item = &ContentFile> {
+        self.files.iter()
+    }
+
+    /// Find an NCA by entry name. Returns [`None`] if not found.
+    pub fn get_file(&self, name: &str) -> Option<&ContentFile> {
+        self.files().find(|f| f.name == name)
+    }
+
+    /// NCA holding the content of type `content_type` of `title_id`.
+    ///
+    /// Returns [`None`] if the title has no such content or its NCA is not
+    /// in the package.
+    pub fn content(&self, title_id: u64, content_type: ContentRecordType) -> Option<&ContentFile> {
+        let record = self
+            .title(title_id)?
+            .contents
+            .iter()
+            .find(|c| c.content_type == content_type)?;
+        self.get_file(&format!("{}.nca", record.content_id_hex()))
+    }
+
+    /// Like [`content`](Self::content), but returns [`Error::NotFound`]
+    /// naming the missing content.
+    pub fn try_content(
+        &self,
+        title_id: u64,
+        content_type: ContentRecordType,
+    ) -> Result<&ContentFile> {
+        self.content(title_id, content_type)
+            .ok_or_else(|| Error::NotFound {
+                name: format!("{content_type:?} NCA of {title_id:016x}"),
+            })
+    }
+
+    /// Tickets in the package, in package order. Only the title keys of
+    /// common tickets are imported into [`keys`](Self::keys).
+    pub fn tickets(&self) -> &[Ticket] {
+        &self.tickets
+    }
+
+    /// Ticket for `rights_id`, or [`None`] if the package has none.
+    pub fn ticket(&self, rights_id: &[u8; 16]) -> Option<&Ticket> {
+        self.tickets.iter().find(|t| &t.rights_id == rights_id)
+    }
+
+    /// Keys used to open NCAs, including the imported title keys.
+    pub fn keys(&self) -> &KeySet {
+        &self.keys
+    }
+
+    /// Consume the package, returning the inner reader and the keys.
+    pub fn into_inner(self) -> (R, KeySet) {
+        (self.inner, self.keys)
+    }
+}
+
+impl<R: ReadAt> SwitchFs<R> {
+    /// Open an NCA for decrypted access through a shared reference.
+    ///
+    /// Like [`open_nca`](Self::open_nca), but several NCAs can be open at
+    /// once, e.g. a base and an update Program NCA for
+    /// [`crate::patching`].
+    pub fn open_nca_at(&self, file: &ContentFile) -> Result<NcaReader<RangeReader<&R>>> {
+        NcaReader::new(
+            RangeReader::new(&self.inner, file.offset, file.size),
+            &self.keys,
+        )
+    }
+
+    /// Like [`open_content`](Self::open_content), through a shared
+    /// reference.
+    pub fn open_content_at(
+        &self,
+        title_id: u64,
+        content_type: ContentRecordType,
+    ) -> Result<NcaReader<RangeReader<&R>>> {
+        self.open_nca_at(self.try_content(title_id, content_type)?)
+    }
+}
+
+/// Entries of the `secure` partition of the XCI in `r`, with absolute
+/// offsets.
+fn secure_entries<R: Read + Seek>(r: &mut R) -> Result<Vec<ContentFile>> {
+    let root = Xci::parse(r)?.root_partition;
+    let secure = root
+        .files
+        .iter()
+        .find(|f| f.name == "secure")
+        .ok_or_else(|| Error::NotFound {
+            name: "secure".into(),
+        })?;
+    r.seek(SeekFrom::Start(root.data_offset + secure.offset))?;
+    let hfs0 = Hfs0::parse(r)?;
+    Ok(hfs0
+        .files
+        .into_iter()
+        .map(|f| ContentFile {
+            name: f.name,
+            offset: hfs0.data_offset + f.offset,
+            size: f.size,
+        })
+        .collect())
+}
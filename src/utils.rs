@@ -1,9 +1,14 @@
-//! Low-level I/O primitives shared by all parsers.
+//! Low-level I/O primitives shared by all parsers and writers.
 //!
 //! Each function reads exactly the bytes it promises or returns an error -
 //! there is no partial-read ambiguity.
 
-use std::io::Read;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::PathBuf;
 
 use crate::{Error, Result};
 
@@ -55,6 +60,14 @@ pub(crate) fn be_u32<R: Read>(r: &mut R) -> Result<u32> {
     Ok(u32::from_be_bytes(b))
 }
 
+/// Read a big-endian [`u64`].
+#[inline]
+pub(crate) fn be_u64<R: Read>(r: &mut R) -> Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_be_bytes(b))
+}
+
 /// Read a [`u16`] with caller-supplied endianness.
 #[inline]
 pub(crate) fn end_u16<R: Read>(r: &mut R, le: bool) -> Result<u16> {
@@ -67,6 +80,12 @@ pub(crate) fn end_u32<R: Read>(r: &mut R, le: bool) -> Result<u32> {
     if le { le_u32(r) } else { be_u32(r) }
 }
 
+/// Read a [`u64`] with caller-supplied endianness.
+#[inline]
+pub(crate) fn end_u64<R: Read>(r: &mut R, le: bool) -> Result<u64> {
+    if le { le_u64(r) } else { be_u64(r) }
+}
+
 /// Read exactly `N` bytes into a fixed-size array.
 #[inline]
 pub(crate) fn bytesa<const N: usize>(r: &mut impl Read) -> Result<[u8; N]> {
@@ -83,6 +102,22 @@ pub(crate) fn bytesv<R: Read>(r: &mut R, len: usize) -> Result<Vec<u8>> {
     Ok(b)
 }
 
+/// Read exactly `len` bytes into a [`Vec`] that grows as data arrives.
+///
+/// For tables whose size comes from a header: unlike [`bytesv`], a corrupt
+/// size cannot make this allocate much more than the stream holds.
+#[inline]
+pub(crate) fn table<R: Read>(r: &mut R, len: u64) -> Result<Vec<u8>> {
+    const STEP: u64 = 0x10_0000;
+    let mut b = Vec::new();
+    while (b.len() as u64) < len {
+        let start = b.len();
+        b.resize(start + (len - start as u64).min(STEP) as usize, 0);
+        r.read_exact(&mut b[start..])?;
+    }
+    Ok(b)
+}
+
 /// Verify that the next `N` bytes in the stream match `expected`.
 ///
 /// Returns [`Error::BadMagic`] on mismatch.
@@ -95,18 +130,48 @@ pub(crate) fn magic<R: Read, const N: usize>(r: &mut R, expected: &[u8; N]) -> R
     Ok(())
 }
 
-/// Extract a null-terminated UTF-8 string from a byte slice at `offset`.
+/// Borrow a null-terminated UTF-8 string from a byte slice at `offset`.
 ///
-/// Returns [`Error::InvalidRange`] if `offset` is out of bounds, or
+/// Only allocates if the name is not valid UTF-8. Returns
+/// [`Error::InvalidRange`] if `offset` is out of bounds, or
 /// [`Error::UnterminatedName`] if no null byte is found.
 #[inline]
-pub(crate) fn null_string(buf: &[u8], offset: usize) -> Result<String> {
+pub(crate) fn null_str(buf: &[u8], offset: usize) -> Result<Cow<'_, str>> {
+    Ok(String::from_utf8_lossy(&buf[null_range(buf, offset)?]))
+}
+
+/// Byte range of the null-terminated string at `offset` in `buf`, without
+/// the terminator.
+///
+/// Fails like [`null_str`].
+#[inline]
+pub(crate) fn null_range(buf: &[u8], offset: usize) -> Result<Range<usize>> {
     let slice = buf.get(offset..).ok_or(Error::InvalidRange)?;
-    let end = slice
+    let len = slice
         .iter()
         .position(|&b| b == 0)
         .ok_or(Error::UnterminatedName)?;
-    Ok(String::from_utf8_lossy(&slice[..end]).into_owned())
+    Ok(offset..offset + len)
+}
+
+/// Convert a size or offset read from a file to `usize`.
+///
+/// Returns [`Error::InvalidRange`] if it does not fit, as on 32-bit targets
+/// such as `wasm32`, rather than silently truncating.
+#[inline]
+pub(crate) fn to_usize(n: u64) -> Result<usize> {
+    usize::try_from(n).map_err(|_| Error::InvalidRange)
+}
+
+/// Borrow `len` bytes of `buf` starting at `offset`.
+///
+/// Returns [`Error::InvalidRange`] if the range does not fit in `buf`.
+#[inline]
+pub(crate) fn subslice(buf: &[u8], offset: u64, len: u64) -> Result<&[u8]> {
+    let start = to_usize(offset)?;
+    let len = to_usize(len)?;
+    let end = start.checked_add(len).ok_or(Error::InvalidRange)?;
+    buf.get(start..end).ok_or(Error::InvalidRange)
 }
 
 /// Decode a null-padded fixed-width byte slice into a [`String`].
@@ -119,16 +184,176 @@ pub(crate) fn null_padded_string(buf: &[u8]) -> String {
     String::from_utf8_lossy(&buf[..end]).into_owned()
 }
 
-/// Read a null-terminated UTF-8 string byte-by-byte.
-#[inline]
-pub(crate) fn read_null_string<R: Read>(r: &mut R) -> Result<String> {
-    let mut bytes = Vec::new();
-    loop {
-        let b = u8(r)?;
-        if b == 0 {
-            break;
+/// Build a string table of null-terminated `names`.
+///
+/// Returns the table and the offset of each name within it.
+pub(crate) fn string_table<'a>(names: impl IntoIterator<Item = &'a str>) -> (Vec<u8>, Vec<u32>) {
+    let mut table = Vec::new();
+    let mut offsets = Vec::new();
+    for name in names {
+        offsets.push(table.len() as u32);
+        table.extend_from_slice(name.as_bytes());
+        table.push(0);
+    }
+    (table, offsets)
+}
+
+/// Data for one entry of an archive being written.
+pub(crate) enum EntrySource<'a> {
+    Reader(Box<dyn Read + 'a>),
+    Path(PathBuf),
+}
+
+impl<'a> EntrySource<'a> {
+    /// Open the entry for reading. Paths are opened only now, so a writer can
+    /// hold many entries without holding their files open.
+    pub(crate) fn open(self) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Self::Reader(r) => r,
+            Self::Path(path) => Box::new(BufReader::new(File::open(path)?)),
+        })
+    }
+}
+
+/// Window of `len` bytes starting at `start` in an owned stream, read as a
+/// stream of its own.
+///
+/// Offsets passed to [`Seek`] are relative to the window, so the window can
+/// be handed to any parser: an HFS0 partition of an XCI, an NCA inside it,
+/// and so on. Archive readers return one from `into_file`.
+#[derive(Debug)]
+pub struct SubReader<R> {
+    inner: R,
+    start: u64,
+    len: u64,
+    pos: u64,
+    /// Whether the inner stream's position matches `pos`.
+    synced: bool,
+}
+
+impl<R: Read + Seek> SubReader<R> {
+    /// View `len` bytes of `inner` starting at `start`.
+    ///
+    /// The inner stream is not touched until the first read.
+    pub fn new(inner: R, start: u64, len: u64) -> Self {
+        Self {
+            inner,
+            start,
+            len,
+            pos: 0,
+            synced: false,
+        }
+    }
+
+    /// Length of the window in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// `true` if the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Absolute offset of the window in the inner stream.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Consume the window, returning the inner stream.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for SubReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (buf.len() as u64).min(self.len.saturating_sub(self.pos)) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        if !self.synced {
+            self.inner.seek(SeekFrom::Start(self.start + self.pos))?;
+            self.synced = true;
+        }
+        let n = self.inner.read(&mut buf[..len])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for SubReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        let target = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of window")
+        })?;
+        if target != self.pos {
+            self.pos = target;
+            self.synced = false;
+        }
+        Ok(self.pos)
+    }
+}
+
+/// Reader over the byte range `pos..end` of a stream shared with other
+/// readers, so several entries of one archive can be queued on a writer at
+/// once. Seeks before every read.
+pub(crate) struct SharedRange<'a, R> {
+    inner: &'a RefCell<R>,
+    pos: u64,
+    end: u64,
+}
+
+impl<'a, R> SharedRange<'a, R> {
+    pub(crate) fn new(inner: &'a RefCell<R>, start: u64, len: u64) -> Self {
+        Self {
+            inner,
+            pos: start,
+            end: start + len,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for SharedRange<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (buf.len() as u64).min(self.end - self.pos) as usize;
+        if len == 0 {
+            return Ok(0);
         }
-        bytes.push(b);
+        let mut inner = self.inner.borrow_mut();
+        inner.seek(SeekFrom::Start(self.pos))?;
+        let n = inner.read(&mut buf[..len])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// Serde adapter for byte arrays longer than the 32 elements serde supports,
+/// used as `#[serde(with = "crate::utils::byte_array")]`.
+#[cfg(feature = "serde")]
+pub(crate) mod byte_array {
+    use std::borrow::Borrow;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer, T: Borrow<[u8; N]>, const N: usize>(
+        v: &T,
+        s: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_bytes(v.borrow())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(
+        d: D,
+    ) -> std::result::Result<T, D::Error> {
+        let v = Vec::<u8>::deserialize(d)?;
+        let len = v.len();
+        T::try_from(v).map_err(|_| D::Error::invalid_length(len, &"a fixed-size byte array"))
     }
-    Ok(String::from_utf8_lossy(&bytes).into_owned())
 }
@@ -0,0 +1,297 @@
+//! Integrity checks for a whole NSP or XCI.
+//!
+//! [`verify`] opens a package as a [`SwitchFs`] and runs every check this
+//! crate has over it, collecting the results in one [`VerifyReport`]:
+//! ```text
+//! Structure    PFS0 / XCI header, root and partition HFS0 headers, tickets,
+//!              meta NCAs and CNMTs parse          (failures are errors)
+//! Partitions   HFS0 entry hashes of the XCI root and every partition
+//! NCAs         header signature (sig[0]), section hash trees, and the
+//!              ticket for NCAs with a rights ID
+//! Contents     every CNMT content record against its NCA (size, SHA-256)
+//! ```
+//! ```text
+//! let report = verify(File::open("game.nsp")?, keys)?;
+//! for nca in report.ncas.iter().filter(|n| !n.is_ok()) {
+//!     println!("{}: {:?} {:?} {:?}", nca.name, nca.signature, nca.ticket, nca.error);
+//! }
+//! ```
+//! With the `serde` feature the report serializes as is.
+//!
+//! Every NCA is read twice: once decrypted for its section hashes and once
+//! raw for the SHA-256 in its content record. NCZ entries are not listed
+//! by [`SwitchFs`], so records of an NSZ show up as
+//! [`ContentStatus::Missing`]; decompress it first.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::crypto::sha256::Sha256;
+use crate::formats::cnmt::{ContentRecord, ContentStatus};
+use crate::formats::hfs0::{Hfs0Reader, Hfs0Report};
+use crate::formats::nca::{SectionCheck, SectionStatus};
+use crate::formats::ticket::TitleKeyType;
+use crate::formats::xci::Xci;
+use crate::formats::{FormatKind, detect};
+use crate::keys::KeySet;
+use crate::switch_fs::{ContentFile, PackageKind, SwitchFs};
+use crate::{Error, Result};
+
+/// Outcome of checking an NCA header signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignatureStatus {
+    /// The fixed-key signature over the header matches.
+    Ok,
+    /// The fixed-key signature does not match.
+    Invalid,
+    /// The `nca_hdr_fixed_key_modulus` for the NCA's generation is not
+    /// loaded, or the header could not be read.
+    Unchecked,
+}
+
+/// Ticket state of an NCA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TicketStatus {
+    /// The NCA has no rights ID and needs no ticket.
+    NotRequired,
+    /// The package has a common ticket for the NCA's rights ID.
+    Common,
+    /// The package has a personalized ticket for the NCA's rights ID; its
+    /// title key can only be unwrapped with console keys.
+    Personalized,
+    /// The NCA has a rights ID but the package has no ticket for it.
+    Missing,
+}
+
+/// Entry hashes of one HFS0 partition of an XCI.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PartitionCheck {
+    /// `root` for the root partition, else the partition's entry name in
+    /// the root (`update`, `normal`, `secure`, `logo`).
+    pub name: String,
+    pub report: Hfs0Report,
+}
+
+/// Result of checking one NCA.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NcaCheck {
+    /// Entry name in the package.
+    pub name: String,
+    pub signature: SignatureStatus,
+    pub ticket: TicketStatus,
+    /// One entry per present section; empty if `error` is set.
+    pub sections: Vec<SectionCheck>,
+    /// Why the NCA could not be checked, e.g. a missing key.
+    pub error: Option<String>,
+}
+
+impl NcaCheck {
+    /// `true` if the signature is not invalid, no ticket is missing, the
+    /// NCA could be checked, and no section failed.
+    /// [`SectionStatus::Unchecked`] sections do not count as failures.
+    pub fn is_ok(&self) -> bool {
+        self.signature != SignatureStatus::Invalid
+            && self.ticket != TicketStatus::Missing
+            && self.error.is_none()
+            && self
+                .sections
+                .iter()
+                .all(|c| matches!(c.status, SectionStatus::Ok | SectionStatus::Unchecked))
+    }
+}
+
+/// Results of [`verify`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyReport {
+    pub kind: PackageKind,
+    /// HFS0 partitions, root first; empty for an NSP.
+    pub partitions: Vec<PartitionCheck>,
+    /// Every NCA, meta NCAs included, in package order.
+    pub ncas: Vec<NcaCheck>,
+    /// Every content record of every CNMT, with its status.
+    pub contents: Vec<(ContentRecord, ContentStatus)>,
+    /// NCAs not referenced by any CNMT (meta NCAs excluded).
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    /// `true` if every partition, NCA, and content record passed and there
+    /// are no extra NCAs.
+    pub fn is_ok(&self) -> bool {
+        self.partitions.iter().all(|p| p.report.is_ok())
+            && self.ncas.iter().all(NcaCheck::is_ok)
+            && self.contents.iter().all(|(_, s)| *s == ContentStatus::Ok)
+            && self.extra.is_empty()
+    }
+
+    /// NCAs that did not pass; see [`NcaCheck::is_ok`].
+    pub fn failed_ncas(&self) -> impl Iterator<Item = &NcaCheck> {
+        self.ncas.iter().filter(|n| !n.is_ok())
+    }
+}
+
+/// Check every partition, NCA, ticket, and content record of an NSP or
+/// XCI.
+///
+/// `reader` and `keys` are as for [`SwitchFs::new`]. Problems with one
+/// NCA or content, a truncated NCA included, are recorded in the report;
+/// structural errors in the package and failures of `reader` itself are
+/// returned.
+pub fn verify<R: Read + Seek>(mut reader: R, keys: KeySet) -> Result<VerifyReport> {
+    let start = reader.stream_position()?;
+    let partitions = match detect(&mut reader)? {
+        FormatKind::Xci => check_partitions(&mut reader)?,
+        _ => Vec::new(),
+    };
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut fs = SwitchFs::new(reader, keys)?;
+    let files: Vec<ContentFile> = fs.files().cloned().collect();
+    let mut ncas = Vec::with_capacity(files.len());
+    for file in &files {
+        ncas.push(check_nca(&mut fs, file)?);
+    }
+
+    let records: Vec<ContentRecord> = fs
+        .titles()
+        .iter()
+        .flat_map(|t| t.contents.iter().cloned())
+        .collect();
+    let mut contents = Vec::with_capacity(records.len());
+    for record in records {
+        let status = match fs.get_file(&format!("{}.nca", record.content_id_hex())) {
+            None => ContentStatus::Missing,
+            Some(file) if file.size != record.size => ContentStatus::WrongSize(file.size),
+            Some(file) => {
+                let file = file.clone();
+                let mut hasher = Sha256::new();
+                io::copy(&mut fs.read_file(&file)?, &mut hasher)?;
+                if hasher.finalize() == record.hash {
+                    ContentStatus::Ok
+                } else {
+                    ContentStatus::WrongHash
+                }
+            }
+        };
+        contents.push((record, status));
+    }
+
+    let extra = files
+        .into_iter()
+        .filter(|f| {
+            let stem = f.name.trim_end_matches(".nca");
+            !f.name.ends_with(".cnmt.nca")
+                && !contents.iter().any(|(r, _)| r.content_id_hex() == stem)
+        })
+        .map(|f| f.name)
+        .collect();
+
+    Ok(VerifyReport {
+        kind: fs.kind(),
+        partitions,
+        ncas,
+        contents,
+        extra,
+    })
+}
+
+/// Check the root HFS0 of the XCI in `r` and every partition in it.
+fn check_partitions<R: Read + Seek>(r: &mut R) -> Result<Vec<PartitionCheck>> {
+    let xci = Xci::parse(r)?;
+    r.seek(SeekFrom::Start(xci.hfs0_offset))?;
+    let mut root = Hfs0Reader::new(&mut *r)?;
+    let mut checks = vec![PartitionCheck {
+        name: "root".into(),
+        report: root.verify_report()?,
+    }];
+
+    let root = xci.root_partition;
+    for partition in &root.files {
+        r.seek(SeekFrom::Start(root.data_offset + partition.offset))?;
+        checks.push(PartitionCheck {
+            name: partition.name.clone(),
+            report: Hfs0Reader::new(&mut *r)?.verify_report()?,
+        });
+    }
+    Ok(checks)
+}
+
+/// `true` if `e` is a failure of the package reader rather than a problem
+/// with one NCA: any I/O error except running out of data.
+fn is_reader_failure(e: &Error) -> bool {
+    matches!(e.root(), Error::Io(io) if io.kind() != io::ErrorKind::UnexpectedEof)
+}
+
+/// Check the header signature, section hashes, and ticket of one NCA.
+///
+/// Errors are recorded in the check, including running out of data in a
+/// truncated NCA; other I/O errors come from the package reader and are
+/// returned.
+fn check_nca<R: Read + Seek>(fs: &mut SwitchFs<R>, file: &ContentFile) -> Result<NcaCheck> {
+    let mut check = NcaCheck {
+        name: file.name.clone(),
+        signature: SignatureStatus::Unchecked,
+        ticket: TicketStatus::NotRequired,
+        sections: Vec::new(),
+        error: None,
+    };
+    let recorded = |check: &mut NcaCheck, e: Error| {
+        if is_reader_failure(&e) {
+            return Err(e);
+        }
+        check.error = Some(e.to_string());
+        Ok(())
+    };
+
+    let mut reader = match fs.open_nca(file) {
+        Ok(reader) => reader,
+        Err(e) => {
+            recorded(&mut check, e)?;
+            return Ok(check);
+        }
+    };
+    match reader.verify() {
+        Ok(report) => check.sections = report.sections,
+        Err(e) => recorded(&mut check, e)?,
+    }
+    let nca = reader.nca;
+
+    check.signature = match nca.verify_header_signature(fs.keys()) {
+        Ok(true) => SignatureStatus::Ok,
+        Ok(false) => SignatureStatus::Invalid,
+        Err(_) => SignatureStatus::Unchecked,
+    };
+    if nca.uses_titlekey_crypto() {
+        check.ticket = match fs.ticket(&nca.rights_id) {
+            Some(t) if t.title_key_type == TitleKeyType::Common => TicketStatus::Common,
+            Some(_) => TicketStatus::Personalized,
+            None => TicketStatus::Missing,
+        };
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Context;
+
+    #[test]
+    fn truncated_nca_is_recorded() {
+        let eof = Error::Io(io::ErrorKind::UnexpectedEof.into());
+        assert!(!is_reader_failure(&eof));
+        let eof: Result<()> = Err(eof);
+        assert!(!is_reader_failure(
+            &eof.at("NCA", 0, || "section 0".into()).unwrap_err()
+        ));
+        assert!(!is_reader_failure(&Error::UnexpectedEof));
+        assert!(!is_reader_failure(&Error::MissingKey("header_key".into())));
+        assert!(is_reader_failure(&Error::Io(
+            io::ErrorKind::PermissionDenied.into()
+        )));
+    }
+}